@@ -1,21 +1,251 @@
+#[cfg(feature = "serialize_serde")]
+use serde::Serialize;
 use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::bft::consensus::authenticator::MacKeys;
+use crate::bft::consensus::usig::Usig;
+use crate::bft::evidence::MisbehaviourReporter;
+use crate::bft::log::decided::{StableCheckpoints, DEFAULT_RETAINED_PROOFS};
+
 #[derive(Debug, Deserialize)]
-pub struct PBFTConfig {
+pub struct PBFTConfig<RQ> {
     pub timeout_dur: Duration,
     pub proposer_config: ProposerConfig,
     pub watermark: u32,
+    #[serde(default)]
+    pub fault_model: FaultModel,
+    /// How long we wait, after sending our prepare, for all n replicas
+    /// to send matching prepares so the decision can skip the commit round.
+    /// `None` disables the fast path.
+    #[serde(default)]
+    pub fast_path_window: Option<Duration>,
+    /// Compact the prepare and commit quorums of decided proofs into
+    /// a signer bitmap plus the vote signature of each signer.
+    /// This requires every replica to sign its votes.
+    #[serde(default)]
+    pub compact_certificates: bool,
+    /// The trusted counter used by the [FaultModel::TrustedCounter] model
+    #[serde(skip)]
+    pub usig: Option<Arc<dyn Usig>>,
+    /// The pairwise keys used to authenticate prepares and commits with MAC
+    /// authenticators instead of signatures. `None` keeps every vote signed.
+    #[serde(skip)]
+    pub mac_keys: Option<Arc<dyn MacKeys>>,
+    /// Monitor the performance of the leader and proactively
+    /// replace it when it becomes too slow. `None` disables the monitor.
+    #[serde(default)]
+    pub leader_monitor: Option<LeaderMonitorConfig>,
+    /// Have the leader send null requests while idle, so the other replicas
+    /// can quickly detect a crashed leader. `None` disables heartbeats.
+    #[serde(default)]
+    pub heartbeat: Option<HeartbeatConfig>,
+    /// How long the remaining leaders have to send their pre prepare, after the
+    /// first one is received, before their slots are voted to be filled with
+    /// empty batches. `None` keeps waiting for every leader.
+    #[serde(default)]
+    pub pre_prepare_slot_timeout: Option<Duration>,
+    /// Receives the evidence of replicas caught sending conflicting messages
+    #[serde(skip)]
+    pub misbehaviour_reporter: Option<Arc<dyn MisbehaviourReporter<RQ>>>,
+    /// How many of the latest decided proofs are retained, and where
+    #[serde(default)]
+    pub decision_log: DecisionLogConfig,
+    /// Tells us which checkpoints became stable, so the decisions they cover
+    /// can be dropped from the decision log. `None` only truncates it by size
+    #[serde(skip)]
+    pub stable_checkpoints: Option<StableCheckpoints>,
 }
 
-impl PBFTConfig {
+impl<RQ> PBFTConfig<RQ> {
     pub fn new(timeout_dur: Duration, watermark: u32, proposer_config: ProposerConfig) -> Self {
         Self {
             timeout_dur,
             proposer_config,
             watermark,
+            fault_model: FaultModel::default(),
+            fast_path_window: None,
+            compact_certificates: false,
+            usig: None,
+            mac_keys: None,
+            leader_monitor: None,
+            heartbeat: None,
+            pre_prepare_slot_timeout: None,
+            misbehaviour_reporter: None,
+            decision_log: DecisionLogConfig::default(),
+            stable_checkpoints: None,
+        }
+    }
+
+    /// Run the protocol under the given fault model
+    pub fn with_fault_model(mut self, fault_model: FaultModel) -> Self {
+        self.fault_model = fault_model;
+
+        self
+    }
+
+    /// Enable the optimistic fast path, which decides as soon as all replicas
+    /// send matching prepares within the given window
+    pub fn with_fast_path(mut self, window: Duration) -> Self {
+        self.fast_path_window = Some(window);
+
+        self
+    }
+
+    /// Sign votes and compact decided proofs into quorum certificates
+    pub fn with_compact_certificates(mut self) -> Self {
+        self.compact_certificates = true;
+
+        self
+    }
+
+    /// Authenticate prepares and commits with MAC authenticators built
+    /// from the given pairwise keys
+    pub fn with_mac_authenticators(mut self, mac_keys: Arc<dyn MacKeys>) -> Self {
+        self.mac_keys = Some(mac_keys);
+
+        self
+    }
+
+    /// Proactively replace leaders whose performance falls below the expected level
+    pub fn with_leader_monitor(mut self, leader_monitor: LeaderMonitorConfig) -> Self {
+        self.leader_monitor = Some(leader_monitor);
+
+        self
+    }
+
+    /// Have idle leaders propose a null request every `interval`, and start a view change
+    /// when the leader has not been heard from for `timeout`
+    pub fn with_leader_heartbeats(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.heartbeat = Some(HeartbeatConfig { interval, timeout });
+
+        self
+    }
+
+    /// Fill the slots of leaders which do not send their pre prepare within
+    /// `timeout` of the first one with empty batches, and drop them from the leader set
+    pub fn with_pre_prepare_slot_timeout(mut self, timeout: Duration) -> Self {
+        self.pre_prepare_slot_timeout = Some(timeout);
+
+        self
+    }
+
+    /// Report the evidence of replicas caught sending conflicting messages
+    pub fn with_misbehaviour_reporter(
+        mut self,
+        reporter: Arc<dyn MisbehaviourReporter<RQ>>,
+    ) -> Self {
+        self.misbehaviour_reporter = Some(reporter);
+
+        self
+    }
+
+    /// Retain the decided proofs as described by the given configuration
+    pub fn with_decision_log(mut self, decision_log: DecisionLogConfig) -> Self {
+        self.decision_log = decision_log;
+
+        self
+    }
+
+    /// Truncate the decision log whenever the given handle is notified of a stable checkpoint.
+    /// The same handle should be notified by the state transfer protocol
+    pub fn with_stable_checkpoints(mut self, stable_checkpoints: StableCheckpoints) -> Self {
+        self.stable_checkpoints = Some(stable_checkpoints);
+
+        self
+    }
+
+    /// Run the protocol with the given trusted counter.
+    /// This is required by the [FaultModel::TrustedCounter] model.
+    pub fn with_usig(mut self, usig: Arc<dyn Usig>) -> Self {
+        self.usig = Some(usig);
+
+        self
+    }
+}
+
+/// The kind of faults the protocol is meant to tolerate.
+///
+/// The same pipeline is used for both models, only the quorums
+/// and the steps which are required for Byzantine safety change.
+#[cfg_attr(feature = "serialize_serde", derive(Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum FaultModel {
+    /// Up to f arbitrary faults, with n = 3f + 1 replicas and 2f + 1 quorums
+    #[default]
+    Byzantine,
+    /// Up to f crash faults, with n = 2f + 1 replicas and majority quorums.
+    /// The separate commit phase is skipped, as a majority of matching prepares
+    /// already guarantees that any later view will see the decided value.
+    Crash,
+    /// Up to f Byzantine faults with n = 2f + 1 replicas, relying on a trusted
+    /// monotonic counter (USIG) attached to every PrePrepare and Prepare.
+    /// Since replicas cannot equivocate, f + 1 matching prepares are enough to
+    /// decide, without a separate commit phase (MinBFT style).
+    /// View change messages are certified by the trusted counter as well,
+    /// so view changes only require the collects of f + 1 replicas.
+    TrustedCounter,
+}
+
+/// The fault model the ordering protocol of this process was initialized with.
+///
+/// The [OrderProtocolTolerance](atlas_core::ordering_protocol::OrderProtocolTolerance)
+/// functions are not given a protocol instance, so they read it from here.
+static CONFIGURED_FAULT_MODEL: AtomicU8 = AtomicU8::new(FaultModel::Byzantine as u8);
+
+impl FaultModel {
+    /// The fault model the ordering protocol of this process was initialized with.
+    /// Until one is initialized, this is the default Byzantine model.
+    pub fn configured() -> FaultModel {
+        match CONFIGURED_FAULT_MODEL.load(Ordering::Relaxed) {
+            model if model == FaultModel::Crash as u8 => FaultModel::Crash,
+            model if model == FaultModel::TrustedCounter as u8 => FaultModel::TrustedCounter,
+            _ => FaultModel::Byzantine,
+        }
+    }
+
+    /// Record this as the fault model of the ordering protocol of this process
+    pub(crate) fn configure(self) {
+        CONFIGURED_FAULT_MODEL.store(self as u8, Ordering::Relaxed);
+    }
+
+    /// The amount of replicas required to tolerate f faults
+    pub fn n_for_f(&self, f: usize) -> usize {
+        match self {
+            FaultModel::Byzantine => 3 * f + 1,
+            FaultModel::Crash | FaultModel::TrustedCounter => 2 * f + 1,
+        }
+    }
+
+    /// The amount of faults that can be tolerated with n replicas
+    pub fn f_for_n(&self, n: usize) -> usize {
+        match self {
+            FaultModel::Byzantine => (n - 1) / 3,
+            FaultModel::Crash | FaultModel::TrustedCounter => (n - 1) / 2,
         }
     }
+
+    /// The size of a quorum with n replicas
+    pub fn quorum_for_n(&self, n: usize) -> usize {
+        match self {
+            FaultModel::Byzantine => 2 * self.f_for_n(n) + 1,
+            FaultModel::Crash => n / 2 + 1,
+            FaultModel::TrustedCounter => self.f_for_n(n) + 1,
+        }
+    }
+
+    /// Do we need a trusted counter identifier on PrePrepare, Prepare and view change messages?
+    pub fn requires_usig(&self) -> bool {
+        matches!(self, FaultModel::TrustedCounter)
+    }
+
+    /// Do we need the commit phase in order to decide?
+    pub fn requires_commit_phase(&self) -> bool {
+        matches!(self, FaultModel::Byzantine)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,3 +264,82 @@ impl ProposerConfig {
         }
     }
 }
+
+/// The configuration of the window of decided proofs kept by the decision log
+#[derive(Debug, Clone, Deserialize)]
+pub struct DecisionLogConfig {
+    /// How many of the latest decided proofs are retained. Proofs are also dropped
+    /// once they are covered by a stable checkpoint, but the last one is always kept.
+    pub retained_proofs: usize,
+    /// A directory in which the retained proofs are also kept, so they survive restarts.
+    /// `None` keeps them in memory only.
+    #[serde(default)]
+    pub persist_dir: Option<PathBuf>,
+}
+
+impl DecisionLogConfig {
+    pub fn new(retained_proofs: usize) -> Self {
+        Self {
+            retained_proofs,
+            persist_dir: None,
+        }
+    }
+
+    /// Also keep the retained proofs in the given directory
+    pub fn with_persist_dir(mut self, persist_dir: impl Into<PathBuf>) -> Self {
+        self.persist_dir = Some(persist_dir.into());
+
+        self
+    }
+}
+
+impl Default for DecisionLogConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETAINED_PROOFS)
+    }
+}
+
+/// The configuration of the leader heartbeats
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct HeartbeatConfig {
+    /// How long the leader may stay idle before proposing a null request
+    pub interval: Duration,
+    /// How long the other replicas wait for a pre prepare from the leader
+    /// before starting a view change. Must be larger than the interval.
+    pub timeout: Duration,
+}
+
+/// The configuration of the leader performance monitor (Aardvark style)
+#[derive(Debug, Clone, Deserialize)]
+pub struct LeaderMonitorConfig {
+    /// How many decisions are evaluated at a time.
+    /// A new leader is never judged before it has completed a full window.
+    pub window: usize,
+    /// The fraction of the peak performance a leader is expected to sustain
+    pub expected_fraction: f64,
+    /// How much of the peak performance is forgotten at every view change,
+    /// so the expectations adapt when the system becomes slower
+    pub peak_decay: f64,
+    /// Windows which ordered less requests than this are not evaluated,
+    /// as there was not enough load to tell a slow leader apart from an idle system
+    pub min_window_requests: usize,
+    /// The minimum time between two proactive view changes started by this replica
+    pub cooldown: Duration,
+}
+
+impl LeaderMonitorConfig {
+    pub fn new(
+        window: usize,
+        expected_fraction: f64,
+        min_window_requests: usize,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            window,
+            expected_fraction,
+            peak_decay: 0.1,
+            min_window_requests,
+            cooldown,
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use chrono::Utc;
 
-use tracing::debug;
+use tracing::{debug, error};
 
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
@@ -17,6 +17,10 @@ use atlas_communication::reconfiguration::NetworkInformationProvider;
 use atlas_core::ordering_protocol::networking::OrderProtocolSendNode;
 
 use crate::bft::consensus::accessory::AccessoryConsensus;
+use crate::bft::consensus::authenticator::{authenticate_vote, MacKeys};
+use crate::bft::consensus::decision::DecisionOptions;
+use crate::bft::consensus::usig::{attach_ui, Usig};
+use crate::bft::log::certificate::{sign_vote, VoteKind};
 use crate::bft::log::deciding::WorkingDecisionLog;
 use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage};
 use crate::bft::sync::view::ViewInfo;
@@ -27,6 +31,12 @@ where
     RQ: SerType,
 {
     speculative_commits: Arc<Mutex<BTreeMap<NodeId, StoredSerializedMessage<SysMsg<RQ>>>>>,
+    /// The trusted counter used to certify our prepare messages
+    usig: Option<Arc<dyn Usig>>,
+    /// Should we sign the vote statement of our prepares and commits
+    sign_votes: bool,
+    /// The pairwise keys used to authenticate our votes with MACs
+    mac_keys: Option<Arc<dyn MacKeys>>,
 }
 
 impl<RQ> AccessoryConsensus<RQ> for ReplicaAccessory<RQ>
@@ -68,46 +78,71 @@ where
 
         let node_clone = node.clone();
 
-        threadpool::execute(move || {
-            let message = PBFTMessage::Consensus(ConsensusMessage::new(
-                seq,
-                view_seq,
-                ConsensusMessageKind::Commit(current_digest),
-            ));
-
-            let (message, digest) = node_clone.serialize_digest_message(message).unwrap();
-
-            let (message, buf) = message.into_inner();
-
-            for peer_id in NodeId::targets(0..n) {
-                let buf_clone = buf.clone();
-
-                // create header
-                let (header, _, _) = WireMessage::new(
-                    my_id,
-                    peer_id,
-                    MessageModule::Protocol,
-                    buf_clone,
-                    // NOTE: nonce not too important here,
-                    // since we already contain enough random
-                    // data with the unique digest of the
-                    // PRE-PREPARE message
-                    0,
-                    Some(digest),
-                    Some(&*key_pair),
-                )
-                .into_inner();
-
-                // store serialized header + message
-                let serialized = SerializedMessage::new(message.clone(), buf.clone());
-
-                let stored = StoredMessage::new(header, serialized);
-
-                let mut map = speculative_commits.lock().unwrap();
-
-                map.insert(peer_id, stored);
-            }
-        });
+        let sign_votes = self.sign_votes;
+
+        // Under the crash fault model there is no commit phase, so there is no point
+        // in preparing the speculative commits.
+        // Speculative commits are also pre signed, which is what MAC authenticators avoid
+        if view.fault_model().requires_commit_phase() && self.mac_keys.is_none() {
+            threadpool::execute(move || {
+                let message = ConsensusMessage::new(
+                    seq,
+                    view_seq,
+                    ConsensusMessageKind::Commit(current_digest),
+                );
+
+                let message = if sign_votes {
+                    match sign_vote(&key_pair, VoteKind::Commit, seq, view_seq, &current_digest) {
+                        Ok(signature) => message.with_vote_signature(signature),
+                        Err(err) => {
+                            error!(
+                                "{:?} // Failed to sign the speculative commit vote {:?}: {:?}",
+                                my_id, seq, err
+                            );
+
+                            return;
+                        }
+                    }
+                } else {
+                    message
+                };
+
+                let message = PBFTMessage::Consensus(message);
+
+                let (message, digest) = node_clone.serialize_digest_message(message).unwrap();
+
+                let (message, buf) = message.into_inner();
+
+                for peer_id in NodeId::targets(0..n) {
+                    let buf_clone = buf.clone();
+
+                    // create header
+                    let (header, _, _) = WireMessage::new(
+                        my_id,
+                        peer_id,
+                        MessageModule::Protocol,
+                        buf_clone,
+                        // NOTE: nonce not too important here,
+                        // since we already contain enough random
+                        // data with the unique digest of the
+                        // PRE-PREPARE message
+                        0,
+                        Some(digest),
+                        Some(&*key_pair),
+                    )
+                    .into_inner();
+
+                    // store serialized header + message
+                    let serialized = SerializedMessage::new(message.clone(), buf.clone());
+
+                    let stored = StoredMessage::new(header, serialized);
+
+                    let mut map = speculative_commits.lock().unwrap();
+
+                    map.insert(peer_id, stored);
+                }
+            });
+        }
 
         let targets = view.quorum_members().clone();
 
@@ -122,13 +157,58 @@ where
         // Also, since we can have # Leaders > f, if the leaders didn't partake in this
         // Instance we would have situations where faults joined with leaders would cause
         // Unresponsiveness
-        let message = PBFTMessage::Consensus(ConsensusMessage::new(
+        let mut message = ConsensusMessage::new(
             seq,
             view.sequence_number(),
             ConsensusMessageKind::Prepare(current_digest),
-        ));
+        );
 
-        let _ = node.broadcast_signed(message, targets.into_iter());
+        if self.sign_votes {
+            let key_pair = node.network_info_provider().get_key_pair();
+
+            message = match sign_vote(
+                key_pair,
+                VoteKind::Prepare,
+                seq,
+                view.sequence_number(),
+                &current_digest,
+            ) {
+                Ok(signature) => message.with_vote_signature(signature),
+                Err(err) => {
+                    error!(
+                        "{:?} // Failed to sign the prepare vote {:?}: {:?}",
+                        my_id, seq, err
+                    );
+
+                    return;
+                }
+            };
+        }
+
+        if view.fault_model().requires_usig() {
+            let Some(usig) = &self.usig else {
+                error!(
+                    "{:?} // Running under the trusted counter fault model without a USIG, not sending prepare {:?}",
+                    my_id, seq
+                );
+
+                return;
+            };
+
+            message = match attach_ui(&**usig, message) {
+                Ok(message) => message,
+                Err(err) => {
+                    error!(
+                        "{:?} // Failed to create the unique identifier for prepare {:?}: {:?}",
+                        my_id, seq, err
+                    );
+
+                    return;
+                }
+            };
+        }
+
+        self.broadcast_vote(&**node, message, targets);
     }
 
     fn handle_preparing_no_quorum<NT>(
@@ -170,11 +250,33 @@ where
 
             let _ = node.broadcast_serialized(speculative_commits);
         } else {
-            let message = PBFTMessage::Consensus(ConsensusMessage::new(
+            let mut message = ConsensusMessage::new(
                 seq,
                 view.sequence_number(),
                 ConsensusMessageKind::Commit(current_digest),
-            ));
+            );
+
+            if self.sign_votes {
+                let key_pair = node.network_info_provider().get_key_pair();
+
+                message = match sign_vote(
+                    key_pair,
+                    VoteKind::Commit,
+                    seq,
+                    view.sequence_number(),
+                    &current_digest,
+                ) {
+                    Ok(signature) => message.with_vote_signature(signature),
+                    Err(err) => {
+                        error!(
+                            "{:?} // Failed to sign the commit vote {:?}: {:?}",
+                            node_id, seq, err
+                        );
+
+                        return;
+                    }
+                };
+            }
 
             debug!(
                 "{:?} // Broadcasting commit consensus message {:?}",
@@ -183,7 +285,7 @@ where
 
             let targets = view.quorum_members().clone();
 
-            let _ = node.broadcast_signed(message, targets.into_iter());
+            self.broadcast_vote(node, message, targets);
         }
 
         debug!(
@@ -225,7 +327,7 @@ where
     RQ: SerType,
 {
     fn default() -> Self {
-        Self::new()
+        Self::new(&DecisionOptions::default())
     }
 }
 
@@ -233,9 +335,41 @@ impl<RQ> ReplicaAccessory<RQ>
 where
     RQ: SerType,
 {
-    pub fn new() -> Self {
+    pub fn new(options: &DecisionOptions) -> Self {
         Self {
             speculative_commits: Arc::new(Mutex::new(BTreeMap::new())),
+            usig: options.usig.clone(),
+            sign_votes: options.sign_votes,
+            mac_keys: options.mac_keys.clone(),
+        }
+    }
+
+    /// Broadcast one of our votes, authenticated with a MAC authenticator
+    /// if we have pairwise keys, or signed otherwise
+    fn broadcast_vote<NT>(&self, node: &NT, message: ConsensusMessage<RQ>, targets: Vec<NodeId>)
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let Some(mac_keys) = &self.mac_keys else {
+            let _ = node.broadcast_signed(PBFTMessage::Consensus(message), targets.into_iter());
+
+            return;
+        };
+
+        let seq = message.sequence_number();
+
+        match authenticate_vote(&**mac_keys, node.id(), targets.iter().copied(), message) {
+            Ok(message) => {
+                let _ = node.broadcast(PBFTMessage::Consensus(message), targets.into_iter());
+            }
+            Err(err) => {
+                error!(
+                    "{:?} // Failed to create the authenticator for vote {:?}: {:?}",
+                    node.id(),
+                    seq,
+                    err
+                );
+            }
         }
     }
 
@@ -0,0 +1,202 @@
+//! MAC authenticators for normal case votes, as in classic PBFT.
+//!
+//! Instead of signing every `PREPARE` and `COMMIT`, a replica attaches a vector with
+//! one MAC per recipient, each computed with the key it shares with that recipient.
+//! Authenticators are much cheaper than signatures, but they are not transferable:
+//! a third party can only check the entry that was addressed to itself.
+//! Messages which must be transferable (the `PRE-PREPARE`, `StopData` and `Sync`)
+//! are always signed.
+//! Decided proofs are only transferable when the votes also carry a vote signature
+//! (see [compact certificates](crate::bft::config::PBFTConfig::with_compact_certificates)),
+//! otherwise the replicas receiving them reject the proof.
+
+use std::fmt::Debug;
+
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::Orderable;
+use atlas_common::Err;
+
+use crate::bft::log::certificate::{vote_statement, VoteKind};
+use crate::bft::message::{ConsensusMessage, ConsensusMessageKind};
+
+/// The pairwise keys shared between this replica and its peers
+pub trait MacKeys: Send + Sync + Debug {
+    /// The key we share with the given peer
+    fn pairwise_key(&self, peer: NodeId) -> Option<Digest>;
+}
+
+/// Pairwise keys derived from a secret shared by all replicas.
+///
+/// This offers no protection against Byzantine replicas and is only meant for testing.
+#[derive(Debug)]
+pub struct SharedSecretMacKeys {
+    node_id: NodeId,
+    secret: Vec<u8>,
+}
+
+impl SharedSecretMacKeys {
+    pub fn new(node_id: NodeId, secret: Vec<u8>) -> Self {
+        Self { node_id, secret }
+    }
+}
+
+impl MacKeys for SharedSecretMacKeys {
+    fn pairwise_key(&self, peer: NodeId) -> Option<Digest> {
+        let (low, high) = if self.node_id <= peer {
+            (self.node_id, peer)
+        } else {
+            (peer, self.node_id)
+        };
+
+        let (low, high): (u64, u64) = (low.into(), high.into());
+
+        let mut ctx = Context::new();
+
+        ctx.update(&self.secret[..]);
+        ctx.update(&low.to_le_bytes()[..]);
+        ctx.update(&high.to_le_bytes()[..]);
+
+        Some(ctx.finish())
+    }
+}
+
+/// How a consensus message was authenticated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageAuthentication {
+    /// Signed with the sender's private key, so it can be forwarded to others
+    Signature,
+    /// Authenticated with a MAC vector, so it can only be checked by its recipients
+    Authenticator,
+}
+
+/// A vector of MACs, one per recipient of the message
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Authenticator {
+    macs: Vec<(NodeId, Digest)>,
+}
+
+impl Authenticator {
+    /// Create the authenticator for a statement we are sending to the given targets
+    pub fn create(
+        keys: &dyn MacKeys,
+        me: NodeId,
+        targets: impl IntoIterator<Item = NodeId>,
+        statement: &Digest,
+    ) -> Result<Self> {
+        let mut macs = Vec::new();
+
+        for target in targets {
+            let key = keys
+                .pairwise_key(target)
+                .ok_or(AuthenticatorError::MissingKey(target))?;
+
+            macs.push((target, mac(&key, me, statement)));
+        }
+
+        Ok(Self { macs })
+    }
+
+    /// Check the entry addressed to `me` in an authenticator sent by `from`
+    pub fn verify(
+        &self,
+        keys: &dyn MacKeys,
+        me: NodeId,
+        from: NodeId,
+        statement: &Digest,
+    ) -> Result<()> {
+        let (_, received) = self
+            .macs
+            .iter()
+            .find(|(target, _)| *target == me)
+            .ok_or(AuthenticatorError::NotARecipient(me, from))?;
+
+        let key = keys
+            .pairwise_key(from)
+            .ok_or(AuthenticatorError::MissingKey(from))?;
+
+        if mac(&key, from, statement) != *received {
+            return Err!(AuthenticatorError::InvalidMac(from));
+        }
+
+        Ok(())
+    }
+}
+
+#[inline]
+fn mac(key: &Digest, sender: NodeId, statement: &Digest) -> Digest {
+    let sender: u64 = sender.into();
+
+    let mut ctx = Context::new();
+
+    ctx.update(key.as_ref());
+    ctx.update(&sender.to_le_bytes()[..]);
+    ctx.update(statement.as_ref());
+
+    ctx.finish()
+}
+
+/// The statement which is authenticated for a given vote
+fn statement_of<O>(message: &ConsensusMessage<O>) -> Option<Digest> {
+    let (kind, digest) = match message.kind() {
+        ConsensusMessageKind::Prepare(digest) => (VoteKind::Prepare, digest),
+        ConsensusMessageKind::Commit(digest) => (VoteKind::Commit, digest),
+        ConsensusMessageKind::PrePrepare(_) | ConsensusMessageKind::SkipSlot(_) => return None,
+    };
+
+    Some(vote_statement(
+        kind,
+        message.sequence_number(),
+        message.view(),
+        digest,
+    ))
+}
+
+/// Attach an authenticator, for the given targets, to a prepare or commit message
+pub fn authenticate_vote<O>(
+    keys: &dyn MacKeys,
+    me: NodeId,
+    targets: impl IntoIterator<Item = NodeId>,
+    message: ConsensusMessage<O>,
+) -> Result<ConsensusMessage<O>> {
+    let statement = statement_of(&message).ok_or(AuthenticatorError::NotAVote)?;
+
+    let authenticator = Authenticator::create(keys, me, targets, &statement)?;
+
+    Ok(message.with_authenticator(authenticator))
+}
+
+/// Verify the authenticator of a prepare or commit message sent by `from`
+pub fn verify_vote_authenticator<O>(
+    keys: &dyn MacKeys,
+    me: NodeId,
+    from: NodeId,
+    message: &ConsensusMessage<O>,
+) -> Result<()> {
+    let statement = statement_of(message).ok_or(AuthenticatorError::NotAVote)?;
+
+    match message.authenticator() {
+        Some(authenticator) => authenticator.verify(keys, me, from, &statement),
+        None => Err!(AuthenticatorError::MissingAuthenticator(from)),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum AuthenticatorError {
+    #[error("Only prepare and commit messages can be authenticated with MACs")]
+    NotAVote,
+    #[error("There is no pairwise key for {0:?}")]
+    MissingKey(NodeId),
+    #[error("The vote from {0:?} does not carry an authenticator")]
+    MissingAuthenticator(NodeId),
+    #[error("{0:?} is not a recipient of the authenticator sent by {1:?}")]
+    NotARecipient(NodeId, NodeId),
+    #[error("Invalid MAC from {0:?}")]
+    InvalidMac(NodeId),
+}
@@ -1,30 +1,39 @@
-use std::collections::VecDeque;
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use atlas_common::Err;
-use chrono::Utc;
-use tracing::{debug, info, instrument, warn};
+use chrono::{DateTime, Utc};
+use tracing::{debug, error, info, instrument, warn};
 use thiserror::Error;
 
 use atlas_common::error::*;
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::serialization_helper::SerType;
-use atlas_communication::message::Header;
+use atlas_communication::message::{Header, StoredMessage};
+use atlas_communication::reconfiguration::NetworkInformationProvider;
 use atlas_core::messages::{ClientRqInfo, SessionBased};
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
 use atlas_core::ordering_protocol::networking::OrderProtocolSendNode;
 use atlas_core::ordering_protocol::ShareableMessage;
 use atlas_core::timeouts::timeout::TimeoutModHandle;
-use atlas_metrics::metrics::metric_duration;
+use atlas_core::timeouts::{TimeOutable, TimeoutID};
+use atlas_metrics::metrics::{metric_duration, metric_increment};
 
 use crate::bft::consensus::accessory::replica::ReplicaAccessory;
 use crate::bft::consensus::accessory::{AccessoryConsensus, ConsensusDecisionAccessory};
+use crate::bft::consensus::authenticator::{verify_vote_authenticator, MacKeys};
+use crate::bft::consensus::usig::{verify_message_ui, Usig};
+use crate::bft::evidence::{EquivocationDetector, MisbehaviourEvidence};
 use crate::bft::log::deciding::{CompletedBatch, WorkingDecisionLog};
+use crate::bft::log::certificate::{verify_vote, CertificateError};
 use crate::bft::log::decisions::{IncompleteProof, ProofMetadata};
 use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage};
-use crate::bft::metric::{ConsensusMetrics, PRE_PREPARE_ANALYSIS_ID};
+use crate::bft::metric::{ConsensusMetrics, FUTURE_MESSAGES_DROPPED_ID, PRE_PREPARE_ANALYSIS_ID};
+use crate::bft::queue::MESSAGES_PER_SLOT;
 use crate::bft::sync::view::ViewInfo;
 use crate::bft::sync::{AbstractSynchronizer, Synchronizer};
 use crate::bft::PBFT;
@@ -74,6 +83,8 @@ pub enum DecisionPollStatus<O> {
 pub enum DecisionStatus<O> {
     /// A particular node tried voting twice.
     VotedTwice(NodeId),
+    /// A particular node sent conflicting messages, which we have kept as evidence
+    Misbehaved(MisbehaviourEvidence<O>),
     // Returned when a node ignores a message
     MessageIgnored,
     /// The message has been queued for later execution
@@ -92,14 +103,65 @@ pub enum DecisionStatus<O> {
     /// The third is the messages that should be persisted for this batch to be considered persisted
     Decided(ShareableMessage<PBFTMessage<O>>),
     DecidedIgnored,
+    /// The message was processed, but did not progress the decision
+    MessageProcessed,
+    /// The slots of the silent leaders were filled with empty batches,
+    /// completing the pre prepare phase
+    PrePreparesFilled(ProofMetadata),
+}
+
+/// Optional behaviour shared by all the consensus decisions of a replica
+#[derive(Clone, Default)]
+pub struct DecisionOptions {
+    /// The trusted counter, used to create and verify the identifiers of
+    /// messages when running under the trusted counter fault model
+    pub usig: Option<Arc<dyn Usig>>,
+    /// How long we wait, after sending our prepare, for all replicas
+    /// to send matching prepares. `None` disables the fast path
+    pub fast_path_window: Option<Duration>,
+    /// Should we sign the vote statement of our prepares and commits,
+    /// so they can be compacted into quorum certificates
+    pub sign_votes: bool,
+    /// The pairwise keys used to authenticate votes with MACs instead of signatures
+    pub mac_keys: Option<Arc<dyn MacKeys>>,
+    /// How long we wait for the remaining leaders to send their pre prepare,
+    /// after receiving the first one, before voting to skip their slots.
+    /// `None` disables skipping slots
+    pub pre_prepare_slot_timeout: Option<Duration>,
+}
+
+/// The information attached to the timeout of the pre prepare slots of a decision
+#[derive(Debug, Clone)]
+pub struct PrePrepareSlotTimeout {
+    view: SeqNo,
+    seq: SeqNo,
+}
+
+impl TimeOutable for PrePrepareSlotTimeout {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Orderable for PrePrepareSlotTimeout {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
 }
 
 /// A message queue for this particular consensus instance
 pub struct MessageQueue<O> {
     get_queue: bool,
-    pre_prepares: VecDeque<ShareableMessage<PBFTMessage<O>>>,
-    prepares: VecDeque<ShareableMessage<PBFTMessage<O>>>,
-    commits: VecDeque<ShareableMessage<PBFTMessage<O>>>,
+    pre_prepares: SenderBoundedQueue<O>,
+    prepares: SenderBoundedQueue<O>,
+    commits: SenderBoundedQueue<O>,
+}
+
+/// The messages of one kind queued for a consensus instance, with at most
+/// [MESSAGES_PER_SLOT] of them from each sender
+struct SenderBoundedQueue<O> {
+    messages: VecDeque<ShareableMessage<PBFTMessage<O>>>,
+    queued: BTreeMap<NodeId, usize>,
 }
 
 /// The information needed to make a decision on a batch of requests.
@@ -118,19 +180,87 @@ where
     working_log: WorkingDecisionLog<RQ>,
     /// Accessory to the base consensus state machine
     accessory: ConsensusDecisionAccessory<RQ>,
+    /// The optional behaviour of this decision
+    options: DecisionOptions,
+    /// When we entered the preparing phase, used to bound the fast path window
+    preparing_since: Option<Instant>,
+    /// Detects replicas which send conflicting messages in this instance
+    equivocations: EquivocationDetector<RQ>,
     // Metrics about the consensus instance
     consensus_metrics: ConsensusMetrics,
     //TODO: Store things directly into the persistent log as well as delete them when
     // Things go wrong
 }
 
+impl<O> SenderBoundedQueue<O> {
+    fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            queued: BTreeMap::new(),
+        }
+    }
+
+    /// Queue the message, unless its sender has no more room
+    fn push_back(&mut self, message: ShareableMessage<PBFTMessage<O>>) -> bool {
+        let queued = self.queued.entry(message.header().from()).or_insert(0);
+
+        if *queued >= MESSAGES_PER_SLOT {
+            debug!(
+                "Dropping message from {:?} for Seq {:?}, as it already has {} messages of its kind queued",
+                message.header().from(),
+                message.message().sequence_number(),
+                queued
+            );
+
+            metric_increment(FUTURE_MESSAGES_DROPPED_ID, Some(1));
+
+            return false;
+        }
+
+        *queued += 1;
+
+        self.messages.push_back(message);
+
+        true
+    }
+
+    fn pop_front(&mut self) -> Option<ShareableMessage<PBFTMessage<O>>> {
+        let message = self.messages.pop_front()?;
+
+        if let Some(queued) = self.queued.get_mut(&message.header().from()) {
+            *queued = queued.saturating_sub(1);
+
+            if *queued == 0 {
+                self.queued.remove(&message.header().from());
+            }
+        }
+
+        Some(message)
+    }
+}
+
+impl<O> From<VecDeque<ShareableMessage<PBFTMessage<O>>>> for SenderBoundedQueue<O> {
+    fn from(messages: VecDeque<ShareableMessage<PBFTMessage<O>>>) -> Self {
+        let mut queue = Self::new();
+
+        // The messages were already bounded per sender by the queue they come from
+        for message in messages {
+            *queue.queued.entry(message.header().from()).or_insert(0) += 1;
+
+            queue.messages.push_back(message);
+        }
+
+        queue
+    }
+}
+
 impl<O> MessageQueue<O> {
     fn new() -> Self {
         Self {
             get_queue: false,
-            pre_prepares: Default::default(),
-            prepares: Default::default(),
-            commits: Default::default(),
+            pre_prepares: SenderBoundedQueue::new(),
+            prepares: SenderBoundedQueue::new(),
+            commits: SenderBoundedQueue::new(),
         }
     }
 
@@ -143,9 +273,9 @@ impl<O> MessageQueue<O> {
 
         Self {
             get_queue,
-            pre_prepares,
-            prepares,
-            commits,
+            pre_prepares: pre_prepares.into(),
+            prepares: prepares.into(),
+            commits: commits.into(),
         }
     }
 
@@ -158,21 +288,21 @@ impl<O> MessageQueue<O> {
     }
 
     fn queue_pre_prepare(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        self.pre_prepares.push_back(message);
-
-        self.signal();
+        if self.pre_prepares.push_back(message) {
+            self.signal();
+        }
     }
 
     fn queue_prepare(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        self.prepares.push_back(message);
-
-        self.signal();
+        if self.prepares.push_back(message) {
+            self.signal();
+        }
     }
 
     fn queue_commit(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        self.commits.push_back(message);
-
-        self.signal();
+        if self.commits.push_back(message) {
+            self.signal();
+        }
     }
 }
 
@@ -180,16 +310,13 @@ impl<RQ> ConsensusDecision<RQ>
 where
     RQ: SerType + SessionBased + 'static,
 {
-    pub fn init_decision(node_id: NodeId, seq_no: SeqNo, view: &ViewInfo) -> Self {
-        Self {
-            node_id,
-            seq: seq_no,
-            phase: DecisionPhase::Initialize,
-            message_queue: MessageQueue::new(),
-            working_log: WorkingDecisionLog::new(node_id, seq_no, view),
-            accessory: ConsensusDecisionAccessory::Replica(ReplicaAccessory::new()),
-            consensus_metrics: ConsensusMetrics::new(),
-        }
+    pub fn init_decision(
+        node_id: NodeId,
+        seq_no: SeqNo,
+        view: &ViewInfo,
+        options: DecisionOptions,
+    ) -> Self {
+        Self::init_with_msg_log(node_id, seq_no, view, MessageQueue::new(), options)
     }
 
     pub fn init_with_msg_log(
@@ -197,6 +324,7 @@ where
         seq_no: SeqNo,
         view: &ViewInfo,
         message_queue: MessageQueue<RQ>,
+        options: DecisionOptions,
     ) -> Self {
         Self {
             node_id,
@@ -204,14 +332,59 @@ where
             phase: DecisionPhase::Initialize,
             message_queue,
             working_log: WorkingDecisionLog::new(node_id, seq_no, view),
-            accessory: ConsensusDecisionAccessory::Replica(ReplicaAccessory::new()),
+            accessory: ConsensusDecisionAccessory::Replica(ReplicaAccessory::new(&options)),
+            options,
+            preparing_since: None,
+            equivocations: EquivocationDetector::new(),
             consensus_metrics: ConsensusMetrics::new(),
         }
     }
 
+    /// Verify the trusted counter identifier of a PrePrepare or Prepare message,
+    /// when the current fault model requires one.
+    fn verify_unique_identifier(
+        &self,
+        view: &ViewInfo,
+        header: &Header,
+        message: &ConsensusMessage<RQ>,
+    ) -> bool {
+        if !view.fault_model().requires_usig() {
+            return true;
+        }
+
+        let Some(usig) = &self.options.usig else {
+            error!(
+                "{:?} // Running under the trusted counter fault model without a USIG",
+                self.node_id
+            );
+
+            return false;
+        };
+
+        match verify_message_ui(&**usig, header.from(), message) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropped {:?} from {:?} due to an invalid unique identifier: {:?}",
+                    self.node_id,
+                    message,
+                    header.from(),
+                    err
+                );
+
+                false
+            }
+        }
+    }
+
     pub fn queue(&mut self, message: ShareableMessage<PBFTMessage<RQ>>) {
-        match message.message().consensus().kind() {
-            ConsensusMessageKind::PrePrepare(_) => {
+        // Messages of other kinds are filtered out before reaching the decisions
+        let Ok(consensus) = message.message().consensus() else {
+            return;
+        };
+
+        match consensus.kind() {
+            ConsensusMessageKind::PrePrepare(_) | ConsensusMessageKind::SkipSlot(_) => {
                 self.message_queue.queue_pre_prepare(message);
             }
             ConsensusMessageKind::Prepare(_) => {
@@ -248,6 +421,14 @@ where
                 )
             }
             DecisionPhase::Committing(_) if self.message_queue.get_queue => {
+                // Prepares which were left queued when we reached the prepare quorum
+                // can still complete the fast path
+                if self.options.fast_path_window.is_some() {
+                    if let Some(prepare) = self.message_queue.prepares.pop_front() {
+                        return DecisionPollStatus::NextMessage(prepare);
+                    }
+                }
+
                 extract_msg!(
                     DecisionPollStatus::Recv,
                     &mut self.message_queue.get_queue,
@@ -285,7 +466,24 @@ where
     {
         let view = synchronizer.view();
         let header = s_message.header();
-        let message = s_message.message().consensus();
+        let message = s_message.message().consensus()?;
+
+        if self.phase != DecisionPhase::Initialize
+            && message.sequence_number() == self.seq
+            && message.view() == view.sequence_number()
+        {
+            if let Some(evidence) = self.equivocations.check(&s_message) {
+                warn!(
+                    "{:?} // Replica {:?} sent conflicting messages in Seq {:?}: {:?}",
+                    self.node_id,
+                    header.from(),
+                    self.seq,
+                    evidence
+                );
+
+                return Ok(DecisionStatus::Misbehaved(evidence));
+            }
+        }
 
         return match self.phase {
             DecisionPhase::Initialize => {
@@ -301,7 +499,10 @@ where
                 return Ok(DecisionStatus::MessageQueued);
             }
             DecisionPhase::PrePreparing(received) => {
-                let received = match message.kind() {
+                let (received, requests) = match message.kind() {
+                    ConsensusMessageKind::SkipSlot(leader) => {
+                        return self.process_skip_slot(*leader, &s_message, &view, node);
+                    }
                     ConsensusMessageKind::PrePrepare(_)
                         if message.view() != view.sequence_number() =>
                     {
@@ -359,14 +560,43 @@ where
 
                         return Ok(DecisionStatus::MessageQueued);
                     }
-                    ConsensusMessageKind::PrePrepare(_) => {
+                    ConsensusMessageKind::PrePrepare(_)
+                        if self.working_log.slot_skipped(&header.from()) =>
+                    {
+                        // This leader's slot was already filled with an empty batch
+                        debug!("{:?} // Dropped {:?} from {:?} because its slot was skipped",
+                            self.node_id, message, header.from());
+
+                        return Ok(DecisionStatus::MessageIgnored);
+                    }
+                    ConsensusMessageKind::PrePrepare(_)
+                        if self.working_log.skip_pending(&header.from()) =>
+                    {
+                        // We voted to skip this leader's slot, so we hold its pre prepare
+                        // back until we know whether the skip gathers a quorum
+                        debug!("{:?} // Holding back {:?} from {:?} because we voted to skip its slot",
+                            self.node_id, message, header.from());
+
+                        self.working_log
+                            .withhold_pre_prepare(header.from(), s_message);
+
+                        return Ok(DecisionStatus::MessageQueued);
+                    }
+                    ConsensusMessageKind::PrePrepare(_)
+                        if !self.verify_unique_identifier(&view, header, message) =>
+                    {
+                        return Ok(DecisionStatus::MessageIgnored);
+                    }
+                    ConsensusMessageKind::PrePrepare(requests) => {
                         // Everything checks out, we can now process the message
-                        received + 1
+                        (received + 1, requests)
                     }
                 };
 
                 if received == 1 {
                     self.consensus_metrics.first_pre_prepare_recvd();
+
+                    self.watch_pre_prepare_slots(&view, timeouts);
                 }
 
                 let pre_prepare_received_time = Utc::now();
@@ -374,7 +604,7 @@ where
                 //TODO: Try out cloning each request on this method,
                 let digests = request_batch_received(
                     header,
-                    message,
+                    requests,
                     timeouts,
                     synchronizer,
                     &self.working_log,
@@ -388,37 +618,18 @@ where
 
                 let result;
 
-                self.phase = if received == view.leader_set().len() {
-                    let batch_metadata = batch_metadata.unwrap();
-
+                self.phase = if let Some(batch_metadata) = batch_metadata {
                     info!("{:?} // Completed pre prepare phase with all pre prepares Seq {:?} with pre prepare from {:?}. Batch size {:?}",
                         node.id(), self.sequence_number(), header.from(), self.working_log.current_batch_size());
 
-                    //We have received all pre prepare requests for this consensus instance
-                    //We are now ready to broadcast our prepare message and move to the next phase
-                    {
-                        //Update batch meta
-                        let mut meta_guard = self.working_log.batch_meta().lock().unwrap();
-
-                        meta_guard.prepare_sent_time = Utc::now();
-                        meta_guard.pre_prepare_received_time = pre_prepare_received_time;
-                    }
-
-                    self.consensus_metrics
-                        .all_pre_prepares_recvd(self.working_log.current_batch_size());
-
-                    let _current_digest = batch_metadata.batch_digest();
-
-                    self.accessory.handle_pre_prepare_phase_completed(
-                        &self.working_log,
+                    self.complete_pre_prepare_phase(
+                        pre_prepare_received_time,
                         &view,
                         header,
                         message,
                         node,
                     );
 
-                    self.message_queue.signal();
-
                     // Mark that we have transitioned to the next phase
                     result = DecisionStatus::Transitioned(Some(batch_metadata), s_message);
 
@@ -453,6 +664,13 @@ where
 
                         return Ok(DecisionStatus::MessageIgnored);
                     }
+                    ConsensusMessageKind::SkipSlot(_) => {
+                        // The pre prepare phase is already complete, this is just a late vote
+                        debug!("{:?} // Dropped skip slot vote {:?} from {:?} because we are in the preparing phase",
+                            self.node_id, message, header.from());
+
+                        return Ok(DecisionStatus::MessageIgnored);
+                    }
                     ConsensusMessageKind::Commit(_d) => {
                         debug!(
                             "{:?} // Received {:?} from {:?} while in preparing phase",
@@ -498,6 +716,12 @@ where
 
                         return Ok(DecisionStatus::MessageIgnored);
                     }
+                    ConsensusMessageKind::Prepare(_)
+                        if !self.verify_unique_identifier(&view, header, message)
+                            || !self.verify_vote_authentication(header, message, node) =>
+                    {
+                        return Ok(DecisionStatus::MessageIgnored);
+                    }
                     ConsensusMessageKind::Prepare(_) => {
                         // Everything checks out, we can now process the message
                         received + 1
@@ -510,9 +734,37 @@ where
 
                 self.working_log.process_message(s_message.clone())?;
 
+                if self.fast_path_reached(&view) {
+                    info!("{:?} // Received matching prepares from all replicas for Seq {:?}, deciding on the fast path", node.id(), self.sequence_number());
+
+                    self.decide_on_fast_path();
+
+                    return Ok(DecisionStatus::Decided(s_message));
+                }
+
+                if !view.fault_model().requires_commit_phase()
+                    && view.is_quorum(self.working_log.prepare_voters())
+                {
+                    // Under the crash and trusted counter fault models, a quorum of matching
+                    // prepares is enough to decide, so we skip the commit phase entirely
+                    info!("{:?} // Completed prepare phase with all prepares Seq {:?}, deciding without a commit phase", node.id(), self.sequence_number());
+
+                    self.phase = DecisionPhase::Decided;
+
+                    self.working_log
+                        .batch_meta()
+                        .lock()
+                        .unwrap()
+                        .consensus_decision_time = Utc::now();
+
+                    self.consensus_metrics.prepare_quorum_recvd();
+
+                    return Ok(DecisionStatus::Decided(s_message));
+                }
+
                 let result;
 
-                self.phase = if received == view.params().quorum() {
+                self.phase = if view.is_quorum(self.working_log.prepare_voters()) {
                     info!("{:?} // Completed prepare phase with all prepares Seq {:?} with prepare from {:?}", node.id(), self.sequence_number(), header.from());
 
                     self.working_log
@@ -595,7 +847,38 @@ where
 
                         return Ok(DecisionStatus::MessageIgnored);
                     }
+                    ConsensusMessageKind::Commit(_)
+                        if !self.verify_vote_authentication(header, message, node) =>
+                    {
+                        return Ok(DecisionStatus::MessageIgnored);
+                    }
                     ConsensusMessageKind::Commit(_) => received + 1,
+                    ConsensusMessageKind::Prepare(d) if self.options.fast_path_window.is_some() => {
+                        if message.sequence_number() != self.seq
+                            || message.view() != view.sequence_number()
+                            || *d != self.working_log.current_digest().unwrap()
+                            || !self.verify_vote_authentication(header, message, node)
+                        {
+                            warn!("{:?} // Dropped late prepare message {:?} from {:?} as it does not match our decision",
+                                self.node_id, message, header.from());
+
+                            return Ok(DecisionStatus::MessageIgnored);
+                        }
+
+                        // Late prepares are still relevant for the fast path, while
+                        // the commit round runs in parallel as the fallback
+                        self.working_log.process_message(s_message.clone())?;
+
+                        if self.fast_path_reached(&view) {
+                            info!("{:?} // Received matching prepares from all replicas for Seq {:?}, deciding on the fast path", node.id(), self.sequence_number());
+
+                            self.decide_on_fast_path();
+
+                            return Ok(DecisionStatus::Decided(s_message));
+                        }
+
+                        return Ok(DecisionStatus::Deciding(s_message));
+                    }
                     _ => {
                         // Any message relating to any other phase other than commit is not accepted
 
@@ -609,7 +892,7 @@ where
 
                 self.working_log.process_message(s_message.clone())?;
 
-                return if received == view.params().quorum() {
+                return if view.is_quorum(self.working_log.commit_voters()) {
                     info!("{:?} // Completed commit phase with all commits Seq {:?} with commit from {:?}", node.id(), self.sequence_number(),
                     header.from());
 
@@ -661,6 +944,281 @@ where
         };
     }
 
+    /// Complete the pre prepare phase, broadcasting our prepare for the batch
+    fn complete_pre_prepare_phase<NT>(
+        &mut self,
+        pre_prepare_received_time: DateTime<Utc>,
+        view: &ViewInfo,
+        header: &Header,
+        message: &ConsensusMessage<RQ>,
+        node: &Arc<NT>,
+    ) where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
+    {
+        //We have received all pre prepare requests for this consensus instance
+        //We are now ready to broadcast our prepare message and move to the next phase
+        {
+            //Update batch meta
+            let mut meta_guard = self.working_log.batch_meta().lock().unwrap();
+
+            meta_guard.prepare_sent_time = Utc::now();
+            meta_guard.pre_prepare_received_time = pre_prepare_received_time;
+        }
+
+        self.consensus_metrics
+            .all_pre_prepares_recvd(self.working_log.current_batch_size());
+
+        self.accessory.handle_pre_prepare_phase_completed(
+            &self.working_log,
+            view,
+            header,
+            message,
+            node,
+        );
+
+        self.message_queue.signal();
+
+        self.preparing_since = Some(Instant::now());
+    }
+
+    /// Start the timeout for the remaining leaders to send their pre prepares
+    fn watch_pre_prepare_slots(&self, view: &ViewInfo, timeouts: &TimeoutModHandle) {
+        let Some(slot_timeout) = self.options.pre_prepare_slot_timeout else {
+            return;
+        };
+
+        if view.leader_set().len() <= 1 {
+            // A single silent leader is dealt with by the view change
+            return;
+        }
+
+        let info = PrePrepareSlotTimeout {
+            view: view.sequence_number(),
+            seq: self.seq,
+        };
+
+        let _ = timeouts.request_timeouts(
+            vec![(
+                TimeoutID::SessionBased {
+                    session: view.sequence_number(),
+                    seq_no: self.seq,
+                    from: self.node_id,
+                },
+                Some(Box::new(info) as Box<dyn TimeOutable>),
+            )],
+            slot_timeout,
+            1,
+            false,
+        );
+    }
+
+    /// The pre prepare slot timeout of this decision has expired, so we vote to
+    /// fill the slots of the leaders which are still silent with empty batches.
+    ///
+    /// If the timeout expires again while a slot we voted to skip is still empty, the skip
+    /// did not gather a quorum, so we accept the pre prepare we held back from that leader
+    pub fn pre_prepare_slots_timed_out<NT>(
+        &mut self,
+        timeout: &PrePrepareSlotTimeout,
+        view: &ViewInfo,
+        timeouts: &TimeoutModHandle,
+        node: &Arc<NT>,
+    ) where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
+    {
+        if timeout.view != view.sequence_number()
+            || !matches!(self.phase, DecisionPhase::PrePreparing(_))
+        {
+            return;
+        }
+
+        let mut pending_skips = false;
+
+        for leader in self.working_log.missing_leaders() {
+            if self.working_log.skip_pending(&leader) {
+                warn!(
+                    "{:?} // The vote to skip the slot of {:?} for Seq {:?} did not gather a quorum",
+                    self.node_id, leader, self.seq
+                );
+
+                if let Some(pre_prepare) = self.working_log.abandon_skip(&leader) {
+                    self.message_queue.queue_pre_prepare(pre_prepare);
+                }
+
+                continue;
+            }
+
+            warn!(
+                "{:?} // Leader {:?} did not send its pre prepare for Seq {:?} in time, voting to skip its slot",
+                self.node_id, leader, self.seq
+            );
+
+            self.working_log.vote_to_skip(leader);
+
+            pending_skips = true;
+
+            let message = PBFTMessage::Consensus(ConsensusMessage::new(
+                self.seq,
+                view.sequence_number(),
+                ConsensusMessageKind::SkipSlot(leader),
+            ));
+
+            let _ = node.broadcast_signed(message, view.quorum_members().clone().into_iter());
+        }
+
+        if pending_skips {
+            // Give the skip votes a slot timeout to gather a quorum
+            self.watch_pre_prepare_slots(view, timeouts);
+        }
+    }
+
+    /// Process a vote to fill the slot of a silent leader with an empty batch
+    fn process_skip_slot<NT>(
+        &mut self,
+        leader: NodeId,
+        s_message: &ShareableMessage<PBFTMessage<RQ>>,
+        view: &ViewInfo,
+        node: &Arc<NT>,
+    ) -> Result<DecisionStatus<RQ>>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
+    {
+        let (header, message) = (s_message.header(), s_message.message().consensus()?);
+
+        if message.view() != view.sequence_number()
+            || message.sequence_number() != self.seq
+            || !view.quorum_members().contains(&header.from())
+        {
+            debug!(
+                "{:?} // Dropped skip slot vote {:?} from {:?} as it does not match our decision",
+                self.node_id,
+                message,
+                header.from()
+            );
+
+            return Ok(DecisionStatus::MessageIgnored);
+        }
+
+        let Some(batch_metadata) =
+            self.working_log
+                .process_skip_vote(header.from(), leader, view)?
+        else {
+            return Ok(DecisionStatus::MessageProcessed);
+        };
+
+        info!("{:?} // Completed pre prepare phase Seq {:?} by skipping the slot of {:?}. Batch size {:?}",
+            node.id(), self.sequence_number(), leader, self.working_log.current_batch_size());
+
+        self.complete_pre_prepare_phase(Utc::now(), view, header, message, node);
+
+        self.phase = DecisionPhase::Preparing(0);
+
+        Ok(DecisionStatus::PrePreparesFilled(batch_metadata))
+    }
+
+    /// Verify the MAC authenticator of a prepare or commit, when votes
+    /// are authenticated with MACs, and its vote signature, when votes are signed
+    fn verify_vote_authentication<NT>(
+        &self,
+        header: &Header,
+        message: &ConsensusMessage<RQ>,
+        node: &Arc<NT>,
+    ) -> bool
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
+    {
+        if self.options.sign_votes && !self.verify_vote_signature(header, message, node) {
+            return false;
+        }
+
+        let Some(mac_keys) = &self.options.mac_keys else {
+            return true;
+        };
+
+        match verify_vote_authenticator(&**mac_keys, self.node_id, header.from(), message) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropped {:?} from {:?} due to an invalid authenticator: {:?}",
+                    self.node_id,
+                    message,
+                    header.from(),
+                    err
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Verify the vote signature of a prepare or commit, when votes are signed
+    /// so they can be compacted into quorum certificates.
+    /// A single invalid signature would otherwise make the whole certificate invalid
+    fn verify_vote_signature<NT>(
+        &self,
+        header: &Header,
+        message: &ConsensusMessage<RQ>,
+        node: &Arc<NT>,
+    ) -> bool
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
+    {
+        let result = match node.network_info_provider().get_node_info(&header.from()) {
+            Some(info) => verify_vote(info.public_key(), header.from(), message),
+            None => Err!(CertificateError::UnknownSigner(header.from())),
+        };
+
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropped {:?} from {:?} due to an invalid vote signature: {:?}",
+                    self.node_id,
+                    message,
+                    header.from(),
+                    err
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Check if all the replicas have sent us matching prepares within
+    /// the fast path window, in which case we don't need to wait for the commit round
+    fn fast_path_reached(&self, view: &ViewInfo) -> bool {
+        let Some(window) = self.options.fast_path_window else {
+            return false;
+        };
+
+        // Without a commit phase there is nothing to skip
+        if !view.fault_model().requires_commit_phase() {
+            return false;
+        }
+
+        let within_window = self
+            .preparing_since
+            .is_some_and(|since| since.elapsed() <= window);
+
+        within_window
+            && view
+                .quorum_members()
+                .iter()
+                .all(|member| self.working_log.prepare_voters().contains(member))
+    }
+
+    fn decide_on_fast_path(&mut self) {
+        self.phase = DecisionPhase::Decided;
+
+        self.working_log
+            .batch_meta()
+            .lock()
+            .unwrap()
+            .consensus_decision_time = Utc::now();
+
+        self.consensus_metrics.fast_path_decided();
+    }
+
     /// Check if this consensus decision can be finalized
     pub fn is_finalizeable(&self) -> bool {
         matches!(self.phase, DecisionPhase::Decided)
@@ -684,13 +1242,17 @@ where
         }
     }
 
-    pub fn deciding(&self, f: usize) -> IncompleteProof {
-        self.working_log.deciding(f)
+    pub fn deciding(&self, prepared_quorum: usize) -> IncompleteProof {
+        self.working_log.deciding(prepared_quorum)
     }
 
     pub fn phase(&self) -> &DecisionPhase {
         &self.phase
     }
+
+    pub fn metrics(&self) -> &ConsensusMetrics {
+        &self.consensus_metrics
+    }
 }
 
 impl<RQ> Orderable for ConsensusDecision<RQ>
@@ -705,7 +1267,7 @@ where
 #[inline]
 fn request_batch_received<RQ>(
     header: &Header,
-    pre_prepare: &ConsensusMessage<RQ>,
+    requests: &[StoredMessage<RQ>],
     timeouts: &TimeoutModHandle,
     synchronizer: &Synchronizer<RQ>,
     log: &WorkingDecisionLog<RQ>,
@@ -717,17 +1279,12 @@ where
 
     let mut batch_guard = log.batch_meta().lock().unwrap();
 
-    batch_guard.batch_size += match pre_prepare.kind() {
-        ConsensusMessageKind::PrePrepare(req) => req.len(),
-        _ => {
-            panic!("Wrong message type provided")
-        }
-    };
+    batch_guard.batch_size += requests.len();
 
     batch_guard.reception_time = Utc::now();
 
     // Notify the synchronizer that a batch has been received
-    let digests = synchronizer.request_batch_received(header, pre_prepare, timeouts);
+    let digests = synchronizer.request_batch_received(header, requests, timeouts);
 
     metric_duration(PRE_PREPARE_ANALYSIS_ID, start.elapsed());
 
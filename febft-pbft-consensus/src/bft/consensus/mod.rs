@@ -12,42 +12,51 @@ use atlas_common::error::*;
 use atlas_common::globals::ReadOnly;
 use atlas_common::maybe_vec::MaybeVec;
 use atlas_common::node_id::NodeId;
-use atlas_common::ordering::{
-    tbo_advance_message_queue, tbo_advance_message_queue_return, tbo_queue_message_arc, Orderable,
-    SeqNo,
-};
+use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::serialization_helper::SerType;
+use atlas_common::Err;
 use atlas_communication::message::{Header, StoredMessage};
 use atlas_core::messages::{ClientRqInfo, SessionBased};
 use atlas_core::ordering_protocol::networking::OrderProtocolSendNode;
 use atlas_core::ordering_protocol::{Decision, ShareableMessage};
-use atlas_core::timeouts::timeout::TimeoutModHandle;
+use atlas_core::timeouts::timeout::{ModTimeout, TimeoutModHandle};
 use atlas_metrics::metrics::metric_increment;
 
 use crate::bft::consensus::decision::{
-    ConsensusDecision, DecisionPollStatus, DecisionStatus, MessageQueue,
+    ConsensusDecision, DecisionOptions, DecisionPollStatus, DecisionStatus, MessageQueue,
+    PrePrepareSlotTimeout,
 };
+use crate::bft::consensus::usig::{consensus_message_digest, UiOrder, UiSequencer, UsigError};
+use crate::bft::evidence::MisbehaviourEvidence;
 use crate::bft::log::deciding::CompletedBatch;
-use crate::bft::log::decisions::{IncompleteProof, Proof, ProofMetadata};
+use crate::bft::log::decisions::{IncompleteProof, Proof, ProofMetadata, ViewDecisionPair};
 use crate::bft::log::Log;
+use crate::bft::message::verification::{batch_verification, BatchVerification};
 use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage};
-use crate::bft::metric::OPERATIONS_ORDERED_ID;
+use crate::bft::queue::{BoundedTboQueue, QueueBounds};
+use crate::bft::metric::{ConsensusMetrics, MALFORMED_MESSAGES_DROPPED_ID, OPERATIONS_ORDERED_ID};
 use crate::bft::sync::view::ViewInfo;
 use crate::bft::sync::Synchronizer;
 use crate::bft::{OPDecision, SysMsg, PBFT};
 
 pub mod accessory;
+pub mod authenticator;
 pub mod decision;
+pub mod usig;
 
 #[derive(Debug)]
 /// Status returned from processing a consensus message.
 pub enum ConsensusStatus<O> {
     /// A particular node tried voting twice.
     VotedTwice(NodeId),
+    /// A particular node sent conflicting messages
+    Misbehaved(MisbehaviourEvidence<O>),
     /// The message has been ignored
     MessageIgnored,
     /// The message has been queued
     MessageQueued,
+    /// The message has been processed, but no decision has progressed
+    MessageProcessed,
     /// A `febft` quorum still hasn't made a decision
     /// on a client request to be executed.
     Deciding(MaybeVec<OPDecision<O>>),
@@ -82,9 +91,9 @@ pub struct TboQueue<O> {
     curr_seq: SeqNo,
     watermark: u32,
     get_queue: bool,
-    pre_prepares: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
-    prepares: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
-    commits: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
+    pre_prepares: BoundedTboQueue<PBFTMessage<O>>,
+    prepares: BoundedTboQueue<PBFTMessage<O>>,
+    commits: BoundedTboQueue<PBFTMessage<O>>,
 }
 
 impl<O> Orderable for TboQueue<O> {
@@ -96,13 +105,15 @@ impl<O> Orderable for TboQueue<O> {
 
 impl<O> TboQueue<O> {
     fn new(curr_seq: SeqNo, watermark: u32) -> Self {
+        let bounds = QueueBounds::for_instances(watermark);
+
         Self {
             curr_seq,
             watermark,
             get_queue: false,
-            pre_prepares: VecDeque::new(),
-            prepares: VecDeque::new(),
-            commits: VecDeque::new(),
+            pre_prepares: BoundedTboQueue::new(bounds),
+            prepares: BoundedTboQueue::new(bounds),
+            commits: BoundedTboQueue::new(bounds),
         }
     }
 
@@ -119,10 +130,9 @@ impl<O> TboQueue<O> {
     fn advance_queue(&mut self) -> MessageQueue<O> {
         self.curr_seq = self.curr_seq.next();
 
-        let pre_prepares =
-            tbo_advance_message_queue_return(&mut self.pre_prepares).unwrap_or_default();
-        let prepares = tbo_advance_message_queue_return(&mut self.prepares).unwrap_or_default();
-        let commits = tbo_advance_message_queue_return(&mut self.commits).unwrap_or_default();
+        let pre_prepares = self.pre_prepares.advance();
+        let prepares = self.prepares.advance();
+        let commits = self.commits.advance();
 
         MessageQueue::from_messages(pre_prepares, prepares, commits)
     }
@@ -130,16 +140,24 @@ impl<O> TboQueue<O> {
     /// Advances the message queue, and updates the consensus instance id.
     fn next_instance_queue(&mut self) {
         self.curr_seq = self.curr_seq.next();
-        tbo_advance_message_queue(&mut self.pre_prepares);
-        tbo_advance_message_queue(&mut self.prepares);
-        tbo_advance_message_queue(&mut self.commits);
+        self.pre_prepares.advance();
+        self.prepares.advance();
+        self.commits.advance();
     }
 
     /// Queues a consensus message for later processing, or drops it
     /// immediately if it pertains to an older consensus instance.
     pub fn queue(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        match message.message().consensus().kind() {
-            ConsensusMessageKind::PrePrepare(_) => self.queue_pre_prepare(message),
+        // Messages of other kinds are filtered out before reaching the queues
+        let Ok(consensus) = message.message().consensus() else {
+            return;
+        };
+
+        match consensus.kind() {
+            // Skip slot votes belong to the pre prepare phase of the instance
+            ConsensusMessageKind::PrePrepare(_) | ConsensusMessageKind::SkipSlot(_) => {
+                self.queue_pre_prepare(message)
+            }
             ConsensusMessageKind::Prepare(_) => self.queue_prepare(message),
             ConsensusMessageKind::Commit(_) => self.queue_commit(message),
         }
@@ -148,31 +166,19 @@ impl<O> TboQueue<O> {
     /// Queues a `PRE-PREPARE` message for later processing, or drops it
     /// immediately if it pertains to an older consensus instance.
     fn queue_pre_prepare(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        tbo_queue_message_arc(
-            self.base_seq(),
-            &mut self.pre_prepares,
-            (message.sequence_number(), message),
-        )
+        self.pre_prepares.queue_seq(self.base_seq(), message);
     }
 
     /// Queues a `PREPARE` message for later processing, or drops it
     /// immediately if it pertains to an older consensus instance.
     fn queue_prepare(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        tbo_queue_message_arc(
-            self.base_seq(),
-            &mut self.prepares,
-            (message.sequence_number(), message),
-        )
+        self.prepares.queue_seq(self.base_seq(), message);
     }
 
     /// Queues a `COMMIT` message for later processing, or drops it
     /// immediately if it pertains to an older consensus instance.
     fn queue_commit(&mut self, message: ShareableMessage<PBFTMessage<O>>) {
-        tbo_queue_message_arc(
-            self.base_seq(),
-            &mut self.commits,
-            (message.sequence_number(), message),
-        )
+        self.commits.queue_seq(self.base_seq(), message);
     }
 
     /// Clear this queue
@@ -219,7 +225,14 @@ where
     tbo_queue: TboQueue<RQ>,
     /// This queue serves for us to keep track of messages we receive of coming up views.
     /// This is important for us to be able to continue the process of moving views after a view change
-    view_queue: VecDeque<Vec<ShareableMessage<PBFTMessage<RQ>>>>,
+    view_queue: BoundedTboQueue<PBFTMessage<RQ>>,
+    /// Orders the messages of each replica by their trusted counter, when the fault model requires one
+    ui_sequencer: UiSequencer<ShareableMessage<PBFTMessage<RQ>>>,
+    /// The view change messages released by the trusted counter order, waiting to be
+    /// handed to the synchronizer
+    released_view_changes: Vec<ShareableMessage<PBFTMessage<RQ>>>,
+    /// The pre prepares whose requests are still being verified in the background
+    awaiting_verification: Vec<ShareableMessage<PBFTMessage<RQ>>>,
     /// The consensus guard that will be used to ensure that the proposer only proposes one batch
     /// for each consensus instance
     consensus_guard: Arc<ProposerConsensusGuard>,
@@ -227,6 +240,8 @@ where
     timeouts: TimeoutModHandle,
     /// Check if we are currently recovering from a fault, meaning we should ignore timeouts
     is_recovering: bool,
+    /// The optional behaviour of the consensus decisions
+    decision_options: DecisionOptions,
 }
 
 impl<RQ> Consensus<RQ>
@@ -240,6 +255,7 @@ where
         watermark: u32,
         consensus_guard: Arc<ProposerConsensusGuard>,
         timeouts: TimeoutModHandle,
+        decision_options: DecisionOptions,
     ) -> Self {
         let mut curr_seq = seq_no;
 
@@ -251,15 +267,25 @@ where
             curr_view: view.clone(),
             decisions: VecDeque::with_capacity(watermark as usize),
             tbo_queue: TboQueue::new(seq_no, watermark),
-            view_queue: VecDeque::with_capacity(watermark as usize),
+            // A sender can send a message of each kind for each instance in the window
+            view_queue: BoundedTboQueue::new(QueueBounds::for_views(3 * watermark as usize)),
+            ui_sequencer: UiSequencer::new(node_id),
+            released_view_changes: Vec::new(),
+            awaiting_verification: Vec::new(),
             consensus_guard,
             timeouts,
             is_recovering: false,
+            decision_options,
         };
 
         // Initialize the consensus instances
         for _ in 0..watermark {
-            let decision = ConsensusDecision::init_decision(node_id, curr_seq, view);
+            let decision = ConsensusDecision::init_decision(
+                node_id,
+                curr_seq,
+                view,
+                consensus.decision_options.clone(),
+            );
 
             consensus.enqueue_decision(decision);
 
@@ -272,12 +298,187 @@ where
     /// Queue a given message into our message queues.
     #[instrument(skip(self), level = "debug")]
     pub fn queue(&mut self, message: ShareableMessage<PBFTMessage<RQ>>) {
-        let message_seq = message.message().sequence_number();
+        match self.sequence(message) {
+            UiOrder::Deliver(message, released) => {
+                self.queue_in_order(message);
+
+                released
+                    .into_iter()
+                    .for_each(|released| self.queue_in_order(released));
+            }
+            UiOrder::Held => {}
+            UiOrder::Rejected(released) => released
+                .into_iter()
+                .for_each(|released| self.queue_in_order(released)),
+        }
+    }
+
+    /// Queue the pre prepares whose requests were verified since we last checked
+    fn resume_verified_batches(&mut self) {
+        if self.awaiting_verification.is_empty() {
+            return;
+        }
+
+        for message in std::mem::take(&mut self.awaiting_verification) {
+            match batch_verification(self.node_id, message.header().digest()) {
+                BatchVerification::Running => self.awaiting_verification.push(message),
+                BatchVerification::Verified => self.queue_in_order(message),
+                BatchVerification::Failed(err) => {
+                    warn!("{:?} // Dropping pre prepare {:?} from {:?} as one of its requests failed verification: {:?}",
+                        self.node_id, message.message(), message.header().from(), err);
+                }
+            }
+        }
+    }
+
+    /// Check the order of the trusted counter of a message, when the fault model requires one
+    fn sequence(
+        &mut self,
+        message: ShareableMessage<PBFTMessage<RQ>>,
+    ) -> UiOrder<ShareableMessage<PBFTMessage<RQ>>> {
+        if !self.curr_view.fault_model().requires_usig() {
+            return UiOrder::Deliver(message, Vec::new());
+        }
+
+        // Without a USIG (or a consensus message), the decision will reject the message
+        let (Some(usig), Ok(consensus)) = (
+            self.decision_options.usig.clone(),
+            message.message().consensus(),
+        ) else {
+            return UiOrder::Deliver(message, Vec::new());
+        };
+
+        let from = message.header().from();
+
+        match self
+            .ui_sequencer
+            .submit(&*usig, from, consensus, message.clone())
+        {
+            Ok(UiOrder::Held) => {
+                debug!(
+                    "{:?} // Holding message {:?} from {:?} until the counters before it arrive",
+                    self.node_id, message, from
+                );
+
+                UiOrder::Held
+            }
+            Ok(order) => order,
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropped {:?} from {:?} due to its unique identifier: {:?}",
+                    self.node_id, message, from, err
+                );
+
+                UiOrder::Rejected(Vec::new())
+            }
+        }
+    }
+
+    /// Check the order of the trusted counter of a view change message, when the fault model requires one.
+    ///
+    /// Returns the message if it can be processed right away. The messages it released
+    /// are queued, see [Self::take_released_view_changes]
+    pub fn sequence_view_change(
+        &mut self,
+        message: ShareableMessage<PBFTMessage<RQ>>,
+    ) -> Option<ShareableMessage<PBFTMessage<RQ>>> {
+        if !self.curr_view.fault_model().requires_usig() {
+            return Some(message);
+        }
+
+        let (Some(usig), PBFTMessage::ViewChange(view_change)) =
+            (self.decision_options.usig.clone(), message.message())
+        else {
+            return Some(message);
+        };
+
+        let from = message.header().from();
+
+        let (message, released) = match self.ui_sequencer.submit_view_change(
+            &*usig,
+            from,
+            view_change,
+            message.clone(),
+        ) {
+            Ok(UiOrder::Deliver(message, released)) => (Some(message), released),
+            Ok(UiOrder::Held) => {
+                debug!("{:?} // Holding view change message {:?} from {:?} until the counters before it arrive",
+                        self.node_id, message, from);
+
+                (None, Vec::new())
+            }
+            Ok(UiOrder::Rejected(released)) => (None, released),
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropped {:?} from {:?} due to its unique identifier: {:?}",
+                    self.node_id, message, from, err
+                );
+
+                (None, Vec::new())
+            }
+        };
+
+        released
+            .into_iter()
+            .for_each(|released| self.queue_in_order(released));
 
-        let view_seq = message.message().consensus().view();
+        message
+    }
+
+    /// Take the view change messages which were released by the trusted counter order
+    /// of the messages we have received since the last call
+    pub fn take_released_view_changes(&mut self) -> Vec<ShareableMessage<PBFTMessage<RQ>>> {
+        std::mem::take(&mut self.released_view_changes)
+    }
+
+    /// Check the unique identifier of a collect which the leader of a view forwarded to us.
+    ///
+    /// We are not waiting for the collect, so if we are missing messages sent before it we
+    /// resynchronize with its counter, as the view change leaves their views behind
+    pub fn verify_collect(&mut self, collect: &StoredMessage<PBFTMessage<RQ>>) -> Result<()> {
+        let Some(usig) = self.decision_options.usig.clone() else {
+            return Err!(UsigError::NoUsig);
+        };
+
+        let view_change = collect.message().view_change()?;
+
+        self.ui_sequencer
+            .deliver_forwarded(&*usig, collect.header().from(), view_change)?
+            .into_iter()
+            .for_each(|released| self.queue_in_order(released));
+
+        Ok(())
+    }
+
+    /// The values `from` wrote in the prepares it bound to its trusted counter for the given instance
+    pub fn written_by(&self, from: NodeId, seq: SeqNo) -> Vec<ViewDecisionPair> {
+        self.ui_sequencer.written(from, seq)
+    }
+
+    /// Queue a message which is in the order of its trusted counter
+    fn queue_in_order(&mut self, message: ShareableMessage<PBFTMessage<RQ>>) {
+        if let PBFTMessage::ViewChange(_) = message.message() {
+            self.released_view_changes.push(message);
+
+            return;
+        }
+
+        let message_seq = message.message().sequence_number();
 
         let header = message.header();
 
+        let view_seq = match message.message().consensus() {
+            Ok(consensus) => consensus.view(),
+            Err(err) => {
+                warn!("{:?} // Dropping message {:?} from {:?} as it cannot be queued in the consensus: {:?}",
+                    self.node_id, message, header.from(), err);
+
+                metric_increment(MALFORMED_MESSAGES_DROPPED_ID, Some(1));
+
+                return;
+            }
+        };
+
         match view_seq.index(self.curr_view.sequence_number()) {
             Either::Right(i) if i > 0 => {
                 self.enqueue_other_view_message(i, message);
@@ -330,6 +531,8 @@ where
     pub fn poll(&mut self) -> ConsensusPollStatus<RQ> {
         trace!("Current signal queue: {:?}", self.signalled);
 
+        self.resume_verified_batches();
+
         while let Some(seq_no) = self.signalled.pop_signalled() {
             let index = seq_no.index(self.seq_no);
 
@@ -389,7 +592,45 @@ where
     where
         NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
     {
-        let (header, message) = (s_message.header(), s_message.message().consensus());
+        let s_message = match self.sequence(s_message) {
+            UiOrder::Deliver(message, released) => {
+                released
+                    .into_iter()
+                    .for_each(|released| self.queue_in_order(released));
+
+                message
+            }
+            UiOrder::Held => return Ok(ConsensusStatus::MessageQueued),
+            UiOrder::Rejected(released) => {
+                released
+                    .into_iter()
+                    .for_each(|released| self.queue_in_order(released));
+
+                return Ok(ConsensusStatus::MessageIgnored);
+            }
+        };
+
+        let (header, message) = (s_message.header(), s_message.message().consensus()?);
+
+        if let ConsensusMessageKind::PrePrepare(_) = message.kind() {
+            match batch_verification(self.node_id, header.digest()) {
+                BatchVerification::Verified => {}
+                BatchVerification::Running => {
+                    debug!("{:?} // Holding pre prepare {:?} from {:?} until its requests are verified",
+                        self.node_id, message.sequence_number(), header.from());
+
+                    self.awaiting_verification.push(s_message.clone());
+
+                    return Ok(ConsensusStatus::MessageQueued);
+                }
+                BatchVerification::Failed(err) => {
+                    warn!("{:?} // Dropping pre prepare {:?} from {:?} as one of its requests failed verification: {:?}",
+                        self.node_id, message.sequence_number(), header.from(), err);
+
+                    return Ok(ConsensusStatus::MessageIgnored);
+                }
+            }
+        }
 
         let message_seq = message.sequence_number();
 
@@ -447,6 +688,7 @@ where
 
         Ok(match status {
             DecisionStatus::VotedTwice(node) => ConsensusStatus::VotedTwice(node),
+            DecisionStatus::Misbehaved(evidence) => ConsensusStatus::Misbehaved(evidence),
             DecisionStatus::Deciding(message) => ConsensusStatus::Deciding(MaybeVec::from_one(
                 Decision::decision_info_from_message(decision_seq, message),
             )),
@@ -481,9 +723,51 @@ where
             )),
             DecisionStatus::DecidedIgnored => ConsensusStatus::Decided(MaybeVec::None),
             DecisionStatus::MessageIgnored => ConsensusStatus::MessageIgnored,
+            DecisionStatus::MessageProcessed => ConsensusStatus::MessageProcessed,
+            DecisionStatus::PrePreparesFilled(metadata) => {
+                // We have moved to the prepare phase, so queued prepares can now be processed
+                self.signalled.push_signalled(decision_seq);
+
+                ConsensusStatus::Deciding(MaybeVec::from_one(
+                    Decision::decision_info_from_metadata_and_messages(
+                        decision_seq,
+                        metadata,
+                        MaybeVec::None,
+                    ),
+                ))
+            }
         })
     }
 
+    /// Handle the expired pre prepare slot timeouts, voting to skip
+    /// the slots of the leaders which are still silent
+    pub fn pre_prepare_slots_timed_out<NT>(&mut self, timed_out: &[ModTimeout], node: &Arc<NT>)
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
+    {
+        for timeout in timed_out {
+            let Some(slot_timeout) = timeout
+                .extra_info()
+                .and_then(|info| info.as_any().downcast_ref::<PrePrepareSlotTimeout>())
+            else {
+                continue;
+            };
+
+            let Either::Right(i) = slot_timeout.sequence_number().index(self.seq_no) else {
+                continue;
+            };
+
+            if let Some(decision) = self.decisions.get_mut(i) {
+                decision.pre_prepare_slots_timed_out(
+                    slot_timeout,
+                    &self.curr_view,
+                    &self.timeouts,
+                    node,
+                );
+            }
+        }
+    }
+
     /// Are we able to finalize the next consensus instance on the queue?
     pub fn can_finalize(&self) -> bool {
         self.decisions
@@ -492,6 +776,14 @@ where
             .unwrap_or(false)
     }
 
+    /// The metrics of the next decision to be finalized
+    pub(super) fn finalizeable_metrics(&self) -> Option<&ConsensusMetrics> {
+        self.decisions
+            .front()
+            .filter(|d| d.is_finalizeable())
+            .map(|d| d.metrics())
+    }
+
     pub(super) fn finalizeable_count(&self) -> usize {
         let mut count = 0;
 
@@ -565,7 +857,13 @@ where
 
         // Create the decision to keep the queue populated
         let novel_decision =
-            ConsensusDecision::init_with_msg_log(self.node_id, new_seq_no, view, queue);
+            ConsensusDecision::init_with_msg_log(
+                self.node_id,
+                new_seq_no,
+                view,
+                queue,
+                self.decision_options.clone(),
+            );
 
         self.enqueue_decision(novel_decision);
 
@@ -589,7 +887,12 @@ where
 
                 while self.decisions.len() < self.watermark as usize {
                     let novel_decision =
-                        ConsensusDecision::init_decision(self.node_id, sequence_no, view);
+                        ConsensusDecision::init_decision(
+                            self.node_id,
+                            sequence_no,
+                            view,
+                            self.decision_options.clone(),
+                        );
 
                     self.enqueue_decision(novel_decision);
 
@@ -647,6 +950,7 @@ where
                         sequence_no,
                         view,
                         messages,
+                        self.decision_options.clone(),
                     );
 
                     debug!(
@@ -662,7 +966,12 @@ where
 
                 while self.decisions.len() < self.watermark as usize {
                     let decision =
-                        ConsensusDecision::init_decision(self.node_id, sequence_no, view);
+                        ConsensusDecision::init_decision(
+                            self.node_id,
+                            sequence_no,
+                            view,
+                            self.decision_options.clone(),
+                        );
 
                     self.enqueue_decision(decision);
 
@@ -700,6 +1009,7 @@ where
                         sequence_no,
                         view,
                         messages,
+                        self.decision_options.clone(),
                     );
 
                     self.enqueue_decision(decision);
@@ -744,11 +1054,32 @@ where
     /// change protocol.
     #[instrument(skip(self, requests), level = "debug", fields(request_count = requests.len()))]
     pub fn forge_propose(&self, requests: Vec<StoredMessage<RQ>>, view: &ViewInfo) -> SysMsg<RQ> {
-        PBFTMessage::Consensus(ConsensusMessage::new(
+        let message = ConsensusMessage::new(
             self.sequence_number(),
             view.sequence_number(),
             ConsensusMessageKind::PrePrepare(requests),
-        ))
+        );
+
+        let message = match &self.decision_options.usig {
+            Some(usig) if view.fault_model().requires_usig() => {
+                match usig.create_ui(&consensus_message_digest(&message)) {
+                    Ok(ui) => message.with_ui(ui),
+                    Err(err) => {
+                        // The other replicas will reject this pre prepare, so the
+                        // view change will eventually time out
+                        error!(
+                            "{:?} // Failed to create the unique identifier for forged pre prepare {:?}: {:?}",
+                            self.node_id, message.sequence_number(), err
+                        );
+
+                        message
+                    }
+                }
+            }
+            _ => message,
+        };
+
+        PBFTMessage::Consensus(message)
     }
 
     /// Install a given view into the current consensus decisions.
@@ -787,7 +1118,12 @@ where
         let mut sequence_no = self.sequence_number();
 
         while self.decisions.len() < self.watermark as usize {
-            let novel_decision = ConsensusDecision::init_decision(self.node_id, sequence_no, view);
+            let novel_decision = ConsensusDecision::init_decision(
+                self.node_id,
+                sequence_no,
+                view,
+                self.decision_options.clone(),
+            );
 
             self.enqueue_decision(novel_decision);
 
@@ -796,24 +1132,22 @@ where
 
         if view_index > 1 {
             for _ in 0..view_index - 1 {
-                self.view_queue.pop_front();
+                self.view_queue.advance();
             }
         }
 
-        let option = self.view_queue.pop_front();
+        let messages = self.view_queue.advance();
 
         debug!(
             "{:?} // Installing view {:?}, view index: {}. View queue: {:?}",
             self.node_id,
             view.sequence_number(),
             view_index,
-            option
+            messages
         );
 
-        if let Some(messages) = option {
-            for message in messages {
-                self.queue(message);
-            }
+        for message in messages {
+            self.queue_in_order(message);
         }
     }
 
@@ -831,11 +1165,7 @@ where
         // Adjust the index to be 0 based
         let index = index - 1;
 
-        while self.view_queue.len() <= index {
-            self.view_queue.push_back(Vec::new());
-        }
-
-        self.view_queue[index].push(message);
+        self.view_queue.queue(index, message);
     }
 
     /// Finalize the view change protocol
@@ -885,10 +1215,34 @@ where
         Ok(result)
     }
 
+    /// Remove leaders from the current view, without changing views.
+    ///
+    /// The decisions which are already underway keep the leader set they were started
+    /// with, so the new leader set only applies to the decisions created from now on.
+    pub fn remove_leaders(&mut self, view: &ViewInfo) {
+        let first_affected = self
+            .decisions
+            .back()
+            .map(|d| d.sequence_number().next())
+            .unwrap_or(self.seq_no);
+
+        info!(
+            "{:?} // Leader set of view {:?} reduced to {:?}, starting at Seq {:?}",
+            self.node_id,
+            view.sequence_number(),
+            view.leader_set(),
+            first_affected
+        );
+
+        self.curr_view = view.clone();
+        self.consensus_guard
+            .install_leader_set_change(view.clone(), first_affected);
+    }
+
     /// Collect the incomplete proof that is currently being decided
-    pub fn collect_incomplete_proof(&self, f: usize) -> IncompleteProof {
+    pub fn collect_incomplete_proof(&self, prepared_quorum: usize) -> IncompleteProof {
         if let Some(decision) = self.decisions.front() {
-            decision.deciding(f)
+            decision.deciding(prepared_quorum)
         } else {
             unreachable!()
         }
@@ -942,11 +1296,14 @@ pub struct ProposerConsensusGuard {
     /// We must store them due to the way the request pre processor
     /// sends requests to the proposer
     last_view_change: Mutex<Option<BTreeMap<NodeId, BTreeMap<SeqNo, SeqNo>>>>,
+    /// A reduced leader set for the current view, along with the first
+    /// sequence number it applies to
+    leader_set_change: Mutex<Option<(SeqNo, ViewInfo)>>,
 }
 
 impl ProposerConsensusGuard {
     /// Initialize a new consensus guard object
-    pub(super) fn new(view: ViewInfo, watermark: u32) -> Arc<Self> {
+    pub fn new(view: ViewInfo, watermark: u32) -> Arc<Self> {
         Arc::new(Self {
             // We start at false since we have to wait for the state transfer protocol
             can_propose: AtomicBool::new(false),
@@ -954,6 +1311,7 @@ impl ProposerConsensusGuard {
             seq_no_queue: Mutex::new((BinaryHeap::with_capacity(watermark as usize), view)),
             has_pending_view_change_reqs: AtomicBool::new(false),
             last_view_change: Mutex::new(None),
+            leader_set_change: Mutex::new(None),
         })
     }
 
@@ -987,7 +1345,17 @@ impl ProposerConsensusGuard {
     pub fn next_seq_no(&self) -> Option<(SeqNo, ViewInfo)> {
         let mut guard = self.seq_no_queue.lock().unwrap();
 
-        guard.0.pop().map(|first| (first.0, guard.1.clone()))
+        let Reverse(seq) = guard.0.pop()?;
+
+        let mut change = self.leader_set_change.lock().unwrap();
+
+        if change.as_ref().is_some_and(|(from, _)| seq >= *from) {
+            if let Some((_, view)) = change.take() {
+                guard.1 = view;
+            }
+        }
+
+        Some((seq, guard.1.clone()))
     }
 
     /// Mark a given consensus sequence number as available to be proposed to
@@ -1022,6 +1390,15 @@ impl ProposerConsensusGuard {
 
         guard.1 = view;
         guard.0.clear();
+
+        self.leader_set_change.lock().unwrap().take();
+    }
+
+    /// Install a reduced leader set for the current view, which only applies to
+    /// the decisions starting at `from`, as the ones before it were already
+    /// started with the previous leader set
+    pub fn install_leader_set_change(&self, view: ViewInfo, from: SeqNo) {
+        self.leader_set_change.lock().unwrap().replace((from, view));
     }
 
     /// Check if we have pending view change requests
@@ -0,0 +1,965 @@
+//! Unique Sequential Identifier Generator (USIG) support.
+//!
+//! A USIG is a trusted component (usually backed by a TEE) which binds each
+//! message a replica sends to a unique and monotonically increasing counter value.
+//! Since a replica is unable to assign the same counter value to two different
+//! messages, it cannot equivocate, which allows us to run the protocol with
+//! n = 2f + 1 replicas (MinBFT style).
+//!
+//! Certifying a counter value is not enough on its own: a replica could still show
+//! one message to some replicas and hide it from others. We therefore only accept
+//! the messages of each replica in counter order, without gaps, and only accept one
+//! message per replica for each slot (sequence number, view and phase).
+//! This relies on the links between replicas being reliable and FIFO.
+//!
+//! The view change messages are certified as well (like the REQ-VIEW-CHANGE of MinBFT),
+//! and ordered along with the consensus messages of their sender. Once we accept the
+//! collect of a replica, we have seen every prepare it sent before it, so it can
+//! neither hide nor make up the values it wrote.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::Mutex;
+
+use tracing::warn;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::Err;
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::bft::log::decisions::ViewDecisionPair;
+use crate::bft::message::{
+    ConsensusMessage, ConsensusMessageKind, ViewChangeMessage, ViewChangeMessageKind,
+};
+
+/// How many delivered counter values, per replica, we keep around in order to
+/// recognize messages which are processed again after being queued
+const COUNTER_HISTORY: usize = 4096;
+
+/// How many messages, per replica, we hold while waiting for the counters before them
+const MAX_HELD_MESSAGES: usize = 1024;
+
+/// How many slots, per replica, we remember the bound message of
+const SLOT_HISTORY: usize = 4096;
+
+/// The first counter value a trusted counter assigns
+pub const FIRST_COUNTER: u64 = 1;
+
+/// A unique identifier, certifying that a given counter value
+/// was assigned to a given message digest
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UniqueIdentifier {
+    counter: u64,
+    certificate: Digest,
+}
+
+impl UniqueIdentifier {
+    pub fn new(counter: u64, certificate: Digest) -> Self {
+        Self {
+            counter,
+            certificate,
+        }
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    pub fn certificate(&self) -> &Digest {
+        &self.certificate
+    }
+}
+
+/// The trusted counter abstraction.
+///
+/// Implementations must guarantee that `create_ui` never assigns the same counter
+/// value twice and that `verify_ui` only accepts identifiers that were created by the
+/// trusted component of the given node.
+/// The order in which the counters are used is checked by the [UiSequencer].
+pub trait Usig: Send + Sync + Debug {
+    /// Assign the next counter value to the message with the given digest
+    fn create_ui(&self, digest: &Digest) -> Result<UniqueIdentifier>;
+
+    /// Verify that the given identifier was created by `node` for the message
+    /// with the given digest.
+    fn verify_ui(&self, node: NodeId, digest: &Digest, ui: &UniqueIdentifier) -> Result<()>;
+}
+
+/// A pure software implementation of the trusted counter.
+///
+/// All replicas share the same secret, so this offers no actual protection against
+/// Byzantine replicas and is only meant for testing.
+#[derive(Debug)]
+pub struct SoftwareUsig {
+    node_id: NodeId,
+    secret: Vec<u8>,
+    counter: Mutex<u64>,
+}
+
+impl SoftwareUsig {
+    pub fn new(node_id: NodeId, secret: Vec<u8>) -> Self {
+        Self {
+            node_id,
+            secret,
+            counter: Mutex::new(0),
+        }
+    }
+
+    fn certify(&self, node: NodeId, counter: u64, digest: &Digest) -> Digest {
+        let node: u64 = node.into();
+
+        let mut ctx = Context::new();
+
+        ctx.update(&self.secret[..]);
+        ctx.update(&node.to_le_bytes()[..]);
+        ctx.update(&counter.to_le_bytes()[..]);
+        ctx.update(digest.as_ref());
+
+        ctx.finish()
+    }
+}
+
+impl Usig for SoftwareUsig {
+    fn create_ui(&self, digest: &Digest) -> Result<UniqueIdentifier> {
+        let counter = {
+            let mut guard = self.counter.lock().unwrap();
+
+            *guard += 1;
+
+            *guard
+        };
+
+        Ok(UniqueIdentifier::new(
+            counter,
+            self.certify(self.node_id, counter, digest),
+        ))
+    }
+
+    fn verify_ui(&self, node: NodeId, digest: &Digest, ui: &UniqueIdentifier) -> Result<()> {
+        if self.certify(node, ui.counter(), digest) != *ui.certificate() {
+            return Err!(UsigError::InvalidCertificate(node, ui.counter()));
+        }
+
+        Ok(())
+    }
+}
+
+/// The digest which is certified by the unique identifier of a consensus message.
+///
+/// This covers every field of the message except for the identifier itself
+pub fn consensus_message_digest<O>(message: &ConsensusMessage<O>) -> Digest {
+    let mut ctx = Context::new();
+
+    ctx.update(&u32::from(message.sequence_number()).to_le_bytes()[..]);
+    ctx.update(&u32::from(message.view()).to_le_bytes()[..]);
+
+    match message.kind() {
+        ConsensusMessageKind::PrePrepare(requests) => {
+            ctx.update(&[0u8][..]);
+
+            for request in requests {
+                ctx.update(request.header().digest().as_ref());
+            }
+        }
+        ConsensusMessageKind::Prepare(digest) => {
+            ctx.update(&[1u8][..]);
+            ctx.update(digest.as_ref());
+        }
+        ConsensusMessageKind::Commit(digest) => {
+            ctx.update(&[2u8][..]);
+            ctx.update(digest.as_ref());
+        }
+        ConsensusMessageKind::SkipSlot(leader) => {
+            let leader: u64 = (*leader).into();
+
+            ctx.update(&[3u8][..]);
+            ctx.update(&leader.to_le_bytes()[..]);
+        }
+    }
+
+    ctx.finish()
+}
+
+/// The digest which is certified by the unique identifier of a view change message.
+///
+/// This covers every field of the message except for the identifier itself
+pub fn view_change_message_digest<O>(message: &ViewChangeMessage<O>) -> Digest {
+    let mut ctx = Context::new();
+
+    ctx.update(&u32::from(message.sequence_number()).to_le_bytes()[..]);
+
+    match message.kind() {
+        ViewChangeMessageKind::Stop(requests) => {
+            ctx.update(&[0u8][..]);
+
+            for request in requests {
+                ctx.update(request.header().digest().as_ref());
+            }
+        }
+        ViewChangeMessageKind::StopQuorumJoin(node) => {
+            let node: u64 = (*node).into();
+
+            ctx.update(&[1u8][..]);
+            ctx.update(&node.to_le_bytes()[..]);
+        }
+        ViewChangeMessageKind::StopData(collect) => {
+            let proof = collect.incomplete_proof();
+
+            ctx.update(&[2u8][..]);
+            ctx.update(&u32::from(proof.executing()).to_le_bytes()[..]);
+
+            for ViewDecisionPair(view, value) in proof.write_set().iter() {
+                ctx.update(&u32::from(*view).to_le_bytes()[..]);
+                ctx.update(value.as_ref());
+            }
+
+            if let Some(ViewDecisionPair(view, value)) = proof.quorum_prepares() {
+                ctx.update(&[1u8][..]);
+                ctx.update(&u32::from(*view).to_le_bytes()[..]);
+                ctx.update(value.as_ref());
+            }
+
+            if let Some(last_proof) = collect.last_proof() {
+                ctx.update(&[1u8][..]);
+                ctx.update(&u32::from(last_proof.sequence_number()).to_le_bytes()[..]);
+                ctx.update(last_proof.metadata().batch_digest().as_ref());
+            }
+        }
+        ViewChangeMessageKind::Sync(collects) => {
+            ctx.update(&[3u8][..]);
+            ctx.update(collects.proposed().header().digest().as_ref());
+
+            for collect in collects.collects() {
+                ctx.update(collect.header().digest().as_ref());
+            }
+        }
+    }
+
+    ctx.finish()
+}
+
+/// Attach a unique identifier to the given consensus message
+pub fn attach_ui<O>(usig: &dyn Usig, message: ConsensusMessage<O>) -> Result<ConsensusMessage<O>> {
+    let ui = usig.create_ui(&consensus_message_digest(&message))?;
+
+    Ok(message.with_ui(ui))
+}
+
+/// Attach a unique identifier to the given view change message
+pub fn attach_view_change_ui<O>(
+    usig: &dyn Usig,
+    message: ViewChangeMessage<O>,
+) -> Result<ViewChangeMessage<O>> {
+    let ui = usig.create_ui(&view_change_message_digest(&message))?;
+
+    Ok(message.with_ui(ui))
+}
+
+/// Verify the unique identifier attached to a consensus message sent by `from`
+pub fn verify_message_ui<O>(
+    usig: &dyn Usig,
+    from: NodeId,
+    message: &ConsensusMessage<O>,
+) -> Result<()> {
+    match message.ui() {
+        Some(ui) => usig.verify_ui(from, &consensus_message_digest(message), ui),
+        None => Err!(UsigError::MissingUniqueIdentifier(from)),
+    }
+}
+
+/// The slot a consensus message occupies. A replica may only
+/// attach a unique identifier to one message per slot
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct UiSlot {
+    seq: SeqNo,
+    view: SeqNo,
+    phase: u8,
+    /// The leader a skip slot vote is about, as there can be one such vote per leader
+    leader: Option<NodeId>,
+}
+
+impl UiSlot {
+    fn of<O>(message: &ConsensusMessage<O>) -> Self {
+        let (phase, leader) = match message.kind() {
+            ConsensusMessageKind::PrePrepare(_) => (0, None),
+            ConsensusMessageKind::Prepare(_) => (1, None),
+            ConsensusMessageKind::Commit(_) => (2, None),
+            ConsensusMessageKind::SkipSlot(leader) => (3, Some(*leader)),
+        };
+
+        Self {
+            seq: message.sequence_number(),
+            view: message.view(),
+            phase,
+            leader,
+        }
+    }
+
+    /// A replica may only send one view change message of each kind for each view
+    fn of_view_change<O>(message: &ViewChangeMessage<O>) -> Self {
+        let phase = match message.kind() {
+            ViewChangeMessageKind::Stop(_) => 4,
+            ViewChangeMessageKind::StopQuorumJoin(_) => 5,
+            ViewChangeMessageKind::StopData(_) => 6,
+            ViewChangeMessageKind::Sync(_) => 7,
+        };
+
+        Self {
+            seq: message.sequence_number(),
+            view: message.sequence_number(),
+            phase,
+            leader: None,
+        }
+    }
+}
+
+/// What a message with a unique identifier binds its sender to
+#[derive(Clone, Copy)]
+struct UiEntry {
+    slot: UiSlot,
+    digest: Digest,
+    /// The value written by a prepare
+    written: Option<Digest>,
+}
+
+impl UiEntry {
+    fn of<O>(message: &ConsensusMessage<O>) -> Self {
+        let written = match message.kind() {
+            ConsensusMessageKind::Prepare(value) => Some(*value),
+            _ => None,
+        };
+
+        Self {
+            slot: UiSlot::of(message),
+            digest: consensus_message_digest(message),
+            written,
+        }
+    }
+
+    fn of_view_change<O>(message: &ViewChangeMessage<O>) -> Self {
+        Self {
+            slot: UiSlot::of_view_change(message),
+            digest: view_change_message_digest(message),
+            written: None,
+        }
+    }
+}
+
+/// A message which arrived before the counters preceding it
+struct HeldMessage<M> {
+    entry: UiEntry,
+    message: M,
+}
+
+/// The counters we have accepted from a given replica
+struct SenderCounters<M> {
+    /// The counter we are waiting for
+    next: u64,
+    /// The digests of the messages we have delivered, by counter
+    delivered: BTreeMap<u64, Digest>,
+    /// The messages which are waiting for the counters before them, by counter
+    held: BTreeMap<u64, HeldMessage<M>>,
+    /// The digest of the message bound to each slot
+    bound: BTreeMap<UiSlot, Digest>,
+    /// The values written by the prepares we have delivered, by sequence number and view
+    written: BTreeMap<(SeqNo, SeqNo), Digest>,
+}
+
+impl<M> SenderCounters<M> {
+    fn starting_at(next: u64) -> Self {
+        Self {
+            next,
+            delivered: BTreeMap::new(),
+            held: BTreeMap::new(),
+            bound: BTreeMap::new(),
+            written: BTreeMap::new(),
+        }
+    }
+
+    /// Deliver the message with the next counter, binding it to its slot.
+    /// The counter is consumed even if the slot was already bound to another message
+    fn deliver_next(&mut self, from: NodeId, entry: UiEntry) -> Result<()> {
+        let counter = self.next;
+
+        self.next += 1;
+
+        self.delivered.insert(counter, entry.digest);
+
+        while self.delivered.len() > COUNTER_HISTORY {
+            self.delivered.pop_first();
+        }
+
+        match self.bound.get(&entry.slot) {
+            Some(bound) if *bound != entry.digest => {
+                return Err!(UsigError::SlotEquivocation(from, counter));
+            }
+            Some(_) => {}
+            None => {
+                self.bound.insert(entry.slot, entry.digest);
+
+                while self.bound.len() > SLOT_HISTORY {
+                    self.bound.pop_first();
+                }
+
+                self.write(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remember the value written by a prepare
+    fn write(&mut self, entry: UiEntry) {
+        if let Some(value) = entry.written {
+            self.written
+                .insert((entry.slot.seq, entry.slot.view), value);
+
+            while self.written.len() > SLOT_HISTORY {
+                self.written.pop_first();
+            }
+        }
+    }
+
+    /// Deliver the held messages which no longer have a gap before them
+    fn release_held(&mut self, from: NodeId, released: &mut Vec<M>) {
+        while let Some(held) = self.held.remove(&self.next) {
+            let counter = self.next;
+
+            match self.deliver_next(from, held.entry) {
+                Ok(()) => released.push(held.message),
+                Err(err) => warn!(
+                    "Dropping held message with counter {} from {:?}: {:?}",
+                    counter, from, err
+                ),
+            }
+        }
+    }
+}
+
+/// What to do with a message after checking the order of its unique identifier
+pub enum UiOrder<M> {
+    /// The message can be processed, followed by the held messages it released, in counter order
+    Deliver(M, Vec<M>),
+    /// The message must wait for the counters before it
+    Held,
+    /// The message is dropped, as the replica already bound another message to its slot.
+    /// Its counter is still consumed, so the held messages it released can be processed
+    Rejected(Vec<M>),
+}
+
+/// Accepts the messages of each replica in the order of their counters.
+///
+/// Messages which skip ahead are held until the gap is filled, and messages
+/// which were already delivered are recognized, so a message may go through the
+/// sequencer again when it is processed after being queued.
+///
+/// Every replica's counter starts at [FIRST_COUNTER]. When a gap can't be filled
+/// (a message was lost, or we fell behind), the replica is resynchronized to the
+/// counter of the collect it sent in the next view change we install,
+/// see [UiSequencer::deliver_forwarded].
+pub struct UiSequencer<M> {
+    node_id: NodeId,
+    senders: BTreeMap<NodeId, SenderCounters<M>>,
+}
+
+impl<M> UiSequencer<M> {
+    pub fn new(node_id: NodeId) -> Self {
+        Self {
+            node_id,
+            senders: BTreeMap::new(),
+        }
+    }
+
+    /// Check the order of the unique identifier attached to `message`, which was sent by `from`.
+    pub fn submit<O>(
+        &mut self,
+        usig: &dyn Usig,
+        from: NodeId,
+        message: &ConsensusMessage<O>,
+        item: M,
+    ) -> Result<UiOrder<M>> {
+        // Our own messages are ordered by our own counter, and messages without an
+        // identifier are rejected by the decision when the fault model requires one
+        let Some(ui) = message.ui() else {
+            return Ok(UiOrder::Deliver(item, Vec::new()));
+        };
+
+        self.order(usig, from, ui, UiEntry::of(message), item)
+    }
+
+    /// Check the order of the unique identifier attached to the view change `message`,
+    /// which was sent by `from`. View change messages must carry an identifier.
+    pub fn submit_view_change<O>(
+        &mut self,
+        usig: &dyn Usig,
+        from: NodeId,
+        message: &ViewChangeMessage<O>,
+        item: M,
+    ) -> Result<UiOrder<M>> {
+        let Some(ui) = message.ui() else {
+            return Err!(UsigError::MissingUniqueIdentifier(from));
+        };
+
+        self.order(usig, from, ui, UiEntry::of_view_change(message), item)
+    }
+
+    /// Deliver a view change message of `from` which was forwarded to us by another
+    /// replica, as part of a view change which is being installed.
+    ///
+    /// This is how we resynchronize with the counter of a replica: if we are missing some
+    /// of the messages it sent before this one (a message was lost, or we were not up to
+    /// date), we stop waiting for them as they belong to the views we are leaving behind.
+    /// The held messages before its counter are dropped.
+    ///
+    /// Returns the held messages it released, in counter order.
+    pub fn deliver_forwarded<O>(
+        &mut self,
+        usig: &dyn Usig,
+        from: NodeId,
+        message: &ViewChangeMessage<O>,
+    ) -> Result<Vec<M>> {
+        let Some(ui) = message.ui() else {
+            return Err!(UsigError::MissingUniqueIdentifier(from));
+        };
+
+        if from == self.node_id {
+            return Ok(Vec::new());
+        }
+
+        let entry = UiEntry::of_view_change(message);
+
+        usig.verify_ui(from, &entry.digest, ui)?;
+
+        let counter = ui.counter();
+
+        let node_id = self.node_id;
+
+        let sender = self.sender(from);
+
+        if counter < sender.next {
+            return match sender.delivered.get(&counter) {
+                Some(delivered) if *delivered == entry.digest => Ok(Vec::new()),
+                Some(_) => Err!(UsigError::CounterReused(from, counter)),
+                None => Err!(UsigError::StaleCounter(from, counter, sender.next)),
+            };
+        }
+
+        if counter > sender.next {
+            warn!(
+                "{:?} // Resynchronizing the counter of {:?} from {} to {}",
+                node_id, from, sender.next, counter
+            );
+
+            sender.held = sender.held.split_off(&(counter + 1));
+            sender.next = counter;
+        }
+
+        let delivered = sender.deliver_next(from, entry);
+
+        let mut released = Vec::new();
+
+        sender.release_held(from, &mut released);
+
+        delivered.map(|_| released)
+    }
+
+    /// The values `from` wrote in the prepares we delivered for the given sequence number
+    pub fn written(&self, from: NodeId, seq: SeqNo) -> Vec<ViewDecisionPair> {
+        let Some(sender) = self.senders.get(&from) else {
+            return Vec::new();
+        };
+
+        sender
+            .written
+            .iter()
+            .filter(|((written_seq, _), _)| *written_seq == seq)
+            .map(|((_, view), value)| ViewDecisionPair(*view, *value))
+            .collect()
+    }
+
+    fn sender(&mut self, from: NodeId) -> &mut SenderCounters<M> {
+        self.senders
+            .entry(from)
+            .or_insert_with(|| SenderCounters::starting_at(FIRST_COUNTER))
+    }
+
+    fn order(
+        &mut self,
+        usig: &dyn Usig,
+        from: NodeId,
+        ui: &UniqueIdentifier,
+        entry: UiEntry,
+        item: M,
+    ) -> Result<UiOrder<M>> {
+        if from == self.node_id {
+            // We trust our own counter, but we still need the prepares we wrote for our collects
+            self.sender(from).write(entry);
+
+            return Ok(UiOrder::Deliver(item, Vec::new()));
+        }
+
+        usig.verify_ui(from, &entry.digest, ui)?;
+
+        let counter = ui.counter();
+
+        let node_id = self.node_id;
+
+        let sender = self.sender(from);
+
+        if counter < sender.next {
+            return match sender.delivered.get(&counter) {
+                Some(delivered) if *delivered == entry.digest => {
+                    Ok(UiOrder::Deliver(item, Vec::new()))
+                }
+                Some(_) => Err!(UsigError::CounterReused(from, counter)),
+                None => Err!(UsigError::StaleCounter(from, counter, sender.next)),
+            };
+        }
+
+        if counter > sender.next {
+            match sender.held.get(&counter) {
+                Some(held) if held.entry.digest == entry.digest => {}
+                Some(_) => return Err!(UsigError::CounterReused(from, counter)),
+                None if sender.held.len() >= MAX_HELD_MESSAGES => {
+                    return Err!(UsigError::TooFarAhead(from, counter, sender.next));
+                }
+                None => {
+                    sender.held.insert(
+                        counter,
+                        HeldMessage {
+                            entry,
+                            message: item,
+                        },
+                    );
+                }
+            }
+
+            return Ok(UiOrder::Held);
+        }
+
+        let delivered = sender.deliver_next(from, entry);
+
+        let mut released = Vec::new();
+
+        sender.release_held(from, &mut released);
+
+        match delivered {
+            Ok(()) => Ok(UiOrder::Deliver(item, released)),
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropping message from {:?}: {:?}",
+                    node_id, from, err
+                );
+
+                Ok(UiOrder::Rejected(released))
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum UsigError {
+    #[error("Message from {0:?} does not carry a unique identifier")]
+    MissingUniqueIdentifier(NodeId),
+    #[error("Invalid unique identifier certificate from {0:?} for counter {1}")]
+    InvalidCertificate(NodeId, u64),
+    #[error("Node {0:?} reused the counter {1} for a different message")]
+    CounterReused(NodeId, u64),
+    #[error("Node {0:?} bound the counter {1} to a second message for the same slot")]
+    SlotEquivocation(NodeId, u64),
+    #[error("Counter {1} from {0:?} was already used, we are expecting {2}")]
+    StaleCounter(NodeId, u64, u64),
+    #[error("Counter {1} from {0:?} is too far ahead of the expected {2}")]
+    TooFarAhead(NodeId, u64, u64),
+    #[error("Running under the trusted counter fault model without a USIG")]
+    NoUsig,
+}
+
+#[cfg(test)]
+mod usig_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::bft::log::decisions::ViewDecisionPair;
+    use crate::bft::message::{
+        ConsensusMessage, ConsensusMessageKind, ViewChangeMessage, ViewChangeMessageKind,
+    };
+
+    use super::{
+        attach_ui, attach_view_change_ui, SoftwareUsig, UiOrder, UiSequencer, UsigError,
+        MAX_HELD_MESSAGES,
+    };
+
+    const SECRET: &[u8] = b"usig tests";
+
+    fn digest(byte: u8) -> Digest {
+        Digest::from_bytes(&[byte; Digest::LENGTH]).unwrap()
+    }
+
+    fn usig(node: u32) -> SoftwareUsig {
+        SoftwareUsig::new(NodeId::from(node), SECRET.to_vec())
+    }
+
+    fn prepare(usig: &SoftwareUsig, seq: u32, value: u8) -> ConsensusMessage<()> {
+        let message = ConsensusMessage::new(
+            SeqNo::from(seq),
+            SeqNo::ZERO,
+            ConsensusMessageKind::Prepare(digest(value)),
+        );
+
+        attach_ui(usig, message).unwrap()
+    }
+
+    fn stop(usig: &SoftwareUsig, view: u32) -> ViewChangeMessage<()> {
+        let message =
+            ViewChangeMessage::new(SeqNo::from(view), ViewChangeMessageKind::Stop(Vec::new()));
+
+        attach_view_change_ui(usig, message).unwrap()
+    }
+
+    fn delivered(order: UiOrder<u32>) -> Option<Vec<u32>> {
+        match order {
+            UiOrder::Deliver(message, released) => {
+                Some(std::iter::once(message).chain(released).collect())
+            }
+            UiOrder::Held | UiOrder::Rejected(_) => None,
+        }
+    }
+
+    fn usig_error(err: atlas_common::error::Error) -> UsigError {
+        err.downcast::<UsigError>().unwrap()
+    }
+
+    #[test]
+    fn test_messages_are_delivered_in_counter_order() {
+        let (receiver, sender) = (usig(0), usig(1));
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        let messages: Vec<_> = (0..3).map(|seq| prepare(&sender, seq, 1)).collect();
+
+        let third = sequencer.submit(&receiver, from, &messages[2], 3).unwrap();
+        let second = sequencer.submit(&receiver, from, &messages[1], 2).unwrap();
+
+        assert!(matches!(third, UiOrder::Held));
+        assert!(matches!(second, UiOrder::Held));
+
+        let first = sequencer.submit(&receiver, from, &messages[0], 1).unwrap();
+
+        assert_eq!(delivered(first), Some(vec![1, 2, 3]));
+
+        // Messages which go through the sequencer again are recognized
+        let again = sequencer.submit(&receiver, from, &messages[1], 2).unwrap();
+
+        assert_eq!(delivered(again), Some(vec![2]));
+    }
+
+    #[test]
+    fn test_counters_start_at_the_first_counter() {
+        let (receiver, sender) = (usig(0), usig(1));
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        // The first message is lost, so the sender can't skip the counters before the second
+        let _lost = prepare(&sender, 0, 1);
+        let second = prepare(&sender, 1, 1);
+
+        let order = sequencer.submit(&receiver, from, &second, 2).unwrap();
+
+        assert!(matches!(order, UiOrder::Held));
+    }
+
+    #[test]
+    fn test_reused_counters_are_rejected() {
+        let receiver = usig(0);
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        // Two counters in the same state certify different messages with the same value
+        let first = prepare(&usig(1), 0, 1);
+        let reused = prepare(&usig(1), 0, 2);
+
+        let order = sequencer.submit(&receiver, from, &first, 1).unwrap();
+
+        assert_eq!(delivered(order), Some(vec![1]));
+
+        let err = sequencer.submit(&receiver, from, &reused, 2).err().unwrap();
+
+        assert!(matches!(usig_error(err), UsigError::CounterReused(_, 1)));
+    }
+
+    #[test]
+    fn test_forged_certificates_are_rejected() {
+        let receiver = usig(0);
+        let forger = SoftwareUsig::new(NodeId::from(1u32), b"another secret".to_vec());
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        let forged = prepare(&forger, 0, 1);
+
+        let err = sequencer.submit(&receiver, from, &forged, 1).err().unwrap();
+
+        assert!(matches!(
+            usig_error(err),
+            UsigError::InvalidCertificate(_, 1)
+        ));
+    }
+
+    #[test]
+    fn test_slot_equivocation_is_rejected() {
+        let (receiver, sender) = (usig(0), usig(1));
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        let first = prepare(&sender, 0, 1);
+        let equivocation = prepare(&sender, 0, 2);
+        let next = prepare(&sender, 1, 1);
+
+        let held = sequencer.submit(&receiver, from, &next, 3).unwrap();
+
+        assert!(matches!(held, UiOrder::Held));
+
+        let order = sequencer.submit(&receiver, from, &first, 1).unwrap();
+
+        assert_eq!(delivered(order), Some(vec![1]));
+
+        // The counter of the equivocation is consumed, releasing the message after it
+        let order = sequencer.submit(&receiver, from, &equivocation, 2).unwrap();
+
+        assert!(matches!(order, UiOrder::Rejected(released) if released == vec![3]));
+
+        assert_eq!(
+            sequencer
+                .written(from, SeqNo::ZERO)
+                .into_iter()
+                .map(|ViewDecisionPair(_, value)| value)
+                .collect::<Vec<_>>(),
+            vec![digest(1)]
+        );
+    }
+
+    #[test]
+    fn test_messages_too_far_ahead_are_rejected() {
+        let (receiver, sender) = (usig(0), usig(1));
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        let _lost = prepare(&sender, 0, 1);
+
+        for seq in 1..=MAX_HELD_MESSAGES as u32 {
+            let message = prepare(&sender, seq, 1);
+
+            let order = sequencer.submit(&receiver, from, &message, seq).unwrap();
+
+            assert!(matches!(order, UiOrder::Held));
+        }
+
+        let overflow = prepare(&sender, MAX_HELD_MESSAGES as u32 + 1, 1);
+
+        let err = sequencer
+            .submit(&receiver, from, &overflow, 0)
+            .err()
+            .unwrap();
+
+        assert!(matches!(usig_error(err), UsigError::TooFarAhead(_, _, 1)));
+    }
+
+    #[test]
+    fn test_forwarded_view_change_resynchronizes_the_counter() {
+        let (receiver, sender) = (usig(0), usig(1));
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        // A message is lost, so every message after it is held
+        let _lost = prepare(&sender, 0, 1);
+        let stuck = prepare(&sender, 1, 1);
+        let stop = stop(&sender, 1);
+        let after = prepare(&sender, 2, 1);
+
+        let order = sequencer.submit(&receiver, from, &stuck, 2).unwrap();
+
+        assert!(matches!(order, UiOrder::Held));
+
+        let order = sequencer.submit(&receiver, from, &after, 4).unwrap();
+
+        assert!(matches!(order, UiOrder::Held));
+
+        // The view change is forwarded to us, and we give up on the lost message
+        let released = sequencer.deliver_forwarded(&receiver, from, &stop).unwrap();
+
+        assert_eq!(released, vec![4]);
+
+        // The messages before it were dropped, and it is only delivered once
+        let err = sequencer.submit(&receiver, from, &stuck, 2).err().unwrap();
+
+        assert!(matches!(usig_error(err), UsigError::StaleCounter(_, 2, 5)));
+
+        let order = sequencer
+            .submit_view_change(&receiver, from, &stop, 3)
+            .unwrap();
+
+        assert_eq!(delivered(order), Some(vec![3]));
+
+        let next = prepare(&sender, 3, 1);
+
+        let order = sequencer.submit(&receiver, from, &next, 5).unwrap();
+
+        assert_eq!(delivered(order), Some(vec![5]));
+    }
+
+    #[test]
+    fn test_view_changes_require_an_identifier() {
+        let receiver = usig(0);
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        let message =
+            ViewChangeMessage::<()>::new(SeqNo::ONE, ViewChangeMessageKind::Stop(Vec::new()));
+
+        let err = sequencer
+            .submit_view_change(&receiver, from, &message, 1)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            usig_error(err),
+            UsigError::MissingUniqueIdentifier(_)
+        ));
+    }
+
+    #[test]
+    fn test_written_prepares_are_tracked() {
+        let (receiver, sender) = (usig(0), usig(1));
+        let from = NodeId::from(1u32);
+
+        let mut sequencer = UiSequencer::new(NodeId::from(0u32));
+
+        for (seq, value) in [(0, 1), (1, 2)] {
+            let message = prepare(&sender, seq, value);
+
+            sequencer.submit(&receiver, from, &message, seq).unwrap();
+        }
+
+        assert!(matches!(
+            sequencer.written(from, SeqNo::ONE).as_slice(),
+            [ViewDecisionPair(view, value)] if *view == SeqNo::ZERO && *value == digest(2)
+        ));
+
+        assert!(sequencer.written(NodeId::from(2u32), SeqNo::ONE).is_empty());
+    }
+}
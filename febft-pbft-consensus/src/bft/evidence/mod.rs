@@ -0,0 +1,438 @@
+//! Detection of equivocating replicas and the evidence of their misbehaviour.
+//!
+//! A correct replica never sends two different messages for the same phase of the same
+//! consensus instance, in the same view. When we receive two such messages from the same
+//! replica, both of them signed, we keep them as [MisbehaviourEvidence]. The evidence is
+//! self-contained, so anyone with the public key of the offender can check it,
+//! without having to trust the replica which reported it.
+//!
+//! Votes authenticated with MACs are not transferable, so they never produce evidence,
+//! unless they also carry a vote signature. Vote signatures, including the ones kept in
+//! [compacted proofs](crate::bft::log::certificate), are [Testimony] on their own.
+//!
+//! Evidence can also be produced after the fact, from conflicting decisions, by the
+//! [forensics](crate::bft::forensics) module.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::serialization_helper::SerType;
+use atlas_common::Err;
+use atlas_communication::reconfiguration::NetworkInformationProvider;
+use atlas_core::ordering_protocol::networking::serialize::OrderProtocolVerificationHelper;
+use atlas_core::ordering_protocol::ShareableMessage;
+
+use crate::bft::consensus::authenticator::MessageAuthentication;
+use crate::bft::log::certificate::{SignedVote, VoteKind};
+use crate::bft::log::decisions::{CollectData, ViewDecisionPair};
+use crate::bft::message::serialize::PBFTConsensus;
+use crate::bft::message::{
+    ConsensusMessage, ConsensusMessageKind, PBFTMessage, ViewChangeMessageKind,
+};
+
+/// The ways in which a replica can misbehave
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MisbehaviourKind {
+    /// A leader proposed two different batches for the same slot
+    ConflictingPrePrepares,
+    /// A replica prepared two different batches
+    ConflictingPrepares,
+    /// A replica committed two different batches
+    ConflictingCommits,
+    /// A replica committed a batch, and then told the leader of the following view
+    /// that it had not prepared it
+    Amnesia,
+}
+
+/// Receives the evidence of the misbehaviour detected by the replica,
+/// so the application can alert on it or punish the offender
+pub trait MisbehaviourReporter<RQ>: Send + Sync {
+    fn report(&self, evidence: MisbehaviourEvidence<RQ>);
+}
+
+impl<RQ> Debug for dyn MisbehaviourReporter<RQ> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MisbehaviourReporter")
+    }
+}
+
+impl<RQ, F> MisbehaviourReporter<RQ> for F
+where
+    F: Fn(MisbehaviourEvidence<RQ>) + Send + Sync,
+{
+    fn report(&self, evidence: MisbehaviourEvidence<RQ>) {
+        self(evidence)
+    }
+}
+
+/// Something a replica stated, which it cannot deny having said
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+pub enum Testimony<RQ> {
+    /// A signed protocol message
+    Message(ShareableMessage<PBFTMessage<RQ>>),
+    /// A vote signature, such as the ones kept in a compacted proof
+    Vote(SignedVote),
+}
+
+/// Two signed statements from the same replica, which no correct replica would make
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct MisbehaviourEvidence<RQ> {
+    kind: MisbehaviourKind,
+    offender: NodeId,
+    first: Testimony<RQ>,
+    second: Testimony<RQ>,
+}
+
+impl<RQ> Testimony<RQ> {
+    /// The replica which made this statement
+    pub fn sender(&self) -> NodeId {
+        match self {
+            Testimony::Message(message) => message.header().from(),
+            Testimony::Vote(vote) => vote.signer(),
+        }
+    }
+}
+
+impl<RQ> Clone for Testimony<RQ> {
+    fn clone(&self) -> Self {
+        match self {
+            Testimony::Message(message) => Testimony::Message(message.clone()),
+            Testimony::Vote(vote) => Testimony::Vote(vote.clone()),
+        }
+    }
+}
+
+impl<RQ> MisbehaviourEvidence<RQ> {
+    pub(crate) fn new(
+        kind: MisbehaviourKind,
+        first: ShareableMessage<PBFTMessage<RQ>>,
+        second: ShareableMessage<PBFTMessage<RQ>>,
+    ) -> Self {
+        Self::from_testimonies(kind, Testimony::Message(first), Testimony::Message(second))
+    }
+
+    pub(crate) fn from_testimonies(
+        kind: MisbehaviourKind,
+        first: Testimony<RQ>,
+        second: Testimony<RQ>,
+    ) -> Self {
+        Self {
+            kind,
+            offender: first.sender(),
+            first,
+            second,
+        }
+    }
+
+    pub fn kind(&self) -> MisbehaviourKind {
+        self.kind
+    }
+
+    /// The replica which misbehaved
+    pub fn offender(&self) -> NodeId {
+        self.offender
+    }
+
+    pub fn testimonies(&self) -> (&Testimony<RQ>, &Testimony<RQ>) {
+        (&self.first, &self.second)
+    }
+
+    /// Check this evidence, using only the public keys of the replicas.
+    ///
+    /// The evidence is valid if both statements are signed by the offender and no correct
+    /// replica could have made both of them, as described by the [MisbehaviourKind].
+    pub fn verify<NI, OPVH>(&self, network_info: &Arc<NI>) -> Result<()>
+    where
+        RQ: SerType,
+        NI: NetworkInformationProvider,
+        OPVH: OrderProtocolVerificationHelper<RQ, PBFTConsensus<RQ>, NI>,
+    {
+        for testimony in [&self.first, &self.second] {
+            if testimony.sender() != self.offender {
+                return Err!(EvidenceError::WrongSender(self.offender, testimony.sender()));
+            }
+        }
+
+        match self.kind {
+            MisbehaviourKind::Amnesia => self.verify_amnesia()?,
+            _ => self.verify_conflict()?,
+        }
+
+        for testimony in [&self.first, &self.second] {
+            match testimony {
+                Testimony::Message(message) => OPVH::verify_protocol_message(
+                    network_info,
+                    message.header(),
+                    message.message().clone(),
+                )?,
+                Testimony::Vote(vote) => {
+                    let info = network_info
+                        .get_node_info(&self.offender)
+                        .ok_or(EvidenceError::UnknownOffender(self.offender))?;
+
+                    vote.verify(info.public_key())?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Both statements are for the same phase of the same instance, but state different things
+    fn verify_conflict(&self) -> Result<()> {
+        let (first_seq, first_view, first_digest) = claim(&self.first, self.kind)?;
+        let (second_seq, second_view, second_digest) = claim(&self.second, self.kind)?;
+
+        if first_seq != second_seq || first_view != second_view {
+            return Err!(EvidenceError::DifferentInstances(
+                first_seq,
+                first_view,
+                second_seq,
+                second_view
+            ));
+        }
+
+        if first_digest == second_digest {
+            return Err!(EvidenceError::MessagesDoNotConflict);
+        }
+
+        Ok(())
+    }
+
+    /// The first statement is a commit, and the second is the collect the offender sent
+    /// in the view change to the following view, which does not report it as prepared
+    fn verify_amnesia(&self) -> Result<()> {
+        let (seq, view, digest) = match &self.first {
+            Testimony::Message(message) => {
+                let commit = transferable_consensus_message(message)?;
+
+                let ConsensusMessageKind::Commit(digest) = commit.kind() else {
+                    return Err!(EvidenceError::WrongMessageKind(self.kind));
+                };
+
+                (commit.sequence_number(), commit.view(), *digest)
+            }
+            Testimony::Vote(vote) if vote.kind() == VoteKind::Commit => {
+                (vote.sequence_number(), vote.view(), *vote.digest())
+            }
+            Testimony::Vote(_) => return Err!(EvidenceError::WrongMessageKind(self.kind)),
+        };
+
+        let Testimony::Message(collect) = &self.second else {
+            return Err!(EvidenceError::WrongMessageKind(self.kind));
+        };
+
+        let PBFTMessage::ViewChange(view_change) = collect.message() else {
+            return Err!(EvidenceError::WrongMessageKind(self.kind));
+        };
+
+        let ViewChangeMessageKind::StopData(collect) = view_change.kind() else {
+            return Err!(EvidenceError::WrongMessageKind(self.kind));
+        };
+
+        let executing = collect.incomplete_proof().executing();
+
+        if view_change.sequence_number() != view.next() || executing != seq {
+            return Err!(EvidenceError::DifferentInstances(
+                seq,
+                view,
+                executing,
+                view_change.sequence_number()
+            ));
+        }
+
+        if reports_prepared(collect, view, &digest) {
+            return Err!(EvidenceError::MessagesDoNotConflict);
+        }
+
+        Ok(())
+    }
+}
+
+/// Does the collect report the given batch as prepared in the given view?
+///
+/// A replica which committed a batch must have prepared it first, so when it
+/// has not yet executed that instance, its collect for the following view has to say so.
+pub(crate) fn reports_prepared<RQ>(collect: &CollectData<RQ>, view: SeqNo, digest: &Digest) -> bool {
+    collect
+        .incomplete_proof()
+        .quorum_prepares()
+        .is_some_and(|ViewDecisionPair(prepared_view, prepared)| {
+            *prepared_view == view && prepared == digest
+        })
+}
+
+/// Keeps the first signed message of each replica, for each phase of a consensus instance,
+/// so that we can detect when a replica sends a conflicting one
+pub(crate) struct EquivocationDetector<RQ> {
+    first_seen: BTreeMap<(NodeId, MisbehaviourKind), ShareableMessage<PBFTMessage<RQ>>>,
+    /// We only report each misbehaviour once
+    reported: BTreeSet<(NodeId, MisbehaviourKind)>,
+}
+
+impl<RQ> EquivocationDetector<RQ> {
+    pub(crate) fn new() -> Self {
+        Self {
+            first_seen: Default::default(),
+            reported: Default::default(),
+        }
+    }
+
+    /// Check a message against the ones previously received from the same replica.
+    ///
+    /// Returns the evidence the first time the replica is caught sending conflicting messages
+    pub(crate) fn check(
+        &mut self,
+        s_message: &ShareableMessage<PBFTMessage<RQ>>,
+    ) -> Option<MisbehaviourEvidence<RQ>> {
+        let from = s_message.header().from();
+
+        let message = s_message.message().consensus().ok()?;
+
+        if message.authentication() == MessageAuthentication::Authenticator {
+            return None;
+        }
+
+        let (kind, digest) = statement(s_message)?;
+
+        let Some(first) = self.first_seen.get(&(from, kind)) else {
+            self.first_seen.insert((from, kind), s_message.clone());
+
+            return None;
+        };
+
+        let first_message = first.message().consensus().ok()?;
+
+        if first_message.view() != message.view() {
+            // A new view means new messages, so we only have to remember the latest ones
+            if first_message.view() < message.view() {
+                self.first_seen.insert((from, kind), s_message.clone());
+            }
+
+            return None;
+        }
+
+        if statement(first).is_some_and(|(_, first_digest)| first_digest == digest) {
+            return None;
+        }
+
+        if !self.reported.insert((from, kind)) {
+            return None;
+        }
+
+        Some(MisbehaviourEvidence::new(kind, first.clone(), s_message.clone()))
+    }
+}
+
+/// What a given message states, for the purposes of detecting conflicts
+fn statement<RQ>(s_message: &ShareableMessage<PBFTMessage<RQ>>) -> Option<(MisbehaviourKind, Digest)> {
+    let PBFTMessage::Consensus(message) = s_message.message() else {
+        return None;
+    };
+
+    match message.kind() {
+        ConsensusMessageKind::PrePrepare(_) => Some((
+            MisbehaviourKind::ConflictingPrePrepares,
+            *s_message.header().digest(),
+        )),
+        ConsensusMessageKind::Prepare(digest) => {
+            Some((MisbehaviourKind::ConflictingPrepares, *digest))
+        }
+        ConsensusMessageKind::Commit(digest) => {
+            Some((MisbehaviourKind::ConflictingCommits, *digest))
+        }
+        // A replica can legitimately vote to skip the slots of several leaders
+        ConsensusMessageKind::SkipSlot(_) => None,
+    }
+}
+
+/// The instance, view and value vouched for by a testimony,
+/// which must be able to take part in the given kind of conflict
+fn claim<RQ>(testimony: &Testimony<RQ>, kind: MisbehaviourKind) -> Result<(SeqNo, SeqNo, Digest)> {
+    let (claimed, seq, view, digest) = match testimony {
+        Testimony::Message(s_message) => {
+            let message = transferable_consensus_message(s_message)?;
+
+            let (claimed, digest) =
+                statement(s_message).ok_or(EvidenceError::WrongMessageKind(kind))?;
+
+            (claimed, message.sequence_number(), message.view(), digest)
+        }
+        Testimony::Vote(vote) => {
+            let claimed = match vote.kind() {
+                VoteKind::Prepare => MisbehaviourKind::ConflictingPrepares,
+                VoteKind::Commit => MisbehaviourKind::ConflictingCommits,
+            };
+
+            (claimed, vote.sequence_number(), vote.view(), *vote.digest())
+        }
+    };
+
+    if claimed != kind {
+        return Err!(EvidenceError::WrongMessageKind(kind));
+    }
+
+    Ok((seq, view, digest))
+}
+
+fn transferable_consensus_message<RQ>(
+    s_message: &ShareableMessage<PBFTMessage<RQ>>,
+) -> Result<&ConsensusMessage<RQ>> {
+    let PBFTMessage::Consensus(message) = s_message.message() else {
+        return Err!(EvidenceError::NotAConsensusMessage);
+    };
+
+    if message.authentication() == MessageAuthentication::Authenticator {
+        return Err!(EvidenceError::NotTransferable);
+    }
+
+    Ok(message)
+}
+
+impl<RQ> Debug for Testimony<RQ> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Testimony::Message(message) => write!(f, "{:?}", message.message()),
+            Testimony::Vote(vote) => write!(f, "{:?}", vote),
+        }
+    }
+}
+
+impl<RQ> Debug for MisbehaviourEvidence<RQ> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} by {:?}: {:?} and {:?}",
+            self.kind, self.offender, self.first, self.second
+        )
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum EvidenceError {
+    #[error("The evidence contains a message from {1:?} instead of the offender {0:?}")]
+    WrongSender(NodeId, NodeId),
+    #[error("The evidence must be made up of consensus messages")]
+    NotAConsensusMessage,
+    #[error("Messages authenticated with MACs cannot be used as evidence")]
+    NotTransferable,
+    #[error("The messages pertain to different instances, seq {0:?} view {1:?} vs seq {2:?} view {3:?}")]
+    DifferentInstances(SeqNo, SeqNo, SeqNo, SeqNo),
+    #[error("The messages are not of the kind required by {0:?}")]
+    WrongMessageKind(MisbehaviourKind),
+    #[error("The messages do not conflict")]
+    MessagesDoNotConflict,
+    #[error("The offender {0:?} is not known, so its votes cannot be checked")]
+    UnknownOffender(NodeId),
+}
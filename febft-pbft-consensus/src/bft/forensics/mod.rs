@@ -0,0 +1,574 @@
+//! Post-hoc forensics of safety violations.
+//!
+//! Safety can only be violated when more than f replicas misbehave. When two correct
+//! replicas hold [Proof]s of different batches for the same sequence number, the
+//! messages in those proofs, together with the view change transcripts between them,
+//! let us point at the replicas which broke the protocol:
+//!
+//! - when both batches were committed in the same view, the two commit quorums
+//!   intersect in at least f+1 replicas, each of which committed both batches;
+//! - when the first batch was committed in view v, the collects sent in the view change
+//!   to view v+1 by at least f+1 of the replicas which committed it must report it as
+//!   prepared. The ones which did not lied to the new leader.
+//!
+//! Every culprit comes with [MisbehaviourEvidence], which auditors can check on their own
+//! with [MisbehaviourEvidence::verify]. The analysis itself does not check signatures.
+//!
+//! Compacted proofs no longer carry the vote messages, but they keep the vote signature of
+//! each signer, which is blamed with that [SignedVote] instead. Votes authenticated only
+//! with MACs are not transferable, so they cannot be used to blame individual replicas.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
+use atlas_common::globals::ReadOnly;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::Err;
+use atlas_communication::message::StoredMessage;
+
+use crate::bft::consensus::authenticator::MessageAuthentication;
+use crate::bft::evidence::{reports_prepared, MisbehaviourEvidence, MisbehaviourKind, Testimony};
+use crate::bft::log::certificate::{SignedVote, VoteKind};
+use crate::bft::log::decisions::Proof;
+use crate::bft::message::{ConsensusMessageKind, PBFTMessage, ViewChangeMessageKind};
+use crate::bft::sync::view::ViewInfo;
+use crate::bft::sync::LeaderCollects;
+
+/// The outcome of analysing two conflicting decisions
+pub struct ForensicReport<RQ> {
+    seq: SeqNo,
+    evidence: Vec<MisbehaviourEvidence<RQ>>,
+}
+
+impl<RQ> ForensicReport<RQ> {
+    /// The sequence number of the conflicting decisions
+    pub fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+
+    /// The replicas which provably violated the protocol
+    pub fn culprits(&self) -> BTreeSet<NodeId> {
+        self.evidence
+            .iter()
+            .map(|evidence| evidence.offender())
+            .collect()
+    }
+
+    /// The evidence against each of the culprits
+    pub fn evidence(&self) -> &[MisbehaviourEvidence<RQ>] {
+        &self.evidence
+    }
+
+    /// Have we identified more replicas than the system is meant to tolerate?
+    /// When the proofs and transcripts are complete, this should always be the case.
+    pub fn is_conclusive(&self, view: &ViewInfo) -> bool {
+        view.exceeds_fault_weight(self.culprits().iter())
+    }
+}
+
+/// Identify the replicas responsible for two conflicting proofs of the same instance.
+///
+/// `transcripts` are the view change transcripts held by the auditors. Only the view
+/// change following the view where a batch was committed is relevant, the others are ignored.
+pub fn analyse_conflicting_proofs<RQ>(
+    first: &Proof<RQ>,
+    second: &Proof<RQ>,
+    transcripts: &[LeaderCollects<RQ>],
+) -> Result<ForensicReport<RQ>> {
+    let seq = first.sequence_number();
+
+    if second.sequence_number() != seq {
+        return Err!(ForensicsError::DifferentInstances(
+            seq,
+            second.sequence_number()
+        ));
+    }
+
+    if first.batch_digest() == second.batch_digest() {
+        return Err!(ForensicsError::ProofsDoNotConflict(seq));
+    }
+
+    let mut evidence = Vec::new();
+    let mut culprits = BTreeSet::new();
+
+    let first_commits = transferable_votes(first, VoteKind::Commit);
+    let second_commits = transferable_votes(second, VoteKind::Commit);
+
+    // Replicas which committed both batches in the same view
+    for (node, first_commit) in &first_commits {
+        let Some(second_commit) = second_commits.get(node) else {
+            continue;
+        };
+
+        if vote_view(first_commit) == vote_view(second_commit) && culprits.insert(*node) {
+            evidence.push(MisbehaviourEvidence::from_testimonies(
+                MisbehaviourKind::ConflictingCommits,
+                first_commit.clone(),
+                second_commit.clone(),
+            ));
+        }
+    }
+
+    // Replicas which prepared both batches in the same view
+    let first_prepares = transferable_votes(first, VoteKind::Prepare);
+    let second_prepares = transferable_votes(second, VoteKind::Prepare);
+
+    for (node, first_prepare) in &first_prepares {
+        let Some(second_prepare) = second_prepares.get(node) else {
+            continue;
+        };
+
+        if vote_view(first_prepare) == vote_view(second_prepare) && culprits.insert(*node) {
+            evidence.push(MisbehaviourEvidence::from_testimonies(
+                MisbehaviourKind::ConflictingPrepares,
+                first_prepare.clone(),
+                second_prepare.clone(),
+            ));
+        }
+    }
+
+    // Replicas which forgot about a batch they committed, in the following view change
+    for (commits, digest) in [
+        (&first_commits, first.batch_digest()),
+        (&second_commits, second.batch_digest()),
+    ] {
+        for transcript in transcripts {
+            for collect in transcript.collects() {
+                let node = collect.header().from();
+
+                let Some(commit) = commits.get(&node) else {
+                    continue;
+                };
+
+                if culprits.contains(&node) || !forgot_commit(collect, commit, seq, &digest) {
+                    continue;
+                }
+
+                culprits.insert(node);
+
+                evidence.push(MisbehaviourEvidence::from_testimonies(
+                    MisbehaviourKind::Amnesia,
+                    commit.clone(),
+                    Testimony::Message(Arc::new(ReadOnly::new(collect.clone()))),
+                ));
+            }
+        }
+    }
+
+    Ok(ForensicReport { seq, evidence })
+}
+
+/// The votes of a proof which can be shown to others, for its batch, by voter.
+///
+/// Signed messages are kept as they are. Otherwise, we fall back to the vote signature,
+/// which is all that is left of the votes of a compacted proof.
+fn transferable_votes<RQ>(proof: &Proof<RQ>, kind: VoteKind) -> BTreeMap<NodeId, Testimony<RQ>> {
+    let (seq, digest) = (proof.sequence_number(), proof.batch_digest());
+
+    if let Some(certificate) = proof.certificate() {
+        let votes = match kind {
+            VoteKind::Prepare => Some(certificate.prepares()),
+            VoteKind::Commit => certificate.commits(),
+        };
+
+        return votes
+            .into_iter()
+            .flat_map(|votes| votes.votes(kind, seq, &digest))
+            .map(|vote| (vote.signer(), Testimony::Vote(vote)))
+            .collect();
+    }
+
+    let votes = match kind {
+        VoteKind::Prepare => proof.prepares(),
+        VoteKind::Commit => proof.commits(),
+    };
+
+    votes
+        .iter()
+        .filter_map(|vote| {
+            let PBFTMessage::Consensus(message) = vote.message() else {
+                return None;
+            };
+
+            if message.sequence_number() != seq
+                || !message.has_proposed_digest(&digest).unwrap_or(false)
+            {
+                return None;
+            }
+
+            let from = vote.header().from();
+
+            let testimony = if message.authentication() == MessageAuthentication::Signature {
+                Testimony::Message(vote.clone())
+            } else {
+                Testimony::Vote(SignedVote::from_message(from, message)?)
+            };
+
+            Some((from, testimony))
+        })
+        .collect()
+}
+
+fn vote_view<RQ>(vote: &Testimony<RQ>) -> Option<SeqNo> {
+    match vote {
+        Testimony::Message(message) => message
+            .message()
+            .consensus()
+            .ok()
+            .map(|message| message.view()),
+        Testimony::Vote(vote) => Some(vote.view()),
+    }
+}
+
+/// Did the replica, in its collect for the view after the one where it committed
+/// the batch, fail to report the batch as prepared?
+fn forgot_commit<RQ>(
+    collect: &StoredMessage<PBFTMessage<RQ>>,
+    commit: &Testimony<RQ>,
+    seq: SeqNo,
+    digest: &Digest,
+) -> bool {
+    let PBFTMessage::ViewChange(view_change) = collect.message() else {
+        return false;
+    };
+
+    let ViewChangeMessageKind::StopData(collect_data) = view_change.kind() else {
+        return false;
+    };
+
+    let commit_view = match commit {
+        Testimony::Message(message) => match message.message().consensus() {
+            Ok(message) if matches!(message.kind(), ConsensusMessageKind::Commit(_)) => {
+                message.view()
+            }
+            _ => return false,
+        },
+        Testimony::Vote(vote) if vote.kind() == VoteKind::Commit => vote.view(),
+        Testimony::Vote(_) => return false,
+    };
+
+    if view_change.sequence_number() != commit_view.next()
+        || collect_data.incomplete_proof().executing() != seq
+    {
+        // Replicas which have already executed the instance no longer report it,
+        // and the ones in later views may have legitimately moved on
+        return false;
+    }
+
+    !reports_prepared(collect_data, commit_view, digest)
+}
+
+#[derive(Error, Debug)]
+pub enum ForensicsError {
+    #[error("The proofs pertain to different instances {0:?} and {1:?}")]
+    DifferentInstances(SeqNo, SeqNo),
+    #[error("The proofs for instance {0:?} decide the same batch")]
+    ProofsDoNotConflict(SeqNo),
+}
+
+#[cfg(test)]
+mod forensics_tests {
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::crypto::signature::{KeyPair, PublicKey};
+    use atlas_common::globals::ReadOnly;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+    use atlas_communication::lookup_table::MessageModule;
+    use atlas_communication::message::{Header, StoredMessage, WireMessage};
+
+    use crate::bft::evidence::{MisbehaviourKind, Testimony};
+    use crate::bft::log::certificate::{sign_vote, VoteKind};
+    use crate::bft::log::decisions::{
+        CollectData, IncompleteProof, PrepareSet, Proof, ProofMetadata, StoredConsensusMessage,
+        ViewDecisionPair,
+    };
+    use crate::bft::message::{
+        ConsensusMessage, ConsensusMessageKind, FwdConsensusMessage, PBFTMessage,
+        ViewChangeMessage, ViewChangeMessageKind,
+    };
+    use crate::bft::sync::view::ViewInfo;
+    use crate::bft::sync::LeaderCollects;
+
+    use super::analyse_conflicting_proofs;
+
+    const SEQ: u32 = 7;
+
+    fn digest(byte: u8) -> Digest {
+        Digest::from_bytes(&[byte; Digest::LENGTH]).unwrap()
+    }
+
+    fn header(from: u32) -> Header {
+        let from = NodeId::from(from);
+
+        let (header, _, _) = WireMessage::new(
+            from,
+            from,
+            MessageModule::Protocol,
+            Vec::new().into(),
+            0,
+            Some(digest(0)),
+            None,
+        )
+        .into_inner();
+
+        header
+    }
+
+    fn vote(from: u32, view: u32, kind: ConsensusMessageKind<()>) -> StoredConsensusMessage<()> {
+        let message = ConsensusMessage::new(SeqNo::from(SEQ), SeqNo::from(view), kind);
+
+        Arc::new(ReadOnly::new(StoredMessage::new(
+            header(from),
+            PBFTMessage::Consensus(message),
+        )))
+    }
+
+    /// A proof of `batch`, prepared and committed by `voters` in `view`
+    fn proof(view: u32, batch: Digest, voters: &[u32]) -> Proof<()> {
+        let metadata = ProofMetadata::new(SeqNo::from(SEQ), batch, vec![batch], 0);
+
+        let prepares = voters
+            .iter()
+            .map(|voter| vote(*voter, view, ConsensusMessageKind::Prepare(batch)))
+            .collect();
+
+        let commits = voters
+            .iter()
+            .map(|voter| vote(*voter, view, ConsensusMessageKind::Commit(batch)))
+            .collect();
+
+        Proof::new(metadata, Vec::new(), prepares, commits)
+    }
+
+    fn key_pair(node: u32) -> KeyPair {
+        KeyPair::from_bytes(&[node as u8 + 1; 32][..]).unwrap()
+    }
+
+    /// A vote carrying a vote signature, so that it can be compacted
+    fn signed_vote(
+        from: u32,
+        view: u32,
+        kind: VoteKind,
+        batch: Digest,
+    ) -> StoredConsensusMessage<()> {
+        let (seq, view) = (SeqNo::from(SEQ), SeqNo::from(view));
+
+        let message_kind = match kind {
+            VoteKind::Prepare => ConsensusMessageKind::Prepare(batch),
+            VoteKind::Commit => ConsensusMessageKind::Commit(batch),
+        };
+
+        let signature = sign_vote(&key_pair(from), kind, seq, view, &batch).unwrap();
+
+        let message = ConsensusMessage::new(seq, view, message_kind).with_vote_signature(signature);
+
+        Arc::new(ReadOnly::new(StoredMessage::new(
+            header(from),
+            PBFTMessage::Consensus(message),
+        )))
+    }
+
+    /// Like [proof], but compacted into a quorum certificate
+    fn compacted_proof(view: u32, batch: Digest, voters: &[u32]) -> Proof<()> {
+        let metadata = ProofMetadata::new(SeqNo::from(SEQ), batch, vec![batch], 0);
+
+        let votes = |kind| {
+            voters
+                .iter()
+                .map(|voter| signed_vote(*voter, view, kind, batch))
+                .collect()
+        };
+
+        let mut proof = Proof::new(
+            metadata,
+            Vec::new(),
+            votes(VoteKind::Prepare),
+            votes(VoteKind::Commit),
+        );
+
+        proof.compact().unwrap();
+
+        assert!(proof.certificate().is_some());
+        assert!(proof.commits().is_empty());
+
+        proof
+    }
+
+    /// The collect sent by `from` in the view change to `view`, reporting `prepared`
+    fn collect(
+        from: u32,
+        view: u32,
+        prepared: Option<ViewDecisionPair>,
+    ) -> StoredMessage<PBFTMessage<()>> {
+        let incomplete = IncompleteProof::new(SeqNo::from(SEQ), PrepareSet(Vec::new()), prepared);
+
+        let message = ViewChangeMessage::new(
+            SeqNo::from(view),
+            ViewChangeMessageKind::StopData(CollectData::new(incomplete, None)),
+        );
+
+        StoredMessage::new(header(from), PBFTMessage::ViewChange(message))
+    }
+
+    fn transcript(
+        leader: u32,
+        view: u32,
+        collects: Vec<StoredMessage<PBFTMessage<()>>>,
+    ) -> LeaderCollects<()> {
+        let proposed = ConsensusMessage::new(
+            SeqNo::from(SEQ),
+            SeqNo::from(view),
+            ConsensusMessageKind::PrePrepare(Vec::new()),
+        );
+
+        LeaderCollects::new(
+            FwdConsensusMessage::new(header(leader), proposed),
+            collects,
+            None,
+        )
+    }
+
+    fn nodes(ids: &[u32]) -> BTreeSet<NodeId> {
+        ids.iter().copied().map(NodeId::from).collect()
+    }
+
+    #[test]
+    fn test_same_view_double_commit_blames_the_intersection() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let first = proof(0, digest(1), &[0, 1, 2]);
+        let second = proof(0, digest(2), &[1, 2, 3]);
+
+        let report = analyse_conflicting_proofs(&first, &second, &[]).unwrap();
+
+        assert_eq!(report.sequence_number(), SeqNo::from(SEQ));
+        assert_eq!(report.culprits(), nodes(&[1, 2]));
+        assert_eq!(report.evidence().len(), 2);
+        assert!(report
+            .evidence()
+            .iter()
+            .all(|evidence| evidence.kind() == MisbehaviourKind::ConflictingCommits));
+        assert!(report.is_conclusive(&view));
+    }
+
+    #[test]
+    fn test_amnesia_across_view_change_blames_the_forgetful_committers() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let (committed, later) = (digest(1), digest(2));
+
+        let first = proof(0, committed, &[0, 1, 2]);
+        let second = proof(1, later, &[1, 2, 3]);
+
+        let transcripts = [
+            // Replica 0 reported the committed batch, replicas 1 and 2 claimed to have
+            // prepared nothing and replica 3 never committed it, so it had nothing to report
+            transcript(
+                1,
+                1,
+                vec![
+                    collect(0, 1, Some(ViewDecisionPair(SeqNo::ZERO, committed))),
+                    collect(1, 1, None),
+                    collect(2, 1, None),
+                    collect(3, 1, None),
+                ],
+            ),
+            // Only the view change right after the commit is relevant
+            transcript(2, 2, vec![collect(0, 2, None)]),
+        ];
+
+        let report = analyse_conflicting_proofs(&first, &second, &transcripts).unwrap();
+
+        assert_eq!(report.culprits(), nodes(&[1, 2]));
+        assert_eq!(report.evidence().len(), 2);
+        assert!(report
+            .evidence()
+            .iter()
+            .all(|evidence| evidence.kind() == MisbehaviourKind::Amnesia));
+        assert!(report.is_conclusive(&view));
+    }
+
+    #[test]
+    fn test_amnesia_is_not_reported_without_transcripts() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let first = proof(0, digest(1), &[0, 1, 2]);
+        let second = proof(1, digest(2), &[1, 2, 3]);
+
+        let report = analyse_conflicting_proofs(&first, &second, &[]).unwrap();
+
+        assert!(report.culprits().is_empty());
+        assert!(!report.is_conclusive(&view));
+    }
+
+    #[test]
+    fn test_compacted_proofs_blame_the_signers_of_both_certificates() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let first = compacted_proof(0, digest(1), &[0, 1, 2]);
+        let second = proof(0, digest(2), &[1, 2, 3]);
+
+        let report = analyse_conflicting_proofs(&first, &second, &[]).unwrap();
+
+        assert_eq!(report.culprits(), nodes(&[1, 2]));
+        assert!(report.is_conclusive(&view));
+
+        for evidence in report.evidence() {
+            assert_eq!(evidence.kind(), MisbehaviourKind::ConflictingCommits);
+
+            let (Testimony::Vote(vote), Testimony::Message(_)) = evidence.testimonies() else {
+                panic!("Expected a vote from the certificate and a signed message");
+            };
+
+            let offender = u32::from(evidence.offender());
+            let public_key = PublicKey::from(key_pair(offender).public_key());
+
+            assert_eq!(vote.signer(), evidence.offender());
+            assert_eq!(vote.kind(), VoteKind::Commit);
+            assert_eq!(*vote.digest(), digest(1));
+            assert!(vote.verify(&public_key).is_ok());
+
+            let other = PublicKey::from(key_pair(offender + 1).public_key());
+
+            assert!(vote.verify(&other).is_err());
+        }
+    }
+
+    #[test]
+    fn test_compacted_proofs_reveal_amnesia() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let committed = digest(1);
+
+        let first = compacted_proof(0, committed, &[0, 1, 2]);
+        let second = compacted_proof(1, digest(2), &[1, 2, 3]);
+
+        let transcripts = [transcript(
+            1,
+            1,
+            vec![
+                collect(0, 1, Some(ViewDecisionPair(SeqNo::ZERO, committed))),
+                collect(1, 1, None),
+                collect(2, 1, None),
+            ],
+        )];
+
+        let report = analyse_conflicting_proofs(&first, &second, &transcripts).unwrap();
+
+        assert_eq!(report.culprits(), nodes(&[1, 2]));
+        assert!(report
+            .evidence()
+            .iter()
+            .all(|evidence| evidence.kind() == MisbehaviourKind::Amnesia
+                && matches!(evidence.testimonies().0, Testimony::Vote(_))));
+        assert!(report.is_conclusive(&view));
+    }
+}
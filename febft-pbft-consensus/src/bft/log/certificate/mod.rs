@@ -0,0 +1,628 @@
+//! Compact quorum certificates for decided proofs.
+//!
+//! Instead of carrying every `PREPARE` and `COMMIT` message, a compacted proof
+//! carries a bitmap of the replicas which voted and the signature of each of them
+//! over a canonical vote statement, which does not depend on the message headers.
+//!
+//! This is not an aggregate signature: the certificate still grows with the number of
+//! signers, by one signature each, but it drops the headers and the payloads of the votes.
+
+use std::fmt::{Debug, Formatter};
+
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::crypto::signature::{KeyPair, PublicKey, Signature};
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::Err;
+
+use crate::bft::log::decisions::StoredConsensusMessage;
+use crate::bft::message::{ConsensusMessage, ConsensusMessageKind};
+
+/// The kind of vote which is certified
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoteKind {
+    Prepare,
+    Commit,
+}
+
+/// The set of replicas that contributed to a certificate.
+/// Bit `i` is set when the replica with id `i` signed.
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SignerBitmap(Vec<u8>);
+
+/// The signatures of a set of replicas over the same vote statement
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct VoteCertificate {
+    view: SeqNo,
+    signers: SignerBitmap,
+    /// The signatures, ordered by the id of the signer
+    signatures: Vec<Signature>,
+}
+
+/// The vote of a single replica, as kept in a certificate.
+///
+/// Like the signed message it was taken from, it can be shown to anyone
+/// holding the public key of the signer.
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct SignedVote {
+    signer: NodeId,
+    kind: VoteKind,
+    seq: SeqNo,
+    view: SeqNo,
+    digest: Digest,
+    signature: Signature,
+}
+
+/// The compacted prepare and commit quorums of a decided proof
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct QuorumCertificate {
+    prepares: VoteCertificate,
+    /// Not present when the fault model does not require the commit phase
+    commits: Option<VoteCertificate>,
+}
+
+impl SignerBitmap {
+    pub fn insert(&mut self, node: NodeId) -> bool {
+        let index = node_index(&node);
+        let (byte, bit) = (index / 8, index % 8);
+
+        if self.0.len() <= byte {
+            self.0.resize(byte + 1, 0);
+        }
+
+        let already_present = self.0[byte] & (1 << bit) != 0;
+
+        self.0[byte] |= 1 << bit;
+
+        !already_present
+    }
+
+    pub fn contains(&self, node: &NodeId) -> bool {
+        let index = node_index(node);
+
+        self.0
+            .get(index / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|byte| byte.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate the signers, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.0.iter().enumerate().flat_map(|(byte_index, byte)| {
+            (0..8)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| NodeId::from((byte_index * 8 + bit) as u32))
+        })
+    }
+}
+
+impl VoteCertificate {
+    pub fn view(&self) -> SeqNo {
+        self.view
+    }
+
+    pub fn signers(&self) -> &SignerBitmap {
+        &self.signers
+    }
+
+    /// The individual vote of each signer, for the given instance and value
+    pub fn votes<'a>(
+        &'a self,
+        kind: VoteKind,
+        seq: SeqNo,
+        digest: &'a Digest,
+    ) -> impl Iterator<Item = SignedVote> + 'a {
+        self.signers
+            .iter()
+            .zip(self.signatures.iter())
+            .map(move |(signer, signature)| SignedVote {
+                signer,
+                kind,
+                seq,
+                view: self.view,
+                digest: *digest,
+                signature: signature.clone(),
+            })
+    }
+
+    /// Build a certificate from the votes of a proof.
+    /// Every vote must be signed, for the same view and value.
+    fn from_votes<O>(
+        kind: VoteKind,
+        seq: SeqNo,
+        digest: &Digest,
+        votes: &[StoredConsensusMessage<O>],
+    ) -> Result<Option<Self>> {
+        let Some(first) = votes.first() else {
+            return Ok(None);
+        };
+
+        let view = first.message().consensus()?.view();
+
+        let mut signed_votes = Vec::with_capacity(votes.len());
+
+        for vote in votes {
+            let message = vote.message().consensus()?;
+
+            let voted = match (kind, message.kind()) {
+                (VoteKind::Prepare, ConsensusMessageKind::Prepare(voted))
+                | (VoteKind::Commit, ConsensusMessageKind::Commit(voted)) => voted,
+                _ => return Err!(CertificateError::WrongVoteKind(kind)),
+            };
+
+            if voted != digest || message.view() != view || message.sequence_number() != seq {
+                return Err!(CertificateError::MismatchedVote(vote.header().from()));
+            }
+
+            let signature = message
+                .vote_signature()
+                .ok_or(CertificateError::UnsignedVote(vote.header().from()))?;
+
+            signed_votes.push((vote.header().from(), signature.clone()));
+        }
+
+        signed_votes.sort_by_key(|(node, _)| *node);
+        signed_votes.dedup_by_key(|(node, _)| *node);
+
+        let mut signers = SignerBitmap::default();
+        let mut signatures = Vec::with_capacity(signed_votes.len());
+
+        for (node, signature) in signed_votes {
+            signers.insert(node);
+            signatures.push(signature);
+        }
+
+        Ok(Some(Self {
+            view,
+            signers,
+            signatures,
+        }))
+    }
+
+    fn verify<F>(&self, kind: VoteKind, seq: SeqNo, digest: &Digest, public_key_of: &F) -> Result<()>
+    where
+        F: Fn(NodeId) -> Option<PublicKey>,
+    {
+        if self.signers.len() != self.signatures.len() {
+            return Err!(CertificateError::SignatureCountMismatch(
+                self.signers.len(),
+                self.signatures.len()
+            ));
+        }
+
+        let statement = vote_statement(kind, seq, self.view, digest);
+
+        for (signer, signature) in self.signers.iter().zip(self.signatures.iter()) {
+            let public_key =
+                public_key_of(signer).ok_or(CertificateError::UnknownSigner(signer))?;
+
+            if public_key.verify(statement.as_ref(), signature).is_err() {
+                return Err!(CertificateError::InvalidSignature(signer, kind));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl QuorumCertificate {
+    /// Compact the votes of a decided proof into a certificate
+    pub(crate) fn from_votes<O>(
+        seq: SeqNo,
+        digest: &Digest,
+        prepares: &[StoredConsensusMessage<O>],
+        commits: &[StoredConsensusMessage<O>],
+    ) -> Result<Self> {
+        let prepares = VoteCertificate::from_votes(VoteKind::Prepare, seq, digest, prepares)?
+            .ok_or(CertificateError::NoVotes(VoteKind::Prepare))?;
+
+        let commits = VoteCertificate::from_votes(VoteKind::Commit, seq, digest, commits)?;
+
+        Ok(Self { prepares, commits })
+    }
+
+    pub fn prepares(&self) -> &VoteCertificate {
+        &self.prepares
+    }
+
+    pub fn commits(&self) -> Option<&VoteCertificate> {
+        self.commits.as_ref()
+    }
+
+    /// Verify every signature contained in this certificate.
+    ///
+    /// This does not check whether the signers form a quorum, as that depends
+    /// on the view the decision was taken in.
+    pub fn verify<F>(&self, seq: SeqNo, digest: &Digest, public_key_of: F) -> Result<()>
+    where
+        F: Fn(NodeId) -> Option<PublicKey>,
+    {
+        self.prepares
+            .verify(VoteKind::Prepare, seq, digest, &public_key_of)?;
+
+        if let Some(commits) = &self.commits {
+            commits.verify(VoteKind::Commit, seq, digest, &public_key_of)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SignedVote {
+    /// Take the vote signature out of a prepare or commit sent by `from`
+    pub(crate) fn from_message<O>(from: NodeId, message: &ConsensusMessage<O>) -> Option<Self> {
+        let (kind, digest) = match message.kind() {
+            ConsensusMessageKind::Prepare(digest) => (VoteKind::Prepare, digest),
+            ConsensusMessageKind::Commit(digest) => (VoteKind::Commit, digest),
+            _ => return None,
+        };
+
+        Some(Self {
+            signer: from,
+            kind,
+            seq: message.sequence_number(),
+            view: message.view(),
+            digest: *digest,
+            signature: message.vote_signature()?.clone(),
+        })
+    }
+
+    pub fn signer(&self) -> NodeId {
+        self.signer
+    }
+
+    pub fn kind(&self) -> VoteKind {
+        self.kind
+    }
+
+    pub fn view(&self) -> SeqNo {
+        self.view
+    }
+
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// Check the signature of the vote against the public key of its signer
+    pub fn verify(&self, public_key: &PublicKey) -> Result<()> {
+        let statement = vote_statement(self.kind, self.seq, self.view, &self.digest);
+
+        if public_key
+            .verify(statement.as_ref(), &self.signature)
+            .is_err()
+        {
+            return Err!(CertificateError::InvalidSignature(self.signer, self.kind));
+        }
+
+        Ok(())
+    }
+}
+
+impl Orderable for SignedVote {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+/// The canonical statement signed by a replica when voting.
+///
+/// Unlike the signature in the message header, this does not depend on
+/// the destination of the message, so it can be kept in a certificate.
+pub fn vote_statement(kind: VoteKind, seq: SeqNo, view: SeqNo, digest: &Digest) -> Digest {
+    let mut ctx = Context::new();
+
+    let tag: u8 = match kind {
+        VoteKind::Prepare => 0,
+        VoteKind::Commit => 1,
+    };
+
+    ctx.update(&[tag][..]);
+    ctx.update(&u32::from(seq).to_le_bytes()[..]);
+    ctx.update(&u32::from(view).to_le_bytes()[..]);
+    ctx.update(digest.as_ref());
+
+    ctx.finish()
+}
+
+/// Sign the vote statement with our key pair
+pub fn sign_vote(
+    key_pair: &KeyPair,
+    kind: VoteKind,
+    seq: SeqNo,
+    view: SeqNo,
+    digest: &Digest,
+) -> Result<Signature> {
+    key_pair.sign(vote_statement(kind, seq, view, digest).as_ref())
+}
+
+/// Verify the vote signature of a prepare or commit sent by `from`,
+/// so that it can later be kept in a certificate
+pub fn verify_vote<O>(
+    public_key: &PublicKey,
+    from: NodeId,
+    message: &ConsensusMessage<O>,
+) -> Result<()> {
+    let (kind, digest) = match message.kind() {
+        ConsensusMessageKind::Prepare(digest) => (VoteKind::Prepare, digest),
+        ConsensusMessageKind::Commit(digest) => (VoteKind::Commit, digest),
+        // Only prepares and commits are certified
+        _ => return Ok(()),
+    };
+
+    let signature = message
+        .vote_signature()
+        .ok_or(CertificateError::UnsignedVote(from))?;
+
+    let statement = vote_statement(kind, message.sequence_number(), message.view(), digest);
+
+    if public_key.verify(statement.as_ref(), signature).is_err() {
+        return Err!(CertificateError::InvalidSignature(from, kind));
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn node_index(node: &NodeId) -> usize {
+    let index: u64 = (*node).into();
+
+    index as usize
+}
+
+impl Debug for SignerBitmap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl Debug for SignedVote {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "SignedVote {{ signer: {:?}, kind: {:?}, seq: {:?}, view: {:?}, digest: {:?} }}",
+            self.signer, self.kind, self.seq, self.view, self.digest
+        )
+    }
+}
+
+impl Debug for VoteCertificate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "VoteCertificate {{ view: {:?}, signers: {:?} }}",
+            self.view, self.signers
+        )
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CertificateError {
+    #[error("There are no {0:?} votes to build the certificate from")]
+    NoVotes(VoteKind),
+    #[error("Expected {0:?} votes only")]
+    WrongVoteKind(VoteKind),
+    #[error("The vote from {0:?} does not match the decided value")]
+    MismatchedVote(NodeId),
+    #[error("The vote from {0:?} does not carry a vote signature")]
+    UnsignedVote(NodeId),
+    #[error("The certificate has {0} signers but {1} signatures")]
+    SignatureCountMismatch(usize, usize),
+    #[error("Unknown signer {0:?}")]
+    UnknownSigner(NodeId),
+    #[error("Invalid {1:?} signature from {0:?}")]
+    InvalidSignature(NodeId, VoteKind),
+}
+
+#[cfg(test)]
+mod certificate_tests {
+    use std::sync::Arc;
+
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::crypto::signature::{KeyPair, PublicKey};
+    use atlas_common::globals::ReadOnly;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+    use atlas_communication::lookup_table::MessageModule;
+    use atlas_communication::message::{StoredMessage, WireMessage};
+
+    use crate::bft::log::decisions::StoredConsensusMessage;
+    use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage};
+
+    use super::{sign_vote, CertificateError, QuorumCertificate, VoteKind};
+
+    fn digest(byte: u8) -> Digest {
+        Digest::from_bytes(&[byte; Digest::LENGTH]).unwrap()
+    }
+
+    fn key_pair(node: u32) -> KeyPair {
+        KeyPair::from_bytes(&[node as u8 + 1; 32][..]).unwrap()
+    }
+
+    fn public_key_of(node: NodeId) -> Option<PublicKey> {
+        let node = u32::from(node);
+
+        (node < 4).then(|| PublicKey::from(key_pair(node).public_key()))
+    }
+
+    /// A vote of `from`, signed with the key pair of `signer`
+    fn vote(kind: VoteKind, from: u32, signer: u32, value: u8) -> StoredConsensusMessage<()> {
+        let (seq, view) = (SeqNo::ONE, SeqNo::ZERO);
+
+        let message_kind = match kind {
+            VoteKind::Prepare => ConsensusMessageKind::Prepare(digest(value)),
+            VoteKind::Commit => ConsensusMessageKind::Commit(digest(value)),
+        };
+
+        let signature = sign_vote(&key_pair(signer), kind, seq, view, &digest(value)).unwrap();
+
+        let message = ConsensusMessage::new(seq, view, message_kind).with_vote_signature(signature);
+
+        let from = NodeId::from(from);
+
+        let (header, _, _) = WireMessage::new(
+            from,
+            from,
+            MessageModule::Protocol,
+            Vec::new().into(),
+            0,
+            Some(digest(0)),
+            None,
+        )
+        .into_inner();
+
+        Arc::new(ReadOnly::new(StoredMessage::new(
+            header,
+            PBFTMessage::Consensus(message),
+        )))
+    }
+
+    fn votes(kind: VoteKind, voters: &[u32]) -> Vec<StoredConsensusMessage<()>> {
+        voters
+            .iter()
+            .map(|node| vote(kind, *node, *node, 1))
+            .collect()
+    }
+
+    fn certificate_error(err: atlas_common::error::Error) -> CertificateError {
+        err.downcast::<CertificateError>().unwrap()
+    }
+
+    #[test]
+    fn test_certificate_verifies() {
+        let certificate = QuorumCertificate::from_votes(
+            SeqNo::ONE,
+            &digest(1),
+            &votes(VoteKind::Prepare, &[2, 0, 1]),
+            &votes(VoteKind::Commit, &[0, 1, 3]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            certificate.prepares().signers().iter().collect::<Vec<_>>(),
+            NodeId::targets_u32(0..3).collect::<Vec<_>>()
+        );
+
+        certificate
+            .verify(SeqNo::ONE, &digest(1), public_key_of)
+            .unwrap();
+
+        // The certificate only holds for the decided value
+        assert!(certificate
+            .verify(SeqNo::ONE, &digest(2), public_key_of)
+            .is_err());
+        assert!(certificate
+            .verify(SeqNo::ZERO, &digest(1), public_key_of)
+            .is_err());
+    }
+
+    #[test]
+    fn test_forged_certificates_are_rejected() {
+        // Replica 3 signs the vote it attributes to replica 2
+        let mut prepares = votes(VoteKind::Prepare, &[0, 1]);
+
+        prepares.push(vote(VoteKind::Prepare, 2, 3, 1));
+
+        let certificate =
+            QuorumCertificate::from_votes(SeqNo::ONE, &digest(1), &prepares, &[]).unwrap();
+
+        let err = certificate
+            .verify(SeqNo::ONE, &digest(1), public_key_of)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            certificate_error(err),
+            CertificateError::InvalidSignature(node, VoteKind::Prepare) if node == NodeId::from(2u32)
+        ));
+
+        // Signers we don't know about can't be verified
+        let certificate = QuorumCertificate::from_votes(
+            SeqNo::ONE,
+            &digest(1),
+            &votes(VoteKind::Prepare, &[0, 4]),
+            &[],
+        )
+        .unwrap();
+
+        let err = certificate
+            .verify(SeqNo::ONE, &digest(1), public_key_of)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            certificate_error(err),
+            CertificateError::UnknownSigner(_)
+        ));
+    }
+
+    #[test]
+    fn test_short_certificates_are_rejected() {
+        let mut certificate = QuorumCertificate::from_votes(
+            SeqNo::ONE,
+            &digest(1),
+            &votes(VoteKind::Prepare, &[0, 1, 2]),
+            &[],
+        )
+        .unwrap();
+
+        // A signer whose signature was dropped
+        certificate.prepares.signatures.pop();
+
+        let err = certificate
+            .verify(SeqNo::ONE, &digest(1), public_key_of)
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            certificate_error(err),
+            CertificateError::SignatureCountMismatch(3, 2)
+        ));
+    }
+
+    #[test]
+    fn test_certificates_require_matching_signed_votes() {
+        let mut prepares = votes(VoteKind::Prepare, &[0, 1]);
+
+        prepares.push(vote(VoteKind::Prepare, 2, 2, 2));
+
+        let err = QuorumCertificate::from_votes(SeqNo::ONE, &digest(1), &prepares, &[])
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            certificate_error(err),
+            CertificateError::MismatchedVote(node) if node == NodeId::from(2u32)
+        ));
+
+        let err = QuorumCertificate::from_votes(
+            SeqNo::ONE,
+            &digest(1),
+            &votes(VoteKind::Commit, &[0, 1]),
+            &[],
+        )
+        .err()
+        .unwrap();
+
+        assert!(matches!(
+            certificate_error(err),
+            CertificateError::WrongVoteKind(VoteKind::Prepare)
+        ));
+    }
+}
@@ -1,47 +1,256 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+use tracing::{info, warn};
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
 use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::serialization_helper::SerType;
 
+use crate::bft::config::DecisionLogConfig;
+use crate::bft::log::decided::store::ProofStore;
 use crate::bft::log::decisions::Proof;
+use crate::bft::message::ConsensusMessageKind;
+
+pub mod store;
+
+/// How many of the latest decisions we keep around by default, so replicas
+/// which have fallen slightly behind can catch up through the log transfer
+pub const DEFAULT_RETAINED_PROOFS: usize = 128;
+
+/// Tells the ordering protocol which checkpoints became stable, so it can truncate
+/// the decisions they cover. The state transfer protocol is expected to hold a clone
+/// and [notify](StableCheckpoints::notify) it, while the ordering protocol picks
+/// the latest stable checkpoint up whenever it is polled
+#[derive(Clone, Default)]
+pub struct StableCheckpoints {
+    latest: Arc<Mutex<Option<SeqNo>>>,
+}
+
+impl StableCheckpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The checkpoint covering every decision up to `seq` became stable
+    pub fn notify(&self, seq: SeqNo) {
+        let mut latest = self.latest.lock().unwrap();
+
+        if latest.map_or(true, |latest| latest < seq) {
+            *latest = Some(seq);
+        }
+    }
+
+    /// The latest stable checkpoint we were not yet told about
+    pub(crate) fn take(&self) -> Option<SeqNo> {
+        self.latest.lock().unwrap().take()
+    }
+}
+
+impl Debug for StableCheckpoints {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "StableCheckpoints({:?})", self.latest.lock().unwrap())
+    }
+}
 
 /// A necessary decision log for the ability to perform view changes.
-/// Only stores the latest performed decision
+/// Stores a window of the latest performed decisions, which is truncated
+/// whenever a checkpoint becomes stable
 pub struct DecisionLog<O> {
-    /// The last decision that was performed by the ordering protocol
-    last_decision: Option<Proof<O>>,
+    /// The last decisions that were performed by the ordering protocol, in order
+    decisions: VecDeque<Proof<O>>,
+    /// How many decisions we retain
+    window: usize,
+    /// The decision each retained client request was ordered in, by the unique digest of the request
+    requests: BTreeMap<Digest, SeqNo>,
+    /// Keeps a copy of the retained decisions on disk, when enabled
+    store: Option<ProofStore>,
 }
 
-impl<O> DecisionLog<O> {
-    pub(crate) fn init(last_proof: Option<Proof<O>>) -> Self {
-        DecisionLog {
-            last_decision: last_proof,
+impl<O> DecisionLog<O>
+where
+    O: SerType,
+{
+    pub(crate) fn init(config: &DecisionLogConfig) -> Result<Self> {
+        let mut log = DecisionLog {
+            decisions: VecDeque::new(),
+            window: config.retained_proofs.max(1),
+            requests: BTreeMap::new(),
+            store: None,
+        };
+
+        if let Some(dir) = &config.persist_dir {
+            let store = ProofStore::open(dir)?;
+
+            let stored = store.load::<O>()?;
+
+            info!("Restoring {} decided proofs from {:?}", stored.len(), dir);
+
+            let stored_seqs: Vec<SeqNo> = stored.iter().map(|proof| proof.seq_no()).collect();
+
+            // Proofs loaded from disk are already stored, so we only attach the store afterwards
+            stored.into_iter().for_each(|proof| log.append_proof(proof));
+
+            // Drop the stored proofs which did not make it into the window
+            for seq in stored_seqs {
+                if log.proof(seq).is_none() {
+                    store.remove(seq)?;
+                }
+            }
+
+            log.store = Some(store);
         }
+
+        Ok(log)
     }
 
     /// Install a given proof
     pub fn install_proof(&mut self, proof: Proof<O>) {
-        self.last_decision = Some(proof)
+        self.append_proof(proof)
     }
 
     /// Get the last decision
     pub fn last_decision(&self) -> Option<Proof<O>> {
-        self.last_decision.clone()
+        self.decisions.back().cloned()
     }
 
     pub fn last_execution(&self) -> Option<SeqNo> {
-        self.last_decision
-            .as_ref()
+        self.decisions
+            .back()
             .map(|decision| decision.sequence_number())
     }
 
+    /// The sequence number of the oldest decision we still retain
+    pub fn first_retained(&self) -> Option<SeqNo> {
+        self.decisions
+            .front()
+            .map(|decision| decision.sequence_number())
+    }
+
+    /// How many decisions we retain at most
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// The retained decision with the given sequence number
+    pub fn proof(&self, seq: SeqNo) -> Option<&Proof<O>> {
+        let first = u32::from(self.first_retained()?);
+
+        let index = u32::from(seq).checked_sub(first)?;
+
+        self.decisions.get(index as usize)
+    }
+
+    /// The retained decision which ordered the client request with the given unique digest
+    pub fn proof_for_request(&self, digest: &Digest) -> Option<&Proof<O>> {
+        self.requests.get(digest).and_then(|seq| self.proof(*seq))
+    }
+
+    /// The retained decisions from `first` up to (and including) `last`, in order.
+    /// If we no longer retain `first`, no decisions are returned, as the requester
+    /// would not be able to install the others
+    pub fn proofs_between(&self, first: SeqNo, last: SeqNo) -> Vec<Proof<O>> {
+        match self.first_retained() {
+            Some(oldest) if oldest <= first => self
+                .decisions
+                .iter()
+                .skip_while(|decision| decision.sequence_number() < first)
+                .take_while(|decision| decision.sequence_number() <= last)
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn append_proof(&mut self, proof: Proof<O>) {
-        self.last_decision = Some(proof)
+        // A decision which does not follow the last one (e.g. installed by the
+        // state transfer) breaks the chain, so the older ones can no longer be served
+        if self
+            .last_execution()
+            .is_some_and(|last| last.next() != proof.sequence_number())
+        {
+            while self.pop_oldest().is_some() {}
+        }
+
+        if let Some(store) = &self.store {
+            if let Err(err) = store.write(&proof) {
+                warn!(
+                    "Failed to store the proof of decision {:?}: {:?}",
+                    proof.sequence_number(),
+                    err
+                );
+            }
+        }
+
+        for digest in request_digests(&proof) {
+            self.requests.insert(digest, proof.sequence_number());
+        }
+
+        self.decisions.push_back(proof);
+
+        while self.decisions.len() > self.window {
+            self.pop_oldest();
+        }
+    }
+
+    /// A checkpoint covering every decision up to `seq` has become stable,
+    /// so those decisions no longer need to be retained.
+    /// The last decision is always kept, as it is required by the view change protocol
+    pub fn checkpoint_stable(&mut self, seq: SeqNo) {
+        while self.decisions.len() > 1
+            && self
+                .first_retained()
+                .is_some_and(|first| first <= seq)
+        {
+            self.pop_oldest();
+        }
+    }
+
+    fn pop_oldest(&mut self) -> Option<Proof<O>> {
+        let proof = self.decisions.pop_front()?;
+
+        for digest in request_digests(&proof) {
+            // The request may have been ordered again in a later decision
+            if self.requests.get(&digest) == Some(&proof.sequence_number()) {
+                self.requests.remove(&digest);
+            }
+        }
+
+        if let Some(store) = &self.store {
+            if let Err(err) = store.remove(proof.sequence_number()) {
+                warn!(
+                    "Failed to remove the stored proof of decision {:?}: {:?}",
+                    proof.sequence_number(),
+                    err
+                );
+            }
+        }
+
+        Some(proof)
     }
 }
 
+/// The unique digests of the client requests ordered in the given decision
+fn request_digests<O>(proof: &Proof<O>) -> Vec<Digest> {
+    proof
+        .pre_prepares()
+        .iter()
+        .filter_map(|pre_prepare| match pre_prepare.message().consensus().ok()?.kind() {
+            ConsensusMessageKind::PrePrepare(requests) => Some(requests),
+            _ => None,
+        })
+        .flatten()
+        .map(|request| request.header().unique_digest())
+        .collect()
+}
+
 impl<O> Orderable for DecisionLog<O> {
     fn sequence_number(&self) -> SeqNo {
-        self.last_decision
-            .as_ref()
-            .map(|f| f.sequence_number())
+        self.decisions
+            .back()
+            .map(|decision| decision.sequence_number())
             .unwrap_or(SeqNo::ZERO)
     }
 }
@@ -0,0 +1,205 @@
+//! Keeps a copy of the retained decided proofs on disk, one file per decision,
+//! so the window of proofs can be restored when the replica restarts.
+//!
+//! Proofs are serialized by the caller, but written (and removed) by a dedicated
+//! thread, in the order they were issued, so the ordering protocol never waits on the disk.
+//! Each proof is written to a temporary file which is synced and then renamed into
+//! place, so a crash never leaves a partially written proof behind.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+
+use anyhow::Context;
+use thiserror::Error;
+use tracing::warn;
+
+use atlas_common::error::*;
+use atlas_common::ordering::SeqNo;
+use atlas_common::serialization_helper::SerType;
+use atlas_common::Err;
+
+use crate::bft::log::decisions::Proof;
+use crate::bft::message::serialize::{deserialize_proof, serialize_proof};
+
+const PROOF_EXTENSION: &str = "proof";
+
+const TEMP_EXTENSION: &str = "tmp";
+
+/// An operation to be performed by the store thread
+enum StoreOp {
+    Write(SeqNo, Vec<u8>),
+    Remove(SeqNo),
+}
+
+pub struct ProofStore {
+    dir: PathBuf,
+    ops: Sender<StoreOp>,
+}
+
+impl ProofStore {
+    /// Open the store in the given directory, creating it if needed
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create the proof directory {:?}", dir))?;
+
+        let (ops, rx) = mpsc::channel();
+
+        let store_dir = dir.to_path_buf();
+
+        std::thread::Builder::new()
+            .name("Proof store thread".to_string())
+            .spawn(move || {
+                while let Ok(op) = rx.recv() {
+                    let (seq, result) = match op {
+                        StoreOp::Write(seq, bytes) => (seq, write_proof(&store_dir, seq, &bytes)),
+                        StoreOp::Remove(seq) => (seq, remove_proof(&store_dir, seq)),
+                    };
+
+                    if let Err(err) = result {
+                        warn!("Failed to update the stored proof of decision {:?}: {:?}", seq, err);
+                    }
+                }
+            })
+            .context("Failed to start the proof store thread")?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            ops,
+        })
+    }
+
+    /// Queue the given proof to be written to disk
+    pub fn write<O>(&self, proof: &Proof<O>) -> Result<()>
+    where
+        O: SerType,
+    {
+        let bytes = serialize_proof(proof)?;
+
+        self.submit(StoreOp::Write(proof.seq_no(), bytes))
+    }
+
+    /// Queue the proof with the given sequence number to be removed from disk
+    pub fn remove(&self, seq: SeqNo) -> Result<()> {
+        self.submit(StoreOp::Remove(seq))
+    }
+
+    fn submit(&self, op: StoreOp) -> Result<()> {
+        if self.ops.send(op).is_err() {
+            return Err!(ProofStoreError::StoreThreadExited);
+        }
+
+        Ok(())
+    }
+
+    /// Load every stored proof, ordered by sequence number.
+    ///
+    /// Proofs which can't be read back (e.g. written by an older version) are
+    /// deleted, along with any temporary file left behind by a crash
+    pub fn load<O>(&self) -> Result<Vec<Proof<O>>>
+    where
+        O: SerType,
+    {
+        let mut stored = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(PROOF_EXTENSION) => {}
+                Some(TEMP_EXTENSION) => {
+                    discard(&path);
+
+                    continue;
+                }
+                _ => continue,
+            }
+
+            let Some(seq) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse::<u32>().ok())
+            else {
+                continue;
+            };
+
+            stored.push((seq, path));
+        }
+
+        stored.sort_by_key(|(seq, _)| *seq);
+
+        let proofs = stored
+            .into_iter()
+            .filter_map(|(_, path)| {
+                let proof = fs::read(&path)
+                    .with_context(|| format!("Failed to read proof {:?}", path))
+                    .and_then(|bytes| deserialize_proof(&bytes));
+
+                match proof {
+                    Ok(proof) => Some(proof),
+                    Err(err) => {
+                        warn!("Discarding the undecodable proof {:?}: {:?}", path, err);
+
+                        discard(&path);
+
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(proofs)
+    }
+}
+
+fn path_of(dir: &Path, seq: SeqNo, extension: &str) -> PathBuf {
+    dir.join(format!("{:010}.{}", u32::from(seq), extension))
+}
+
+/// Write the proof to a temporary file, sync it and only then move it into place
+fn write_proof(dir: &Path, seq: SeqNo, bytes: &[u8]) -> Result<()> {
+    let temp = path_of(dir, seq, TEMP_EXTENSION);
+    let path = path_of(dir, seq, PROOF_EXTENSION);
+
+    let mut file =
+        File::create(&temp).with_context(|| format!("Failed to create proof {:?}", temp))?;
+
+    file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("Failed to write proof to {:?}", temp))?;
+
+    fs::rename(&temp, &path)
+        .with_context(|| format!("Failed to move proof {:?} into place", temp))?;
+
+    sync_dir(dir)
+}
+
+fn remove_proof(dir: &Path, seq: SeqNo) -> Result<()> {
+    let path = path_of(dir, seq, PROOF_EXTENSION);
+
+    fs::remove_file(&path).with_context(|| format!("Failed to remove proof {:?}", path))?;
+
+    sync_dir(dir)
+}
+
+/// Sync the directory, so the renames and removals in it are durable
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)
+        .and_then(|dir| dir.sync_all())
+        .with_context(|| format!("Failed to sync the proof directory {:?}", dir))?;
+
+    Ok(())
+}
+
+fn discard(path: &Path) {
+    if let Err(err) = fs::remove_file(path) {
+        warn!("Failed to remove {:?}: {:?}", path, err);
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProofStoreError {
+    #[error("The proof store thread has exited")]
+    StoreThreadExited,
+}
@@ -48,6 +48,10 @@ pub struct CompletedBatch<O> {
     pub(super) client_request_info: Vec<ClientRqInfo>,
     // The client requests contained in this batch
     pub(super) client_requests: Vec<StoredMessage<O>>,
+    // The slots of the pre prepare ordering that were filled with an empty batch
+    pub(super) skipped_slots: Vec<usize>,
+    // The leaders whose slots were filled with an empty batch
+    pub(super) skipped_leaders: Vec<NodeId>,
 
     // The metadata for the batch
     pub(super) batch_meta: BatchMeta,
@@ -82,6 +86,14 @@ pub struct WorkingDecisionLog<O> {
     batch_meta: Arc<Mutex<BatchMeta>>,
     // The contained requests per each of the received pre prepares
     contained_requests: Vec<Option<Vec<StoredMessage<O>>>>,
+    // The replicas that voted to fill the slot of each silent leader with an empty batch
+    skip_votes: BTreeMap<NodeId, BTreeSet<NodeId>>,
+    // The leaders we have voted to skip, whose pre prepares we hold back
+    voted_to_skip: BTreeSet<NodeId>,
+    // The pre prepares we received from leaders we had voted to skip, kept in case the skip fails
+    withheld_pre_prepares: BTreeMap<NodeId, ShareableMessage<PBFTMessage<O>>>,
+    // The slots that were filled with an empty batch
+    skipped_slots: Vec<usize>,
 }
 
 /// Checks to make sure replicas aren't providing more than one vote for the
@@ -125,11 +137,8 @@ impl<O> MessageLog<O> {
 
     pub fn finalize(self) -> FinishedMessageLog<O> {
         FinishedMessageLog {
-            pre_prepares: self
-                .pre_prepare
-                .into_iter()
-                .map(|opt| opt.unwrap())
-                .collect(),
+            // Skipped slots have no pre prepare message
+            pre_prepares: self.pre_prepare.into_iter().flatten().collect(),
             prepares: self.prepares,
             commits: self.commits,
         }
@@ -156,6 +165,10 @@ where
             message_log: MessageLog::with_leader_count(view.leader_set().len(), view.quorum()),
             batch_meta: Arc::new(Mutex::new(BatchMeta::new())),
             contained_requests: iter::repeat(None).take(leader_count).collect(),
+            skip_votes: Default::default(),
+            voted_to_skip: Default::default(),
+            withheld_pre_prepares: Default::default(),
+            skipped_slots: Vec::new(),
         }
     }
 
@@ -170,7 +183,11 @@ where
         digest: Digest,
         mut batch_rq_digests: Vec<ClientRqInfo>,
     ) -> Result<Option<ProofMetadata>> {
-        let (header, message) = (s_message.header(), s_message.message().consensus());
+        let (header, message) = (s_message.header(), s_message.message().consensus()?);
+
+        let ConsensusMessageKind::PrePrepare(requests) = message.kind() else {
+            return Err!(DecidingLogError::UnexpectedMessageKind(header.from()));
+        };
 
         let start = Instant::now();
 
@@ -202,10 +219,7 @@ where
         }
 
         self.pre_prepare_digests[leader_index] = Some(digest);
-        self.contained_requests[leader_index] = Some(match message.kind() {
-            ConsensusMessageKind::PrePrepare(requests) => requests.clone(),
-            _ => unreachable!(),
-        });
+        self.contained_requests[leader_index] = Some(requests.clone());
 
         self.current_received_pre_prepares += 1;
 
@@ -217,27 +231,114 @@ where
 
         metric_duration(PRE_PREPARE_LOG_ANALYSIS_ID, start.elapsed());
 
-        // if we have received all of the messages in the set, calculate the digest.
-        Ok(
-            if self.current_received_pre_prepares == self.leader_set.len() {
-                // We have received all of the required batches
-                let result = self.calculate_instance_digest();
+        self.complete_pre_prepares()
+    }
 
-                let (digest, ordering) =
-                    result.ok_or(DecidingLogError::FailedToCalculateDigest(self.seq_no))?;
+    /// If we have received (or skipped) all of the pre prepares in the set, calculate the digest.
+    fn complete_pre_prepares(&mut self) -> Result<Option<ProofMetadata>> {
+        if self.current_received_pre_prepares != self.leader_set.len() {
+            return Ok(None);
+        }
 
-                self.batch_digest = Some(digest);
+        // We have received all of the required batches
+        let result = self.calculate_instance_digest();
 
-                Some(ProofMetadata::new(
-                    self.seq_no,
-                    digest,
-                    ordering,
-                    self.current_batch_size,
-                ))
-            } else {
-                None
-            },
-        )
+        let (digest, ordering) =
+            result.ok_or(DecidingLogError::FailedToCalculateDigest(self.seq_no))?;
+
+        self.batch_digest = Some(digest);
+
+        Ok(Some(
+            ProofMetadata::new(self.seq_no, digest, ordering, self.current_batch_size)
+                .with_skipped_slots(self.skipped_slots.clone()),
+        ))
+    }
+
+    /// The leaders whose pre prepares we are still waiting for
+    pub fn missing_leaders(&self) -> Vec<NodeId> {
+        self.leader_set
+            .iter()
+            .zip(self.pre_prepare_digests.iter())
+            .filter(|(_, digest)| digest.is_none())
+            .map(|(leader, _)| *leader)
+            .collect()
+    }
+
+    /// Register that we have voted to fill the slot of the given leader with an empty batch.
+    /// From now on, that leader's pre prepare for this instance is held back
+    /// until we know whether the skip succeeded.
+    pub fn vote_to_skip(&mut self, leader: NodeId) {
+        self.voted_to_skip.insert(leader);
+    }
+
+    /// Have we voted to skip the slot of the given leader, without that slot being filled yet
+    pub fn skip_pending(&self, leader: &NodeId) -> bool {
+        self.voted_to_skip.contains(leader) && !self.slot_skipped(leader)
+    }
+
+    /// Has the slot of the given leader already been filled with an empty batch
+    pub fn slot_skipped(&self, leader: &NodeId) -> bool {
+        self.skipped_slots
+            .iter()
+            .any(|slot| self.leader_set.get(*slot) == Some(leader))
+    }
+
+    /// Hold back the pre prepare of a leader we have voted to skip, so it can
+    /// still be accepted if the skip does not gather a quorum.
+    /// Only the first pre prepare of each leader is kept.
+    pub fn withhold_pre_prepare(
+        &mut self,
+        leader: NodeId,
+        pre_prepare: ShareableMessage<PBFTMessage<O>>,
+    ) {
+        self.withheld_pre_prepares.entry(leader).or_insert(pre_prepare);
+    }
+
+    /// Give up on skipping the slot of the given leader, as the skip did not gather a quorum.
+    ///
+    /// Returns the pre prepare we held back from that leader, if any, so it can be accepted
+    pub fn abandon_skip(&mut self, leader: &NodeId) -> Option<ShareableMessage<PBFTMessage<O>>> {
+        self.voted_to_skip.remove(leader);
+
+        self.withheld_pre_prepares.remove(leader)
+    }
+
+    /// Process a vote, from `voter`, to fill the slot of `leader` with an empty batch.
+    ///
+    /// When a quorum agrees, the slot is filled. If a pre prepare was already accepted
+    /// for that slot it is kept, and any disagreement on the batch is resolved
+    /// in the prepare phase (or by a view change).
+    pub fn process_skip_vote(
+        &mut self,
+        voter: NodeId,
+        leader: NodeId,
+        view: &ViewInfo,
+    ) -> Result<Option<ProofMetadata>> {
+        let leader_index = pre_prepare_index_of(&self.leader_set, &leader)?;
+
+        if voter == leader {
+            // A leader does not get a say on skipping its own slot
+            return Ok(None);
+        }
+
+        let voters = self.skip_votes.entry(leader).or_default();
+
+        if !voters.insert(voter) {
+            return Err!(DecidingLogError::DuplicateVoteFromNode(voter));
+        }
+
+        if self.pre_prepare_digests[leader_index].is_some() || !view.is_quorum(voters.iter()) {
+            return Ok(None);
+        }
+
+        self.pre_prepare_digests[leader_index] = Some(skipped_slot_digest(self.seq_no, &leader));
+        self.contained_requests[leader_index] = Some(Vec::new());
+        self.skipped_slots.push(leader_index);
+        self.withheld_pre_prepares.remove(&leader);
+
+        self.current_received_pre_prepares += 1;
+
+        self.complete_pre_prepares()
     }
 
     /// Process the message received
@@ -245,7 +346,7 @@ where
         &mut self,
         s_message: ShareableMessage<PBFTMessage<O>>,
     ) -> Result<()> {
-        let (header, message) = (s_message.header(), s_message.message().consensus());
+        let (header, message) = (s_message.header(), s_message.message().consensus()?);
 
         match message.kind() {
             ConsensusMessageKind::Prepare(_) => {
@@ -258,12 +359,22 @@ where
                     .insert_commit_received(header.from())?;
                 self.message_log.insert_commit(s_message);
             }
-            _ => unreachable!(),
+            _ => return Err!(DecidingLogError::UnexpectedMessageKind(header.from())),
         }
 
         Ok(())
     }
 
+    /// The nodes whose prepare messages have been accepted
+    pub fn prepare_voters(&self) -> &BTreeSet<NodeId> {
+        &self.duplicate_detection.received_prepare_messages
+    }
+
+    /// The nodes whose commit messages have been accepted
+    pub fn commit_voters(&self) -> &BTreeSet<NodeId> {
+        &self.duplicate_detection.received_commit_messages
+    }
+
     /// Getter for batch_meta
     pub fn batch_meta(&self) -> &Arc<Mutex<BatchMeta>> {
         &self.batch_meta
@@ -298,54 +409,41 @@ where
         Some((ctx.finish(), batch_ordered_digests))
     }
 
-    /// Get the current decision
-    pub fn deciding(&self, f: usize) -> IncompleteProof {
-        let write_set = PrepareSet({
-            let mut set = Vec::new();
-
-            for shareable_msg in self.message_log.prepares.iter().rev() {
-                let digest = match shareable_msg.message().consensus().kind() {
-                    ConsensusMessageKind::Prepare(d) => *d,
-                    _ => unreachable!(),
-                };
-
-                set.push(ViewDecisionPair(
-                    shareable_msg.message().consensus().view(),
-                    digest,
-                ));
-            }
-
-            set
-        });
+    /// Get the current decision.
+    /// `prepared_quorum` is the amount of matching prepares required to consider the value prepared
+    pub fn deciding(&self, prepared_quorum: usize) -> IncompleteProof {
+        // Only prepares are ever inserted into the prepare log
+        let prepares: Vec<ViewDecisionPair> = self
+            .message_log
+            .prepares
+            .iter()
+            .rev()
+            .filter_map(|stored| prepared_pair(stored))
+            .collect();
 
         let quorum_prepares = 'outer: {
-            let quorum = f << 1;
+            let quorum = prepared_quorum;
             let mut last_view = None;
             let mut count = 0;
 
-            for stored in self.message_log.prepares.iter().rev() {
+            for pair in &prepares {
                 match last_view {
                     None => (),
-                    Some(v) if stored.message().consensus().view() == v => (),
+                    Some(v) if pair.0 == v => (),
                     _ => count = 0,
                 }
-                last_view = Some(stored.message().consensus().view());
+                last_view = Some(pair.0);
                 count += 1;
                 if count == quorum {
-                    let digest = match stored.message().consensus().kind() {
-                        ConsensusMessageKind::Prepare(d) => *d,
-                        _ => unreachable!(),
-                    };
-                    break 'outer Some(ViewDecisionPair(
-                        stored.message().consensus().view(),
-                        digest,
-                    ));
+                    break 'outer Some(pair.clone());
                 }
             }
 
             break 'outer None;
         };
 
+        let write_set = PrepareSet(prepares);
+
         IncompleteProof::new(self.seq_no, write_set, quorum_prepares)
     }
 
@@ -357,18 +455,27 @@ where
 
         let current_digest = self.batch_digest?;
 
+        // The batch digest is only calculated once every slot has been filled
         let pre_prepare_ordering = self
             .pre_prepare_digests
             .into_iter()
-            .map(|elem| elem.unwrap())
-            .collect();
+            .collect::<Option<Vec<_>>>()?;
 
         let mut requests = Vec::with_capacity(self.current_batch_size);
 
         for pre_prepare_request in self.contained_requests {
-            requests.append(&mut pre_prepare_request.unwrap());
+            requests.append(&mut pre_prepare_request?);
         }
 
+        let mut skipped_slots = self.skipped_slots;
+
+        skipped_slots.sort_unstable();
+
+        let skipped_leaders = skipped_slots
+            .iter()
+            .map(|slot| self.leader_set[*slot])
+            .collect();
+
         Some(CompletedBatch {
             seq: self.seq_no,
             digest: current_digest,
@@ -377,6 +484,8 @@ where
             client_request_info: self.client_rqs,
             batch_meta,
             client_requests: requests,
+            skipped_slots,
+            skipped_leaders,
         })
     }
 }
@@ -405,12 +514,20 @@ impl<O> CompletedBatch<O> {
             client_request_info,
             client_requests,
             batch_meta,
+            skipped_slots: Vec::new(),
+            skipped_leaders: Vec::new(),
         }
     }
 
     pub fn request_count(&self) -> usize {
         self.client_requests.len()
     }
+
+    /// The leaders whose slots were filled with an empty batch, as they failed to
+    /// send their pre prepare in time
+    pub fn skipped_leaders(&self) -> &[NodeId] {
+        &self.skipped_leaders
+    }
 }
 
 impl<O> Orderable for WorkingDecisionLog<O> {
@@ -479,6 +596,34 @@ pub fn pre_prepare_index_of_from_digest(
     }
 }
 
+/// The digest that takes the place of the pre prepare of a skipped leader.
+///
+/// This is bound to the sequence number and the leader, so agreeing on the
+/// batch digest also means agreeing on which slots were skipped.
+pub fn skipped_slot_digest(seq: SeqNo, leader: &NodeId) -> Digest {
+    let leader: u64 = (*leader).into();
+
+    let mut ctx = Context::new();
+
+    ctx.update(b"skipped-slot");
+    ctx.update(&u32::from(seq).to_le_bytes()[..]);
+    ctx.update(&leader.to_le_bytes()[..]);
+
+    ctx.finish()
+}
+
+/// The view and digest voted for by a prepare message
+fn prepared_pair<O>(stored: &ShareableMessage<PBFTMessage<O>>) -> Option<ViewDecisionPair> {
+    let PBFTMessage::Consensus(message) = stored.message() else {
+        return None;
+    };
+
+    match message.kind() {
+        ConsensusMessageKind::Prepare(digest) => Some(ViewDecisionPair(message.view(), *digest)),
+        _ => None,
+    }
+}
+
 pub fn pre_prepare_index_of(leader_set: &[NodeId], proposer: &NodeId) -> Result<usize> {
     match leader_set.iter().position(|node| *node == *proposer) {
         None => {
@@ -507,4 +652,6 @@ pub enum DecidingLogError {
     BatchContainsRequestsNotInLeaderAddrSpace(NodeId),
     #[error("Failed to get leader's request space {0:?}")]
     FailedToGetLeadersRequestSpace(NodeId),
+    #[error("Received a consensus message of an unexpected kind from {0:?}")]
+    UnexpectedMessageKind(NodeId),
 }
@@ -8,11 +8,13 @@ use thiserror::Error;
 
 use atlas_common::crypto::hash::Digest;
 use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::Err;
 use atlas_core::ordering_protocol::networking::serialize::OrderProtocolProof;
 use atlas_core::ordering_protocol::ShareableMessage;
 
+use crate::bft::log::certificate::QuorumCertificate;
 use crate::bft::message::{ConsensusMessageKind, PBFTMessage};
 
 pub type StoredConsensusMessage<O> = ShareableMessage<PBFTMessage<O>>;
@@ -35,6 +37,13 @@ pub struct ProofMetadata {
     batch_digest: Digest,
     pre_prepare_ordering: Vec<Digest>,
     contained_client_rqs: usize,
+    /// The compacted prepare and commit quorums, when the proof was compacted
+    #[cfg_attr(feature = "serialize_serde", serde(default))]
+    certificate: Option<QuorumCertificate>,
+    /// The slots of the pre prepare ordering which were filled with an empty batch,
+    /// since their leader did not send its pre prepare in time
+    #[cfg_attr(feature = "serialize_serde", serde(default))]
+    skipped_slots: Vec<usize>,
 }
 
 impl Orderable for ProofMetadata {
@@ -98,9 +107,19 @@ impl ProofMetadata {
             batch_digest: digest,
             pre_prepare_ordering,
             contained_client_rqs: contained_rqs,
+            certificate: None,
+            skipped_slots: Vec::new(),
         }
     }
 
+    pub(crate) fn with_skipped_slots(mut self, mut skipped_slots: Vec<usize>) -> Self {
+        skipped_slots.sort_unstable();
+
+        self.skipped_slots = skipped_slots;
+
+        self
+    }
+
     pub fn seq_no(&self) -> SeqNo {
         self.seq_no
     }
@@ -116,6 +135,33 @@ impl ProofMetadata {
     pub fn contained_client_rqs(&self) -> usize {
         self.contained_client_rqs
     }
+
+    /// The compact quorum certificate of this proof, if it has been compacted
+    pub fn certificate(&self) -> Option<&QuorumCertificate> {
+        self.certificate.as_ref()
+    }
+
+    pub fn skipped_slots(&self) -> &[usize] {
+        &self.skipped_slots
+    }
+
+    /// The leaders whose slots were skipped, given the leader set the decision was made with
+    pub fn skipped_leaders(&self, leader_set: &[NodeId]) -> Vec<NodeId> {
+        self.skipped_slots
+            .iter()
+            .filter_map(|slot| leader_set.get(*slot))
+            .copied()
+            .collect()
+    }
+
+    /// The digests of the pre prepares which are actually part of the decision
+    fn proposed_pre_prepares(&self) -> impl Iterator<Item = &Digest> {
+        self.pre_prepare_ordering
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.skipped_slots.binary_search(index).is_err())
+            .map(|(_, digest)| digest)
+    }
 }
 
 impl<O> Proof<O> {
@@ -144,7 +190,7 @@ impl<O> Proof<O> {
         let mut commits = Vec::new();
 
         for x in messages {
-            match x.message().consensus().kind() {
+            match x.message().consensus()?.kind() {
                 ConsensusMessageKind::PrePrepare(_) => {
                     let option = metadata
                         .pre_prepare_ordering()
@@ -153,6 +199,10 @@ impl<O> Proof<O> {
 
                     let index = option.ok_or(ProofError::PrePrepareNotContainedInMetadata)?;
 
+                    if metadata.skipped_slots().binary_search(&index).is_ok() {
+                        return Err!(ProofError::PrePrepareForSkippedSlot(index));
+                    }
+
                     pre_prepares[index] = Some(x);
                 }
                 ConsensusMessageKind::Prepare(_) => {
@@ -161,12 +211,19 @@ impl<O> Proof<O> {
                 ConsensusMessageKind::Commit(_) => {
                     commits.push(x);
                 }
+                ConsensusMessageKind::SkipSlot(_) => {
+                    return Err!(ProofError::SkipSlotInProof);
+                }
             }
         }
 
         let mut pre_prepares_f = Vec::with_capacity(metadata.pre_prepare_ordering().len());
 
-        for message in pre_prepares.into_iter() {
+        for (index, message) in pre_prepares.into_iter().enumerate() {
+            if metadata.skipped_slots().binary_search(&index).is_ok() {
+                continue;
+            }
+
             pre_prepares_f.push(message.ok_or(ProofError::PrePrepareListNotComplete)?);
         }
 
@@ -199,9 +256,12 @@ impl<O> Proof<O> {
 
     /// Check if the amount of pre prepares line up with the expected amount
     fn check_pre_prepare_sizes(&self) -> Result<()> {
-        if self.metadata.pre_prepare_ordering().len() != self.pre_prepares.len() {
+        let expected =
+            self.metadata.pre_prepare_ordering().len() - self.metadata.skipped_slots().len();
+
+        if expected != self.pre_prepares.len() {
             return Err!(ProofError::WrongPrePrepareCount(
-                self.metadata.pre_prepare_ordering().len(),
+                expected,
                 self.pre_prepares.len()
             ));
         }
@@ -214,10 +274,12 @@ impl<O> Proof<O> {
     pub fn are_pre_prepares_ordered(&self) -> Result<bool> {
         self.check_pre_prepare_sizes()?;
 
-        for index in 0..self.metadata.pre_prepare_ordering().len() {
-            if self.metadata.pre_prepare_ordering()[index]
-                != *self.pre_prepares[index].header().digest()
-            {
+        for (digest, pre_prepare) in self
+            .metadata
+            .proposed_pre_prepares()
+            .zip(self.pre_prepares.iter())
+        {
+            if *digest != *pre_prepare.header().digest() {
                 return Ok(false);
             }
         }
@@ -229,11 +291,9 @@ impl<O> Proof<O> {
     pub fn order_pre_prepares(&mut self) -> Result<()> {
         self.check_pre_prepare_sizes()?;
 
-        let pre_prepare_ordering = self.metadata.pre_prepare_ordering();
+        let mut ordered_pre_prepares = Vec::with_capacity(self.pre_prepares.len());
 
-        let mut ordered_pre_prepares = Vec::with_capacity(pre_prepare_ordering.len());
-
-        for digest in pre_prepare_ordering {
+        for digest in self.metadata.proposed_pre_prepares() {
             let pre_prepare = self
                 .pre_prepares
                 .iter()
@@ -256,6 +316,30 @@ impl<O> Proof<O> {
         Ok(())
     }
 
+    /// Compact the prepare and commit quorums of this proof into a single
+    /// [QuorumCertificate], dropping the messages themselves.
+    ///
+    /// Fails if any of the votes does not carry a vote signature, in which
+    /// case the proof is left untouched.
+    pub fn compact(&mut self) -> Result<()> {
+        if self.metadata.certificate.is_some() {
+            return Ok(());
+        }
+
+        let certificate = QuorumCertificate::from_votes(
+            self.metadata.seq_no,
+            &self.metadata.batch_digest,
+            &self.prepares,
+            &self.commits,
+        )?;
+
+        self.metadata.certificate = Some(certificate);
+        self.prepares = Vec::new();
+        self.commits = Vec::new();
+
+        Ok(())
+    }
+
     pub fn into_parts(self) -> (ProofMetadata, Vec<ShareableMessage<PBFTMessage<O>>>) {
         let mut vec =
             Vec::with_capacity(self.pre_prepares.len() + self.prepares.len() + self.commits.len());
@@ -420,4 +504,8 @@ pub enum ProofError {
     WrongPrePrepareCount(usize, usize),
     #[error("Proof's batches do not match with the digests provided.")]
     BatchDigestsDoNotMatch,
+    #[error("Failed to create proof as a pre prepare was provided for the skipped slot {0:?}")]
+    PrePrepareForSkippedSlot(usize),
+    #[error("Failed to create proof as skip slot messages are not part of proofs")]
+    SkipSlotInProof,
 }
@@ -1,6 +1,7 @@
 use atlas_common::Err;
 use either::Either;
 use thiserror::Error;
+use tracing::warn;
 
 use atlas_common::error::*;
 use atlas_common::node_id::NodeId;
@@ -10,12 +11,14 @@ use atlas_communication::message::Header;
 use atlas_core::messages::{ClientRqInfo, SessionBased};
 use atlas_core::ordering_protocol::{BatchedDecision, Decision, ProtocolConsensusDecision};
 
+use crate::bft::config::DecisionLogConfig;
 use crate::bft::log::decided::DecisionLog;
 use crate::bft::log::deciding::{CompletedBatch, FinishedMessageLog};
 use crate::bft::log::decisions::{Proof, ProofMetadata};
 use crate::bft::message::ConsensusMessageKind;
 use crate::bft::OPDecision;
 
+pub mod certificate;
 pub mod decided;
 pub mod deciding;
 pub mod decisions;
@@ -25,6 +28,8 @@ where
     RQ: SerType,
 {
     decided: DecisionLog<RQ>,
+    /// Should decided proofs be compacted into quorum certificates?
+    compact_certificates: bool,
 }
 
 impl<RQ> Log<RQ>
@@ -35,11 +40,25 @@ where
         &self.decided
     }
 
+    /// Compact the proofs of the decisions we finalize into quorum certificates
+    pub fn set_compact_certificates(&mut self, compact_certificates: bool) {
+        self.compact_certificates = compact_certificates;
+    }
+
     pub fn last_proof(&self) -> Option<Proof<RQ>> {
         self.decided.last_decision()
     }
 
+    /// A checkpoint covering every decision up to `seq` has become stable,
+    /// so the decisions it covers no longer need to be retained
+    pub fn checkpoint_stable(&mut self, seq: SeqNo) {
+        self.decided.checkpoint_stable(seq);
+    }
+
     pub fn install_proof(&mut self, proof: Proof<RQ>) -> Result<OPDecision<RQ>> {
+        // Proofs can come from other replicas, so make sure they hold a batch before installing them
+        let batch_info = ProtocolConsensusDecision::try_from(&proof)?;
+
         if let Some(decision) = self.decision_log().last_execution() {
             match proof.seq_no().index(decision) {
                 Either::Left(_) | Either::Right(0) => {
@@ -48,9 +67,7 @@ where
                         install_attempt: proof.sequence_number()
                     });
                 }
-                Either::Right(1) => {
-                    self.decided.append_proof(proof.clone());
-                }
+                Either::Right(1) => {}
                 Either::Right(_) => {
                     return Err!(LogError::CannotInstallWouldSkip {
                         install_attempt: proof.sequence_number(),
@@ -60,10 +77,20 @@ where
             }
         }
 
-        let batch_info = ProtocolConsensusDecision::from(&proof);
         let sequence = proof.sequence_number();
 
-        let (metadata, messages) = proof.into_parts();
+        // The decision carries every vote, while the log may only keep their certificate
+        let metadata = proof.metadata().clone();
+
+        let messages = proof
+            .pre_prepares()
+            .iter()
+            .chain(proof.prepares())
+            .chain(proof.commits())
+            .cloned()
+            .collect();
+
+        self.append_proof(proof);
 
         Ok(Decision::full_decision_info(
             sequence, metadata, messages, batch_info,
@@ -81,10 +108,13 @@ where
             contained_messages,
             client_request_info,
             client_requests,
+            skipped_slots,
+            skipped_leaders: _,
             batch_meta: _,
         } = completed;
 
-        let metadata = ProofMetadata::new(seq, digest, pre_prepare_ordering, client_requests.len());
+        let metadata = ProofMetadata::new(seq, digest, pre_prepare_ordering, client_requests.len())
+            .with_skipped_slots(skipped_slots);
 
         let FinishedMessageLog {
             pre_prepares,
@@ -92,9 +122,7 @@ where
             commits,
         } = contained_messages;
 
-        let proof = Proof::new(metadata, pre_prepares, prepares, commits);
-
-        self.decided.append_proof(proof);
+        self.append_proof(Proof::new(metadata, pre_prepares, prepares, commits));
 
         let mut batch = BatchedDecision::new_with_cap(seq, client_requests.len());
 
@@ -109,15 +137,31 @@ where
             digest,
         ))
     }
+
+    /// Append a decided proof to the log, compacting it first when we keep certificates
+    fn append_proof(&mut self, mut proof: Proof<RQ>) {
+        if self.compact_certificates {
+            if let Err(err) = proof.compact() {
+                warn!(
+                    "Failed to compact the proof for decision {:?}, keeping the full proof: {:?}",
+                    proof.sequence_number(),
+                    err
+                );
+            }
+        }
+
+        self.decided.append_proof(proof);
+    }
 }
 
-pub fn initialize_decided_log<RQ>(_node_id: NodeId) -> Log<RQ>
+pub fn initialize_decided_log<RQ>(_node_id: NodeId, config: &DecisionLogConfig) -> Result<Log<RQ>>
 where
     RQ: SerType,
 {
-    Log {
-        decided: DecisionLog::init(None),
-    }
+    Ok(Log {
+        decided: DecisionLog::init(config)?,
+        compact_certificates: false,
+    })
 }
 
 #[inline]
@@ -138,43 +182,45 @@ pub fn operation_key_raw(from: NodeId, session: SeqNo) -> u64 {
     client_id | (session_id << 32)
 }
 
-impl<O> From<&Proof<O>> for ProtocolConsensusDecision<O>
+impl<O> TryFrom<&Proof<O>> for ProtocolConsensusDecision<O>
 where
     O: Clone + SessionBased,
 {
-    fn from(value: &Proof<O>) -> Self {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Proof<O>) -> Result<Self> {
+        if !value.are_pre_prepares_ordered()? {
+            // Proofs received from other replicas are not necessarily ordered
+            let mut ordered = value.clone();
+
+            ordered.order_pre_prepares()?;
+
+            return Self::try_from(&ordered);
+        }
+
         let mut decided_batch =
             BatchedDecision::new_with_cap(value.seq_no(), value.metadata().contained_client_rqs());
         let mut client_rqs = Vec::with_capacity(value.metadata().contained_client_rqs());
 
-        if !value.are_pre_prepares_ordered().unwrap() {
-            //The batch should be provided to this already ordered.
-            todo!()
-        }
-
         for pre_prepare in value.pre_prepares() {
-            let consensus_msg = (*pre_prepare.message()).clone();
-
-            let reqs = match consensus_msg.into_consensus().into_kind() {
-                ConsensusMessageKind::PrePrepare(reqs) => reqs,
-                _ => {
-                    unreachable!()
-                }
+            let ConsensusMessageKind::PrePrepare(reqs) = pre_prepare.message().consensus()?.kind()
+            else {
+                return Err!(LogError::NotAPrePrepare(value.seq_no()));
             };
 
             for request in reqs {
-                client_rqs.push(ClientRqInfo::from(&request));
+                client_rqs.push(ClientRqInfo::from(request));
 
-                decided_batch.add_message(request);
+                decided_batch.add_message(request.clone());
             }
         }
 
-        ProtocolConsensusDecision::new(
+        Ok(ProtocolConsensusDecision::new(
             value.seq_no(),
             decided_batch,
             client_rqs,
             value.metadata().batch_digest(),
-        )
+        ))
     }
 }
 
@@ -190,4 +236,6 @@ pub enum LogError {
         install_attempt: SeqNo,
         currently_installed: SeqNo,
     },
+    #[error("The proof for decision {0:?} contains a pre prepare slot with another kind of message")]
+    NotAPrePrepare(SeqNo),
 }
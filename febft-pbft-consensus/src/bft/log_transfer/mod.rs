@@ -0,0 +1,466 @@
+//! Log transfer, with which a replica that has fallen a few decisions behind catches up.
+//!
+//! Instead of transferring the whole state, the replica asks one of the others for the proofs
+//! of the decisions it is missing, checks that each of them carries a quorum of valid votes and
+//! installs them in order, as if it had taken part in those consensus instances.
+//! Replicas only retain a window of the latest decisions (see [DecisionLog]), so when none of
+//! the others can serve the missing range, we fall back to the state transfer protocol.
+
+use std::any::Any;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use atlas_common::maybe_vec::MaybeVec;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::serialization_helper::SerType;
+use atlas_core::messages::SessionBased;
+use atlas_core::ordering_protocol::networking::OrderProtocolSendNode;
+use atlas_core::ordering_protocol::ShareableMessage;
+use atlas_core::timeouts::timeout::{ModTimeout, TimeoutModHandle};
+use atlas_core::timeouts::{TimeOutable, TimeoutID};
+use atlas_metrics::metrics::metric_increment;
+
+use crate::bft::consensus::Consensus;
+use crate::bft::log::decided::DecisionLog;
+use crate::bft::log::decisions::Proof;
+use crate::bft::log::Log;
+use crate::bft::message::{LogTransferMessage, PBFTMessage};
+use crate::bft::metric::LOG_TRANSFER_PROOFS_INSTALLED_ID;
+use crate::bft::sync::view::ViewInfo;
+use crate::bft::sync::Synchronizer;
+use crate::bft::{OPDecision, PBFT};
+
+/// The most proofs we send in a single reply, so replies stay reasonably sized.
+/// Larger ranges are fetched with successive requests
+pub const MAX_PROOFS_PER_REPLY: usize = 32;
+
+/// The information attached to a log transfer timeout
+#[derive(Debug, Clone)]
+pub struct LogTransferTimeout {
+    epoch: u32,
+}
+
+impl TimeOutable for LogTransferTimeout {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// The result of advancing the log transfer
+pub enum LogTransferStatus<RQ> {
+    /// The log transfer is not affected
+    Nil,
+    /// We are waiting for the proofs we have requested
+    Running,
+    /// We have installed the given decisions, but are still missing some
+    Progressed(MaybeVec<OPDecision<RQ>>),
+    /// We have installed the given decisions, which were the last ones we were missing
+    Finished(MaybeVec<OPDecision<RQ>>),
+    /// None of the replicas could provide the decisions we are missing,
+    /// so we must run the state transfer protocol
+    RunCst,
+}
+
+enum LogTransferPhase {
+    Idle,
+    Fetching {
+        /// The next decision we must install
+        next: SeqNo,
+        /// The last decision we are missing
+        last: SeqNo,
+        /// The replicas we can fetch the decisions from
+        peers: Vec<NodeId>,
+        /// The replica we are currently fetching from, as an index of `peers`
+        current: usize,
+        /// How many replicas in a row failed to provide any decision
+        failed: usize,
+    },
+}
+
+pub struct LogTransfer<RQ> {
+    node_id: NodeId,
+    /// How long we wait for a replica to reply before moving on to the next one
+    timeout: Duration,
+    phase: LogTransferPhase,
+    /// Identifies the currently armed timeout, so we can ignore stale ones
+    epoch: u32,
+    _marker: PhantomData<fn() -> RQ>,
+}
+
+impl<RQ> LogTransfer<RQ>
+where
+    RQ: SerType + SessionBased + 'static,
+{
+    pub fn new(node_id: NodeId, timeout: Duration) -> Self {
+        Self {
+            node_id,
+            timeout,
+            phase: LogTransferPhase::Idle,
+            epoch: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Are we currently fetching decisions
+    pub fn is_running(&self) -> bool {
+        matches!(self.phase, LogTransferPhase::Fetching { .. })
+    }
+
+    /// Start fetching the decisions from `first` up to (and including) `last`
+    pub fn start<NT>(
+        &mut self,
+        first: SeqNo,
+        last: SeqNo,
+        view: &ViewInfo,
+        node: &NT,
+        timeouts: &TimeoutModHandle,
+    ) -> LogTransferStatus<RQ>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let peers: Vec<NodeId> = view
+            .quorum_members()
+            .iter()
+            .copied()
+            .filter(|member| *member != self.node_id)
+            .collect();
+
+        if peers.is_empty() {
+            return LogTransferStatus::RunCst;
+        }
+
+        info!(
+            "{:?} // Starting log transfer of decisions {:?} to {:?}",
+            self.node_id, first, last
+        );
+
+        // Spread the load of lagging replicas over the others
+        let current = (u64::from(self.node_id) % peers.len() as u64) as usize;
+
+        self.phase = LogTransferPhase::Fetching {
+            next: first,
+            last,
+            peers,
+            current,
+            failed: 0,
+        };
+
+        self.request(node, timeouts);
+
+        LogTransferStatus::Running
+    }
+
+    /// Process a log transfer message received from another replica
+    pub fn process_message<NT>(
+        &mut self,
+        s_message: ShareableMessage<PBFTMessage<RQ>>,
+        synchronizer: &Synchronizer<RQ>,
+        consensus: &mut Consensus<RQ>,
+        log: &mut Log<RQ>,
+        node: &NT,
+        timeouts: &TimeoutModHandle,
+    ) -> LogTransferStatus<RQ>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let from = s_message.header().from();
+
+        let Ok(message) = s_message.message().log_transfer() else {
+            return LogTransferStatus::Nil;
+        };
+
+        match message {
+            LogTransferMessage::RequestProofs { first, last } => {
+                self.serve(from, *first, *last, log, node);
+
+                LogTransferStatus::Nil
+            }
+            LogTransferMessage::ReplyProofs { first, proofs } => {
+                self.receive_proofs(
+                    from,
+                    *first,
+                    proofs,
+                    synchronizer,
+                    consensus,
+                    log,
+                    node,
+                    timeouts,
+                )
+            }
+        }
+    }
+
+    /// Reply to a request for proofs with the ones we retain
+    pub fn serve<NT>(&self, from: NodeId, first: SeqNo, last: SeqNo, log: &Log<RQ>, node: &NT)
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        // Only clone as many proofs as fit in a single reply
+        let reply_last = last.min(first + SeqNo::from(MAX_PROOFS_PER_REPLY as u32 - 1));
+
+        let proofs: Vec<Proof<RQ>> = log.decision_log().proofs_between(first, reply_last);
+
+        debug!(
+            "{:?} // Serving {} proofs from {:?} to {:?}, who requested up to {:?}",
+            self.node_id,
+            proofs.len(),
+            first,
+            from,
+            last
+        );
+
+        let message = PBFTMessage::LogTransfer(LogTransferMessage::ReplyProofs { first, proofs });
+
+        let _ = node.send_signed(message, from, true);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn receive_proofs<NT>(
+        &mut self,
+        from: NodeId,
+        first: SeqNo,
+        proofs: &[Proof<RQ>],
+        synchronizer: &Synchronizer<RQ>,
+        consensus: &mut Consensus<RQ>,
+        log: &mut Log<RQ>,
+        node: &NT,
+        timeouts: &TimeoutModHandle,
+    ) -> LogTransferStatus<RQ>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let LogTransferPhase::Fetching {
+            next,
+            last,
+            peers,
+            current,
+            ..
+        } = &mut self.phase
+        else {
+            debug!(
+                "{:?} // Ignoring log transfer reply from {:?}, as we are not fetching decisions",
+                self.node_id, from
+            );
+
+            return LogTransferStatus::Nil;
+        };
+
+        if peers[*current] != from || first != *next {
+            debug!(
+                "{:?} // Ignoring log transfer reply from {:?} for {:?}, we are waiting on {:?} for {:?}",
+                self.node_id, from, first, peers[*current], next
+            );
+
+            return LogTransferStatus::Nil;
+        }
+
+        let mut view = synchronizer.view();
+
+        let mut decisions = MaybeVec::builder();
+        let mut installed = 0;
+
+        for proof in proofs.iter().take(MAX_PROOFS_PER_REPLY) {
+            if proof.sequence_number() != *next || proof.sequence_number() > *last {
+                warn!(
+                    "{:?} // Replica {:?} sent proof {:?} when we expected {:?}",
+                    self.node_id,
+                    from,
+                    proof.sequence_number(),
+                    next
+                );
+
+                break;
+            }
+
+            if !synchronizer.proof_valid(node, proof) {
+                warn!(
+                    "{:?} // Replica {:?} sent an invalid proof for decision {:?}",
+                    self.node_id, from, next
+                );
+
+                break;
+            }
+
+            let skipped_leaders = proof.metadata().skipped_leaders(view.leader_set());
+
+            match consensus.catch_up_to_quorum(&view, proof.clone(), log) {
+                Ok(decision) => decisions.push(decision),
+                Err(err) => {
+                    warn!(
+                        "{:?} // Failed to install proof {:?} sent by {:?}: {:?}",
+                        self.node_id, next, from, err
+                    );
+
+                    break;
+                }
+            }
+
+            // The replicas which took part in this decision removed the skipped
+            // leaders right after it, so we must do the same before the next one
+            if !skipped_leaders.is_empty() {
+                if let Some(reduced) = synchronizer.remove_leaders(&skipped_leaders) {
+                    consensus.remove_leaders(&reduced);
+
+                    view = reduced;
+                }
+            }
+
+            *next = next.next();
+            installed += 1;
+        }
+
+        if installed > 0 {
+            metric_increment(LOG_TRANSFER_PROOFS_INSTALLED_ID, Some(installed as u64));
+        }
+
+        let decisions = decisions.build();
+
+        if *next > *last {
+            info!(
+                "{:?} // Log transfer finished, installed decisions up to {:?}",
+                self.node_id, last
+            );
+
+            self.phase = LogTransferPhase::Idle;
+
+            return LogTransferStatus::Finished(decisions);
+        }
+
+        // A correct replica sends every proof it retains in the requested range (up to the
+        // limit), so anything short of that means it can't (or won't) help us any further
+        let complete = installed == proofs.len() && installed == MAX_PROOFS_PER_REPLY;
+
+        if !complete && !self.rotate(installed > 0) {
+            self.phase = LogTransferPhase::Idle;
+
+            return LogTransferStatus::RunCst;
+        }
+
+        self.request(node, timeouts);
+
+        if installed > 0 {
+            LogTransferStatus::Progressed(decisions)
+        } else {
+            LogTransferStatus::Running
+        }
+    }
+
+    /// Check the given timeouts for an expired log transfer timeout,
+    /// moving on to the next replica if the current one did not reply in time
+    pub fn timed_out<NT>(
+        &mut self,
+        timed_out: &[ModTimeout],
+        node: &NT,
+        timeouts: &TimeoutModHandle,
+    ) -> LogTransferStatus<RQ>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let current = timed_out.iter().any(|timeout| {
+            timeout
+                .extra_info()
+                .and_then(|info| info.as_any().downcast_ref::<LogTransferTimeout>())
+                .is_some_and(|info| info.epoch == self.epoch)
+        });
+
+        if !current || !self.is_running() {
+            return LogTransferStatus::Nil;
+        }
+
+        if !self.rotate(false) {
+            self.phase = LogTransferPhase::Idle;
+
+            return LogTransferStatus::RunCst;
+        }
+
+        self.request(node, timeouts);
+
+        LogTransferStatus::Running
+    }
+
+    /// Move on to the next replica, returning false once every replica
+    /// in a row has failed to provide us with any decision
+    fn rotate(&mut self, progressed: bool) -> bool {
+        let LogTransferPhase::Fetching {
+            peers,
+            current,
+            failed,
+            next,
+            ..
+        } = &mut self.phase
+        else {
+            return false;
+        };
+
+        *failed = if progressed { 0 } else { *failed + 1 };
+
+        if *failed >= peers.len() {
+            warn!(
+                "{:?} // No replica could provide decision {:?}, falling back to the state transfer",
+                self.node_id, next
+            );
+
+            return false;
+        }
+
+        *current = (*current + 1) % peers.len();
+
+        true
+    }
+
+    /// Request the decisions we are missing from the current replica
+    fn request<NT>(&mut self, node: &NT, timeouts: &TimeoutModHandle)
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let LogTransferPhase::Fetching {
+            next,
+            last,
+            peers,
+            current,
+            ..
+        } = &self.phase
+        else {
+            return;
+        };
+
+        let (first, last, peer) = (*next, *last, peers[*current]);
+
+        debug!(
+            "{:?} // Requesting decisions {:?} to {:?} from {:?}",
+            self.node_id, first, last, peer
+        );
+
+        let message = PBFTMessage::LogTransfer(LogTransferMessage::RequestProofs { first, last });
+
+        let _ = node.send_signed(message, peer, true);
+
+        self.epoch = self.epoch.wrapping_add(1);
+
+        let _ = timeouts.request_timeouts(
+            vec![(
+                TimeoutID::SessionBased {
+                    session: first,
+                    seq_no: SeqNo::from(self.epoch),
+                    from: peer,
+                },
+                Some(Box::new(LogTransferTimeout { epoch: self.epoch }) as Box<dyn TimeOutable>),
+            )],
+            self.timeout,
+            1,
+            false,
+        );
+    }
+}
+
+/// Can the decisions from `first` up to `last` be fetched with the log transfer.
+/// Replicas are expected to retain as many decisions as we do
+pub fn within_retained<RQ>(first: SeqNo, last: SeqNo, log: &DecisionLog<RQ>) -> bool
+where
+    RQ: SerType,
+{
+    (u32::from(last) - u32::from(first)) as usize < log.window()
+}
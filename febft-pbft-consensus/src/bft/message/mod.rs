@@ -5,20 +5,26 @@ use std::fmt::{Debug, Formatter};
 use std::io::Write;
 
 use getset::Getters;
+use thiserror::Error;
 #[cfg(feature = "serialize_serde")]
 use serde::{Deserialize, Serialize};
 
 use atlas_common::crypto::hash::Digest;
-
+use atlas_common::crypto::signature::Signature;
+use atlas_common::error::*;
+use atlas_common::Err;
 use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_communication::message::{Header, StoredMessage};
 
-use crate::bft::log::decisions::CollectData;
+use crate::bft::consensus::authenticator::{Authenticator, MessageAuthentication};
+use crate::bft::consensus::usig::UniqueIdentifier;
+use crate::bft::log::decisions::{CollectData, Proof};
 use crate::bft::sync::view::ViewInfo;
 use crate::bft::sync::LeaderCollects;
 
 pub mod serialize;
+pub mod verification;
 
 /// PBFT protocol messages
 #[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
@@ -30,6 +36,8 @@ pub enum PBFTMessage<R> {
     ViewChange(ViewChangeMessage<R>),
     //Observer related messages
     ObserverMessage(ObserverMessage),
+    /// Log transfer messages
+    LogTransfer(LogTransferMessage<R>),
 }
 
 impl<R> Debug for PBFTMessage<R> {
@@ -44,6 +52,9 @@ impl<R> Debug for PBFTMessage<R> {
             PBFTMessage::ObserverMessage(_) => {
                 write!(f, "Observer msg")
             }
+            PBFTMessage::LogTransfer(log_transfer) => {
+                write!(f, "Log transfer msg {:?}", log_transfer)
+            }
         }
     }
 }
@@ -54,52 +65,92 @@ impl<R> Orderable for PBFTMessage<R> {
             PBFTMessage::Consensus(consensus) => consensus.sequence_number(),
             PBFTMessage::ViewChange(view) => view.sequence_number(),
             PBFTMessage::ObserverMessage(_obs) => SeqNo::ZERO,
+            PBFTMessage::LogTransfer(log_transfer) => log_transfer.sequence_number(),
         }
     }
 }
 
 impl<R> PBFTMessage<R> {
-    pub fn consensus(&self) -> &ConsensusMessage<R> {
+    /// The kind of message contained in this [PBFTMessage].
+    /// Messages come from other replicas, so a message of the wrong kind
+    /// is an error to be handled, never a reason to crash.
+    pub fn message_type(&self) -> PBFTMessageType {
         match self {
-            PBFTMessage::Consensus(msg) => msg,
-            _ => panic!("Not a consensus message"),
+            PBFTMessage::Consensus(_) => PBFTMessageType::Consensus,
+            PBFTMessage::ViewChange(_) => PBFTMessageType::ViewChange,
+            PBFTMessage::ObserverMessage(_) => PBFTMessageType::Observer,
+            PBFTMessage::LogTransfer(_) => PBFTMessageType::LogTransfer,
         }
     }
 
-    pub fn into_consensus(self) -> ConsensusMessage<R> {
+    pub fn consensus(&self) -> Result<&ConsensusMessage<R>> {
         match self {
-            PBFTMessage::Consensus(msg) => msg,
-            _ => panic!("Not a consensus message"),
+            PBFTMessage::Consensus(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::Consensus)),
         }
     }
 
-    pub fn view_change(&self) -> &ViewChangeMessage<R> {
+    pub fn into_consensus(self) -> Result<ConsensusMessage<R>> {
         match self {
-            PBFTMessage::ViewChange(msg) => msg,
-            _ => panic!("Not a view change message"),
+            PBFTMessage::Consensus(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::Consensus)),
         }
     }
 
-    pub fn into_view_change(self) -> ViewChangeMessage<R> {
+    pub fn view_change(&self) -> Result<&ViewChangeMessage<R>> {
         match self {
-            PBFTMessage::ViewChange(msg) => msg,
-            _ => panic!("Not a view change message"),
+            PBFTMessage::ViewChange(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::ViewChange)),
         }
     }
 
-    pub fn observer_message(&self) -> &ObserverMessage {
+    pub fn into_view_change(self) -> Result<ViewChangeMessage<R>> {
         match self {
-            PBFTMessage::ObserverMessage(msg) => msg,
-            _ => panic!("Not an observer message"),
+            PBFTMessage::ViewChange(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::ViewChange)),
         }
     }
 
-    pub fn into_observer_message(self) -> ObserverMessage {
+    pub fn observer_message(&self) -> Result<&ObserverMessage> {
         match self {
-            PBFTMessage::ObserverMessage(msg) => msg,
-            _ => panic!("Not an observer message"),
+            PBFTMessage::ObserverMessage(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::Observer)),
         }
     }
+
+    pub fn into_observer_message(self) -> Result<ObserverMessage> {
+        match self {
+            PBFTMessage::ObserverMessage(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::Observer)),
+        }
+    }
+
+    pub fn log_transfer(&self) -> Result<&LogTransferMessage<R>> {
+        match self {
+            PBFTMessage::LogTransfer(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::LogTransfer)),
+        }
+    }
+
+    pub fn into_log_transfer(self) -> Result<LogTransferMessage<R>> {
+        match self {
+            PBFTMessage::LogTransfer(msg) => Ok(msg),
+            _ => Err!(self.wrong_type(PBFTMessageType::LogTransfer)),
+        }
+    }
+
+    fn wrong_type(&self, expected: PBFTMessageType) -> MessageError {
+        MessageError::WrongMessageType(expected, self.message_type())
+    }
+}
+
+/// The kinds of [PBFTMessage]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PBFTMessageType {
+    Consensus,
+    ViewChange,
+    Observer,
+    LogTransfer,
 }
 
 #[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
@@ -107,6 +158,8 @@ impl<R> PBFTMessage<R> {
 pub struct ViewChangeMessage<O> {
     view: SeqNo,
     kind: ViewChangeMessageKind<O>,
+    /// The trusted counter identifier, when running with a USIG
+    ui: Option<UniqueIdentifier>,
 }
 
 impl<O> Orderable for ViewChangeMessage<O> {
@@ -126,7 +179,23 @@ impl<O> ViewChangeMessage<O> {
     /// Creates a new `ViewChangeMessage`, pertaining to the view
     /// with sequence number `view`, and of the kind `kind`.
     pub fn new(view: SeqNo, kind: ViewChangeMessageKind<O>) -> Self {
-        Self { view, kind }
+        Self {
+            view,
+            kind,
+            ui: None,
+        }
+    }
+
+    /// Attach a trusted counter identifier to this message
+    pub fn with_ui(mut self, ui: UniqueIdentifier) -> Self {
+        self.ui = Some(ui);
+
+        self
+    }
+
+    /// The trusted counter identifier attached to this message, if any
+    pub fn ui(&self) -> Option<&UniqueIdentifier> {
+        self.ui.as_ref()
     }
 
     /// Returns a reference to the view change message kind.
@@ -170,6 +239,13 @@ pub struct ConsensusMessage<O> {
     seq: SeqNo,
     view: SeqNo,
     kind: ConsensusMessageKind<O>,
+    /// The trusted counter identifier, when running with a USIG
+    ui: Option<UniqueIdentifier>,
+    /// The signature over the vote statement of prepares and commits,
+    /// used to build compact quorum certificates
+    vote_signature: Option<Signature>,
+    /// The MAC vector of prepares and commits, when running with MAC authenticators
+    authenticator: Option<Authenticator>,
 }
 
 impl<O> Debug for ConsensusMessage<O> {
@@ -186,6 +262,9 @@ impl<O> Debug for ConsensusMessage<O> {
             ConsensusMessageKind::Commit(d) => {
                 write!(f, "Commit message {:?}", d)
             }
+            ConsensusMessageKind::SkipSlot(leader) => {
+                write!(f, "Skip slot message for leader {:?}", leader)
+            }
         }
     }
 }
@@ -210,6 +289,11 @@ pub enum ConsensusMessageKind<O> {
     /// The `Digest` represents the hash of the serialized `PRE-PREPARE`,
     /// where the batch of requests were proposed.
     Commit(Digest),
+    /// Vote to fill the slot of a silent leader with an empty batch,
+    /// in a multi leader instance.
+    ///
+    /// The `NodeId` is the leader whose `PRE-PREPARE` timed out.
+    SkipSlot(NodeId),
 }
 
 impl<O> Orderable for ConsensusMessage<O> {
@@ -230,6 +314,7 @@ where
             }
             ConsensusMessageKind::Prepare(digest) => ConsensusMessageKind::Prepare(*digest),
             ConsensusMessageKind::Commit(digest) => ConsensusMessageKind::Commit(*digest),
+            ConsensusMessageKind::SkipSlot(leader) => ConsensusMessageKind::SkipSlot(*leader),
         }
     }
 }
@@ -238,7 +323,60 @@ impl<O> ConsensusMessage<O> {
     /// Creates a new `ConsensusMessage` with sequence number `seq`,
     /// and of the kind `kind`.
     pub fn new(seq: SeqNo, view: SeqNo, kind: ConsensusMessageKind<O>) -> Self {
-        Self { seq, view, kind }
+        Self {
+            seq,
+            view,
+            kind,
+            ui: None,
+            vote_signature: None,
+            authenticator: None,
+        }
+    }
+
+    /// Attach a trusted counter identifier to this message
+    pub fn with_ui(mut self, ui: UniqueIdentifier) -> Self {
+        self.ui = Some(ui);
+
+        self
+    }
+
+    /// The trusted counter identifier attached to this message, if any
+    pub fn ui(&self) -> Option<&UniqueIdentifier> {
+        self.ui.as_ref()
+    }
+
+    /// Attach the signature over the vote statement of this prepare or commit
+    pub fn with_vote_signature(mut self, signature: Signature) -> Self {
+        self.vote_signature = Some(signature);
+
+        self
+    }
+
+    /// The signature over the vote statement of this message, if any
+    pub fn vote_signature(&self) -> Option<&Signature> {
+        self.vote_signature.as_ref()
+    }
+
+    /// Attach a MAC authenticator to this prepare or commit
+    pub fn with_authenticator(mut self, authenticator: Authenticator) -> Self {
+        self.authenticator = Some(authenticator);
+
+        self
+    }
+
+    /// The MAC authenticator of this message, if any
+    pub fn authenticator(&self) -> Option<&Authenticator> {
+        self.authenticator.as_ref()
+    }
+
+    /// How this message was authenticated by its sender.
+    /// Only signed messages can be forwarded to (and verified by) other replicas.
+    pub fn authentication(&self) -> MessageAuthentication {
+        if self.authenticator.is_some() {
+            MessageAuthentication::Authenticator
+        } else {
+            MessageAuthentication::Signature
+        }
     }
 
     /// Returns a reference to the consensus message kind.
@@ -253,10 +391,10 @@ impl<O> ConsensusMessage<O> {
     /// Checks if a consensus message refers to the digest of the
     /// proposed value.
     ///
-    /// Evidently, this predicate is not defined for `PRE-PREPARE` messages.
+    /// Evidently, this predicate is not defined for `PRE-PREPARE` and `SKIP-SLOT` messages.
     pub fn has_proposed_digest(&self, digest: &Digest) -> Option<bool> {
         match self.kind {
-            ConsensusMessageKind::PrePrepare(_) => None,
+            ConsensusMessageKind::PrePrepare(_) | ConsensusMessageKind::SkipSlot(_) => None,
             ConsensusMessageKind::Prepare(d) | ConsensusMessageKind::Commit(d) => {
                 Some(&d == digest)
             }
@@ -308,6 +446,42 @@ impl<O> FwdConsensusMessage<O> {
     }
 }
 
+/// Messages of the log transfer sub protocol, with which a replica that has fallen
+/// a few decisions behind fetches the proofs it is missing from the other replicas,
+/// instead of transferring the whole state
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub enum LogTransferMessage<O> {
+    /// Request the proofs of the decisions from `first` up to (and including) `last`
+    RequestProofs { first: SeqNo, last: SeqNo },
+    /// The requested proofs which the sender still retains, in order.
+    /// Empty when the sender no longer retains the first requested decision
+    ReplyProofs { first: SeqNo, proofs: Vec<Proof<O>> },
+}
+
+impl<O> Orderable for LogTransferMessage<O> {
+    /// Returns the sequence number of the first requested decision
+    fn sequence_number(&self) -> SeqNo {
+        match self {
+            LogTransferMessage::RequestProofs { first, .. } => *first,
+            LogTransferMessage::ReplyProofs { first, .. } => *first,
+        }
+    }
+}
+
+impl<O> Debug for LogTransferMessage<O> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogTransferMessage::RequestProofs { first, last } => {
+                write!(f, "Request proofs {:?} to {:?}", first, last)
+            }
+            LogTransferMessage::ReplyProofs { first, proofs } => {
+                write!(f, "Reply with {} proofs from {:?}", proofs.len(), first)
+            }
+        }
+    }
+}
+
 ///Observer related messages
 ///@{
 #[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
@@ -425,3 +599,9 @@ impl<O> Debug for ViewChangeMessageKind<O> {
         }
     }
 }
+
+#[derive(Error, Debug)]
+pub enum MessageError {
+    #[error("Expected a {0:?} message, but got a {1:?} message")]
+    WrongMessageType(PBFTMessageType, PBFTMessageType),
+}
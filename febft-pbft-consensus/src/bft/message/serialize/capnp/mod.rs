@@ -220,6 +220,10 @@ where
         }
         ConsensusMessageKind::Prepare(digest) => consensus.set_prepare(digest.as_ref()),
         ConsensusMessageKind::Commit(digest) => consensus.set_commit(digest.as_ref()),
+        ConsensusMessageKind::SkipSlot(_) => {
+            return Err("Skip slot messages are not part of the capnp schema".to_string())
+                .wrapped(ErrorKind::CommunicationSerialize);
+        }
     }
 
     Ok(())
@@ -364,7 +368,9 @@ fn deserialize_observer_message(
                     Ok(ObserveEventKind::Consensus(seq.into()))
                 }
                 consensus_messages_capnp::observed_value::value::NormalPhase(Ok(phase)) => {
-                    let view = phase.get_view().unwrap();
+                    let view = phase
+                        .get_view()
+                        .wrapped(ErrorKind::CommunicationSerialize)?;
 
                     let view_seq: SeqNo = view.get_view_num().into();
                     let n: usize = view.get_n() as usize;
@@ -372,7 +378,7 @@ fn deserialize_observer_message(
 
                     let seq_num: SeqNo = phase.get_seq_num().into();
 
-                    let view_info = ViewInfo::new(view_seq, n, f).unwrap();
+                    let view_info = ViewInfo::new(view_seq, n, f)?;
 
                     Ok(ObserveEventKind::NormalPhase((view_info, seq_num)))
                 }
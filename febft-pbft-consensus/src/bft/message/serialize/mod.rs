@@ -20,9 +20,13 @@ use atlas_core::ordering_protocol::networking::serialize::{
     OrderProtocolVerificationHelper, OrderingProtocolMessage, PermissionedOrderingProtocolMessage,
 };
 
+use crate::bft::consensus::authenticator::MessageAuthentication;
+use crate::bft::log::certificate::{verify_vote, CertificateError};
 use crate::bft::log::decisions::{Proof, ProofMetadata};
+use crate::bft::message::verification::{verify_requests, verify_requests_inline};
 use crate::bft::message::{
-    ConsensusMessage, ConsensusMessageKind, PBFTMessage, ViewChangeMessageKind,
+    ConsensusMessage, ConsensusMessageKind, LogTransferMessage, PBFTMessage,
+    ViewChangeMessageKind,
 };
 use crate::bft::sync::view::ViewInfo;
 
@@ -60,6 +64,28 @@ where
     Ok(result)
 }
 
+/// Serialize a decided proof, so it can be kept on disk
+pub fn serialize_proof<RQ>(proof: &Proof<RQ>) -> Result<Vec<u8>>
+where
+    RQ: SerType,
+{
+    #[cfg(feature = "serialize_serde")]
+    let bytes = serde::serialize_proof::<RQ>(proof)?;
+
+    Ok(bytes)
+}
+
+/// Deserialize a decided proof which was kept on disk
+pub fn deserialize_proof<RQ>(bytes: &[u8]) -> Result<Proof<RQ>>
+where
+    RQ: SerType,
+{
+    #[cfg(feature = "serialize_serde")]
+    let proof = serde::deserialize_proof::<RQ>(bytes)?;
+
+    Ok(proof)
+}
+
 /// The serializable type, to be used to appease the compiler and it's requirements
 pub struct PBFTConsensus<RQ>(PhantomData<fn() -> RQ>);
 
@@ -72,7 +98,7 @@ where
 
     fn internally_verify_message<NI, OPVH>(
         network_info: &Arc<NI>,
-        _header: &Header,
+        header: &Header,
         message: &Self::ProtocolMessage,
     ) -> Result<()>
     where
@@ -86,40 +112,21 @@ where
 
                 match consensus.kind() {
                     ConsensusMessageKind::PrePrepare(requests) => {
-                        let request_iter = requests.iter();
-
-                        for request in request_iter {
-                            let (header, message) = (request.header(), request.message());
-
-                            let _ = OPVH::verify_request_message(
-                                network_info,
-                                header,
-                                message.clone(),
-                            )?;
-                        }
-
-                        Ok(())
+                        verify_requests::<RQ, NI, OPVH>(network_info, header, &requests[..])
                     }
                     ConsensusMessageKind::Prepare(_digest) => Ok(()),
                     ConsensusMessageKind::Commit(_digest) => Ok(()),
+                    ConsensusMessageKind::SkipSlot(_leader) => Ok(()),
                 }
             }
             PBFTMessage::ViewChange(view_change) => {
                 let _view = view_change.sequence_number();
 
                 match view_change.kind() {
+                    // Only the requests which timed out at the sender are carried, so
+                    // they are few enough to be verified on the receiving thread
                     ViewChangeMessageKind::Stop(timed_out_req) => {
-                        for client_rq in timed_out_req.iter() {
-                            let (header, message) = (client_rq.header(), client_rq.message());
-
-                            let _ = OPVH::verify_request_message(
-                                network_info,
-                                header,
-                                message.clone(),
-                            )?;
-                        }
-
-                        Ok(())
+                        verify_requests_inline::<RQ, NI, OPVH>(network_info, &timed_out_req[..])
                     }
                     ViewChangeMessageKind::StopQuorumJoin(_node) => Ok(()),
                     ViewChangeMessageKind::StopData(collect_data) => {
@@ -156,6 +163,30 @@ where
                 }
             }
             PBFTMessage::ObserverMessage(_m) => Ok(()),
+            PBFTMessage::LogTransfer(log_transfer) => match log_transfer {
+                LogTransferMessage::RequestProofs { .. } => Ok(()),
+                LogTransferMessage::ReplyProofs { proofs, .. } => {
+                    for proof in proofs {
+                        let messages = proof
+                            .pre_prepares()
+                            .iter()
+                            .chain(proof.prepares().iter())
+                            .chain(proof.commits().iter());
+
+                        for stored in messages {
+                            let (header, message) = (stored.header(), stored.message());
+
+                            let _ = OPVH::verify_protocol_message(
+                                network_info,
+                                header,
+                                message.clone(),
+                            )?;
+                        }
+                    }
+
+                    Ok(())
+                }
+            },
         }
     }
 
@@ -227,10 +258,38 @@ where
         let (metadata, messages) = proof.into_parts();
 
         for msg in &messages {
+            // Votes authenticated with MACs are not transferable, as we can't check an
+            // authenticator which was not addressed to us. They can only be part of a
+            // proof we receive when they also carry a vote signature.
+            if let PBFTMessage::Consensus(consensus) = msg.message() {
+                if consensus.authentication() == MessageAuthentication::Authenticator {
+                    let from = msg.header().from();
+
+                    let public_key = network_info
+                        .get_node_info(&from)
+                        .map(|info| info.public_key().clone())
+                        .ok_or(CertificateError::UnknownSigner(from))?;
+
+                    verify_vote(&public_key, from, consensus)?;
+
+                    continue;
+                }
+            }
+
             let _ =
                 OPVH::verify_protocol_message(network_info, msg.header(), msg.message().clone())?;
         }
 
+        // Compacted proofs carry their votes in a certificate, which we
+        // check directly against the public keys of the signers
+        if let Some(certificate) = metadata.certificate() {
+            certificate.verify(metadata.seq_no(), &metadata.batch_digest(), |node| {
+                network_info
+                    .get_node_info(&node)
+                    .map(|info| info.public_key().clone())
+            })?;
+        }
+
         let proof = Proof::init_from_messages(metadata, messages)?;
 
         Ok(proof)
@@ -1,3 +1,4 @@
+use crate::bft::log::decisions::Proof;
 use crate::bft::message::ConsensusMessage;
 use anyhow::Context;
 use atlas_common::error::*;
@@ -27,3 +28,23 @@ where
 
     Ok(msg)
 }
+
+pub fn serialize_proof<RQ>(proof: &Proof<RQ>) -> Result<Vec<u8>>
+where
+    RQ: SerType,
+{
+    let bytes = bincode::serde::encode_to_vec(proof, bincode::config::standard())
+        .context("Failed to serialize proof")?;
+
+    Ok(bytes)
+}
+
+pub fn deserialize_proof<RQ>(bytes: &[u8]) -> Result<Proof<RQ>>
+where
+    RQ: SerType,
+{
+    let (proof, _) = bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+        .context("Failed to deserialize proof")?;
+
+    Ok(proof)
+}
@@ -0,0 +1,242 @@
+//! Parallel verification of the client requests carried by `PRE-PREPARE` messages.
+//!
+//! A `PRE-PREPARE` can carry thousands of client requests, each with its own signature.
+//! Checking them one by one on the thread which received the message stalls every other
+//! message behind it, so large batches are split into chunks which are verified on the
+//! thread pool, aborting all of the chunks as soon as one request fails verification.
+//!
+//! The thread which received the message does not wait for the chunks. The message is
+//! delivered right away and the consensus holds it back, by checking
+//! [batch_verification], until every chunk has reported back.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{mpsc, Arc, Mutex};
+
+use lazy_static::lazy_static;
+use thiserror::Error;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::serialization_helper::SerType;
+use atlas_common::threadpool;
+use atlas_common::Err;
+use atlas_communication::message::{Header, StoredMessage};
+use atlas_communication::reconfiguration::NetworkInformationProvider;
+use atlas_core::ordering_protocol::networking::serialize::OrderProtocolVerificationHelper;
+
+use crate::bft::message::serialize::PBFTConsensus;
+
+/// How many requests are verified by each task.
+/// Batches which fit in a single task are verified on the calling thread.
+const REQUESTS_PER_TASK: usize = 128;
+
+/// How many batches can be verified in the background at the same time.
+/// Further batches are verified on the calling thread.
+const MAX_RUNNING_VERIFICATIONS: usize = 1024;
+
+lazy_static! {
+    /// The verifications running in the background, by the replica which received
+    /// the batch and the digest of the message carrying it
+    static ref RUNNING: Mutex<BTreeMap<(NodeId, Digest), RunningVerification>> =
+        Mutex::new(BTreeMap::new());
+}
+
+/// The chunks of a batch which are being verified on the thread pool
+struct RunningVerification {
+    results: Receiver<Result<()>>,
+    /// How many chunks have yet to report back
+    remaining: usize,
+    /// The outcome, once every chunk reported back or one of them failed
+    outcome: Option<Result<()>>,
+}
+
+impl RunningVerification {
+    /// Collect the results reported so far, without blocking
+    fn collect(&mut self) {
+        while self.outcome.is_none() {
+            match self.results.try_recv() {
+                Ok(Ok(())) => {
+                    self.remaining -= 1;
+
+                    if self.remaining == 0 {
+                        self.outcome = Some(Ok(()));
+                    }
+                }
+                Ok(Err(err)) => self.outcome = Some(Err(err)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.outcome = Some(Err!(RequestVerificationError::WorkerDisconnected))
+                }
+            }
+        }
+    }
+}
+
+/// The state of the verification of the requests carried by a message
+pub(crate) enum BatchVerification {
+    /// Every request was verified
+    Verified,
+    /// Some of the requests are still being verified
+    Running,
+    /// One of the requests failed verification
+    Failed(Error),
+}
+
+/// Verify every request of the batch carried by the message with the given header.
+///
+/// Small batches are verified right away. Larger ones are verified on the thread pool,
+/// in which case this returns before they are done and the receiver of the message
+/// must wait for [batch_verification] to report them as verified.
+pub(crate) fn verify_requests<RQ, NI, OPVH>(
+    network_info: &Arc<NI>,
+    header: &Header,
+    requests: &[StoredMessage<RQ>],
+) -> Result<()>
+where
+    RQ: SerType,
+    NI: NetworkInformationProvider,
+    OPVH: OrderProtocolVerificationHelper<RQ, PBFTConsensus<RQ>, NI>,
+{
+    if requests.len() <= REQUESTS_PER_TASK {
+        return verify_requests_inline::<RQ, NI, OPVH>(network_info, requests);
+    }
+
+    let key = (network_info.get_own_id(), *header.digest());
+
+    let mut running = RUNNING.lock().unwrap();
+
+    if running.len() >= MAX_RUNNING_VERIFICATIONS {
+        // Verifications which succeeded but whose message was never processed (as it
+        // was no longer relevant) can be forgotten, as a missing entry means verified.
+        // Failed ones must be kept until their message is processed
+        running.retain(|_, verification| {
+            verification.collect();
+
+            !matches!(verification.outcome, Some(Ok(())))
+        });
+
+        if running.len() >= MAX_RUNNING_VERIFICATIONS {
+            drop(running);
+
+            return verify_requests_inline::<RQ, NI, OPVH>(network_info, requests);
+        }
+    }
+
+    let aborted = Arc::new(AtomicBool::new(false));
+
+    let (tx, rx) = mpsc::channel();
+
+    let mut tasks = 0;
+
+    for chunk in requests.chunks(REQUESTS_PER_TASK) {
+        // The verification takes the requests by value, so each request is only copied once
+        let chunk = chunk.to_vec();
+
+        let network_info = network_info.clone();
+        let aborted = aborted.clone();
+        let tx = tx.clone();
+
+        threadpool::execute(move || {
+            let result = verify_owned_chunk::<RQ, NI, OPVH>(&network_info, chunk, &aborted);
+
+            // The receiver is gone when the message was already dropped
+            let _ = tx.send(result);
+        });
+
+        tasks += 1;
+    }
+
+    running.insert(
+        key,
+        RunningVerification {
+            results: rx,
+            remaining: tasks,
+            outcome: None,
+        },
+    );
+
+    Ok(())
+}
+
+/// Check on the verification of the requests carried by the message with the
+/// given digest, received by `me`, without blocking.
+///
+/// Messages whose requests were verified when they were received have no running verification.
+pub(crate) fn batch_verification(me: NodeId, message_digest: &Digest) -> BatchVerification {
+    let mut running = RUNNING.lock().unwrap();
+
+    let key = (me, *message_digest);
+
+    let Some(verification) = running.get_mut(&key) else {
+        return BatchVerification::Verified;
+    };
+
+    verification.collect();
+
+    if verification.outcome.is_none() {
+        return BatchVerification::Running;
+    }
+
+    match running.remove(&key).and_then(|verification| verification.outcome) {
+        Some(Err(err)) => BatchVerification::Failed(err),
+        _ => BatchVerification::Verified,
+    }
+}
+
+/// Verify the requests of a batch on the calling thread
+pub(crate) fn verify_requests_inline<RQ, NI, OPVH>(
+    network_info: &Arc<NI>,
+    requests: &[StoredMessage<RQ>],
+) -> Result<()>
+where
+    RQ: SerType,
+    NI: NetworkInformationProvider,
+    OPVH: OrderProtocolVerificationHelper<RQ, PBFTConsensus<RQ>, NI>,
+{
+    for request in requests {
+        let (header, message) = (request.header(), request.message());
+
+        OPVH::verify_request_message(network_info, header, message.clone())?;
+    }
+
+    Ok(())
+}
+
+/// Verify a chunk of requests, stopping early if another chunk has already failed.
+/// The chunk that fails is responsible for signalling the others to stop.
+fn verify_owned_chunk<RQ, NI, OPVH>(
+    network_info: &Arc<NI>,
+    requests: Vec<StoredMessage<RQ>>,
+    aborted: &AtomicBool,
+) -> Result<()>
+where
+    RQ: SerType,
+    NI: NetworkInformationProvider,
+    OPVH: OrderProtocolVerificationHelper<RQ, PBFTConsensus<RQ>, NI>,
+{
+    for request in requests {
+        if aborted.load(Ordering::Relaxed) {
+            // Another chunk has failed and will report its own error
+            return Ok(());
+        }
+
+        let (header, message) = request.into_inner();
+
+        if let Err(err) = OPVH::verify_request_message(network_info, &header, message) {
+            aborted.store(true, Ordering::Relaxed);
+
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum RequestVerificationError {
+    #[error("A request verification worker exited without reporting its result")]
+    WorkerDisconnected,
+}
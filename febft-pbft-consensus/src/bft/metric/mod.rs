@@ -1,4 +1,4 @@
-use atlas_metrics::metrics::{metric_duration, metric_store_count, MetricKind};
+use atlas_metrics::metrics::{metric_duration, metric_increment, metric_store_count, MetricKind};
 use atlas_metrics::{MetricLevel, MetricRegistry};
 use std::time::Instant;
 
@@ -83,6 +83,34 @@ pub const SYNC_FORWARDED_REQUESTS_ID: usize = 124;
 pub const SYNC_FORWARDED_COUNT: &str = "SYNC_FORWARDED_COUNT";
 pub const SYNC_FORWARDED_COUNT_ID: usize = 125;
 
+/// 130-139: Consensus extensions
+pub const CONSENSUS_FAST_PATH_DECISIONS: &str = "CONSENSUS_FAST_PATH_DECISIONS";
+pub const CONSENSUS_FAST_PATH_DECISIONS_ID: usize = 130;
+
+pub const LEADER_MONITOR_VIEW_CHANGES: &str = "LEADER_MONITOR_VIEW_CHANGES";
+pub const LEADER_MONITOR_VIEW_CHANGES_ID: usize = 131;
+
+pub const MISBEHAVIOUR_DETECTED: &str = "MISBEHAVIOUR_DETECTED";
+pub const MISBEHAVIOUR_DETECTED_ID: usize = 132;
+
+pub const MALFORMED_MESSAGES_DROPPED: &str = "MALFORMED_MESSAGES_DROPPED";
+pub const MALFORMED_MESSAGES_DROPPED_ID: usize = 133;
+
+pub const FUTURE_MESSAGES_DROPPED: &str = "FUTURE_MESSAGES_DROPPED";
+pub const FUTURE_MESSAGES_DROPPED_ID: usize = 134;
+
+pub const FUTURE_MESSAGES_EVICTED: &str = "FUTURE_MESSAGES_EVICTED";
+pub const FUTURE_MESSAGES_EVICTED_ID: usize = 135;
+
+pub const LOG_TRANSFER_PROOFS_INSTALLED: &str = "LOG_TRANSFER_PROOFS_INSTALLED";
+pub const LOG_TRANSFER_PROOFS_INSTALLED_ID: usize = 136;
+
+pub const LOG_TRANSFER_FALLBACK_CST: &str = "LOG_TRANSFER_FALLBACK_CST";
+pub const LOG_TRANSFER_FALLBACK_CST_ID: usize = 137;
+
+pub const STALE_MESSAGES_DROPPED: &str = "STALE_MESSAGES_DROPPED";
+pub const STALE_MESSAGES_DROPPED_ID: usize = 138;
+
 pub fn metrics() -> Vec<MetricRegistry> {
     vec![
         (
@@ -240,6 +268,60 @@ pub fn metrics() -> Vec<MetricRegistry> {
             MetricKind::Counter,
         )
             .into(),
+        (
+            CONSENSUS_FAST_PATH_DECISIONS_ID,
+            CONSENSUS_FAST_PATH_DECISIONS.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            LEADER_MONITOR_VIEW_CHANGES_ID,
+            LEADER_MONITOR_VIEW_CHANGES.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            MISBEHAVIOUR_DETECTED_ID,
+            MISBEHAVIOUR_DETECTED.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            MALFORMED_MESSAGES_DROPPED_ID,
+            MALFORMED_MESSAGES_DROPPED.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            FUTURE_MESSAGES_DROPPED_ID,
+            FUTURE_MESSAGES_DROPPED.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            FUTURE_MESSAGES_EVICTED_ID,
+            FUTURE_MESSAGES_EVICTED.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            LOG_TRANSFER_PROOFS_INSTALLED_ID,
+            LOG_TRANSFER_PROOFS_INSTALLED.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            LOG_TRANSFER_FALLBACK_CST_ID,
+            LOG_TRANSFER_FALLBACK_CST.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
+        (
+            STALE_MESSAGES_DROPPED_ID,
+            STALE_MESSAGES_DROPPED.to_string(),
+            MetricKind::Counter,
+        )
+            .into(),
     ]
 }
 
@@ -326,4 +408,14 @@ impl ConsensusMetrics {
             self.first_commit_rcvd_time.elapsed(),
         )
     }
+
+    pub fn fast_path_decided(&mut self) {
+        self.prepare_quorum_time = Instant::now();
+
+        metric_duration(
+            CONSENSUS_PREPARE_LATENCY_ID,
+            self.first_prepare_rcvd_time.elapsed(),
+        );
+        metric_increment(CONSENSUS_FAST_PATH_DECISIONS_ID, Some(1));
+    }
 }
@@ -13,17 +13,25 @@ use anyhow::anyhow;
 use either::Either;
 use lazy_static::lazy_static;
 
-use crate::bft::config::PBFTConfig;
+use crate::bft::config::{FaultModel, PBFTConfig};
+use crate::bft::consensus::decision::DecisionOptions;
 use crate::bft::consensus::{
     Consensus, ConsensusPollStatus, ConsensusStatus, ProposerConsensusGuard,
 };
-use crate::bft::log::decided::DecisionLog;
+use crate::bft::evidence::{MisbehaviourEvidence, MisbehaviourReporter};
+use crate::bft::log::decided::{DecisionLog, StableCheckpoints};
 use crate::bft::log::decisions::{Proof, ProofMetadata};
 use crate::bft::log::{initialize_decided_log, Log};
+use crate::bft::log_transfer::{LogTransfer, LogTransferStatus};
 use crate::bft::message::serialize::PBFTConsensus;
 use crate::bft::message::{ConsensusMessageKind, ObserveEventKind, PBFTMessage};
+use crate::bft::message::LogTransferMessage;
+use crate::bft::metric::{
+    LOG_TRANSFER_FALLBACK_CST_ID, MALFORMED_MESSAGES_DROPPED_ID, MISBEHAVIOUR_DETECTED_ID,
+};
 use crate::bft::proposer::Proposer;
-use crate::bft::sync::view::ViewInfo;
+use crate::bft::sync::monitor::LeaderMonitor;
+use crate::bft::sync::view::{ViewInfo, VoteWeights};
 use crate::bft::sync::{
     AbstractSynchronizer, SyncReconfigurationResult, Synchronizer, SynchronizerPollStatus,
     SynchronizerStatus,
@@ -55,14 +63,19 @@ use atlas_core::reconfiguration_protocol::ReconfigurationProtocol;
 use atlas_core::request_pre_processing::RequestPreProcessor;
 use atlas_core::serialize::ReconfigurationProtocolMessage;
 use atlas_core::timeouts::timeout::{ModTimeout, TimeoutModHandle, TimeoutableMod};
+use atlas_metrics::metrics::metric_increment;
 
 pub mod config;
 pub mod consensus;
+pub mod evidence;
+pub mod forensics;
 pub mod log;
+pub mod log_transfer;
 pub mod message;
 pub mod metric;
 pub mod observer;
 pub mod proposer;
+pub mod queue;
 pub mod sync;
 
 // The types responsible for this protocol
@@ -92,6 +105,9 @@ pub enum SyncPhaseRes<O> {
     RunSyncProtocol,
     SyncProtocolFinished(ConsensusStatus<O>, Option<OPDecision<O>>),
     JoinedQuorum(ConsensusStatus<O>, Option<OPDecision<O>>, NodeId),
+    /// The view change goes on, but we must first execute the decision we were caught up to
+    ViewAbandoned(OPDecision<O>),
+    RunLogTransfer,
     RunCSTProtocol,
 }
 
@@ -110,6 +126,8 @@ pub struct PBFTOrderProtocol<RQ, NT>
     consensus: Consensus<RQ>,
     /// The synchronizer state machine
     synchronizer: Arc<Synchronizer<RQ>>,
+    /// Fetches the decisions we are missing when we fall slightly behind
+    log_transfer: LogTransfer<RQ>,
     /// The request pre processor
     pre_processor: RequestPreProcessor<RQ>,
     // A reference to the timeouts layer
@@ -127,6 +145,12 @@ pub struct PBFTOrderProtocol<RQ, NT>
     proposer: Arc<Proposer<RQ, NT>>,
     // The networking layer for a Node in the network (either Client or Replica)
     node: Arc<NT>,
+    // Monitors the performance of the current leader, when enabled
+    leader_monitor: Option<LeaderMonitor>,
+    // Receives the evidence of misbehaving replicas, when provided
+    misbehaviour_reporter: Option<Arc<dyn MisbehaviourReporter<RQ>>>,
+    // Tells us which checkpoints became stable, when provided
+    stable_checkpoints: Option<StableCheckpoints>,
 }
 
 impl<RQ, NT> Orderable for PBFTOrderProtocol<RQ, NT>
@@ -144,18 +168,18 @@ impl<RQ, NT> OrderProtocolTolerance for PBFTOrderProtocol<RQ, NT>
         RQ: SerType,
         NT: 'static + OrderProtocolSendNode<RQ, PBFT<RQ>>,
 {
+    // These follow the fault model the protocol was configured with,
+    // see `FaultModel::configured`
     fn get_n_for_f(f: usize) -> usize {
-        3 * f + 1
+        FaultModel::configured().n_for_f(f)
     }
 
     fn get_quorum_for_n(n: usize) -> usize {
-        //n = 2f+1
-
-        2 * Self::get_f_for_n(n) + 1
+        FaultModel::configured().quorum_for_n(n)
     }
 
     fn get_f_for_n(n: usize) -> usize {
-        (n - 1) / 3
+        FaultModel::configured().f_for_n(n)
     }
 }
 
@@ -189,6 +213,14 @@ for PBFTOrderProtocol<RQ, NT>
         &mut self,
         timeout: Vec<ModTimeout>,
     ) -> Result<OPExecResult<ProofMetadata, PBFTMessage<RQ>, RQ>> {
+        let log_transfer = self
+            .log_transfer
+            .timed_out(&timeout, &*self.node, &self.timeouts);
+
+        if let LogTransferStatus::RunCst = log_transfer {
+            return Ok(self.log_transfer_result(log_transfer));
+        }
+
         if self.consensus.is_catching_up() {
             warn!(
                 "{:?} // Ignoring timeouts while catching up",
@@ -198,10 +230,24 @@ for PBFTOrderProtocol<RQ, NT>
             return Ok(OPExecResult::MessageDropped);
         }
 
+        // The client request timeouts of this batch must still be handled when the
+        // leader liveness timeout also expired, so the requests are not forgotten
+        let leader_silent = self
+            .synchronizer
+            .leader_liveness_timed_out(&self.timeouts, &timeout);
+
+        // There is no point in skipping the slots of a leader we are replacing
+        if let (ConsensusPhase::NormalPhase, false) = (&self.phase, leader_silent) {
+            self.consensus
+                .pre_prepare_slots_timed_out(&timeout, &self.node);
+        }
+
         let status = self
             .synchronizer
             .client_requests_timed_out(self.node.id(), &timeout);
 
+        let mut stopped_requests = Vec::new();
+
         if let SynchronizerStatus::RequestsTimedOut { forwarded, stopped } = status {
             if !forwarded.is_empty() {
                 let requests = self.pre_processor.clone_pending_rqs(forwarded);
@@ -210,19 +256,21 @@ for PBFTOrderProtocol<RQ, NT>
             }
 
             if !stopped.is_empty() {
-                let stopped = self.pre_processor.clone_pending_rqs(stopped);
-
-                self.switch_phase(ConsensusPhase::SyncPhase);
-
-                self.synchronizer.begin_view_change(
-                    Some(stopped),
-                    &*self.node,
-                    &self.timeouts,
-                    &self.message_log,
-                );
+                stopped_requests = self.pre_processor.clone_pending_rqs(stopped);
             }
         };
 
+        if leader_silent || !stopped_requests.is_empty() {
+            self.switch_phase(ConsensusPhase::SyncPhase);
+
+            self.synchronizer.begin_view_change(
+                Some(stopped_requests),
+                &*self.node,
+                &self.timeouts,
+                &self.message_log,
+            );
+        }
+
         Ok(OPExecResult::MessageProcessedNoUpdate)
     }
 }
@@ -233,7 +281,7 @@ impl<RQ, NT> OrderingProtocol<RQ> for PBFTOrderProtocol<RQ, NT>
         NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
 {
     type Serialization = PBFTConsensus<RQ>;
-    type Config = PBFTConfig;
+    type Config = PBFTConfig<RQ>;
 
     fn handle_off_ctx_message(&mut self, message: ShareableMessage<PBFTMessage<RQ>>) {
         match message.message() {
@@ -251,12 +299,39 @@ impl<RQ, NT> OrderingProtocol<RQ> for PBFTOrderProtocol<RQ, NT>
                     self.node.id(),
                     view_change
                 );
-                self.synchronizer.queue(message);
 
-                self.synchronizer.signal();
+                if let Some(message) = self.consensus.sequence_view_change(message) {
+                    self.synchronizer.queue(message);
+
+                    self.synchronizer.signal();
+                }
             }
-            _ => {
-                todo!()
+            PBFTMessage::LogTransfer(LogTransferMessage::RequestProofs { first, last }) => {
+                // We can serve the decisions we retain, even while we can't run the protocol
+                self.log_transfer.serve(
+                    message.header().from(),
+                    *first,
+                    *last,
+                    &self.message_log,
+                    &*self.node,
+                );
+            }
+            PBFTMessage::LogTransfer(LogTransferMessage::ReplyProofs { .. }) => {
+                debug!(
+                    "{:?} // Dropping off context log transfer reply from {:?}",
+                    self.node.id(),
+                    message.header().from()
+                );
+            }
+            PBFTMessage::ObserverMessage(_) => {
+                // Observer messages are never exchanged between replicas
+                warn!(
+                    "{:?} // Dropping off context observer message from {:?}",
+                    self.node.id(),
+                    message.header().from()
+                );
+
+                metric_increment(MALFORMED_MESSAGES_DROPPED_ID, Some(1));
             }
         }
     }
@@ -284,6 +359,16 @@ impl<RQ, NT> OrderingProtocol<RQ> for PBFTOrderProtocol<RQ, NT>
     fn poll(&mut self) -> Result<OPPollResult<ProofMetadata, PBFTMessage<RQ>, RQ>> {
         trace!("{:?} // Polling {:?}", self.node.id(), self.phase);
 
+        self.queue_released_view_changes();
+
+        if let Some(seq) = self
+            .stable_checkpoints
+            .as_ref()
+            .and_then(StableCheckpoints::take)
+        {
+            self.checkpoint_stable(seq);
+        }
+
         match self.phase {
             ConsensusPhase::NormalPhase => self.poll_normal_phase(),
             ConsensusPhase::SyncPhase => self.poll_sync_phase(),
@@ -322,11 +407,23 @@ impl<RQ, NT> PermissionedOrderingProtocol for PBFTOrderProtocol<RQ, NT>
     fn install_view(&mut self, view: ViewInfo) {
         let current_view = self.view();
 
-        match view.sequence_number().index(current_view.sequence_number()) {
-            Either::Left(_) | Either::Right(0) => {
+        let removed_leaders = Self::leaders_removed_in(&current_view, &view);
+
+        match (
+            view.sequence_number().index(current_view.sequence_number()),
+            removed_leaders,
+        ) {
+            (Either::Right(0), Some(removed)) => {
+                // The replicas we got the state from removed the leaders whose slots were
+                // skipped in the decisions we did not take part in, so we must do the same
+                if let Some(reduced) = self.synchronizer.remove_leaders(&removed) {
+                    self.consensus.remove_leaders(&reduced);
+                }
+            }
+            (Either::Left(_) | Either::Right(0), _) => {
                 warn!("Attempted to install view that is the same or older than the current view that is in place? New: {:?} vs {:?}", view, current_view);
             }
-            Either::Right(_) => {
+            (Either::Right(_), _) => {
                 self.consensus.install_view(&view);
                 if self.synchronizer.received_view_from_state_transfer(view) {
                     info!("Installed the view and synchronizer now requires execution in order to make sure everything is correctly setup.");
@@ -344,7 +441,7 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
         NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
 {
     fn initialize_protocol(
-        config: PBFTConfig,
+        config: PBFTConfig<RQ>,
         args: OrderingProtocolArgs<RQ, NT>,
         _initial_state: Option<DecisionLog<RQ>>,
     ) -> Result<Self> {
@@ -352,8 +449,33 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
             timeout_dur,
             proposer_config,
             watermark,
+            fault_model,
+            fast_path_window,
+            compact_certificates,
+            usig,
+            mac_keys,
+            leader_monitor,
+            heartbeat,
+            pre_prepare_slot_timeout,
+            misbehaviour_reporter,
+            decision_log,
+            stable_checkpoints,
         } = config;
 
+        if fault_model.requires_usig() && usig.is_none() {
+            return Err(anyhow!(
+                "The trusted counter fault model requires a USIG to be provided"
+            ));
+        }
+
+        if heartbeat.is_some_and(|heartbeat| heartbeat.timeout <= heartbeat.interval) {
+            return Err(anyhow!(
+                "The leader liveness timeout must be larger than the heartbeat interval"
+            ));
+        }
+
+        fault_model.configure();
+
         let OrderingProtocolArgs(node_id, timeouts, pre_processor, batch_input, node, quorum) =
             args;
 
@@ -362,8 +484,21 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
             SeqNo::ZERO,
             quorum.clone(),
             timeout_dur,
+            fault_model,
         )?;
 
+        if let Some(mac_keys) = &mac_keys {
+            sync.set_mac_keys(mac_keys.clone());
+        }
+
+        if let Some(usig) = &usig {
+            sync.set_usig(usig.clone());
+        }
+
+        if let Some(heartbeat) = &heartbeat {
+            sync.enable_leader_liveness(heartbeat.timeout);
+        }
+
         let consensus_guard = ProposerConsensusGuard::new(sync.view(), watermark);
 
         let consensus = Consensus::<RQ>::new_replica(
@@ -373,9 +508,18 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
             watermark,
             consensus_guard.clone(),
             timeouts.clone(),
+            DecisionOptions {
+                usig: usig.clone(),
+                fast_path_window,
+                sign_votes: compact_certificates,
+                mac_keys,
+                pre_prepare_slot_timeout,
+            },
         );
 
-        let dec_log = initialize_decided_log::<RQ>(node_id);
+        let mut dec_log = initialize_decided_log::<RQ>(node_id, &decision_log)?;
+
+        dec_log.set_compact_certificates(compact_certificates);
 
         let proposer = Proposer::<RQ, NT>::new(
             node.clone(),
@@ -384,11 +528,14 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
             timeouts.clone(),
             consensus_guard.clone(),
             proposer_config,
+            usig,
+            heartbeat.map(|heartbeat| heartbeat.interval),
         );
 
         let replica = Self {
             phase: ConsensusPhase::NormalPhase,
             consensus,
+            log_transfer: LogTransfer::new(node_id, timeout_dur),
             synchronizer: sync,
             pre_processor,
             timeouts,
@@ -397,8 +544,13 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
             message_log: dec_log,
             proposer,
             node,
+            leader_monitor: leader_monitor.map(|config| LeaderMonitor::new(node_id, config)),
+            misbehaviour_reporter,
+            stable_checkpoints,
         };
 
+        replica.synchronizer.arm_leader_liveness(&replica.timeouts);
+
         let crr_view = replica.synchronizer.view();
 
         info!(
@@ -417,6 +569,8 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
 
         info!("{:?} // Watermark: {}", replica.node.id(), watermark);
 
+        info!("{:?} // Fault model: {:?}", replica.node.id(), fault_model);
+
         println!(
             "{:?} // Leader count: {}, Leader set: {:?}, Quorum: {:?}",
             replica.node.id(),
@@ -432,6 +586,11 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
     }
 
     fn poll_sync_phase(&mut self) -> Result<OPPollResult<ProofMetadata, PBFTMessage<RQ>, RQ>> {
+        if self.log_transfer.is_running() {
+            // The view change is paused until we have fetched the decisions we are missing
+            return Ok(OPPollResult::ReceiveMsg);
+        }
+
         // retrieve a view change message to be processed
         let poll_result = self.synchronizer.poll();
 
@@ -454,8 +613,6 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                     &self.node,
                 );
 
-                self.switch_phase(ConsensusPhase::NormalPhase);
-
                 if let Some(sync_status) = sync_status {
                     match sync_status {
                         SynchronizerStatus::NewViewJoinedQuorum(
@@ -463,6 +620,8 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                             decisions,
                             node,
                         ) => {
+                            self.switch_phase(ConsensusPhase::NormalPhase);
+
                             let _quorum_members = self.synchronizer.view().quorum_members().clone();
 
                             let decisions = self.handle_sync_result(consensus_status, decisions)?;
@@ -487,6 +646,8 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                             }
                         }
                         SynchronizerStatus::NewView(consensus_status, decisions) => {
+                            self.switch_phase(ConsensusPhase::NormalPhase);
+
                             let decisions = self.handle_sync_result(consensus_status, decisions)?;
 
                             Ok(OPPollResult::ProgressedDecision(
@@ -494,13 +655,19 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                                 decisions,
                             ))
                         }
+                        // The leader turned out to be faulty, so the view change goes on
+                        SynchronizerStatus::Running => Ok(OPPollResult::RePoll),
                         _ => {
                             warn!("Received sync status that is not handled");
 
+                            self.switch_phase(ConsensusPhase::NormalPhase);
+
                             Ok(OPPollResult::RePoll)
                         }
                     }
                 } else {
+                    self.switch_phase(ConsensusPhase::NormalPhase);
+
                     Ok(OPPollResult::RePoll)
                 }
             }
@@ -532,6 +699,10 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                             // As that has already been done by the adv sync method
                             OPPollResult::RunCst
                         }
+                        SyncPhaseRes::RunLogTransfer => {
+                            // The log transfer has already been started by the adv sync method
+                            OPPollResult::RePoll
+                        }
                         SyncPhaseRes::SyncProtocolNotNeeded => {
                             error!("Polling the sync phase should never return anything other than a run sync protocol or run cst protocol message, SyncProtocolNotNeeded");
                             OPPollResult::RePoll
@@ -544,6 +715,10 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                             error!("Polling the sync phase should never return anything other than a run sync protocol or run cst protocol message, Protocol Finished");
                             OPPollResult::RePoll
                         }
+                        SyncPhaseRes::ViewAbandoned(_) => {
+                            error!("Polling the sync phase should never return anything other than a run sync protocol or run cst protocol message, ViewAbandoned");
+                            OPPollResult::RePoll
+                        }
                     });
                 } else {
                     // The synchronizer should never return anything other than a view
@@ -577,6 +752,10 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
     ) -> Result<OPExecResult<ProofMetadata, PBFTMessage<RQ>, RQ>> {
         match message.message() {
             PBFTMessage::ViewChange(_view_change) => {
+                let Some(message) = self.consensus.sequence_view_change(message) else {
+                    return Ok(OPExecResult::MessageProcessedNoUpdate);
+                };
+
                 return Ok(match self.adv_sync(message) {
                     SyncPhaseRes::SyncProtocolNotNeeded => OPExecResult::MessageProcessedNoUpdate,
                     SyncPhaseRes::RunSyncProtocol => OPExecResult::MessageProcessedNoUpdate,
@@ -609,12 +788,20 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                             )
                         }
                     }
+                    SyncPhaseRes::ViewAbandoned(to_execute) => OPExecResult::ProgressedDecision(
+                        DecisionsAhead::ClearAhead,
+                        self.handle_sync_result(ConsensusStatus::MessageIgnored, Some(to_execute))?,
+                    ),
+                    SyncPhaseRes::RunLogTransfer => OPExecResult::MessageProcessedNoUpdate,
                     SyncPhaseRes::RunCSTProtocol => OPExecResult::RunCst,
                 });
             }
             PBFTMessage::Consensus(_) => {
                 self.consensus.queue(message);
             }
+            PBFTMessage::LogTransfer(_) => {
+                return Ok(self.adv_log_transfer(message));
+            }
             _ => {}
         }
 
@@ -641,6 +828,10 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                 return self.adv_consensus(message);
             }
             PBFTMessage::ViewChange(_) => {
+                let Some(message) = self.consensus.sequence_view_change(message) else {
+                    return Ok(OPExecResult::MessageProcessedNoUpdate);
+                };
+
                 let status = self.synchronizer.process_message(
                     message,
                     &self.timeouts,
@@ -657,10 +848,16 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                     SynchronizerStatus::Running => self.switch_phase(ConsensusPhase::SyncPhase),
                     // should not happen...
                     _ => {
-                        unreachable!()
+                        error!(
+                            "{:?} // The synchronizer returned an unexpected status while in the normal phase",
+                            self.node.id()
+                        );
                     }
                 }
             }
+            PBFTMessage::LogTransfer(_) => {
+                return Ok(self.adv_log_transfer(message));
+            }
             _ => {}
         }
 
@@ -691,7 +888,13 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
             ConsensusStatus::VotedTwice(_) | ConsensusStatus::MessageIgnored => {
                 OPExecResult::MessageDropped
             }
+            ConsensusStatus::Misbehaved(evidence) => {
+                self.report_misbehaviour(evidence);
+
+                OPExecResult::MessageDropped
+            }
             ConsensusStatus::MessageQueued => OPExecResult::MessageQueued,
+            ConsensusStatus::MessageProcessed => OPExecResult::MessageProcessedNoUpdate,
             ConsensusStatus::Deciding(result) => {
                 OPExecResult::ProgressedDecision(DecisionsAhead::Ignore, result)
             }
@@ -702,25 +905,128 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
         })
     }
 
+    /// The leaders of `current` which were removed in `installed`, a reduced leader set of the
+    /// same view. `None` if `installed` is not such a reduction of `current`
+    fn leaders_removed_in(current: &ViewInfo, installed: &ViewInfo) -> Option<Vec<NodeId>> {
+        let reduction = installed.leader_set().len() < current.leader_set().len()
+            && installed
+                .leader_set()
+                .iter()
+                .all(|leader| current.leader_set().contains(leader));
+
+        if !reduction {
+            return None;
+        }
+
+        Some(
+            current
+                .leader_set()
+                .iter()
+                .filter(|leader| !installed.leader_set().contains(leader))
+                .copied()
+                .collect(),
+        )
+    }
+
     /// Finalize all possible consensus instances
     fn finalize_all_possible(&mut self) -> Result<Vec<ProtocolConsensusDecision<RQ>>> {
-        let view = self.synchronizer.view();
+        let mut view = self.synchronizer.view();
 
         let mut finalized_decisions = Vec::with_capacity(self.consensus.finalizeable_count());
 
         while self.consensus.can_finalize() {
+            let metrics = self.consensus.finalizeable_metrics().cloned();
+
             // This will automatically move the consensus machine to the next consensus instance
             let completed_batch = self.consensus.finalize(&view)?.unwrap();
 
+            if let (Some(monitor), Some(metrics)) = (&mut self.leader_monitor, metrics) {
+                monitor.record_decision(&view, &metrics, completed_batch.request_count());
+            }
+
+            if !completed_batch.skipped_leaders().is_empty() {
+                // Every correct replica agreed on the skipped slots, so they all
+                // remove the same leaders at this same point of the execution
+                if let Some(reduced) = self
+                    .synchronizer
+                    .remove_leaders(completed_batch.skipped_leaders())
+                {
+                    self.consensus.remove_leaders(&reduced);
+
+                    view = reduced;
+                }
+            }
+
             //Should the execution be scheduled here or will it be scheduled by the persistent log?
             let exec_info = self.message_log.finalize_batch(completed_batch)?;
 
             finalized_decisions.push(exec_info);
         }
 
+        self.monitor_leader(&view);
+
         Ok(finalized_decisions)
     }
 
+    /// Hand the evidence of a misbehaving replica to the application
+    fn report_misbehaviour(&self, evidence: MisbehaviourEvidence<RQ>) {
+        metric_increment(MISBEHAVIOUR_DETECTED_ID, Some(1));
+
+        match &self.misbehaviour_reporter {
+            Some(reporter) => reporter.report(evidence),
+            None => warn!(
+                "{:?} // Detected misbehaviour {:?}, but there is no reporter to hand it to",
+                self.node.id(),
+                evidence
+            ),
+        }
+    }
+
+    /// Proactively start a view change if the leader is performing below expectations
+    fn monitor_leader(&mut self, view: &ViewInfo) {
+        let Some(monitor) = &mut self.leader_monitor else {
+            return;
+        };
+
+        if !matches!(self.phase, ConsensusPhase::NormalPhase) {
+            return;
+        }
+
+        if let Some(performance) = monitor.evaluate(view) {
+            info!(
+                "{:?} // Proactively replacing leader {:?} of view {:?} due to its performance {:?}",
+                self.node.id(),
+                view.leader(),
+                view.sequence_number(),
+                performance
+            );
+
+            self.switch_phase(ConsensusPhase::SyncPhase);
+
+            self.synchronizer.begin_view_change(
+                Some(Vec::new()),
+                &*self.node,
+                &self.timeouts,
+                &self.message_log,
+            );
+        }
+    }
+
+    /// Hand the view change messages released by the trusted counter order to the synchronizer
+    fn queue_released_view_changes(&mut self) {
+        let released = self.consensus.take_released_view_changes();
+
+        if released.is_empty() {
+            return;
+        }
+
+        released
+            .into_iter()
+            .for_each(|message| self.synchronizer.queue(message));
+
+        self.synchronizer.signal();
+    }
+
     /// Advance the sync phase of the algorithm
     fn adv_sync(&mut self, message: ShareableMessage<PBFTMessage<RQ>>) -> SyncPhaseRes<RQ> {
         let status = self.synchronizer.process_message(
@@ -753,6 +1059,11 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
 
                 SyncPhaseRes::JoinedQuorum(consensus_decision, decision, node)
             }
+            SynchronizerStatus::ViewAbandoned(to_execute) => {
+                self.switch_phase(ConsensusPhase::SyncPhase);
+
+                SyncPhaseRes::ViewAbandoned(to_execute)
+            }
             SynchronizerStatus::RunCst => {
                 //This happens when a new view is being introduced and we are not up to date
                 //With the rest of the replicas. This might happen because the replica was faulty
@@ -766,6 +1077,25 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
 
                 SyncPhaseRes::RunCSTProtocol
             }
+            SynchronizerStatus::RunLogTransfer { first, last } => {
+                //We are only a few decisions behind the rest of the replicas, so we fetch them
+                //from the others. Once we have them, polling the sync phase resumes the view change
+                self.switch_phase(ConsensusPhase::SyncPhase);
+
+                let view = self.synchronizer.view();
+
+                match self
+                    .log_transfer
+                    .start(first, last, &view, &*self.node, &self.timeouts)
+                {
+                    LogTransferStatus::RunCst => {
+                        metric_increment(LOG_TRANSFER_FALLBACK_CST_ID, Some(1));
+
+                        SyncPhaseRes::RunCSTProtocol
+                    }
+                    _ => SyncPhaseRes::RunLogTransfer,
+                }
+            }
             // should not happen...
             _ => {
                 unreachable!()
@@ -773,6 +1103,53 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
         }
     }
 
+    /// Advance the log transfer with a received message
+    fn adv_log_transfer(
+        &mut self,
+        message: ShareableMessage<PBFTMessage<RQ>>,
+    ) -> OPExecResult<ProofMetadata, PBFTMessage<RQ>, RQ> {
+        let status = self.log_transfer.process_message(
+            message,
+            &self.synchronizer,
+            &mut self.consensus,
+            &mut self.message_log,
+            &*self.node,
+            &self.timeouts,
+        );
+
+        self.log_transfer_result(status)
+    }
+
+    /// Handle the result of advancing the log transfer
+    fn log_transfer_result(
+        &mut self,
+        status: LogTransferStatus<RQ>,
+    ) -> OPExecResult<ProofMetadata, PBFTMessage<RQ>, RQ> {
+        match status {
+            LogTransferStatus::Nil | LogTransferStatus::Running => {
+                OPExecResult::MessageProcessedNoUpdate
+            }
+            LogTransferStatus::Progressed(decisions) => {
+                OPExecResult::ProgressedDecision(DecisionsAhead::Ignore, decisions)
+            }
+            LogTransferStatus::Finished(decisions) => {
+                // The next poll of the sync phase resumes the view change
+                OPExecResult::ProgressedDecision(DecisionsAhead::Ignore, decisions)
+            }
+            LogTransferStatus::RunCst => {
+                info!(
+                    "{:?} // Log transfer could not fetch the missing decisions, running the CST protocol",
+                    self.node.id()
+                );
+
+                metric_increment(LOG_TRANSFER_FALLBACK_CST_ID, Some(1));
+
+                // The view change stays paused, and is resumed once the state transfer is done
+                OPExecResult::RunCst
+            }
+        }
+    }
+
     fn merge_decisions(
         &mut self,
         status: MaybeVec<OPDecision<RQ>>,
@@ -872,6 +1249,31 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
         RQ: SerType + SessionBased + 'static,
         NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
 {
+    /// Reassign the vote weights of the quorum members.
+    ///
+    /// The new weights will only take effect once the next view change is completed,
+    /// so all correct replicas must be given the same assignment.
+    pub fn reassign_vote_weights(&self, weights: VoteWeights) -> Result<()> {
+        self.synchronizer.queue_weight_reassignment(weights)
+    }
+
+    /// The window of the latest decided proofs we retain
+    pub fn decision_log(&self) -> &DecisionLog<RQ> {
+        self.message_log.decision_log()
+    }
+
+    /// A checkpoint covering every decision up to `seq` has become stable,
+    /// so the decided proofs it covers no longer need to be retained
+    pub fn checkpoint_stable(&mut self, seq: SeqNo) {
+        debug!(
+            "{:?} // Checkpoint of {:?} is stable, truncating the decision log",
+            self.node.id(),
+            seq
+        );
+
+        self.message_log.checkpoint_stable(seq);
+    }
+
     pub(crate) fn switch_phase(&mut self, new_phase: ConsensusPhase) {
         info!(
             "{:?} // Switching from phase {:?} to phase {:?}",
@@ -900,7 +1302,10 @@ impl<RQ, NT> PBFTOrderProtocol<RQ, NT>
                     //Other operations.
                     self.consensus_guard.lock_consensus();
                 }
-                (ConsensusPhase::SyncPhase, ConsensusPhase::NormalPhase) => {}
+                (ConsensusPhase::SyncPhase, ConsensusPhase::NormalPhase) => {
+                    // Give the leader of the new view a full liveness timeout
+                    self.synchronizer.arm_leader_liveness(&self.timeouts);
+                }
                 (_, _) => {}
             }
 
@@ -951,6 +1356,9 @@ for PBFTOrderProtocol<RQ, NT>
                 ConsensusMessageKind::PrePrepare(_) => Ok(CF_PRE_PREPARES),
                 ConsensusMessageKind::Prepare(_) => Ok(CF_PREPARES),
                 ConsensusMessageKind::Commit(_) => Ok(CF_COMMIT),
+                ConsensusMessageKind::SkipSlot(_) => {
+                    Err(anyhow!("Skip slot messages are not persisted."))
+                }
             },
             PBFTMessage::ViewChange(_view_change) => {
                 Err(anyhow!("Failed to get type for view change message."))
@@ -958,6 +1366,9 @@ for PBFTOrderProtocol<RQ, NT>
             PBFTMessage::ObserverMessage(_) => {
                 Err(anyhow!("Failed to get type for view change message."))
             }
+            PBFTMessage::LogTransfer(_) => {
+                Err(anyhow!("Log transfer messages are not persisted."))
+            }
         }
     }
 
@@ -1004,7 +1415,7 @@ for PBFTOrderProtocol<RQ, NT>
     fn get_requests_in_proof(
         proof: &PProof<RQ, PBFTConsensus<RQ>, PBFTConsensus<RQ>>,
     ) -> Result<ProtocolConsensusDecision<RQ>> {
-        Ok(ProtocolConsensusDecision::from(proof))
+        ProtocolConsensusDecision::try_from(proof)
     }
 }
 
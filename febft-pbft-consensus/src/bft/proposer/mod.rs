@@ -18,6 +18,7 @@ use atlas_core::timeouts::timeout::TimeoutModHandle;
 use atlas_metrics::metrics::{metric_duration, metric_increment, metric_store_count};
 
 use crate::bft::config::ProposerConfig;
+use crate::bft::consensus::usig::{attach_ui, Usig};
 use crate::bft::consensus::ProposerConsensusGuard;
 use crate::bft::message::{ConsensusMessage, ConsensusMessageKind, PBFTMessage};
 use crate::bft::metric::{
@@ -56,6 +57,11 @@ where
     //Time limit for generating a batch with target_global_batch_size size
     global_batch_time_limit: u128,
     max_batch_size: usize,
+    /// The trusted counter used to certify our pre prepare messages
+    usig: Option<Arc<dyn Usig>>,
+    /// When set, we only propose empty batches (null requests) after
+    /// having been idle for this long, to signal we are still alive
+    heartbeat_interval: Option<Duration>,
 }
 
 const TIMEOUT: Duration = Duration::from_micros(10);
@@ -95,6 +101,8 @@ where
         timeouts: TimeoutModHandle,
         consensus_guard: Arc<ProposerConsensusGuard>,
         proposer_config: ProposerConfig,
+        usig: Option<Arc<dyn Usig>>,
+        heartbeat_interval: Option<Duration>,
     ) -> Arc<Self> {
         let ProposerConfig {
             target_batch_size,
@@ -112,6 +120,8 @@ where
             target_global_batch_size: target_batch_size as usize,
             global_batch_time_limit: batch_timeout as u128,
             max_batch_size: max_batch_size as usize,
+            usig,
+            heartbeat_interval,
         })
     }
 
@@ -257,6 +267,15 @@ where
                     //Batch isn't large enough and time hasn't passed, don't even attempt to propose
                     return false;
                 }
+
+                if let Some(heartbeat_interval) = self.heartbeat_interval {
+                    if current_batch_size == 0
+                        && propose.last_proposal.elapsed() < heartbeat_interval
+                    {
+                        // Nothing to propose and it's not yet time for a null request
+                        return false;
+                    }
+                }
             }
 
             let last_proposed_batch = propose.last_proposal;
@@ -346,6 +365,14 @@ where
 
         let targets = view.quorum_members().clone();
 
+        if currently_accumulated.is_empty() && self.heartbeat_interval.is_some() {
+            debug!(
+                "{:?} // Proposing a null request {:?} as a heartbeat",
+                self.node_ref.id(),
+                seq
+            );
+        }
+
         info!(
             "{:?} // Proposing new batch with {} request count {:?} to quorum: {:?}",
             self.node_ref.id(),
@@ -354,13 +381,41 @@ where
             targets
         );
 
-        let message = PBFTMessage::Consensus(ConsensusMessage::new(
+        let mut message = ConsensusMessage::new(
             seq,
             view.sequence_number(),
             ConsensusMessageKind::PrePrepare(currently_accumulated),
-        ));
+        );
+
+        if view.fault_model().requires_usig() {
+            let Some(usig) = &self.usig else {
+                error!(
+                    "{:?} // Running under the trusted counter fault model without a USIG, not proposing {:?}",
+                    self.node_ref.id(),
+                    seq
+                );
+
+                return;
+            };
+
+            message = match attach_ui(&**usig, message) {
+                Ok(message) => message,
+                Err(err) => {
+                    error!(
+                        "{:?} // Failed to create the unique identifier for pre prepare {:?}: {:?}",
+                        self.node_ref.id(),
+                        seq,
+                        err
+                    );
+
+                    return;
+                }
+            };
+        }
 
-        let _ = self.node_ref.broadcast_signed(message, targets.into_iter());
+        let _ = self
+            .node_ref
+            .broadcast_signed(PBFTMessage::Consensus(message), targets.into_iter());
 
         metric_increment(PROPOSER_BATCHES_MADE_ID, Some(1));
     }
@@ -0,0 +1,390 @@
+//! Bounded buffering of messages which arrive ahead of their consensus instance or view.
+//!
+//! Correct replicas only ever run a bounded number of instances (the watermark) or views
+//! ahead of each other, and send a bounded number of messages of each kind for each of them.
+//! We therefore only buffer messages up to a horizon, and cap how many messages of each kind
+//! every sender can have buffered, so a byzantine replica cannot exhaust our memory by
+//! flooding us with messages for far away instances or views.
+//!
+//! When a sender runs out of room, its message for the furthest away slot is evicted in favour
+//! of one for a nearer slot, as those are needed first to make progress. A replica which falls
+//! further behind than the horizon catches up through the state transfer instead.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use either::Either;
+use tracing::debug;
+
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_core::ordering_protocol::ShareableMessage;
+use atlas_metrics::metrics::metric_increment;
+
+use crate::bft::metric::{
+    FUTURE_MESSAGES_DROPPED_ID, FUTURE_MESSAGES_EVICTED_ID, STALE_MESSAGES_DROPPED_ID,
+};
+
+/// How many messages of a given kind we buffer, for each sender, per slot of the horizon.
+/// Correct replicas send at most one per slot, the slack covers retransmissions
+/// and the skip slot votes, which share the queue of the pre prepares
+pub const MESSAGES_PER_SLOT: usize = 4;
+
+/// How many views ahead of the current one we buffer messages for
+pub const VIEW_HORIZON: usize = 16;
+
+/// The limits of a [BoundedTboQueue]
+#[derive(Clone, Copy, Debug)]
+pub struct QueueBounds {
+    /// How many slots (instances or views) ahead we buffer messages for
+    horizon: usize,
+    /// How many messages each sender can have buffered in the queue
+    per_sender: usize,
+}
+
+impl QueueBounds {
+    pub fn new(horizon: usize, per_sender: usize) -> Self {
+        Self {
+            horizon,
+            per_sender,
+        }
+    }
+
+    /// The bounds for messages of upcoming consensus instances, which lie within
+    /// one more watermark window of the instances currently being decided
+    pub fn for_instances(watermark: u32) -> Self {
+        let horizon = watermark.max(1) as usize;
+
+        Self::new(horizon, horizon * MESSAGES_PER_SLOT)
+    }
+
+    /// The bounds for messages of upcoming views, of which a sender
+    /// can send `per_view` of each kind in each view
+    pub fn for_views(per_view: usize) -> Self {
+        Self::new(VIEW_HORIZON, VIEW_HORIZON * per_view.max(1) * MESSAGES_PER_SLOT)
+    }
+
+    pub fn horizon(&self) -> usize {
+        self.horizon
+    }
+
+    pub fn per_sender(&self) -> usize {
+        self.per_sender
+    }
+}
+
+/// What happened to a message handed to a [BoundedTboQueue]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueStatus {
+    /// The message was buffered
+    Queued,
+    /// The message was buffered in place of one of the sender's
+    /// messages for a further away slot
+    QueuedEvicting,
+    /// The message pertains to a slot we have already moved past
+    DroppedStale,
+    /// The message lies beyond the horizon
+    DroppedBeyondHorizon,
+    /// The sender has no more room for messages this close
+    DroppedSenderFull,
+}
+
+/// A queue of messages, split in slots for the upcoming instances (or views),
+/// bounded in how far ahead it reaches and in how many messages each sender can have in it
+pub struct BoundedTboQueue<M> {
+    slots: VecDeque<VecDeque<ShareableMessage<M>>>,
+    queued: BTreeMap<NodeId, usize>,
+    bounds: QueueBounds,
+}
+
+impl<M> BoundedTboQueue<M> {
+    pub fn new(bounds: QueueBounds) -> Self {
+        Self {
+            slots: VecDeque::new(),
+            queued: BTreeMap::new(),
+            bounds,
+        }
+    }
+
+    pub fn bounds(&self) -> &QueueBounds {
+        &self.bounds
+    }
+
+    /// How many slots currently hold (or held) messages
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// How many messages the given sender has buffered
+    pub fn queued_by(&self, sender: &NodeId) -> usize {
+        self.queued.get(sender).copied().unwrap_or(0)
+    }
+
+    /// Are there messages for the first slot
+    pub fn has_front_messages(&self) -> bool {
+        self.slots
+            .front()
+            .map(|slot| !slot.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Queue a message for the slot `index` positions after the first one
+    pub fn queue(&mut self, index: usize, message: ShareableMessage<M>) -> QueueStatus {
+        let sender = message.header().from();
+
+        if index >= self.bounds.horizon {
+            debug!(
+                "Dropping message from {:?} for slot {}, beyond the horizon of {}",
+                sender, index, self.bounds.horizon
+            );
+
+            metric_increment(FUTURE_MESSAGES_DROPPED_ID, Some(1));
+
+            return QueueStatus::DroppedBeyondHorizon;
+        }
+
+        let mut status = QueueStatus::Queued;
+
+        if self.queued_by(&sender) >= self.bounds.per_sender {
+            if !self.evict_furthest(sender, index) {
+                debug!(
+                    "Dropping message from {:?} for slot {}, as it already has {} messages queued",
+                    sender,
+                    index,
+                    self.queued_by(&sender)
+                );
+
+                metric_increment(FUTURE_MESSAGES_DROPPED_ID, Some(1));
+
+                return QueueStatus::DroppedSenderFull;
+            }
+
+            metric_increment(FUTURE_MESSAGES_EVICTED_ID, Some(1));
+
+            status = QueueStatus::QueuedEvicting;
+        }
+
+        while self.slots.len() <= index {
+            self.slots.push_back(VecDeque::new());
+        }
+
+        self.slots[index].push_back(message);
+
+        *self.queued.entry(sender).or_insert(0) += 1;
+
+        status
+    }
+
+    /// Queue a message in the slot of its sequence number,
+    /// where `first` is the sequence number of the first slot
+    pub fn queue_seq(&mut self, first: SeqNo, message: ShareableMessage<M>) -> QueueStatus
+    where
+        M: Orderable,
+    {
+        match message.message().sequence_number().index(first) {
+            Either::Right(index) => self.queue(index, message),
+            Either::Left(_) => {
+                debug!(
+                    "Dropping message from {:?} for {:?}, as we have already moved on to {:?}",
+                    message.header().from(),
+                    message.message().sequence_number(),
+                    first
+                );
+
+                metric_increment(STALE_MESSAGES_DROPPED_ID, Some(1));
+
+                QueueStatus::DroppedStale
+            }
+        }
+    }
+
+    /// Remove the sender's message for the furthest slot, if it lies further than `index`
+    fn evict_furthest(&mut self, sender: NodeId, index: usize) -> bool {
+        for slot in (index + 1..self.slots.len()).rev() {
+            let position = self.slots[slot]
+                .iter()
+                .rposition(|message| message.header().from() == sender);
+
+            if let Some(position) = position {
+                self.slots[slot].remove(position);
+
+                self.release(sender);
+
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Take the first message of the first slot
+    pub fn pop_front_message(&mut self) -> Option<ShareableMessage<M>> {
+        let message = self.slots.front_mut()?.pop_front()?;
+
+        self.release(message.header().from());
+
+        Some(message)
+    }
+
+    /// Move on to the next slot, returning the messages of the current one
+    pub fn advance(&mut self) -> VecDeque<ShareableMessage<M>> {
+        let slot = self.slots.pop_front().unwrap_or_default();
+
+        slot.iter()
+            .for_each(|message| self.release(message.header().from()));
+
+        slot
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.queued.clear();
+    }
+
+    fn release(&mut self, sender: NodeId) {
+        if let Some(count) = self.queued.get_mut(&sender) {
+            *count = count.saturating_sub(1);
+
+            if *count == 0 {
+                self.queued.remove(&sender);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod queue_tests {
+    use std::sync::Arc;
+
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::globals::ReadOnly;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::{Orderable, SeqNo};
+    use atlas_communication::lookup_table::MessageModule;
+    use atlas_communication::message::{StoredMessage, WireMessage};
+    use atlas_core::ordering_protocol::ShareableMessage;
+
+    use super::{BoundedTboQueue, QueueBounds, QueueStatus};
+
+    struct Message(SeqNo);
+
+    impl Orderable for Message {
+        fn sequence_number(&self) -> SeqNo {
+            self.0
+        }
+    }
+
+    fn message(from: u32, seq: u32) -> ShareableMessage<Message> {
+        let from = NodeId::from(from);
+
+        let (header, _, _) = WireMessage::new(
+            from,
+            from,
+            MessageModule::Protocol,
+            Vec::new().into(),
+            0,
+            Some(Digest::from_bytes(&[0; Digest::LENGTH]).unwrap()),
+            None,
+        )
+        .into_inner();
+
+        Arc::new(ReadOnly::new(StoredMessage::new(
+            header,
+            Message(SeqNo::from(seq)),
+        )))
+    }
+
+    /// The sequence numbers of the messages in each slot
+    fn drain(queue: &mut BoundedTboQueue<Message>) -> Vec<Vec<u32>> {
+        let mut slots = Vec::new();
+
+        while !queue.is_empty() {
+            slots.push(
+                queue
+                    .advance()
+                    .iter()
+                    .map(|message| u32::from(message.message().sequence_number()))
+                    .collect(),
+            );
+        }
+
+        slots
+    }
+
+    #[test]
+    fn test_messages_beyond_the_horizon_are_dropped() {
+        let mut queue = BoundedTboQueue::new(QueueBounds::new(4, 8));
+
+        assert_eq!(queue.queue(3, message(0, 3)), QueueStatus::Queued);
+        assert_eq!(
+            queue.queue(4, message(0, 4)),
+            QueueStatus::DroppedBeyondHorizon
+        );
+        assert_eq!(queue.queued_by(&NodeId::from(0u32)), 1);
+    }
+
+    #[test]
+    fn test_stale_messages_are_dropped() {
+        let mut queue = BoundedTboQueue::new(QueueBounds::new(4, 8));
+
+        assert_eq!(
+            queue.queue_seq(SeqNo::from(5u32), message(0, 3)),
+            QueueStatus::DroppedStale
+        );
+        assert_eq!(
+            queue.queue_seq(SeqNo::from(5u32), message(0, 6)),
+            QueueStatus::Queued
+        );
+        assert_eq!(drain(&mut queue), vec![vec![], vec![6]]);
+    }
+
+    #[test]
+    fn test_sender_cap_only_affects_that_sender() {
+        let mut queue = BoundedTboQueue::new(QueueBounds::new(4, 2));
+
+        assert_eq!(queue.queue(0, message(0, 0)), QueueStatus::Queued);
+        assert_eq!(queue.queue(0, message(0, 0)), QueueStatus::Queued);
+
+        // There is no message further away to make room for this one
+        assert_eq!(
+            queue.queue(0, message(0, 0)),
+            QueueStatus::DroppedSenderFull
+        );
+        assert_eq!(queue.queue(0, message(1, 0)), QueueStatus::Queued);
+
+        assert_eq!(queue.queued_by(&NodeId::from(0u32)), 2);
+        assert_eq!(queue.queued_by(&NodeId::from(1u32)), 1);
+
+        // Taking a message out gives the sender its room back
+        assert!(queue.pop_front_message().is_some());
+
+        assert_eq!(queue.queued_by(&NodeId::from(0u32)), 1);
+        assert_eq!(queue.queue(0, message(0, 0)), QueueStatus::Queued);
+    }
+
+    #[test]
+    fn test_furthest_message_is_evicted_for_a_nearer_one() {
+        let mut queue = BoundedTboQueue::new(QueueBounds::new(4, 2));
+
+        assert_eq!(queue.queue(2, message(0, 2)), QueueStatus::Queued);
+        assert_eq!(queue.queue(3, message(0, 3)), QueueStatus::Queued);
+        assert_eq!(queue.queue(3, message(1, 3)), QueueStatus::Queued);
+
+        assert_eq!(queue.queue(0, message(0, 0)), QueueStatus::QueuedEvicting);
+
+        // The sender has nothing further than these slots left to evict
+        assert_eq!(queue.queue(2, message(0, 2)), QueueStatus::DroppedSenderFull);
+        assert_eq!(
+            queue.queue(3, message(0, 3)),
+            QueueStatus::DroppedSenderFull
+        );
+
+        assert_eq!(queue.queued_by(&NodeId::from(0u32)), 2);
+
+        // Only the messages of the other sender are kept in the furthest slot
+        assert_eq!(drain(&mut queue), vec![vec![0], vec![], vec![2], vec![3]]);
+    }
+}
@@ -1,10 +1,9 @@
 use atlas_common::ordering::Orderable;
 use atlas_common::serialization_helper::SerType;
+use atlas_communication::message::StoredMessage;
 use atlas_core::messages::{ClientRqInfo, SessionBased};
 use std::marker::PhantomData;
 
-use crate::bft::message::{ConsensusMessage, ConsensusMessageKind};
-
 pub struct FollowerSynchronizer<RQ: SerType> {
     _phantom: PhantomData<fn() -> RQ>,
 }
@@ -25,14 +24,7 @@ impl<RQ: SerType + SessionBased + 'static> FollowerSynchronizer<RQ> {
     ///Watch a batch of requests received from a Pre prepare message sent by the leader
     /// In reality we won't watch, more like the contrary, since the requests were already
     /// proposed, they won't timeout
-    pub fn watch_request_batch(&self, pre_prepare: &ConsensusMessage<RQ>) -> Vec<ClientRqInfo> {
-        let requests = match pre_prepare.kind() {
-            ConsensusMessageKind::PrePrepare(req) => req,
-            _ => {
-                panic!()
-            }
-        };
-
+    pub fn watch_request_batch(&self, requests: &[StoredMessage<RQ>]) -> Vec<ClientRqInfo> {
         let mut digests = Vec::with_capacity(requests.len());
 
         //TODO: Cancel ongoing timeouts of requests that are in the batch
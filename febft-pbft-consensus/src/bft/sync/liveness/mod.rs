@@ -0,0 +1,112 @@
+//! Leader liveness monitoring.
+//!
+//! Client request timeouts are only armed when there are client requests, so when
+//! the system is idle a crashed leader goes unnoticed until the next request times out.
+//! When heartbeats are enabled, the leader proposes null requests (empty batches)
+//! whenever it has been idle for the heartbeat interval, and the other replicas
+//! start a view change when they have not heard from the leader for the liveness timeout.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::SeqNo;
+use atlas_core::timeouts::timeout::{ModTimeout, TimeoutModHandle};
+use atlas_core::timeouts::{TimeOutable, TimeoutID};
+
+/// The information attached to a leader liveness timeout
+#[derive(Debug, Clone)]
+pub struct LeaderLivenessTimeout {
+    epoch: u32,
+}
+
+impl TimeOutable for LeaderLivenessTimeout {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct LeaderLiveness {
+    node_id: NodeId,
+    timeout: Duration,
+    /// The last time we received a pre prepare from a leader
+    last_heard: Cell<Instant>,
+    /// Identifies the currently armed timeout, so we can ignore stale ones
+    epoch: Cell<u32>,
+}
+
+impl LeaderLiveness {
+    pub fn new(node_id: NodeId, timeout: Duration) -> Self {
+        Self {
+            node_id,
+            timeout,
+            last_heard: Cell::new(Instant::now()),
+            epoch: Cell::new(0),
+        }
+    }
+
+    /// We have received a pre prepare (possibly a null request) from a leader
+    pub fn leader_heard(&self) {
+        self.last_heard.set(Instant::now());
+    }
+
+    /// (Re)start watching the leader, giving it a full timeout to show signs of life.
+    /// This should be done whenever a new view is installed.
+    pub fn arm(&self, timeouts: &TimeoutModHandle) {
+        self.leader_heard();
+
+        self.request_timeout(timeouts, self.timeout);
+    }
+
+    /// Check the given timeouts for an expired leader liveness timeout.
+    ///
+    /// Returns true when the leader has been silent for longer than the liveness timeout.
+    /// If the leader was heard from in the meantime, the timeout is armed again
+    /// for the remaining time.
+    pub fn timed_out(&self, timeouts: &TimeoutModHandle, timed_out: &[ModTimeout]) -> bool {
+        let current = timed_out.iter().any(|timeout| {
+            timeout
+                .extra_info()
+                .and_then(|info| info.as_any().downcast_ref::<LeaderLivenessTimeout>())
+                .is_some_and(|info| info.epoch == self.epoch.get())
+        });
+
+        if !current {
+            return false;
+        }
+
+        let silent_for = self.last_heard.get().elapsed();
+
+        if silent_for >= self.timeout {
+            return true;
+        }
+
+        debug!(
+            "{:?} // Leader liveness timeout fired, but we heard from the leader {:?} ago",
+            self.node_id, silent_for
+        );
+
+        self.request_timeout(timeouts, self.timeout - silent_for);
+
+        false
+    }
+
+    fn request_timeout(&self, timeouts: &TimeoutModHandle, duration: Duration) {
+        let epoch = self.epoch.get().wrapping_add(1);
+
+        self.epoch.set(epoch);
+
+        let _ = timeouts.request_timeouts(
+            vec![(
+                TimeoutID::SeqNoBased(SeqNo::from(epoch)),
+                Some(Box::new(LeaderLivenessTimeout { epoch }) as Box<dyn TimeOutable>),
+            )],
+            duration,
+            1,
+            false,
+        );
+    }
+}
@@ -4,7 +4,6 @@ use std::fmt::{Debug, Formatter};
 use std::{
     cell::RefCell,
     cmp::Ordering,
-    collections::VecDeque,
     sync::{Arc, Mutex},
     time::Duration,
 };
@@ -16,38 +15,53 @@ use tracing::{debug, error, info, warn};
 #[cfg(feature = "serialize_serde")]
 use serde::{Deserialize, Serialize};
 
-use atlas_common::crypto::hash::Digest;
+use atlas_common::crypto::hash::{Context, Digest};
 use atlas_common::error::*;
 use atlas_common::node_id::NodeId;
-use atlas_common::ordering::{
-    tbo_advance_message_queue, tbo_pop_message, tbo_queue_message_arc, Orderable, SeqNo,
-};
+use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::serialization_helper::SerType;
 use atlas_common::{collections, prng};
 use atlas_communication::lookup_table::MessageModule;
 use atlas_communication::message::{Header, StoredMessage, WireMessage};
 use atlas_communication::reconfiguration::NetworkInformationProvider;
 use atlas_core::messages::{ClientRqInfo, SessionBased};
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
 use atlas_core::ordering_protocol::networking::OrderProtocolSendNode;
 use atlas_core::ordering_protocol::reconfigurable_order_protocol::ReconfigurationAttemptResult;
 use atlas_core::ordering_protocol::{unwrap_shareable_message, ShareableMessage};
 
 use atlas_core::request_pre_processing::RequestPreProcessor;
 use atlas_core::timeouts::timeout::{ModTimeout, TimeoutModHandle};
+use atlas_metrics::metrics::metric_increment;
 
+use crate::bft::config::FaultModel;
 use crate::bft::consensus::{Consensus, ConsensusStatus};
-use crate::bft::log::decisions::{CollectData, Proof, ViewDecisionPair};
+use crate::bft::consensus::usig::{attach_view_change_ui, Usig};
+use crate::bft::consensus::authenticator::{
+    verify_vote_authenticator, MacKeys, MessageAuthentication,
+};
+use crate::bft::log::certificate::QuorumCertificate;
+use crate::bft::log::deciding::skipped_slot_digest;
+use crate::bft::log::decisions::{
+    CollectData, IncompleteProof, PrepareSet, Proof, ViewDecisionPair,
+};
 use crate::bft::log::Log;
+use crate::bft::log_transfer::within_retained;
 use crate::bft::message::{
     ConsensusMessage, ConsensusMessageKind, FwdConsensusMessage, PBFTMessage, ViewChangeMessage,
     ViewChangeMessageKind,
 };
-use crate::bft::sync::view::ViewInfo;
+use crate::bft::metric::MALFORMED_MESSAGES_DROPPED_ID;
+use crate::bft::queue::{BoundedTboQueue, QueueBounds};
+use crate::bft::sync::liveness::LeaderLiveness;
+use crate::bft::sync::view::{ViewInfo, VoteWeights};
 use crate::bft::{OPDecision, PBFT};
 
 use self::{follower_sync::FollowerSynchronizer, replica_sync::ReplicaSynchronizer};
 
 pub mod follower_sync;
+pub mod liveness;
+pub mod monitor;
 pub mod replica_sync;
 pub mod view;
 
@@ -61,7 +75,7 @@ macro_rules! extract_msg {
     };
 
     ($t:ty => $opt:block, $g:expr, $q:expr) => {
-        if let Some(stored) = tbo_pop_message::<ShareableMessage<PBFTMessage<$t>>>($q) {
+        if let Some(stored) = $q.pop_front_message() {
             $opt
             SynchronizerPollStatus::NextMessage(stored)
         } else {
@@ -73,7 +87,7 @@ macro_rules! extract_msg {
 
 macro_rules! stop_status {
     ($i:expr, $view:expr) => {{
-        let f = $view.params().f();
+        let f = $view.f();
         if $i > f {
             SynchronizerStatus::Running
         } else {
@@ -105,6 +119,12 @@ macro_rules! finalize_view_change {
                 $self.phase.replace(ProtoPhase::SyncingState);
                 SynchronizerStatus::RunCst
             }
+            // we need to fetch the decisions we are missing before proceeding
+            FinalizeStatus::RunLogTransfer(state, first, last) => {
+                $self.finalize_state.replace(Some(state));
+                $self.phase.replace(ProtoPhase::SyncingState);
+                SynchronizerStatus::RunLogTransfer { first, last }
+            }
             // we may finish the view change proto
             FinalizeStatus::Commit(state) => {
                 $self.finalize(state, $log, $timeouts, $consensus, $node)
@@ -125,14 +145,23 @@ pub struct LeaderCollects<O> {
     // The collect messages the leader has received.
     #[get = "pub"]
     collects: Vec<StoredMessage<PBFTMessage<O>>>,
+    // The vote weights of the new view, which must match the ones every
+    // correct replica has computed for it
+    #[get = "pub"]
+    weights: Option<VoteWeights>,
 }
 
 impl<O> LeaderCollects<O> {
     pub fn new(
         proposed: FwdConsensusMessage<O>,
         collects: Vec<StoredMessage<PBFTMessage<O>>>,
+        weights: Option<VoteWeights>,
     ) -> Self {
-        Self { proposed, collects }
+        Self {
+            proposed,
+            collects,
+            weights,
+        }
     }
 
     pub fn message(&self) -> &FwdConsensusMessage<O> {
@@ -155,6 +184,7 @@ pub(super) struct FinalizeState<O> {
 pub(super) enum FinalizeStatus<O> {
     NoValue,
     RunCst(FinalizeState<O>),
+    RunLogTransfer(FinalizeState<O>, SeqNo, SeqNo),
     Commit(FinalizeState<O>),
 }
 
@@ -193,23 +223,26 @@ pub struct TboQueue<O> {
     // fetching them from the network
     get_queue: bool,
     // stores all STOP messages for the next view
-    stop: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
+    stop: BoundedTboQueue<PBFTMessage<O>>,
     // stores all STOP-DATA messages for the next view
-    stop_data: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
+    stop_data: BoundedTboQueue<PBFTMessage<O>>,
     // stores all SYNC messages for the next view
-    sync: VecDeque<VecDeque<ShareableMessage<PBFTMessage<O>>>>,
+    sync: BoundedTboQueue<PBFTMessage<O>>,
 }
 
 impl<O> TboQueue<O> {
     pub(crate) fn new(view: ViewInfo) -> Self {
+        // Correct replicas send a single message of each kind per view
+        let bounds = QueueBounds::for_views(1);
+
         Self {
             view,
             next_view: None,
             previous_view: None,
             get_queue: false,
-            stop: VecDeque::new(),
-            stop_data: VecDeque::new(),
-            sync: VecDeque::new(),
+            stop: BoundedTboQueue::new(bounds),
+            stop_data: BoundedTboQueue::new(bounds),
+            sync: BoundedTboQueue::new(bounds),
         }
     }
 
@@ -270,15 +303,20 @@ impl<O> TboQueue<O> {
     }
 
     fn next_instance_queue(&mut self) {
-        tbo_advance_message_queue(&mut self.stop);
-        tbo_advance_message_queue(&mut self.stop_data);
-        tbo_advance_message_queue(&mut self.sync);
+        self.stop.advance();
+        self.stop_data.advance();
+        self.sync.advance();
     }
 
     /// Queues a view change message for later processing, or drops it
     /// immediately if it pertains to an older view change instance.
     pub fn queue(&mut self, m: ShareableMessage<PBFTMessage<O>>) {
-        match m.message().view_change().kind() {
+        // Messages of other kinds are filtered out before reaching the queues
+        let Ok(view_change) = m.message().view_change() else {
+            return;
+        };
+
+        match view_change.kind() {
             ViewChangeMessageKind::Stop(_) | ViewChangeMessageKind::StopQuorumJoin(_) => {
                 self.queue_stop(m)
             }
@@ -290,28 +328,19 @@ impl<O> TboQueue<O> {
     /// Verifies if we have new `STOP` messages to be processed for
     /// the current view.
     pub fn can_process_stops(&self) -> bool {
-        self.stop
-            .front()
-            .map(|deque| !deque.is_empty())
-            .unwrap_or(false)
+        self.stop.has_front_messages()
     }
 
     /// Verifies if we have new `STOP` messages to be processed for
     /// the current view.
     pub fn can_process_stop_data(&self) -> bool {
-        self.stop_data
-            .front()
-            .map(|deque| !deque.is_empty())
-            .unwrap_or(false)
+        self.stop_data.has_front_messages()
     }
 
     /// Verifies if we have new `STOP` messages to be processed for
     /// the current view.
     pub fn can_process_sync(&self) -> bool {
-        self.sync
-            .front()
-            .map(|deque| !deque.is_empty())
-            .unwrap_or(false)
+        self.sync.has_front_messages()
     }
 
     /// Queues a `STOP` message for later processing, or drops it
@@ -320,21 +349,21 @@ impl<O> TboQueue<O> {
         // NOTE: we use next() because we want to retrieve messages
         // for v+1, as we haven't started installing the new view yet
         let seq = self.view.sequence_number().next();
-        tbo_queue_message_arc(seq, &mut self.stop, (m.sequence_number(), m))
+        self.stop.queue_seq(seq, m);
     }
 
     /// Queues a `STOP-DATA` message for later processing, or drops it
     /// immediately if it pertains to an older view change instance.
     fn queue_stop_data(&mut self, m: ShareableMessage<PBFTMessage<O>>) {
         let seq = self.view.sequence_number().next();
-        tbo_queue_message_arc(seq, &mut self.stop_data, (m.sequence_number(), m))
+        self.stop_data.queue_seq(seq, m);
     }
 
     /// Queues a `SYNC` message for later processing, or drops it
     /// immediately if it pertains to an older view change instance.
     fn queue_sync(&mut self, m: ShareableMessage<PBFTMessage<O>>) {
         let seq = self.view.sequence_number().next();
-        tbo_queue_message_arc(seq, &mut self.sync, (m.sequence_number(), m))
+        self.sync.queue_seq(seq, m);
     }
 
     pub fn view(&self) -> &ViewInfo {
@@ -374,7 +403,7 @@ pub(super) enum ProtoPhase {
     Syncing,
     // we are running the SYNC phase of Mod-SMaRt,
     // but are paused while waiting for the state
-    // transfer protocol to finish
+    // transfer (or log transfer) protocol to finish
     SyncingState,
 }
 
@@ -390,9 +419,16 @@ pub enum SynchronizerStatus<O> {
     /// The view change protocol just finished running and we
     /// have successfully joined the quorum.
     NewViewJoinedQuorum(ConsensusStatus<O>, Option<OPDecision<O>>, NodeId),
+    /// The leader of the new view caught us up to its last decision, but then proposed
+    /// something we could not process, so we gave up on its view.
+    /// The decision was already installed, so it must still be executed.
+    ViewAbandoned(OPDecision<O>),
     /// Before we finish the view change protocol, we need
     /// to run the CST protocol.
     RunCst,
+    /// Before we finish the view change protocol, we need to
+    /// fetch the decisions from `first` up to `last` with the log transfer.
+    RunLogTransfer { first: SeqNo, last: SeqNo },
     /// The following set of client requests timed out.
     ///
     /// We need to invoke the leader change protocol if
@@ -476,6 +512,14 @@ pub struct Synchronizer<RQ: SerType> {
     finalize_state: RefCell<Option<FinalizeState<RQ>>>,
     // We need to keep track of whether we are entering the quorum
     entering_quorum: Cell<bool>,
+    // A vote weight assignment waiting to be installed in the next view
+    pending_weights: RefCell<Option<VoteWeights>>,
+    // The pairwise keys used to check MAC authenticated votes in proofs
+    mac_keys: RefCell<Option<Arc<dyn MacKeys>>>,
+    // The trusted counter used to certify our view change messages, when the fault model requires one
+    usig: RefCell<Option<Arc<dyn Usig>>>,
+    // Watches the leader for signs of life while the system is idle, when heartbeats are enabled
+    leader_liveness: RefCell<Option<LeaderLiveness>>,
     // Replica accessory
     accessory: SynchronizerAccessory<RQ>,
 }
@@ -554,6 +598,19 @@ where
     }
 
     fn queue(&self, message: ShareableMessage<PBFTMessage<RQ>>) {
+        if let Err(err) = message.message().view_change() {
+            warn!(
+                "{:?} // Dropping message from {:?} as it cannot be queued in the synchronizer: {:?}",
+                self.node_id,
+                message.header().from(),
+                err
+            );
+
+            metric_increment(MALFORMED_MESSAGES_DROPPED_ID, Some(1));
+
+            return;
+        }
+
         self.tbo.lock().unwrap().queue(message)
     }
 }
@@ -573,6 +630,10 @@ where
             tbo: Mutex::new(TboQueue::new(view)),
             finalize_state: RefCell::new(None),
             entering_quorum: Cell::new(false),
+            pending_weights: RefCell::new(None),
+            mac_keys: RefCell::new(None),
+            usig: RefCell::new(None),
+            leader_liveness: RefCell::new(None),
             accessory: SynchronizerAccessory::Follower(FollowerSynchronizer::new()),
         })
     }
@@ -588,6 +649,10 @@ where
             tbo: Mutex::new(TboQueue::new(view)),
             finalize_state: RefCell::new(None),
             entering_quorum: Cell::new(false),
+            pending_weights: RefCell::new(None),
+            mac_keys: RefCell::new(None),
+            usig: RefCell::new(None),
+            leader_liveness: RefCell::new(None),
             accessory: SynchronizerAccessory::Replica(ReplicaSynchronizer::new(timeout_dur)),
         })
     }
@@ -598,12 +663,10 @@ where
         seq_no: SeqNo,
         quorum_members: Vec<NodeId>,
         timeout_dur: Duration,
+        fault_model: FaultModel,
     ) -> Result<Arc<Self>> {
-        let n = quorum_members.len();
-
-        let _f = (n - 1) / 3;
-
-        let view_info = ViewInfo::from_quorum(seq_no, quorum_members)?;
+        let view_info =
+            ViewInfo::from_quorum_with_fault_model(seq_no, quorum_members, fault_model)?;
 
         info!("Initializing synchronizer with view {:?}", view_info);
 
@@ -617,6 +680,10 @@ where
             collects: Mutex::new(Default::default()),
             finalize_state: RefCell::new(None),
             entering_quorum: Cell::new(false),
+            pending_weights: RefCell::new(None),
+            mac_keys: RefCell::new(None),
+            usig: RefCell::new(None),
+            leader_liveness: RefCell::new(None),
             accessory: SynchronizerAccessory::Replica(ReplicaSynchronizer::new(timeout_dur)),
         }))
     }
@@ -680,8 +747,8 @@ where
                 );
 
                 if let SynchronizerPollStatus::NextMessage(message) = &result {
-                    match message.message().view_change().kind() {
-                        ViewChangeMessageKind::StopQuorumJoin(_) => {
+                    match message.message().view_change().map(ViewChangeMessage::kind) {
+                        Ok(ViewChangeMessageKind::StopQuorumJoin(_)) => {
                             self.phase.replace(ProtoPhase::ViewStopping(0));
                         }
                         _ => {
@@ -732,18 +799,32 @@ where
     where
         NT: OrderProtocolSendNode<RQ, PBFT<RQ>> + 'static,
     {
+        let message = match s_message.message().view_change() {
+            Ok(view_change) => view_change,
+            Err(err) => {
+                warn!(
+                    "{:?} // Dropping message from {:?} as it is not a view change message: {:?}",
+                    node.id(),
+                    s_message.header().from(),
+                    err
+                );
+
+                metric_increment(MALFORMED_MESSAGES_DROPPED_ID, Some(1));
+
+                return SynchronizerStatus::Nil;
+            }
+        };
+
         debug!(
             "{:?} // Processing view change message {:?} in phase {:?} from {:?}",
             node.id(),
-            s_message.message().view_change(),
+            message,
             self.phase.get(),
             s_message.header().from()
         );
 
         match self.phase.get() {
             ProtoPhase::Init => {
-                let (_header, message) = (s_message.header(), s_message.message().view_change());
-
                 return match message.kind() {
                     ViewChangeMessageKind::Stop(_) | ViewChangeMessageKind::StopQuorumJoin(_) => {
                         let mut guard = self.tbo.lock().unwrap();
@@ -789,7 +870,7 @@ where
                 };
             }
             ProtoPhase::Stopping(i) | ProtoPhase::Stopping2(i) => {
-                let (header, message) = (s_message.header(), s_message.message().view_change());
+                let header = s_message.header();
 
                 let msg_seq = message.sequence_number();
                 let current_view = self.view();
@@ -874,8 +955,10 @@ where
 
                 // NOTE: we only take this branch of the code before
                 // we have sent our own STOP message
+                let stopping_voters = self.stopping_voters();
+
                 if let ProtoPhase::Stopping(_i) = self.phase.get() {
-                    return if i > current_view.params().f() {
+                    return if current_view.exceeds_fault_weight(&stopping_voters) {
                         self.begin_view_change(None, &**node, timeouts, log);
 
                         SynchronizerStatus::Running
@@ -886,8 +969,21 @@ where
                     };
                 }
 
-                if i >= current_view.params().quorum() {
-                    let next_view = current_view.next_view();
+                if current_view.is_quorum(&stopping_voters) {
+                    let next_view = match self.take_next_view(&current_view) {
+                        Ok(next_view) => next_view,
+                        Err(err) => {
+                            error!(
+                                "{:?} // Failed to install the vote weight reassignment in the next view, failing the view change {:?}",
+                                node.id(),
+                                err
+                            );
+
+                            self.phase.replace(ProtoPhase::Stopping2(i));
+
+                            return SynchronizerStatus::Running;
+                        }
+                    };
 
                     let previous_view = current_view.clone();
 
@@ -935,7 +1031,7 @@ where
                 SynchronizerStatus::Running
             }
             ProtoPhase::ViewStopping(received) | ProtoPhase::ViewStopping2(received) => {
-                let (header, message) = (s_message.header(), s_message.message().view_change());
+                let header = s_message.header();
 
                 let msg_seq = message.sequence_number();
                 let current_view = self.view();
@@ -1021,23 +1117,38 @@ where
                 //TODO: Is this the correct procedure?
                 self.phase.replace(ProtoPhase::ViewStopping(received));
 
-                if received >= current_view.params().quorum() {
+                if received >= current_view.quorum() {
                     let mut votes: Vec<_> = self
                         .currently_adding
                         .borrow()
                         .iter()
-                        .map(|(node, voters)| (*node, voters.len()))
+                        .map(|(node, voters)| (*node, current_view.weight_of_votes(voters)))
                         .collect();
 
                     votes.sort_by(|(_node, votes), (_node_2, votes_2)| votes_2.cmp(votes));
 
                     if let Some(vote_count) = votes.first() {
-                        if vote_count.1 >= current_view.params().quorum() {
+                        if vote_count.1 >= current_view.quorum_weight() {
                             self.currently_adding_node.replace(Some(node_id));
 
                             let node_to_add = vote_count.0;
 
-                            let next_view = current_view.next_view_with_new_node(node_to_add);
+                            let next_view = match self
+                                .take_next_view_with_new_node(&current_view, node_to_add)
+                            {
+                                Ok(next_view) => next_view,
+                                Err(err) => {
+                                    error!("{:?} // Failed to assign the vote weights of the view with node {:?}, abandoning the quorum change {:?}",
+                                        node.id(), node_to_add, err);
+
+                                    // Every correct replica has queued the same weights,
+                                    // so they all give up on this quorum change here
+                                    self.currently_adding.borrow_mut().clear();
+                                    self.phase.replace(ProtoPhase::Init);
+
+                                    return SynchronizerStatus::Nil;
+                                }
+                            };
 
                             let previous_view = current_view.clone();
 
@@ -1072,10 +1183,15 @@ where
                                 self.phase.replace(ProtoPhase::Syncing);
                             }
                         } else if received >= current_view.params().n() {
-                            error!("We have received view stopping messages from all nodes in the network and yet we don't have quorum {} votes for any node. {:?}",
-                                   current_view.params().quorum(), votes);
+                            error!("{:?} // We have received view stopping messages from all nodes in the network and yet we don't have quorum {} votes for any node. {:?}. Abandoning the quorum change",
+                                   node.id(), current_view.quorum_weight(), votes);
 
-                            todo!("")
+                            // Every replica has voted, so no node can be added with these votes.
+                            // Every correct replica sees the same votes, so they all give up here
+                            self.currently_adding.borrow_mut().clear();
+                            self.phase.replace(ProtoPhase::Init);
+
+                            return SynchronizerStatus::Nil;
                         } else {
                             warn!("{:?} // Stopping quorum reached, but not enough votes to add node {:?}. ", node.id(), vote_count.0);
                         }
@@ -1087,7 +1203,7 @@ where
                 SynchronizerStatus::Running
             }
             ProtoPhase::StoppingData(i) => {
-                let (header, message) = (s_message.header(), s_message.message().view_change());
+                let header = s_message.header();
 
                 match &self.accessory {
                     SynchronizerAccessory::Follower(_) => {
@@ -1099,9 +1215,13 @@ where
                         //Obtain the view seq no of the message
                         let msg_seq = message.sequence_number();
 
-                        let next_view = self
-                            .next_view()
-                            .expect("We can't be here without having installed the next view?");
+                        let Some(next_view) = self.next_view() else {
+                            warn!("{:?} // Received {:?} while collecting stop data, but we have not installed the next view. Ignoring",
+                                node.id(), message);
+
+                            return SynchronizerStatus::Running;
+                        };
+
                         let seq = next_view.sequence_number();
 
                         // reject STOP-DATA messages if we are not the leader
@@ -1189,7 +1309,12 @@ where
 
                         collects_guard.insert(header.from().into(), unwrapped_msg);
 
-                        if i < next_view.params().quorum() {
+                        let stop_data_voters: Vec<NodeId> = collects_guard
+                            .values()
+                            .map(|stored| stored.header().from())
+                            .collect();
+
+                        if !next_view.is_view_change_quorum(&stop_data_voters) {
                             self.phase.replace(ProtoPhase::StoppingData(i));
 
                             SynchronizerStatus::Running
@@ -1206,8 +1331,14 @@ where
                             // then we should also use the previous view to verify the validity of them
                             let previous_view_ref = &current_view;
 
-                            let proof =
-                                Self::highest_proof(&*collects_guard, previous_view_ref, &**node);
+                            let mac_keys = self.mac_keys.borrow();
+
+                            let proof = Self::highest_proof(
+                                &*collects_guard,
+                                previous_view_ref,
+                                &**node,
+                                mac_keys.as_deref(),
+                            );
 
                             info!("{:?} // Highest proof: {:?}", node.id(), proof);
 
@@ -1219,10 +1350,10 @@ where
                             //Here we use the normalized_collects method, which uses data from self.collects
                             //Which is protected by a mutex. Therefore, we must carry the consensus guard along
                             //While we access the normalized collects to prevent any errors.
-                            let normalized_collects: Vec<Option<&CollectData<RQ>>> =
+                            let normalized_collects: Vec<(NodeId, Option<&CollectData<RQ>>)> =
                                 Self::normalized_collects(&*collects_guard, curr_cid).collect();
 
-                            let sound = sound(&next_view, &normalized_collects);
+                            let sound = attested_sound(&next_view, consensus, &normalized_collects);
 
                             if !sound.test() {
                                 //FIXME: BFT-SMaRt doesn't do anything if `sound`
@@ -1284,13 +1415,14 @@ where
 
                             let collects = collects_guard.values().cloned().collect();
 
-                            let message = PBFTMessage::ViewChange(ViewChangeMessage::new(
+                            let message = ViewChangeMessage::new(
                                 next_view.sequence_number(),
                                 ViewChangeMessageKind::Sync(LeaderCollects {
                                     proposed: fwd_request.clone(),
                                     collects,
+                                    weights: next_view.weights().cloned(),
                                 }),
-                            ));
+                            );
 
                             let our_id = node.id();
 
@@ -1300,7 +1432,10 @@ where
                                 .into_iter()
                                 .filter(move |&id| id != our_id);
 
-                            let _ = node.broadcast_signed(message, targets);
+                            if let Some(message) = self.certify(&next_view, message) {
+                                let _ = node
+                                    .broadcast_signed(PBFTMessage::ViewChange(message), targets);
+                            }
 
                             let state = FinalizeState {
                                 curr_cid,
@@ -1325,13 +1460,18 @@ where
             }
             ProtoPhase::Syncing => {
                 let msg_seq = s_message.sequence_number();
-                let next_view = self
-                    .next_view()
-                    .expect("We should have a next view in this situation");
+
+                let Some(next_view) = self.next_view() else {
+                    warn!("{:?} // Received {:?} while syncing, but we have not installed the next view. Ignoring",
+                        node.id(), message);
+
+                    return SynchronizerStatus::Running;
+                };
+
                 let seq = next_view.sequence_number();
 
                 // reject SYNC messages if these were not sent by the leader
-                let (proposed, collects) = match s_message.message().view_change().kind() {
+                let (proposed, collects) = match message.kind() {
                     ViewChangeMessageKind::Stop(_) | ViewChangeMessageKind::StopQuorumJoin(_) => {
                         {
                             let mut guard = self.tbo.lock().unwrap();
@@ -1371,7 +1511,7 @@ where
                     ViewChangeMessageKind::Sync(_) if msg_seq != seq => {
                         {
                             debug!("{:?} // Received sync message whose sequence number does not match our current one {:?} vs {:?}. Queueing", node.id(),
-                                s_message.message().view_change(), next_view);
+                                message, next_view);
 
                             let mut guard = self.tbo.lock().unwrap();
 
@@ -1391,19 +1531,69 @@ where
 
                         let (_header, message) = stored_message.into_inner();
 
-                        message
+                        let Some(collects) = message
                             .into_view_change()
-                            .take_collects()
-                            .unwrap()
-                            .into_inner()
+                            .ok()
+                            .and_then(ViewChangeMessage::take_collects)
+                        else {
+                            return SynchronizerStatus::Running;
+                        };
+
+                        if collects.weights().as_ref() != next_view.weights() {
+                            warn!(
+                                "{:?} // The leader {:?} proposed the vote weights {:?} for view {:?}, which do not match ours {:?}",
+                                node.id(),
+                                next_view.leader(),
+                                collects.weights(),
+                                seq,
+                                next_view.weights()
+                            );
+
+                            return self.abandon_next_view(&**node, timeouts, log);
+                        }
+
+                        let (proposed, collects) = collects.into_inner();
+
+                        if !matches!(proposed.consensus().kind(), ConsensusMessageKind::PrePrepare(_)) {
+                            warn!(
+                                "{:?} // The leader {:?} proposed something other than a pre prepare in its sync message, ignoring",
+                                node.id(),
+                                next_view.leader()
+                            );
+
+                            metric_increment(MALFORMED_MESSAGES_DROPPED_ID, Some(1));
+
+                            return SynchronizerStatus::Running;
+                        }
+
+                        (proposed, collects)
                     }
                 };
 
                 // leader has already performed this computation in the
                 // STOP-DATA phase of Mod-SMaRt
-                let signed: Vec<_> = signed_collects::<RQ, _>(&**node, collects);
+                let mut signed: Vec<_> = signed_collects::<RQ, _>(&**node, collects);
 
-                let proof = highest_proof::<RQ, _, _>(&next_view, &**node, signed.iter());
+                if next_view.fault_model().requires_usig() {
+                    signed.retain(|collect| match consensus.verify_collect(collect) {
+                        Ok(()) => true,
+                        Err(err) => {
+                            warn!("{:?} // Ignoring the collect of {:?} forwarded by the leader {:?}: {:?}",
+                                node.id(), collect.header().from(), next_view.leader(), err);
+
+                            false
+                        }
+                    });
+                }
+
+                let mac_keys = self.mac_keys.borrow();
+
+                let proof = highest_proof::<RQ, _, _>(
+                    &next_view,
+                    &**node,
+                    mac_keys.as_deref(),
+                    signed.iter(),
+                );
 
                 let curr_cid = proof
                     .map(|p| p.sequence_number())
@@ -1413,7 +1603,7 @@ where
                 let normalized_collects: Vec<_> =
                     { normalized_collects(curr_cid, collect_data(signed.iter())).collect() };
 
-                let sound = sound(&next_view, &normalized_collects);
+                let sound = attested_sound(&next_view, consensus, &normalized_collects);
 
                 if !sound.test() {
                     error!(
@@ -1577,7 +1767,18 @@ where
         // so the view change to integrate us into the quorum might be delayed
 
         // Simulate that we were accepted into the quorum
-        let view = current_view.next_view_with_new_node(node.id());
+        let view = match self.take_next_view_with_new_node(&current_view, node.id()) {
+            Ok(view) => view,
+            Err(err) => {
+                error!(
+                    "{:?} // Cannot join the weighted quorum without its new vote weights {:?}",
+                    node.id(),
+                    err
+                );
+
+                return ReconfigurationAttemptResult::Failed;
+            }
+        };
 
         self.entering_quorum.replace(true);
         self.currently_adding_node.replace(Some(self.node_id));
@@ -1733,7 +1934,7 @@ where
         &self,
         state: FinalizeState<RQ>,
         proof: Option<&Proof<RQ>>,
-        _normalized_collects: Vec<Option<&CollectData<RQ>>>,
+        _normalized_collects: Vec<(NodeId, Option<&CollectData<RQ>>)>,
         log: &Log<RQ>,
     ) -> FinalizeStatus<RQ> {
         let last_executed_cid = proof
@@ -1742,18 +1943,31 @@ where
             .unwrap_or(SeqNo::ZERO);
 
         //If we are more than one operation behind the most recent consensus id,
-        //Then we must run a consensus state transfer
+        //Then we must catch up before finishing the view change. The last decision
+        //is installed from the proof in the collects, so we fetch the ones before it
         if u32::from(log.decision_log().last_execution().unwrap_or(SeqNo::ZERO)) + 1
             < u32::from(last_executed_cid)
         {
-            return FinalizeStatus::RunCst(state);
+            let first = log
+                .decision_log()
+                .last_execution()
+                .map(|seq| seq.next())
+                .unwrap_or(SeqNo::ZERO);
+
+            let last = SeqNo::from(u32::from(last_executed_cid) - 1);
+
+            // Beyond the decisions the other replicas retain,
+            // only the state transfer can bring us up to date
+            return if within_retained(first, last, log.decision_log()) {
+                FinalizeStatus::RunLogTransfer(state, first, last)
+            } else {
+                FinalizeStatus::RunCst(state)
+            };
         }
 
-        let rqs = match state.proposed.consensus().kind() {
-            ConsensusMessageKind::PrePrepare(rqs) => rqs,
-            _ => {
-                panic!("Can only have pre prepare messages");
-            }
+        // Proposals other than pre prepares are rejected when the sync message is received
+        let ConsensusMessageKind::PrePrepare(rqs) = state.proposed.consensus().kind() else {
+            return FinalizeStatus::NoValue;
         };
 
         if rqs.is_empty() && !state.sound.test() {
@@ -1815,11 +2029,19 @@ where
             // sent by the leader in the SYNC message
 
             if let Some(last_proof) = last_proof {
-                let quorum_result = consensus
-                    .catch_up_to_quorum(&view, last_proof, log)
-                    .expect("Failed to catch up to quorum");
+                match consensus.catch_up_to_quorum(&view, last_proof, log) {
+                    Ok(quorum_result) => Some(quorum_result),
+                    Err(err) => {
+                        error!(
+                            "{:?} // Failed to catch up to the decision synced by the leader of view {:?}: {:?}",
+                            node.id(),
+                            view.sequence_number(),
+                            err
+                        );
 
-                Some(quorum_result)
+                        return self.abandon_view(&**node, timeouts, log);
+                    }
+                }
             } else {
                 // This maybe happens when a checkpoint is done and the first execution after it
                 // fails, leading to a view change? Don't really know how this would be possible
@@ -1831,9 +2053,31 @@ where
         };
 
         // finalize view change by broadcasting a PREPARE msg
-        let consensus_result = consensus
-            .finalize_view_change((header, message), &view, self, timeouts, log, node)
-            .expect("Failed to finalize view change in consensus");
+        let consensus_result = match consensus.finalize_view_change(
+            (header, message),
+            &view,
+            self,
+            timeouts,
+            log,
+            node,
+        ) {
+            Ok(consensus_result) => consensus_result,
+            Err(err) => {
+                error!(
+                    "{:?} // Failed to process the proposal of the leader of view {:?}: {:?}",
+                    node.id(),
+                    view.sequence_number(),
+                    err
+                );
+
+                let status = self.abandon_view(&**node, timeouts, log);
+
+                return match to_execute {
+                    Some(decision) => SynchronizerStatus::ViewAbandoned(decision),
+                    None => status,
+                };
+            }
+        };
 
         // Update proto phase
         self.phase.replace(ProtoPhase::Init);
@@ -1856,14 +2100,18 @@ where
     pub fn request_batch_received(
         &self,
         header: &Header,
-        pre_prepare: &ConsensusMessage<RQ>,
+        requests: &[StoredMessage<RQ>],
         timeouts: &TimeoutModHandle,
     ) -> Vec<ClientRqInfo> {
+        if let Some(liveness) = &*self.leader_liveness.borrow() {
+            liveness.leader_heard();
+        }
+
         match &self.accessory {
             SynchronizerAccessory::Replica(rep) => {
-                rep.received_request_batch(header, pre_prepare, timeouts)
+                rep.received_request_batch(header, requests, timeouts)
             }
-            SynchronizerAccessory::Follower(fol) => fol.watch_request_batch(pre_prepare),
+            SynchronizerAccessory::Follower(fol) => fol.watch_request_batch(requests),
         }
     }
 
@@ -1914,7 +2162,7 @@ where
     fn normalized_collects(
         collects: &IntMap<StoredMessage<PBFTMessage<RQ>>>,
         in_exec: SeqNo,
-    ) -> impl Iterator<Item = Option<&'_ CollectData<RQ>>> {
+    ) -> impl Iterator<Item = (NodeId, Option<&'_ CollectData<RQ>>)> {
         let values = collects.values();
 
         let collects = normalized_collects(in_exec, collect_data(values));
@@ -1922,17 +2170,272 @@ where
         collects
     }
 
+    /// The nodes from which we have received a STOP message in the current view change
+    fn stopping_voters(&self) -> Vec<NodeId> {
+        self.stopped
+            .borrow()
+            .keys()
+            .map(|node| NodeId::from(*node as u32))
+            .collect()
+    }
+
+    /// Queue a new vote weight assignment to be installed in the next view change.
+    ///
+    /// The assignment must be the same in all correct replicas, so it should only
+    /// be provided by an agreed upon source (for example, the reconfiguration protocol
+    /// or an operation ordered by this protocol). The leader of the next view sends
+    /// it in its `SYNC` message, and the replicas which computed other weights
+    /// refuse to install that view.
+    ///
+    /// Assignments which do not cover every member of the quorum are rejected.
+    pub fn queue_weight_reassignment(&self, weights: VoteWeights) -> Result<()> {
+        self.view().next_view_with_weights(weights.clone())?;
+
+        info!(
+            "{:?} // Queueing vote weight reassignment {:?} for the next view",
+            self.node_id, weights
+        );
+
+        self.pending_weights.replace(Some(weights));
+
+        Ok(())
+    }
+
+    /// Provide the pairwise keys used by the replicas to authenticate their votes,
+    /// so we can check the MAC authenticated votes of the proofs we collect
+    pub fn set_mac_keys(&self, mac_keys: Arc<dyn MacKeys>) {
+        self.mac_keys.replace(Some(mac_keys));
+    }
+
+    /// Provide the trusted counter used to certify the view change messages we send
+    pub fn set_usig(&self, usig: Arc<dyn Usig>) {
+        self.usig.replace(Some(usig));
+    }
+
+    /// Attach a unique identifier to a view change message we are about to send,
+    /// when the fault model of the view requires one.
+    ///
+    /// Returns `None` if the message must not be sent, as the counter failed to certify it
+    pub(super) fn certify(
+        &self,
+        view: &ViewInfo,
+        message: ViewChangeMessage<RQ>,
+    ) -> Option<ViewChangeMessage<RQ>> {
+        if !view.fault_model().requires_usig() {
+            return Some(message);
+        }
+
+        let usig = self.usig.borrow();
+
+        let Some(usig) = usig.as_deref() else {
+            error!(
+                "{:?} // Cannot send {:?} without a trusted counter",
+                self.node_id, message
+            );
+
+            return None;
+        };
+
+        match attach_view_change_ui(usig, message) {
+            Ok(message) => Some(message),
+            Err(err) => {
+                error!(
+                    "{:?} // Failed to certify view change message: {:?}",
+                    self.node_id, err
+                );
+
+                None
+            }
+        }
+    }
+
+    /// Check that a proof received from another replica carries a quorum
+    /// of valid votes in the current view
+    pub fn proof_valid<NT>(&self, node: &NT, proof: &Proof<RQ>) -> bool
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        let mac_keys = self.mac_keys.borrow();
+
+        proof_valid(&self.view(), node, mac_keys.as_deref(), proof)
+    }
+
+    /// Remove the given leaders from the leader set of the current view, without
+    /// running a view change. This must only be done on agreed upon information,
+    /// so every correct replica removes the same leaders at the same point.
+    ///
+    /// Returns the updated view, or `None` if no leader would be left
+    pub fn remove_leaders(&self, leaders: &[NodeId]) -> Option<ViewInfo> {
+        let mut tbo = self.tbo.lock().unwrap();
+
+        let view = tbo.view().without_leaders(leaders)?;
+
+        info!(
+            "{:?} // Removing leaders {:?} from view {:?}, remaining leaders {:?}",
+            self.node_id,
+            leaders,
+            view.sequence_number(),
+            view.leader_set()
+        );
+
+        tbo.view = view.clone();
+
+        Some(view)
+    }
+
+    /// Start a view change when we don't hear from the leader for the given timeout
+    pub fn enable_leader_liveness(&self, timeout: Duration) {
+        self.leader_liveness
+            .replace(Some(LeaderLiveness::new(self.node_id, timeout)));
+    }
+
+    /// Start watching the leader of the current view, if the liveness timeout is enabled
+    pub fn arm_leader_liveness(&self, timeouts: &TimeoutModHandle) {
+        if let SynchronizerAccessory::Follower(_) = &self.accessory {
+            return;
+        }
+
+        if let Some(liveness) = &*self.leader_liveness.borrow() {
+            liveness.arm(timeouts);
+        }
+    }
+
+    /// Check whether the leader liveness timeout has expired.
+    ///
+    /// Only applies while no view change is running, as the view change
+    /// protocol handles its own timeouts.
+    pub fn leader_liveness_timed_out(
+        &self,
+        timeouts: &TimeoutModHandle,
+        timed_out: &[ModTimeout],
+    ) -> bool {
+        if !matches!(self.phase.get(), ProtoPhase::Init) {
+            return false;
+        }
+
+        let Some(liveness) = &*self.leader_liveness.borrow() else {
+            return false;
+        };
+
+        let view = self.view();
+
+        if view.leader_set().contains(&self.node_id) {
+            // We are a leader ourselves, so we are the ones sending the heartbeats
+            return false;
+        }
+
+        let expired = liveness.timed_out(timeouts, timed_out);
+
+        if expired {
+            warn!(
+                "{:?} // Have not heard from the leader {:?} of view {:?} for too long",
+                self.node_id,
+                view.leader(),
+                view.sequence_number()
+            );
+        }
+
+        expired
+    }
+
+    /// Compute the view that follows the given one, applying any pending
+    /// vote weight reassignment.
+    ///
+    /// Fails when the pending assignment can't be installed, as installing the
+    /// next view with any other weights would diverge from the correct replicas
+    /// which did install it. The assignment stays queued.
+    fn take_next_view(&self, current_view: &ViewInfo) -> Result<ViewInfo> {
+        let Some(weights) = self.pending_weights.take() else {
+            return Ok(current_view.next_view());
+        };
+
+        match current_view.next_view_with_weights(weights.clone()) {
+            Ok(view) => Ok(view),
+            Err(err) => {
+                self.pending_weights.replace(Some(weights));
+
+                Err(err)
+            }
+        }
+    }
+
+    /// Compute the view that follows the given one with a new node joining the quorum.
+    ///
+    /// When the view is weighted, the pending vote weight reassignment
+    /// must cover the joining node.
+    fn take_next_view_with_new_node(
+        &self,
+        current_view: &ViewInfo,
+        joined_node: NodeId,
+    ) -> Result<ViewInfo> {
+        let weights = self.pending_weights.take();
+
+        match current_view.next_view_with_new_node(joined_node, weights.clone()) {
+            Ok(view) => Ok(view),
+            Err(err) => {
+                self.pending_weights.replace(weights);
+
+                Err(err)
+            }
+        }
+    }
+
+    /// The leader of the view we are moving to misbehaved while synchronizing us,
+    /// so we give up on its view and stop again, moving on to the view after it
+    fn abandon_next_view<NT>(
+        &self,
+        node: &NT,
+        timeouts: &TimeoutModHandle,
+        log: &Log<RQ>,
+    ) -> SynchronizerStatus<RQ>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        // Give up on the view as if we had installed it, so the STOP we
+        // broadcast is for the view after it
+        if !self.advance_view() {
+            return SynchronizerStatus::Running;
+        }
+
+        self.abandon_view(node, timeouts, log)
+    }
+
+    /// The leader of the view we have just installed is faulty,
+    /// so we stop again, moving on to the view after it
+    fn abandon_view<NT>(
+        &self,
+        node: &NT,
+        timeouts: &TimeoutModHandle,
+        log: &Log<RQ>,
+    ) -> SynchronizerStatus<RQ>
+    where
+        NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+    {
+        warn!(
+            "{:?} // The leader of view {:?} is faulty, moving on to the next view",
+            node.id(),
+            self.view().sequence_number()
+        );
+
+        self.phase.replace(ProtoPhase::Init);
+
+        self.begin_view_change(Some(Vec::new()), node, timeouts, log);
+
+        SynchronizerStatus::Running
+    }
+
     // TODO: quorum sizes may differ when we implement reconfiguration
     #[inline]
     fn highest_proof<'a, NT>(
         guard: &'a IntMap<StoredMessage<PBFTMessage<RQ>>>,
         view: &ViewInfo,
         node: &NT,
+        mac_keys: Option<&dyn MacKeys>,
     ) -> Option<&'a Proof<RQ>>
     where
         NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
     {
-        highest_proof::<RQ, _, _>(view, node, guard.values())
+        highest_proof::<RQ, _, _>(view, node, mac_keys, guard.values())
     }
 }
 
@@ -1956,7 +2459,14 @@ pub enum SynchronizerAccessory<RQ: SerType> {
 //
 ////////////////////////////////////////////////////////////////////////////////
 
-fn sound<O>(curr_view: &ViewInfo, normalized_collects: &[Option<&CollectData<O>>]) -> Sound {
+fn sound<O>(
+    curr_view: &ViewInfo,
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
+) -> Sound {
+    if let FaultModel::Crash | FaultModel::TrustedCounter = curr_view.fault_model() {
+        return highest_written(curr_view, normalized_collects);
+    }
+
     // collect timestamps and values
     let mut seq_numbers = collections::hash_set();
     let mut values = collections::hash_set();
@@ -1966,7 +2476,7 @@ fn sound<O>(curr_view: &ViewInfo, normalized_collects: &[Option<&CollectData<O>>
         curr_view, normalized_collects
     );
 
-    for maybe_collect in normalized_collects.iter() {
+    for (_, maybe_collect) in normalized_collects.iter() {
         // NOTE: BFT-SMaRt assumes normalized values start on view 0,
         // if their CID is different from the one in execution;
         // see `LCManager::normalizeCollects` on its code
@@ -2001,9 +2511,9 @@ fn sound<O>(curr_view: &ViewInfo, normalized_collects: &[Option<&CollectData<O>>
     );
     debug!("View change sound final values: {:?}", values);
 
-    for seq_no in seq_numbers {
+    for seq_no in seq_numbers.iter() {
         for value in values.iter() {
-            if binds(curr_view, seq_no, value, normalized_collects) {
+            if binds(curr_view, *seq_no, value, normalized_collects) {
                 return Sound::Bound(*value);
             } else {
                 debug!("Failed to bind seq no {:?} and value {:?}.", seq_no, value);
@@ -2011,20 +2521,194 @@ fn sound<O>(curr_view: &ViewInfo, normalized_collects: &[Option<&CollectData<O>>
         }
     }
 
+    // A value decided on the fast path may not have a prepared certificate
+    // in any of the collects, so we have to look for it in the write sets.
+    // Since the regular binding takes precedence, this never overrides a value
+    // which might have been decided on the regular path.
+    let fast_bound = seq_numbers
+        .iter()
+        .flat_map(|seq_no| values.iter().map(move |value| (*seq_no, value)))
+        .filter_map(|(seq_no, value)| {
+            fast_path_binds(curr_view, seq_no, value, normalized_collects)
+                .map(|weight| (weight, value))
+        })
+        // Only one value can be above the threshold if it was fast path decided,
+        // otherwise any of them is safe, as long as all replicas pick the same one
+        .max_by(|(weight, value), (other_weight, other_value)| {
+            weight
+                .cmp(other_weight)
+                .then_with(|| value.as_ref().cmp(other_value.as_ref()))
+        });
+
+    if let Some((_, value)) = fast_bound {
+        return Sound::Bound(*value);
+    }
+
     Sound::Unbound(unbound(curr_view, normalized_collects))
 }
 
+/// Check the soundness of a view change with the collects we received.
+///
+/// Under the trusted counter model, a faulty replica could still leave prepares out of its
+/// collect, or make up the prepares of others. Since we have delivered every message its
+/// sender bound to its counter before the collect, we replace its write set with the
+/// prepares it sent itself, which we know about.
+fn attested_sound<RQ>(
+    curr_view: &ViewInfo,
+    consensus: &Consensus<RQ>,
+    normalized_collects: &[(NodeId, Option<&CollectData<RQ>>)],
+) -> Sound
+where
+    RQ: SerType + SessionBased + 'static,
+{
+    if !curr_view.fault_model().requires_usig() {
+        return sound(curr_view, normalized_collects);
+    }
+
+    let attested: Vec<(NodeId, Option<CollectData<RQ>>)> = normalized_collects
+        .iter()
+        .map(|(node, collect)| {
+            let attested = collect.map(|collect| {
+                let executing = collect.incomplete_proof().executing();
+
+                let write_set = PrepareSet(consensus.written_by(*node, executing));
+
+                CollectData::new(IncompleteProof::new(executing, write_set, None), None)
+            });
+
+            (*node, attested)
+        })
+        .collect();
+
+    let attested: Vec<(NodeId, Option<&CollectData<RQ>>)> = attested
+        .iter()
+        .map(|(node, collect)| (*node, collect.as_ref()))
+        .collect();
+
+    sound(curr_view, &attested)
+}
+
+/// Pick the value of a new view under the crash and trusted counter fault models, as Paxos does.
+///
+/// A value decided on a quorum of prepares is only guaranteed to be in the write set
+/// of one of the collects in any quorum of collects, not in their quorum prepares.
+/// Crashed replicas don't lie about what they wrote, replicas with a trusted counter
+/// can't (see [attested_sound]), and the leader of a view only proposes one value,
+/// so the value written at the highest view is the only one which may have been decided.
+fn highest_written<O>(
+    curr_view: &ViewInfo,
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
+) -> Sound {
+    if !curr_view.is_view_change_quorum(normalized_collects.iter().map(|(node, _)| node)) {
+        debug!(
+            "Not enough collects to pick the highest written value. Need {:?}, have {:?}.",
+            curr_view.view_change_quorum_weight(),
+            curr_view.weight_of_votes(normalized_collects.iter().map(|(node, _)| node))
+        );
+
+        return Sound::Unbound(false);
+    }
+
+    let highest = normalized_collects
+        .iter()
+        .filter_map(|(_, collect)| collect.as_ref())
+        .flat_map(|collect| {
+            collect
+                .incomplete_proof()
+                .write_set()
+                .iter()
+                .chain(collect.incomplete_proof().quorum_prepares())
+        })
+        // Ties can only come from different cids being normalized to the same
+        // view, so we break them deterministically
+        .max_by(
+            |ViewDecisionPair(ts, value), ViewDecisionPair(other_ts, other_value)| {
+                ts.cmp(other_ts)
+                    .then_with(|| value.as_ref().cmp(other_value.as_ref()))
+            },
+        );
+
+    debug!("Highest written value: {:?}.", highest);
+
+    match highest {
+        Some(ViewDecisionPair(_, value)) => Sound::Bound(*value),
+        None => Sound::Unbound(true),
+    }
+}
+
+/// Could the value have been decided on the fast path at view `ts`?
+///
+/// Fast path decisions require matching prepares from every replica, so all the
+/// correct replicas in any quorum of collects must have `value` in their write set.
+/// Returns the weight of the replicas that wrote the value, if it is above that bound.
+fn fast_path_binds<O>(
+    curr_view: &ViewInfo,
+    ts: SeqNo,
+    value: &Digest,
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
+) -> Option<u64> {
+    if !curr_view.fault_model().requires_commit_phase()
+        || !curr_view.is_quorum(normalized_collects.iter().map(|(node, _)| node))
+    {
+        return None;
+    }
+
+    let collects = normalized_collects
+        .iter()
+        .filter_map(|(node, collect)| collect.as_ref().map(|collect| (node, collect)));
+
+    // A prepared certificate for a later view supersedes this one
+    let superseded = collects.clone().any(|(_, collect)| {
+        collect
+            .incomplete_proof()
+            .quorum_prepares()
+            .is_some_and(|ViewDecisionPair(other_ts, _)| *other_ts > ts)
+    });
+
+    if superseded {
+        return None;
+    }
+
+    let writers = collects
+        .filter(|(_, collect)| {
+            collect
+                .incomplete_proof()
+                .write_set()
+                .iter()
+                .any(|ViewDecisionPair(other_ts, other_value)| {
+                    *other_ts == ts && other_value == value
+                })
+        })
+        .map(|(node, _)| node);
+
+    let weight = curr_view.weight_of_votes(writers);
+
+    debug!(
+        "Fast path: {:?} written at {:?} with weight {:?}.",
+        value, ts, weight
+    );
+
+    // If the value was decided on the fast path, every correct replica wrote it, so at most
+    // the faulty replicas in this quorum of collects can leave it out, which still leaves
+    // it with the weight of a quorum minus the fault weight. Correct replicas only write
+    // one value per view, so when a value was fast path decided, any other value can only
+    // be written by the faulty replicas and stays below this bound (as the quorum weight
+    // exceeds twice the fault weight). When two values reach the bound, neither of them
+    // was decided, and `sound` breaks the tie deterministically.
+    (weight >= curr_view.quorum_weight() - curr_view.fault_weight()).then_some(weight)
+}
+
 fn binds<O>(
     curr_view: &ViewInfo,
     ts: SeqNo,
     value: &Digest,
-    normalized_collects: &[Option<&CollectData<O>>],
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
 ) -> bool {
-    if normalized_collects.len() < curr_view.params().quorum() {
+    if !curr_view.is_view_change_quorum(normalized_collects.iter().map(|(node, _)| node)) {
         debug!(
             "Not enough collects to bind. Need {:?}, have {:?}.",
-            curr_view.params().quorum(),
-            normalized_collects.len()
+            curr_view.view_change_quorum_weight(),
+            curr_view.weight_of_votes(normalized_collects.iter().map(|(node, _)| node))
         );
 
         false
@@ -2037,19 +2721,22 @@ fn binds<O>(
     }
 }
 
-fn unbound<O>(curr_view: &ViewInfo, normalized_collects: &[Option<&CollectData<O>>]) -> bool {
-    if normalized_collects.len() < curr_view.params().quorum() {
+fn unbound<O>(
+    curr_view: &ViewInfo,
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
+) -> bool {
+    if !curr_view.is_view_change_quorum(normalized_collects.iter().map(|(node, _)| node)) {
         debug!(
             "Not enough collects to unbound. Need {:?}, have {:?}.",
-            curr_view.params().quorum(),
-            normalized_collects.len()
+            curr_view.view_change_quorum_weight(),
+            curr_view.weight_of_votes(normalized_collects.iter().map(|(node, _)| node))
         );
 
         false
     } else {
-        let count = normalized_collects
+        let unbound_voters = normalized_collects
             .iter()
-            .filter(move |maybe_collect| {
+            .filter(move |(_, maybe_collect)| {
                 maybe_collect
                     .map(|collect| {
                         collect
@@ -2063,14 +2750,16 @@ fn unbound<O>(curr_view: &ViewInfo, normalized_collects: &[Option<&CollectData<O
                     // check NOTE above on the `sound` predicate
                     .unwrap_or(true)
             })
-            .count();
+            .map(|(node, _)| node);
+
+        let weight = curr_view.weight_of_votes(unbound_voters);
 
         debug!(
-            "Unbound count: {:?} for collect data: {:?}.",
-            count, normalized_collects
+            "Unbound weight: {:?} for collect data: {:?}.",
+            weight, normalized_collects
         );
 
-        count >= curr_view.params().quorum()
+        weight >= curr_view.view_change_quorum_weight()
     }
 }
 
@@ -2088,11 +2777,11 @@ fn quorum_highest<O>(
     curr_view: &ViewInfo,
     ts: SeqNo,
     value: &Digest,
-    normalized_collects: &[Option<&CollectData<O>>],
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
 ) -> bool {
     let appears = normalized_collects
         .iter()
-        .filter_map(Option::as_ref)
+        .filter_map(|(_, collect)| collect.as_ref())
         .any(|collect| {
             collect
                 .incomplete_proof()
@@ -2103,10 +2792,10 @@ fn quorum_highest<O>(
                 .unwrap_or(false)
         });
 
-    let count = normalized_collects
+    let voters = normalized_collects
         .iter()
-        .filter_map(Option::as_ref)
-        .filter(move |collect| {
+        .filter_map(|(node, collect)| collect.as_ref().map(|collect| (node, collect)))
+        .filter(move |(_, collect)| {
             collect
                 .incomplete_proof()
                 .quorum_prepares()
@@ -2119,60 +2808,64 @@ fn quorum_highest<O>(
                 )
                 .unwrap_or(false)
         })
-        .count();
+        .map(|(node, _)| node);
+
+    let weight = curr_view.weight_of_votes(voters);
 
     debug!(
-        "Quorum highest: {:?} appears? {} {:?} times.",
-        value, appears, count
+        "Quorum highest: {:?} appears? {} with weight {:?}.",
+        value, appears, weight
     );
 
-    appears && count >= curr_view.params().quorum()
+    appears && weight >= curr_view.view_change_quorum_weight()
 }
 
 fn certified_value<O>(
     curr_view: &ViewInfo,
     ts: SeqNo,
     value: &Digest,
-    normalized_collects: &[Option<&CollectData<O>>],
+    normalized_collects: &[(NodeId, Option<&CollectData<O>>)],
 ) -> bool {
-    let count: usize = normalized_collects
+    let weight: u64 = normalized_collects
         .iter()
-        .filter_map(Option::as_ref)
-        .map(move |collect| {
-            collect
+        .filter_map(|(node, collect)| collect.as_ref().map(|collect| (node, collect)))
+        .map(move |(node, collect)| {
+            let count = collect
                 .incomplete_proof()
                 .write_set()
                 .iter()
                 .filter(|ViewDecisionPair(other_ts, other_value)| {
                     *other_ts >= ts && other_value == value
                 })
-                .count()
+                .count() as u64;
+
+            count * curr_view.weight_of(node)
         })
         .sum();
 
-    debug!("Certified value: {:?} appears {:?} times.", value, count);
+    debug!("Certified value: {:?} appears with weight {:?}.", value, weight);
 
-    count > curr_view.params().f()
+    weight > curr_view.fault_weight()
 }
 
 fn collect_data<'a, O: 'a>(
     collects: impl Iterator<Item = &'a StoredMessage<PBFTMessage<O>>>,
-) -> impl Iterator<Item = &'a CollectData<O>> {
-    collects.filter_map(|stored| match stored.message().view_change().kind() {
-        ViewChangeMessageKind::StopData(collects) => Some(collects),
+) -> impl Iterator<Item = (NodeId, &'a CollectData<O>)> {
+    collects.filter_map(|stored| match stored.message().view_change().ok()?.kind() {
+        ViewChangeMessageKind::StopData(collects) => Some((stored.header().from(), collects)),
         _ => None,
     })
 }
 
 fn normalized_collects<'a, O: 'a>(
     in_exec: SeqNo,
-    collects: impl Iterator<Item = &'a CollectData<O>>,
-) -> impl Iterator<Item = Option<&'a CollectData<O>>> {
-    collects.map(move |collect| {
+    collects: impl Iterator<Item = (NodeId, &'a CollectData<O>)>,
+) -> impl Iterator<Item = (NodeId, Option<&'a CollectData<O>>)> {
+    collects.map(move |(node, collect)| {
         if collect.incomplete_proof().executing() == in_exec {
-            Some(collect)
+            (node, Some(collect))
         } else {
-            None
+            (node, None)
         }
     })
 }
@@ -2227,7 +2920,37 @@ where
     wm.is_valid(Some(key.public_key()), false).is_ok()
 }
 
-fn highest_proof<'a, RQ, I, NT>(view: &ViewInfo, node: &NT, collects: I) -> Option<&'a Proof<RQ>>
+/// Check the authentication of a vote contained in a proof.
+///
+/// Votes authenticated with MACs are not transferable, so we can only check
+/// the entry of the authenticator which was addressed to us.
+fn validate_vote<RQ, NT>(
+    node: &NT,
+    mac_keys: Option<&dyn MacKeys>,
+    stored: &StoredMessage<PBFTMessage<RQ>>,
+) -> bool
+where
+    RQ: SerType,
+    NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+{
+    let Ok(message) = stored.message().consensus() else {
+        return false;
+    };
+
+    match message.authentication() {
+        MessageAuthentication::Signature => validate_signature::<RQ, _, _>(node, stored),
+        MessageAuthentication::Authenticator => mac_keys.is_some_and(|mac_keys| {
+            verify_vote_authenticator(mac_keys, node.id(), stored.header().from(), message).is_ok()
+        }),
+    }
+}
+
+fn highest_proof<'a, RQ, I, NT>(
+    view: &ViewInfo,
+    node: &NT,
+    mac_keys: Option<&dyn MacKeys>,
+    collects: I,
+) -> Option<&'a Proof<RQ>>
 where
     RQ: SerType,
     I: Iterator<Item = &'a StoredMessage<PBFTMessage<RQ>>>,
@@ -2235,53 +2958,209 @@ where
 {
     collect_data(collects)
         // fetch proofs
-        .filter_map(|collect| collect.last_proof())
+        .filter_map(|(_, collect)| collect.last_proof())
         // check if COMMIT msgs are signed, and all have the same digest
         //
-        .filter(move |proof| {
-            let digest = proof.batch_digest();
+        .filter(move |proof| proof_valid(view, node, mac_keys, proof))
+        .max_by_key(|proof| proof.sequence_number())
+}
 
-            let commits_valid = proof
-                .commits()
-                .iter()
-                .filter(|stored| {
-                    stored
-                        .message()
-                        .consensus()
-                        .has_proposed_digest(&digest)
-                        //If he does not have the digest, then it is not valid
-                        .unwrap_or(false)
-                })
-                .filter(move |&stored| validate_signature::<RQ, _, _>(node, stored))
-                .count()
-                >= view.params().quorum();
+/// Check that a proof carries a quorum of valid votes for its batch
+pub(crate) fn proof_valid<RQ, NT>(
+    view: &ViewInfo,
+    node: &NT,
+    mac_keys: Option<&dyn MacKeys>,
+    proof: &Proof<RQ>,
+) -> bool
+where
+    RQ: SerType,
+    NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+{
+    let digest = proof.batch_digest();
 
-            let prepares_valid = proof
-                .prepares()
-                .iter()
-                .filter(|stored| {
-                    stored
-                        .message()
-                        .consensus()
-                        .has_proposed_digest(&digest)
-                        //If he does not have the digest, then it is not valid
-                        .unwrap_or(false)
-                })
-                .filter(move |&stored| validate_signature::<RQ, _, _>(node, stored))
-                .count()
-                >= view.params().quorum();
+    if !pre_prepares_valid(view, proof) {
+        debug!(
+            "{:?} // Proof {:?} is invalid, as its pre prepares do not make up its batch",
+            node.id(),
+            proof
+        );
 
-            debug!(
-                "{:?} // Proof {:?} is valid? commits valid: {:?} &&  prepares_valid: {:?}",
-                node.id(),
-                proof,
-                commits_valid,
-                prepares_valid
-            );
+        return false;
+    }
+
+    // Compacted proofs carry their votes in a quorum certificate
+    if let Some(certificate) = proof.certificate() {
+        return certified_proof_valid(view, node, proof, certificate);
+    }
 
-            commits_valid && prepares_valid
+    let prepare_voters: BTreeSet<NodeId> = proof
+        .prepares()
+        .iter()
+        .filter(|stored| {
+            stored
+                .message()
+                .consensus()
+                .ok()
+                .and_then(|message| message.has_proposed_digest(&digest))
+                //If he does not have the digest, then it is not valid
+                .unwrap_or(false)
         })
-        .max_by_key(|proof| proof.sequence_number())
+        .filter(move |&stored| validate_vote::<RQ, _>(node, mac_keys, stored))
+        .map(|stored| stored.header().from())
+        .collect();
+
+    // Decisions taken on the fast path carry matching prepares from
+    // every replica instead of a commit quorum
+    let fast_path = view
+        .quorum_members()
+        .iter()
+        .all(|member| prepare_voters.contains(member));
+
+    // When running under the crash fault model, the commit phase is skipped
+    let commits_valid = !view.fault_model().requires_commit_phase()
+        || fast_path
+        || proof
+            .commits()
+            .iter()
+            .filter(|stored| {
+                stored
+                    .message()
+                    .consensus()
+                    .ok()
+                    .and_then(|message| message.has_proposed_digest(&digest))
+                    //If he does not have the digest, then it is not valid
+                    .unwrap_or(false)
+            })
+            .filter(move |&stored| validate_vote::<RQ, _>(node, mac_keys, stored))
+            .map(|stored| view.weight_of(&stored.header().from()))
+            .sum::<u64>()
+            >= view.quorum_weight();
+
+    let prepares_valid = view.is_quorum(&prepare_voters);
+
+    debug!(
+        "{:?} // Proof {:?} is valid? commits valid: {:?} &&  prepares_valid: {:?}",
+        node.id(),
+        proof,
+        commits_valid,
+        prepares_valid
+    );
+
+    commits_valid && prepares_valid
+}
+
+/// Check that the pre prepares of a proof were sent by the leaders of the view it was
+/// decided in, each in its own slot, and that they (along with the skipped slots)
+/// make up the batch digest the votes were cast for
+fn pre_prepares_valid<RQ>(view: &ViewInfo, proof: &Proof<RQ>) -> bool {
+    let decision_view = proof
+        .pre_prepares()
+        .iter()
+        .chain(proof.prepares().iter())
+        .find_map(|stored| stored.message().consensus().ok())
+        .map(|message| message.view())
+        .unwrap_or(view.sequence_number());
+
+    // The leader set may have been reduced since the view started, which
+    // we only know about for the view we are in
+    let decision_view = if decision_view == view.sequence_number() {
+        view.clone()
+    } else {
+        view.peek(decision_view)
+    };
+
+    let leader_set = decision_view.leader_set();
+    let ordering = proof.pre_prepare_ordering();
+
+    if ordering.len() != leader_set.len() || !proof.are_pre_prepares_ordered().unwrap_or(false) {
+        return false;
+    }
+
+    let mut pre_prepares = proof.pre_prepares().iter();
+
+    for (slot, (leader, slot_digest)) in leader_set.iter().zip(ordering.iter()).enumerate() {
+        if proof.skipped_slots().binary_search(&slot).is_ok() {
+            if *slot_digest != skipped_slot_digest(proof.seq_no(), leader) {
+                return false;
+            }
+
+            continue;
+        }
+
+        let Some(pre_prepare) = pre_prepares.next() else {
+            return false;
+        };
+
+        let from_leader = pre_prepare.header().from() == *leader
+            && pre_prepare
+                .message()
+                .consensus()
+                .is_ok_and(|message| {
+                    message.sequence_number() == proof.seq_no()
+                        && message.view() == decision_view.sequence_number()
+                        && matches!(message.kind(), ConsensusMessageKind::PrePrepare(_))
+                });
+
+        if !from_leader {
+            return false;
+        }
+    }
+
+    let mut ctx = Context::new();
+
+    ordering
+        .iter()
+        .for_each(|slot_digest| ctx.update(slot_digest.as_ref()));
+
+    ctx.finish() == proof.batch_digest()
+}
+
+/// Check a compacted proof, whose votes are stored in a [QuorumCertificate]
+fn certified_proof_valid<RQ, NT>(
+    view: &ViewInfo,
+    node: &NT,
+    proof: &Proof<RQ>,
+    certificate: &QuorumCertificate,
+) -> bool
+where
+    RQ: SerType,
+    NT: OrderProtocolSendNode<RQ, PBFT<RQ>>,
+{
+    let signatures_valid = certificate
+        .verify(proof.seq_no(), &proof.batch_digest(), |signer| {
+            node.network_info_provider()
+                .get_node_info(&signer)
+                .map(|info| info.public_key().clone())
+        })
+        .is_ok();
+
+    let prepare_voters: BTreeSet<NodeId> = certificate.prepares().signers().iter().collect();
+
+    let fast_path = view
+        .quorum_members()
+        .iter()
+        .all(|member| prepare_voters.contains(member));
+
+    let prepares_valid = view.is_quorum(&prepare_voters);
+
+    let commits_valid = !view.fault_model().requires_commit_phase()
+        || fast_path
+        || certificate.commits().is_some_and(|commits| {
+            let commit_voters: BTreeSet<NodeId> = commits.signers().iter().collect();
+
+            view.is_quorum(&commit_voters)
+        });
+
+    debug!(
+        "{:?} // Certified proof {:?} is valid? signatures valid: {:?}, commits valid: {:?} && prepares valid: {:?}",
+        node.id(),
+        proof,
+        signatures_valid,
+        commits_valid,
+        prepares_valid
+    );
+
+    signatures_valid && commits_valid && prepares_valid
 }
 
 impl<O> Debug for SynchronizerPollStatus<O> {
@@ -2295,7 +3174,7 @@ impl<O> Debug for SynchronizerPollStatus<O> {
                     f,
                     "SynchronizerPollStatus::NextMessage Header {:?}, Message {:?}",
                     message.header(),
-                    message.message().view_change()
+                    message.message()
                 )
             }
             SynchronizerPollStatus::ResumeViewChange => {
@@ -2304,3 +3183,209 @@ impl<O> Debug for SynchronizerPollStatus<O> {
         }
     }
 }
+
+#[cfg(test)]
+mod sync_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::bft::config::FaultModel;
+    use crate::bft::log::decisions::{CollectData, IncompleteProof, PrepareSet, ViewDecisionPair};
+    use crate::bft::sync::view::ViewInfo;
+
+    use super::{fast_path_binds, sound, Sound};
+
+    fn digest(byte: u8) -> Digest {
+        Digest::from_bytes(&[byte; Digest::LENGTH]).unwrap()
+    }
+
+    fn writing(ts: SeqNo, value: Option<Digest>) -> CollectData<()> {
+        let write_set = value
+            .map(|value| vec![ViewDecisionPair(ts, value)])
+            .unwrap_or_default();
+
+        CollectData::new(
+            IncompleteProof::new(SeqNo::ZERO, PrepareSet(write_set), None),
+            None,
+        )
+    }
+
+    /// Collects from the first replicas of the view, each writing the given value
+    fn collects(ts: SeqNo, values: &[Option<Digest>]) -> (Vec<NodeId>, Vec<CollectData<()>>) {
+        let nodes = NodeId::targets_u32(0..values.len() as u32).collect();
+
+        let collects = values.iter().map(|value| writing(ts, *value)).collect();
+
+        (nodes, collects)
+    }
+
+    fn normalized<'a>(
+        nodes: &[NodeId],
+        collects: &'a [CollectData<()>],
+    ) -> Vec<(NodeId, Option<&'a CollectData<()>>)> {
+        nodes
+            .iter()
+            .copied()
+            .zip(collects.iter().map(Some))
+            .collect()
+    }
+
+    #[test]
+    fn test_fast_path_decided_value_survives_faulty_collect() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let (decided, other) = (digest(1), digest(2));
+
+        // Replica 3 is correct and wrote the decided value, but its collect was not
+        // among the quorum. Replica 2 is faulty and claims to have written another value
+        let (nodes, collects) = collects(SeqNo::ZERO, &[Some(decided), Some(decided), Some(other)]);
+
+        let normalized = normalized(&nodes, &collects);
+
+        assert_eq!(
+            fast_path_binds(&view, SeqNo::ZERO, &decided, &normalized),
+            Some(2)
+        );
+        assert_eq!(
+            fast_path_binds(&view, SeqNo::ZERO, &other, &normalized),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fast_path_requires_quorum_of_collects() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let value = digest(1);
+
+        let (nodes, collects) = collects(SeqNo::ZERO, &[Some(value), Some(value)]);
+
+        let normalized = normalized(&nodes, &collects);
+
+        assert_eq!(
+            fast_path_binds(&view, SeqNo::ZERO, &value, &normalized),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fast_path_split_binds_majority_value() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let (written, minority) = (digest(1), digest(2));
+
+        let (nodes, collects) = collects(
+            SeqNo::ZERO,
+            &[Some(written), Some(minority), Some(written), None],
+        );
+
+        let normalized = normalized(&nodes, &collects);
+
+        assert_eq!(
+            fast_path_binds(&view, SeqNo::ZERO, &written, &normalized),
+            Some(2)
+        );
+        assert_eq!(
+            fast_path_binds(&view, SeqNo::ZERO, &minority, &normalized),
+            None
+        );
+
+        match sound(&view, &normalized) {
+            Sound::Bound(value) => assert_eq!(value, written),
+            Sound::Unbound(_) => panic!("The written value should be bound"),
+        }
+    }
+
+    #[test]
+    fn test_fast_path_tie_is_broken_deterministically() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let (lower, higher) = (digest(1), digest(2));
+
+        // Neither value can have been decided, as each was written by only half the replicas
+        let orders = [
+            [Some(lower), Some(lower), Some(higher), Some(higher)],
+            [Some(higher), Some(lower), Some(higher), Some(lower)],
+        ];
+
+        for order in orders {
+            let (nodes, collects) = collects(SeqNo::ZERO, &order);
+
+            let normalized = normalized(&nodes, &collects);
+
+            assert_eq!(
+                fast_path_binds(&view, SeqNo::ZERO, &lower, &normalized),
+                Some(2)
+            );
+            assert_eq!(
+                fast_path_binds(&view, SeqNo::ZERO, &higher, &normalized),
+                Some(2)
+            );
+
+            match sound(&view, &normalized) {
+                Sound::Bound(value) => assert_eq!(value, higher),
+                Sound::Unbound(_) => panic!("One of the tied values should be bound"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_crash_decided_value_in_a_single_collect_is_kept() {
+        let members: Vec<NodeId> = NodeId::targets_u32(0..3).collect();
+
+        let view =
+            ViewInfo::from_quorum_with_fault_model(SeqNo::from(2u32), members, FaultModel::Crash)
+                .unwrap();
+
+        let (decided, older) = (digest(1), digest(2));
+
+        // The value was decided in view 1 on the prepares of replicas 0 and 1,
+        // but replica 0 crashed. Only replica 1 is left to report it, while
+        // replica 2 only wrote a value proposed in view 0
+        let collects = [
+            writing(SeqNo::ONE, Some(decided)),
+            writing(SeqNo::ZERO, Some(older)),
+        ];
+
+        let nodes = [NodeId::from(1u32), NodeId::from(2u32)];
+
+        let normalized = normalized(&nodes, &collects);
+
+        match sound(&view, &normalized) {
+            Sound::Bound(value) => assert_eq!(value, decided),
+            Sound::Unbound(_) => panic!("The decided value should be bound"),
+        }
+
+        // The order of the collects does not change the outcome
+        let mut normalized = normalized;
+
+        normalized.reverse();
+
+        match sound(&view, &normalized) {
+            Sound::Bound(value) => assert_eq!(value, decided),
+            Sound::Unbound(_) => panic!("The decided value should be bound"),
+        }
+    }
+
+    #[test]
+    fn test_crash_unwritten_collects_are_unbound() {
+        let members: Vec<NodeId> = NodeId::targets_u32(0..3).collect();
+
+        let view =
+            ViewInfo::from_quorum_with_fault_model(SeqNo::ONE, members, FaultModel::Crash).unwrap();
+
+        let (nodes, collects) = collects(SeqNo::ZERO, &[None, None]);
+
+        assert!(matches!(
+            sound(&view, &normalized(&nodes, &collects)),
+            Sound::Unbound(true)
+        ));
+
+        // A single collect is not a quorum, so it can't tell that nothing was decided
+        assert!(matches!(
+            sound(&view, &normalized(&nodes[..1], &collects[..1])),
+            Sound::Unbound(false)
+        ));
+    }
+}
@@ -0,0 +1,349 @@
+//! Leader performance monitoring, in the style of Aardvark.
+//!
+//! Client request timeouts only catch leaders which stop making progress altogether.
+//! A Byzantine leader can instead keep proposing just fast enough to avoid them,
+//! throttling the whole system. To prevent this, every replica keeps track of the
+//! throughput and pre prepare latency of the current leader and compares them with
+//! the best performance observed in recent views. When the leader falls below the
+//! expected fraction of that performance, the replica proactively starts a view change.
+//!
+//! In order to avoid flapping between leaders:
+//! - a new leader always gets a full window of decisions before being judged;
+//! - windows with too little load are not judged at all;
+//! - a replica only starts one proactive view change per view, and never more than one per cooldown;
+//! - the peak performance decays at every view change, so expectations adapt to a slower system.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_metrics::metrics::metric_increment;
+use tracing::{debug, warn};
+
+use crate::bft::config::LeaderMonitorConfig;
+use crate::bft::metric::{ConsensusMetrics, LEADER_MONITOR_VIEW_CHANGES_ID};
+use crate::bft::sync::view::ViewInfo;
+
+/// The performance of the leader in a single decision
+#[derive(Debug, Clone)]
+struct DecisionSample {
+    started_at: Instant,
+    decided_at: Instant,
+    requests: usize,
+    pre_prepare_latency: Duration,
+}
+
+/// The performance of the leader over a window of decisions
+#[derive(Debug, Clone, Copy)]
+pub struct LeaderPerformance {
+    /// Requests ordered per second
+    pub throughput: f64,
+    /// The average time the leader took to send its pre prepares
+    pub pre_prepare_latency: Duration,
+}
+
+pub struct LeaderMonitor {
+    node_id: NodeId,
+    config: LeaderMonitorConfig,
+    /// The view the samples were taken in
+    view: SeqNo,
+    samples: VecDeque<DecisionSample>,
+    /// The best throughput observed in recent views
+    peak_throughput: f64,
+    /// The best pre prepare latency observed in recent views
+    best_pre_prepare_latency: Option<Duration>,
+    /// Have we already started a view change against the leader of this view?
+    triggered_in_view: bool,
+    last_triggered: Option<Instant>,
+    /// When the last decision was finalized
+    last_decided: Option<Instant>,
+}
+
+impl LeaderMonitor {
+    pub fn new(node_id: NodeId, config: LeaderMonitorConfig) -> Self {
+        Self {
+            node_id,
+            samples: VecDeque::with_capacity(config.window),
+            config,
+            view: SeqNo::ZERO,
+            peak_throughput: 0.0,
+            best_pre_prepare_latency: None,
+            triggered_in_view: false,
+            last_triggered: None,
+            last_decided: None,
+        }
+    }
+
+    /// Record the metrics of a decision finalized in the given view
+    pub fn record_decision(
+        &mut self,
+        view: &ViewInfo,
+        metrics: &ConsensusMetrics,
+        requests: usize,
+    ) {
+        self.record_decision_at(view, metrics, requests, Instant::now())
+    }
+
+    fn record_decision_at(
+        &mut self,
+        view: &ViewInfo,
+        metrics: &ConsensusMetrics,
+        requests: usize,
+        decided_at: Instant,
+    ) {
+        if view.sequence_number() != self.view {
+            self.view_changed(view.sequence_number());
+        }
+
+        // Instances are started as soon as they enter the watermark window, long before
+        // the leader is expected to propose to them. We instead measure from the moment
+        // the previous instance was decided, so a leader which keeps up with the decisions
+        // is not blamed for the depth of the window (a pipelined proposal takes no time)
+        let started_at = self
+            .last_decided
+            .map_or(metrics.consensus_start_time, |last_decided| {
+                last_decided.max(metrics.consensus_start_time)
+            });
+
+        self.samples.push_back(DecisionSample {
+            started_at,
+            decided_at,
+            requests,
+            pre_prepare_latency: metrics
+                .first_pre_prepare_time
+                .saturating_duration_since(started_at),
+        });
+
+        self.last_decided = Some(decided_at);
+
+        while self.samples.len() > self.config.window {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Evaluate the leader of the given view.
+    ///
+    /// Returns the performance of the leader when it should be replaced
+    pub fn evaluate(&mut self, view: &ViewInfo) -> Option<LeaderPerformance> {
+        if view.sequence_number() != self.view || view.leader() == self.node_id {
+            // We never judge ourselves
+            return None;
+        }
+
+        if self.samples.len() < self.config.window {
+            return None;
+        }
+
+        let performance = self.window_performance()?;
+
+        let expected_throughput = self.peak_throughput * self.config.expected_fraction;
+
+        let latency_ceiling = self
+            .best_pre_prepare_latency
+            .map(|best| best.div_f64(self.config.expected_fraction));
+
+        let too_slow = performance.throughput < expected_throughput
+            || latency_ceiling.is_some_and(|ceiling| performance.pre_prepare_latency > ceiling);
+
+        if !too_slow {
+            self.peak_throughput = self.peak_throughput.max(performance.throughput);
+
+            self.best_pre_prepare_latency = Some(match self.best_pre_prepare_latency {
+                Some(best) => best.min(performance.pre_prepare_latency),
+                None => performance.pre_prepare_latency,
+            });
+
+            return None;
+        }
+
+        if self.triggered_in_view {
+            return None;
+        }
+
+        if self
+            .last_triggered
+            .is_some_and(|last| last.elapsed() < self.config.cooldown)
+        {
+            debug!(
+                "{:?} // Leader {:?} is below the expected performance {:?}, but we are still in the cooldown",
+                self.node_id,
+                view.leader(),
+                performance
+            );
+
+            return None;
+        }
+
+        warn!(
+            "{:?} // Leader {:?} is performing below expectations ({:?} vs peak throughput {:.2} rq/s, best latency {:?}), starting a view change",
+            self.node_id,
+            view.leader(),
+            performance,
+            self.peak_throughput,
+            self.best_pre_prepare_latency
+        );
+
+        self.triggered_in_view = true;
+        self.last_triggered = Some(Instant::now());
+
+        metric_increment(LEADER_MONITOR_VIEW_CHANGES_ID, Some(1));
+
+        Some(performance)
+    }
+
+    /// The performance of the leader over the current window, if there was enough load to judge it
+    fn window_performance(&self) -> Option<LeaderPerformance> {
+        let requests: usize = self.samples.iter().map(|sample| sample.requests).sum();
+
+        if requests < self.config.min_window_requests {
+            return None;
+        }
+
+        let first = self.samples.front()?;
+        let last = self.samples.back()?;
+
+        let elapsed = last.decided_at.saturating_duration_since(first.started_at);
+
+        if elapsed.is_zero() {
+            return None;
+        }
+
+        let total_latency: Duration = self
+            .samples
+            .iter()
+            .map(|sample| sample.pre_prepare_latency)
+            .sum();
+
+        Some(LeaderPerformance {
+            throughput: requests as f64 / elapsed.as_secs_f64(),
+            pre_prepare_latency: total_latency / self.samples.len() as u32,
+        })
+    }
+
+    fn view_changed(&mut self, view: SeqNo) {
+        self.view = view;
+        self.samples.clear();
+        self.triggered_in_view = false;
+
+        let retained = 1.0 - self.config.peak_decay;
+
+        self.peak_throughput *= retained;
+        self.best_pre_prepare_latency = self
+            .best_pre_prepare_latency
+            .map(|latency| latency.div_f64(retained.max(f64::EPSILON)));
+    }
+}
+
+#[cfg(test)]
+mod monitor_tests {
+    use std::time::{Duration, Instant};
+
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::bft::config::LeaderMonitorConfig;
+    use crate::bft::metric::ConsensusMetrics;
+    use crate::bft::sync::view::ViewInfo;
+
+    use super::LeaderMonitor;
+
+    fn monitor() -> LeaderMonitor {
+        // The leader of view 0 is replica 0, so replica 1 judges it
+        LeaderMonitor::new(
+            NodeId::from(1u32),
+            LeaderMonitorConfig::new(2, 0.5, 1, Duration::ZERO),
+        )
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Record a decision of an instance started at `start`, whose pre prepare arrived at `pre_prepare`
+    fn decide(
+        monitor: &mut LeaderMonitor,
+        view: &ViewInfo,
+        start: Instant,
+        pre_prepare: Instant,
+        decided: Instant,
+    ) {
+        let mut metrics = ConsensusMetrics::new();
+
+        metrics.consensus_start_time = start;
+        metrics.first_pre_prepare_time = pre_prepare;
+
+        monitor.record_decision_at(view, &metrics, 100, decided);
+    }
+
+    fn last_latency(monitor: &LeaderMonitor) -> Duration {
+        monitor.samples.back().unwrap().pre_prepare_latency
+    }
+
+    #[test]
+    fn test_first_decision_measured_from_its_start() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let mut monitor = monitor();
+
+        let t0 = Instant::now();
+
+        decide(&mut monitor, &view, t0, t0 + ms(10), t0 + ms(20));
+
+        assert_eq!(last_latency(&monitor), ms(10));
+    }
+
+    #[test]
+    fn test_latency_measured_from_previous_decision() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let mut monitor = monitor();
+
+        let t0 = Instant::now();
+
+        decide(&mut monitor, &view, t0, t0 + ms(10), t0 + ms(40));
+
+        // This instance entered the window together with the first one,
+        // but the leader only had to propose to it once the first was decided
+        decide(&mut monitor, &view, t0, t0 + ms(50), t0 + ms(60));
+
+        assert_eq!(last_latency(&monitor), ms(10));
+    }
+
+    #[test]
+    fn test_pipelined_proposal_has_no_latency() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let mut monitor = monitor();
+
+        let t0 = Instant::now();
+
+        decide(&mut monitor, &view, t0, t0 + ms(10), t0 + ms(40));
+        decide(&mut monitor, &view, t0, t0 + ms(15), t0 + ms(45));
+
+        assert_eq!(last_latency(&monitor), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_slow_leader_is_replaced() {
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+        let mut monitor = monitor();
+
+        let t0 = Instant::now();
+
+        decide(&mut monitor, &view, t0, t0 + ms(10), t0 + ms(20));
+        decide(&mut monitor, &view, t0, t0 + ms(30), t0 + ms(40));
+
+        // The leader sets the expectations
+        assert!(monitor.evaluate(&view).is_none());
+
+        decide(&mut monitor, &view, t0, t0 + ms(140), t0 + ms(150));
+        decide(&mut monitor, &view, t0, t0 + ms(250), t0 + ms(260));
+
+        let performance = monitor
+            .evaluate(&view)
+            .expect("The leader should be replaced");
+
+        assert_eq!(performance.pre_prepare_latency, ms(100));
+
+        // Only one view change is started per view
+        assert!(monitor.evaluate(&view).is_none());
+    }
+}
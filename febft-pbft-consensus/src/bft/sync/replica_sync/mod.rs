@@ -7,7 +7,7 @@ use std::cell::Cell;
 use std::marker::PhantomData;
 use std::time::{Duration, Instant};
 
-use tracing::{debug, error, info};
+use tracing::{debug, info};
 
 use atlas_common::collections;
 use atlas_common::node_id::NodeId;
@@ -24,9 +24,7 @@ use atlas_metrics::metrics::{metric_duration, metric_increment};
 use crate::bft::consensus::Consensus;
 use crate::bft::log::decisions::CollectData;
 use crate::bft::log::Log;
-use crate::bft::message::{
-    ConsensusMessage, ConsensusMessageKind, PBFTMessage, ViewChangeMessage, ViewChangeMessageKind,
-};
+use crate::bft::message::{PBFTMessage, ViewChangeMessage, ViewChangeMessageKind};
 use crate::bft::metric::{
     SYNC_BATCH_RECEIVED_ID, SYNC_STOPPED_COUNT_ID, SYNC_STOPPED_REQUESTS_ID, SYNC_WATCH_REQUESTS_ID,
 };
@@ -91,7 +89,7 @@ impl<RQ: SerType + SessionBased + 'static> ReplicaSynchronizer<RQ> {
 
         let last_proof = log.last_proof();
 
-        let incomplete_proof = consensus.collect_incomplete_proof(previous_view.params().f());
+        let incomplete_proof = consensus.collect_incomplete_proof(previous_view.prepared_quorum());
 
         let collect = CollectData::new(incomplete_proof, last_proof);
 
@@ -101,12 +99,22 @@ impl<RQ: SerType + SessionBased + 'static> ReplicaSynchronizer<RQ> {
             collect
         );
 
-        let message = PBFTMessage::ViewChange(ViewChangeMessage::new(
-            current_view_seq,
-            ViewChangeMessageKind::StopData(collect),
-        ));
+        let message =
+            ViewChangeMessage::new(current_view_seq, ViewChangeMessageKind::StopData(collect));
+
+        let Some(message) = base_sync.certify(&view_info, message) else {
+            return;
+        };
+
+        if view_info.fault_model().requires_usig() {
+            // Every replica must see all the messages bound to our counter in order,
+            // or it would be waiting on this one forever
+            let targets = view_info.quorum_members().clone();
 
-        let _ = node.send_signed(message, current_leader, true);
+            let _ = node.broadcast_signed(PBFTMessage::ViewChange(message), targets.into_iter());
+        } else {
+            let _ = node.send_signed(PBFTMessage::ViewChange(message), current_leader, true);
+        }
     }
 
     /// Start a new view change
@@ -139,14 +147,18 @@ impl<RQ: SerType + SessionBased + 'static> ReplicaSynchronizer<RQ> {
             requests.len()
         );
 
-        let message = PBFTMessage::ViewChange(ViewChangeMessage::new(
+        let message = ViewChangeMessage::new(
             current_view.sequence_number().next(),
             ViewChangeMessageKind::Stop(requests),
-        ));
+        );
+
+        let Some(message) = base_sync.certify(&current_view, message) else {
+            return;
+        };
 
         let targets = current_view.quorum_members().clone();
 
-        let _ = node.broadcast_signed(message, targets.into_iter());
+        let _ = node.broadcast_signed(PBFTMessage::ViewChange(message), targets.into_iter());
     }
 
     pub(super) fn handle_begin_quorum_view_change<NT>(
@@ -170,6 +182,10 @@ impl<RQ: SerType + SessionBased + 'static> ReplicaSynchronizer<RQ> {
 
         let message = ViewChangeMessage::new(current_view.sequence_number().next(), message);
 
+        let Some(message) = base_sync.certify(&current_view, message) else {
+            return;
+        };
+
         let message = PBFTMessage::ViewChange(message);
 
         let _ = node.broadcast_signed(message, current_view.quorum_members().clone().into_iter());
@@ -199,20 +215,11 @@ impl<RQ: SerType + SessionBased + 'static> ReplicaSynchronizer<RQ> {
     pub fn received_request_batch(
         &self,
         header: &Header,
-        pre_prepare: &ConsensusMessage<RQ>,
+        requests: &[StoredMessage<RQ>],
         timeouts: &TimeoutModHandle,
     ) -> Vec<ClientRqInfo> {
         let start_time = Instant::now();
 
-        let requests = match pre_prepare.kind() {
-            ConsensusMessageKind::PrePrepare(req) => req,
-            _ => {
-                error!("Cannot receive a request that is not a PrePrepare");
-
-                panic!()
-            }
-        };
-
         let mut timeout_info = Vec::with_capacity(requests.len());
         let mut digests = Vec::with_capacity(requests.len());
 
@@ -19,6 +19,8 @@ use std::ops::{Add, Div};
 use num_traits::Zero;
 use thiserror::Error;
 
+use crate::bft::config::FaultModel;
+
 /// This struct contains information related with an
 /// active `febft` view.
 #[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
@@ -34,6 +36,28 @@ pub struct ViewInfo {
     leader_hash_space_division: BTreeMap<NodeId, (Vec<u8>, Vec<u8>)>,
     // The parameters of the view
     params: SystemParams,
+    // The vote weights of each of the quorum members.
+    // When not present, every member has the same vote weight
+    weights: Option<VoteWeights>,
+    // The fault model this view is working under
+    fault_model: FaultModel,
+}
+
+/// The vote weights assigned to each of the members of a quorum,
+/// following the weighted replication scheme of WHEAT/AWARE.
+///
+/// Quorums are no longer formed by a number of replicas but by an
+/// accumulated vote weight, which allows us to add spare replicas
+/// (with a larger weight) in the best connected sites and reach quorums faster.
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoteWeights {
+    // The weight of each of the members
+    weights: BTreeMap<NodeId, u64>,
+    // The weight required to form a quorum
+    quorum_weight: u64,
+    // The largest weight that can be held by f faulty replicas
+    fault_weight: u64,
 }
 
 impl Orderable for ViewInfo {
@@ -49,7 +73,12 @@ impl NetworkView for ViewInfo {
     }
 
     fn quorum(&self) -> usize {
-        self.params().quorum()
+        match self.fault_model {
+            FaultModel::Byzantine => self.params().quorum(),
+            FaultModel::Crash | FaultModel::TrustedCounter => {
+                self.fault_model.quorum_for_n(self.params().n())
+            }
+        }
     }
 
     fn quorum_members(&self) -> &Vec<NodeId> {
@@ -57,7 +86,12 @@ impl NetworkView for ViewInfo {
     }
 
     fn f(&self) -> usize {
-        self.params().f()
+        match self.fault_model {
+            FaultModel::Byzantine => self.params().f(),
+            FaultModel::Crash | FaultModel::TrustedCounter => {
+                self.fault_model.f_for_n(self.params().n())
+            }
+        }
     }
 
     fn n(&self) -> usize {
@@ -93,6 +127,8 @@ impl ViewInfo {
             leader_set,
             leader_hash_space_division: division,
             params,
+            weights: None,
+            fault_model: FaultModel::Byzantine,
         })
     }
 
@@ -119,6 +155,8 @@ impl ViewInfo {
             leader_set,
             leader_hash_space_division: division,
             params,
+            weights: None,
+            fault_model: FaultModel::Byzantine,
         })
     }
 
@@ -146,9 +184,39 @@ impl ViewInfo {
             leader_set,
             leader_hash_space_division: division,
             params,
+            weights: None,
+            fault_model: FaultModel::Byzantine,
         })
     }
 
+    /// Creates a new instance of `ViewInfo`, from a given list of quorum members,
+    /// working under the given fault model
+    pub fn from_quorum_with_fault_model(
+        seq: SeqNo,
+        quorum_members: Vec<NodeId>,
+        fault_model: FaultModel,
+    ) -> Result<Self> {
+        let mut view = Self::from_quorum(seq, quorum_members)?;
+
+        view.fault_model = fault_model;
+
+        Ok(view)
+    }
+
+    /// Assign vote weights to the members of this view.
+    /// Every quorum member must have a weight assigned.
+    pub fn with_weights(mut self, weights: VoteWeights) -> Result<Self> {
+        for member in &self.quorum_members {
+            if weights.weight_of(member) == 0 {
+                return Err!(ViewError::MissingVoteWeight(*member));
+            }
+        }
+
+        self.weights = Some(weights);
+
+        Ok(self)
+    }
+
     /// Returns a copy of this node's `SystemParams`.
     pub fn params(&self) -> &SystemParams {
         &self.params
@@ -157,15 +225,39 @@ impl ViewInfo {
     /// Returns a new view with the sequence number after
     /// the current view's number.
     pub fn next_view(&self) -> ViewInfo {
-        Self::new(self.seq.next(), self.params.n(), self.params.f()).unwrap()
+        self.carry_weights(Self::new(self.seq.next(), self.params.n(), self.params.f()).unwrap())
     }
 
-    pub fn next_view_with_new_node(&self, joined_node: NodeId) -> ViewInfo {
+    /// Returns the next view, with a new vote weight assignment
+    pub fn next_view_with_weights(&self, weights: VoteWeights) -> Result<ViewInfo> {
+        let mut view = Self::new(self.seq.next(), self.params.n(), self.params.f())?;
+
+        view.fault_model = self.fault_model;
+
+        view.with_weights(weights)
+    }
+
+    /// Returns the next view, with a new node joining the quorum.
+    ///
+    /// The weight assignment depends on n and f, so it is no longer valid once a node joins.
+    /// A weighted view must be given a new assignment which covers the joining node.
+    pub fn next_view_with_new_node(
+        &self,
+        joined_node: NodeId,
+        weights: Option<VoteWeights>,
+    ) -> Result<ViewInfo> {
         let mut quorum_members = self.quorum_members().clone();
 
         quorum_members.push(joined_node);
 
-        Self::from_quorum(self.seq.next(), quorum_members).unwrap()
+        let view =
+            Self::from_quorum_with_fault_model(self.seq.next(), quorum_members, self.fault_model)?;
+
+        match weights {
+            Some(weights) => view.with_weights(weights),
+            None if self.weights.is_some() => Err!(ViewError::MissingVoteWeight(joined_node)),
+            None => Ok(view),
+        }
     }
 
     pub fn previous_view(&self) -> Option<ViewInfo> {
@@ -173,12 +265,98 @@ impl ViewInfo {
             return None;
         }
 
-        Some(Self::new(self.seq.prev(), self.params.n(), self.params.f()).unwrap())
+        Some(self.carry_weights(
+            Self::new(self.seq.prev(), self.params.n(), self.params.f()).unwrap(),
+        ))
     }
 
     /// Returns a new view with the specified sequence number.
     pub fn peek(&self, seq: SeqNo) -> ViewInfo {
-        Self::new(seq, self.params.n(), self.params.f()).unwrap()
+        self.carry_weights(Self::new(seq, self.params.n(), self.params.f()).unwrap())
+    }
+
+    fn carry_weights(&self, mut view: ViewInfo) -> ViewInfo {
+        view.weights = self.weights.clone();
+        view.fault_model = self.fault_model;
+
+        view
+    }
+
+    /// The fault model this view is working under
+    pub fn fault_model(&self) -> FaultModel {
+        self.fault_model
+    }
+
+    /// The amount of matching prepares required for a replica to consider
+    /// a value as prepared, when collecting the data for a view change
+    pub fn prepared_quorum(&self) -> usize {
+        match self.fault_model {
+            FaultModel::Byzantine => self.params.f() << 1,
+            FaultModel::Crash | FaultModel::TrustedCounter => self.quorum(),
+        }
+    }
+
+    /// The vote weights of this view, if any were assigned
+    pub fn weights(&self) -> Option<&VoteWeights> {
+        self.weights.as_ref()
+    }
+
+    /// The vote weight of a given node. Nodes outside of the quorum have no weight.
+    /// When the view is not weighted, every member's vote is worth the same.
+    pub fn weight_of(&self, node: &NodeId) -> u64 {
+        if !self.quorum_members.contains(node) {
+            return 0;
+        }
+
+        match &self.weights {
+            None => 1,
+            Some(weights) => weights.weight_of(node),
+        }
+    }
+
+    /// The accumulated weight of a set of voters
+    pub fn weight_of_votes<'a>(&self, voters: impl IntoIterator<Item = &'a NodeId>) -> u64 {
+        voters.into_iter().map(|node| self.weight_of(node)).sum()
+    }
+
+    /// The weight that is required to form a quorum in this view
+    pub fn quorum_weight(&self) -> u64 {
+        match &self.weights {
+            None => self.quorum() as u64,
+            Some(weights) => weights.quorum_weight(),
+        }
+    }
+
+    /// The largest weight that can be held by the faulty replicas in this view
+    pub fn fault_weight(&self) -> u64 {
+        match &self.weights {
+            None => self.f() as u64,
+            Some(weights) => weights.fault_weight(),
+        }
+    }
+
+    /// Do the given voters form a quorum in this view?
+    pub fn is_quorum<'a>(&self, voters: impl IntoIterator<Item = &'a NodeId>) -> bool {
+        self.weight_of_votes(voters) >= self.quorum_weight()
+    }
+
+    /// The weight of the collects required to pick the value of a new view.
+    ///
+    /// Under the trusted counter model, the view change messages carry a unique
+    /// identifier as well, so a faulty replica can't hide the prepares it sent in its
+    /// collect and the f + 1 quorums of the normal case are enough.
+    pub fn view_change_quorum_weight(&self) -> u64 {
+        self.quorum_weight()
+    }
+
+    /// Do the given voters form a quorum of collects for a view change in this view?
+    pub fn is_view_change_quorum<'a>(&self, voters: impl IntoIterator<Item = &'a NodeId>) -> bool {
+        self.weight_of_votes(voters) >= self.view_change_quorum_weight()
+    }
+
+    /// Do the given voters contain at least one correct replica?
+    pub fn exceeds_fault_weight<'a>(&self, voters: impl IntoIterator<Item = &'a NodeId>) -> bool {
+        self.weight_of_votes(voters) > self.fault_weight()
     }
 
     /// Returns the primary of the current view.
@@ -200,6 +378,135 @@ impl ViewInfo {
     pub fn hash_space_division(&self) -> &BTreeMap<NodeId, (Vec<u8>, Vec<u8>)> {
         &self.leader_hash_space_division
     }
+
+    /// This same view, without the given nodes in its leader set.
+    /// The hash space is divided again between the remaining leaders.
+    ///
+    /// Returns `None` if no leader would be left.
+    pub fn without_leaders(&self, removed: &[NodeId]) -> Option<ViewInfo> {
+        let leader_set: Vec<NodeId> = self
+            .leader_set
+            .iter()
+            .filter(|leader| !removed.contains(leader))
+            .copied()
+            .collect();
+
+        if leader_set.is_empty() {
+            return None;
+        }
+
+        let mut view = self.clone();
+
+        view.leader_hash_space_division = calculate_hash_space_division(&leader_set);
+        view.leader_set = leader_set;
+
+        Some(view)
+    }
+}
+
+impl VoteWeights {
+    /// Create a new weight assignment, tolerating `f` faulty replicas.
+    ///
+    /// The faulty weight is the sum of the `f` largest weights, and the quorum
+    /// weight is the smallest weight such that any two quorums intersect in more than
+    /// the faulty weight. The correct replicas alone must be able to form a quorum.
+    pub fn new(weights: BTreeMap<NodeId, u64>, f: usize) -> Result<Self> {
+        Self::with_fault_model(weights, f, FaultModel::Byzantine)
+    }
+
+    /// Create a new weight assignment for the given fault model.
+    ///
+    /// Under the crash fault model, quorums only have to intersect (in any weight),
+    /// so the quorum weight is a simple majority of the total weight.
+    pub fn with_fault_model(
+        weights: BTreeMap<NodeId, u64>,
+        f: usize,
+        fault_model: FaultModel,
+    ) -> Result<Self> {
+        if let Some((node, _)) = weights.iter().find(|(_, weight)| **weight == 0) {
+            return Err!(ViewError::MissingVoteWeight(*node));
+        }
+
+        let mut sorted: Vec<u64> = weights.values().copied().collect();
+
+        sorted.sort_unstable_by(|a, b| b.cmp(a));
+
+        let total_weight: u64 = sorted.iter().sum();
+        let fault_weight: u64 = sorted.iter().take(f).sum();
+
+        let quorum_weight = match fault_model {
+            FaultModel::Byzantine => ((total_weight + fault_weight) / 2) + 1,
+            FaultModel::Crash => (total_weight / 2) + 1,
+            // Correct replicas can't equivocate, so a single correct voter suffices
+            FaultModel::TrustedCounter => fault_weight + 1,
+        };
+
+        if total_weight - fault_weight < quorum_weight {
+            return Err!(ViewError::UnavailableVoteWeights(
+                total_weight,
+                fault_weight,
+                quorum_weight
+            ));
+        }
+
+        Ok(Self {
+            weights,
+            quorum_weight,
+            fault_weight,
+        })
+    }
+
+    /// The weight assignment proposed by WHEAT.
+    ///
+    /// With `n = 3f + 1 + delta` replicas, the `2f` favoured replicas get a weight
+    /// of `1 + delta/f` and the rest a weight of `1`. (Weights are scaled by `f`
+    /// so they can be represented as integers)
+    pub fn wheat(quorum_members: &[NodeId], f: usize, favoured: &[NodeId]) -> Result<Self> {
+        let n = quorum_members.len();
+
+        if f == 0 || n <= 3 * f + 1 {
+            return Self::new(quorum_members.iter().map(|node| (*node, 1)).collect(), f);
+        }
+
+        if favoured.len() != 2 * f {
+            return Err!(ViewError::WrongFavouredReplicaCount(favoured.len(), 2 * f));
+        }
+
+        let delta = (n - (3 * f + 1)) as u64;
+
+        let min_weight = f as u64;
+        let max_weight = f as u64 + delta;
+
+        let weights = quorum_members
+            .iter()
+            .map(|node| {
+                if favoured.contains(node) {
+                    (*node, max_weight)
+                } else {
+                    (*node, min_weight)
+                }
+            })
+            .collect();
+
+        Self::new(weights, f)
+    }
+
+    /// The weight of a given node. Nodes that are not in the assignment have no weight.
+    pub fn weight_of(&self, node: &NodeId) -> u64 {
+        self.weights.get(node).copied().unwrap_or(0)
+    }
+
+    pub fn weights(&self) -> &BTreeMap<NodeId, u64> {
+        &self.weights
+    }
+
+    pub fn quorum_weight(&self) -> u64 {
+        self.quorum_weight
+    }
+
+    pub fn fault_weight(&self) -> u64 {
+        self.fault_weight
+    }
 }
 
 /// Get the division of hash spaces for a given leader_set
@@ -313,18 +620,161 @@ mod view_tests {
             );
         }
     }
+
+    #[test]
+    fn test_wheat_weight_assignment() {
+        use super::*;
+
+        let members: Vec<NodeId> = NodeId::targets_u32(0..5).collect();
+
+        let favoured = [members[0], members[1]];
+
+        let view = ViewInfo::from_quorum(SeqNo::ZERO, members.clone())
+            .unwrap()
+            .with_weights(VoteWeights::wheat(&members, 1, &favoured).unwrap())
+            .unwrap();
+
+        // The two favoured replicas and any other replica form a quorum
+        assert!(view.is_quorum(&[members[0], members[1], members[4]]));
+        // But the three remaining replicas do not
+        assert!(!view.is_quorum(&[members[2], members[3], members[4]]));
+        // The quorum is still available if a favoured replica is faulty
+        assert!(view.is_quorum(&[members[1], members[2], members[3], members[4]]));
+
+        // The weights are carried across views
+        assert_eq!(view.next_view().quorum_weight(), view.quorum_weight());
+    }
+
+    #[test]
+    fn test_crash_fault_model_quorums() {
+        use super::*;
+
+        let members: Vec<NodeId> = NodeId::targets_u32(0..3).collect();
+
+        let view =
+            ViewInfo::from_quorum_with_fault_model(SeqNo::ZERO, members, FaultModel::Crash)
+                .unwrap();
+
+        assert_eq!(view.f(), 1);
+        assert_eq!(view.quorum(), 2);
+        assert_eq!(view.next_view().fault_model(), FaultModel::Crash);
+    }
+
+    #[test]
+    fn test_trusted_counter_fault_model_quorums() {
+        use super::*;
+
+        let members: Vec<NodeId> = NodeId::targets_u32(0..5).collect();
+
+        let view = ViewInfo::from_quorum_with_fault_model(
+            SeqNo::ZERO,
+            members.clone(),
+            FaultModel::TrustedCounter,
+        )
+        .unwrap();
+
+        assert_eq!(view.f(), 2);
+        assert_eq!(view.quorum(), 3);
+        assert!(!view.fault_model().requires_commit_phase());
+
+        let weights = VoteWeights::with_fault_model(
+            members.iter().map(|n| (*n, 1)).collect(),
+            2,
+            FaultModel::TrustedCounter,
+        )
+        .unwrap();
+
+        assert_eq!(weights.quorum_weight(), 3);
+
+        // View changes are certified by the trusted counter, so a crashed leader can be replaced
+        assert_eq!(view.view_change_quorum_weight(), 3);
+        assert!(!view.is_view_change_quorum(&members[..2]));
+        assert!(view.is_view_change_quorum(&members[..3]));
+    }
+
+    #[test]
+    fn test_uniform_weights_match_count_quorums() {
+        use super::*;
+
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let members = view.quorum_members().clone();
+
+        let weighted = view
+            .clone()
+            .with_weights(VoteWeights::new(members.iter().map(|n| (*n, 1)).collect(), 1).unwrap())
+            .unwrap();
+
+        assert_eq!(view.quorum_weight(), weighted.quorum_weight());
+        assert_eq!(view.fault_weight(), weighted.fault_weight());
+    }
+
+    #[test]
+    fn test_non_members_have_no_weight() {
+        use super::*;
+
+        let view = ViewInfo::new(SeqNo::ZERO, 4, 1).unwrap();
+
+        let outsider = NodeId::from(10u32);
+
+        assert_eq!(view.weight_of(&outsider), 0);
+
+        let mut voters = view.quorum_members()[..2].to_vec();
+
+        voters.push(outsider);
+
+        // Votes from outside of the quorum do not count towards it
+        assert!(!view.is_quorum(&voters));
+    }
+
+    #[test]
+    fn test_joining_a_weighted_view_requires_new_weights() {
+        use super::*;
+
+        let members: Vec<NodeId> = NodeId::targets_u32(0..4).collect();
+
+        let view = ViewInfo::from_quorum(SeqNo::ZERO, members.clone())
+            .unwrap()
+            .with_weights(VoteWeights::new(members.iter().map(|n| (*n, 1)).collect(), 1).unwrap())
+            .unwrap();
+
+        let joined = NodeId::from(4u32);
+
+        assert!(view.next_view_with_new_node(joined, None).is_err());
+
+        let weights = VoteWeights::wheat(
+            &NodeId::targets_u32(0..5).collect::<Vec<_>>(),
+            1,
+            &[members[0], members[1]],
+        )
+        .unwrap();
+
+        let next_view = view
+            .next_view_with_new_node(joined, Some(weights.clone()))
+            .unwrap();
+
+        assert_eq!(next_view.weights(), Some(&weights));
+        assert!(next_view.weight_of(&joined) > 0);
+
+        // Unweighted views don't need an assignment
+        let unweighted = ViewInfo::from_quorum(SeqNo::ZERO, members).unwrap();
+
+        assert!(unweighted.next_view_with_new_node(joined, None).is_ok());
+    }
 }
 
 impl Debug for ViewInfo {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Seq: {:?}, quorum: {:?}, primary: {:?}, leader_set: {:?}, params: {:?}",
+            "Seq: {:?}, quorum: {:?}, primary: {:?}, leader_set: {:?}, params: {:?}, weights: {:?}, fault model: {:?}",
             self.seq,
             self.quorum_members,
             self.leader(),
             self.leader_set,
-            self.params
+            self.params,
+            self.weights,
+            self.fault_model
         )
     }
 }
@@ -333,4 +783,10 @@ impl Debug for ViewInfo {
 pub enum ViewError {
     #[error("Leader is not contained in the quorum participants. Leader {0:?}, quorum {1:?}")]
     LeaderNotInQuorum(NodeId, Vec<NodeId>),
+    #[error("Quorum member {0:?} has no vote weight assigned")]
+    MissingVoteWeight(NodeId),
+    #[error("Vote weights are not available. Total weight {0}, fault weight {1}, quorum weight {2}")]
+    UnavailableVoteWeights(u64, u64, u64),
+    #[error("Wrong number of favoured replicas {0}, expected {1}")]
+    WrongFavouredReplicaCount(usize, usize),
 }
@@ -0,0 +1,490 @@
+//! Stable checkpoint certificates.
+//!
+//! Once a replica finalizes a checkpoint, it signs the sequence number and the
+//! digest of the checkpointed state and sends that vote to the other replicas.
+//! A quorum of matching votes makes the checkpoint stable, and the signatures are
+//! kept as a certificate which is served along with the state, so a recovering
+//! replica can verify the state it receives from a single replica.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::io::{Read, Write};
+
+#[cfg(feature = "serialize_serde")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::crypto::signature::{KeyPair, PublicKey, Signature};
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::Err;
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
+use atlas_smr_application::state::monolithic_state::MonolithicState;
+use atlas_smr_core::state_transfer::Checkpoint;
+
+use crate::message::CheckpointVote;
+
+/// The most signers a stored certificate may have, so a corrupted one is not read for long
+const MAX_STORED_SIGNERS: usize = 1024;
+
+/// The longest signature a stored certificate may have
+const MAX_STORED_SIGNATURE_LENGTH: usize = 1024;
+
+/// A quorum of signatures over the sequence number and digest of a checkpoint
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct CheckpointCertificate {
+    seq: SeqNo,
+    digest: Digest,
+    /// The signatures of the replicas which agreed on the checkpoint, by signer
+    signatures: BTreeMap<NodeId, Signature>,
+}
+
+impl CheckpointCertificate {
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    /// The replicas which signed this certificate, in ascending order
+    pub fn signers(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.signatures.keys().copied()
+    }
+
+    /// Whether this certificate is for the given checkpoint
+    pub fn certifies<S>(&self, checkpoint: &Checkpoint<S>) -> bool {
+        self.is_for(checkpoint.sequence_number(), checkpoint.digest())
+    }
+
+    /// Whether this certificate is for the checkpoint with the given sequence number and digest
+    pub fn is_for(&self, seq: SeqNo, digest: &Digest) -> bool {
+        self.seq == seq && self.digest == *digest
+    }
+
+    /// Verify that a quorum of the members of the given view signed this certificate
+    pub fn verify<V, F>(&self, view: &V, public_key_of: F) -> Result<()>
+    where
+        V: NetworkView,
+        F: Fn(NodeId) -> Option<PublicKey>,
+    {
+        if self.signatures.len() < view.quorum() {
+            return Err!(CheckpointCertificateError::NotEnoughSigners(
+                self.signatures.len(),
+                view.quorum()
+            ));
+        }
+
+        let statement = checkpoint_statement(self.seq, &self.digest);
+
+        for (signer, signature) in &self.signatures {
+            if !view.quorum_members().contains(signer) {
+                return Err!(CheckpointCertificateError::NotAMember(*signer));
+            }
+
+            let public_key =
+                public_key_of(*signer).ok_or(CheckpointCertificateError::UnknownSigner(*signer))?;
+
+            if public_key.verify(statement.as_ref(), signature).is_err() {
+                return Err!(CheckpointCertificateError::InvalidSignature(*signer));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write this certificate in the format read by [CheckpointCertificate::read_from],
+    /// so it can be stored along with the checkpoint it certifies
+    pub fn write_to<W>(&self, mut w: W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_all(&u32::from(self.seq).to_le_bytes())?;
+        w.write_all(self.digest.as_ref())?;
+        w.write_all(&(self.signatures.len() as u32).to_le_bytes())?;
+
+        for (signer, signature) in &self.signatures {
+            let signature: &[u8] = signature.as_ref();
+
+            w.write_all(&u32::from(*signer).to_le_bytes())?;
+            w.write_all(&(signature.len() as u32).to_le_bytes())?;
+            w.write_all(signature)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a certificate written by [CheckpointCertificate::write_to].
+    /// The signatures still have to be verified
+    pub fn read_from<R>(mut r: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let seq = SeqNo::from(read_u32(&mut r)?);
+
+        let mut digest = [0; Digest::LENGTH];
+
+        r.read_exact(&mut digest)?;
+
+        let digest = Digest::from_bytes(&digest[..])?;
+
+        let signers = read_u32(&mut r)?;
+
+        if signers as usize > MAX_STORED_SIGNERS {
+            return Err!(CheckpointCertificateError::TooManySigners(signers as usize));
+        }
+
+        let mut signatures = BTreeMap::new();
+
+        for _ in 0..signers {
+            let signer = NodeId::from(read_u32(&mut r)?);
+
+            let length = read_u32(&mut r)? as usize;
+
+            if length > MAX_STORED_SIGNATURE_LENGTH {
+                return Err!(CheckpointCertificateError::InvalidSignatureLength(
+                    signer, length
+                ));
+            }
+
+            let mut signature = vec![0; length];
+
+            r.read_exact(&mut signature)?;
+
+            signatures.insert(signer, Signature::from_bytes(&signature[..])?);
+        }
+
+        Ok(Self {
+            seq,
+            digest,
+            signatures,
+        })
+    }
+}
+
+fn read_u32<R>(r: &mut R) -> Result<u32>
+where
+    R: Read,
+{
+    let mut bytes = [0; 4];
+
+    r.read_exact(&mut bytes)?;
+
+    Ok(u32::from_le_bytes(bytes))
+}
+
+impl Orderable for CheckpointCertificate {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+/// The latest checkpoint vote we have received from each replica.
+///
+/// Only the latest vote of each replica is kept, so a faulty replica
+/// can't make us buffer votes for any number of checkpoints.
+pub(crate) struct CheckpointVotes {
+    votes: BTreeMap<NodeId, CheckpointVote>,
+}
+
+impl CheckpointVotes {
+    pub(crate) fn new() -> Self {
+        Self {
+            votes: BTreeMap::new(),
+        }
+    }
+
+    /// Record an already verified vote.
+    ///
+    /// Returns false if we already have a vote from this replica for a later checkpoint
+    pub(crate) fn insert(&mut self, from: NodeId, vote: CheckpointVote) -> bool {
+        match self.votes.get(&from) {
+            Some(current) if current.sequence_number() > vote.sequence_number() => false,
+            _ => {
+                self.votes.insert(from, vote);
+
+                true
+            }
+        }
+    }
+
+    /// Forget the votes of the replicas which are not members of the view
+    pub(crate) fn retain_members(&mut self, members: &[NodeId]) {
+        self.votes.retain(|from, _| members.contains(from));
+    }
+
+    /// Build the certificate for the given checkpoint, if a quorum voted for it
+    pub(crate) fn certificate(
+        &self,
+        seq: SeqNo,
+        digest: &Digest,
+        quorum: usize,
+    ) -> Option<CheckpointCertificate> {
+        let signatures: BTreeMap<NodeId, Signature> = self
+            .votes
+            .iter()
+            .filter(|(_, vote)| vote.sequence_number() == seq && vote.digest() == digest)
+            .map(|(from, vote)| (*from, vote.signature().clone()))
+            .collect();
+
+        (signatures.len() >= quorum).then(|| CheckpointCertificate {
+            seq,
+            digest: *digest,
+            signatures,
+        })
+    }
+
+    /// A digest other than the given one which a quorum voted for at the given sequence number
+    pub(crate) fn conflicting(&self, seq: SeqNo, digest: &Digest, quorum: usize) -> Option<Digest> {
+        let mut counts: BTreeMap<Digest, usize> = BTreeMap::new();
+
+        self.votes
+            .values()
+            .filter(|vote| vote.sequence_number() == seq && vote.digest() != digest)
+            .for_each(|vote| *counts.entry(*vote.digest()).or_default() += 1);
+
+        counts
+            .into_iter()
+            .find(|(_, count)| *count >= quorum)
+            .map(|(digest, _)| digest)
+    }
+}
+
+/// The statement signed by a replica when voting for a checkpoint.
+///
+/// Unlike the signature in the message header, this does not depend on
+/// the destination of the message, so the votes can be gathered into a certificate.
+pub fn checkpoint_statement(seq: SeqNo, digest: &Digest) -> Digest {
+    let mut ctx = Context::new();
+
+    ctx.update(&u32::from(seq).to_le_bytes()[..]);
+    ctx.update(digest.as_ref());
+
+    ctx.finish()
+}
+
+/// Sign the checkpoint statement with our key pair
+pub fn sign_checkpoint(key_pair: &KeyPair, seq: SeqNo, digest: &Digest) -> Result<Signature> {
+    key_pair.sign(checkpoint_statement(seq, digest).as_ref())
+}
+
+/// Verify the signature of a checkpoint vote
+pub fn verify_vote(public_key: &PublicKey, vote: &CheckpointVote) -> Result<()> {
+    let statement = checkpoint_statement(vote.sequence_number(), vote.digest());
+
+    public_key.verify(statement.as_ref(), vote.signature())
+}
+
+/// The digest of an application state, as the state is serialized by the application.
+///
+/// The checkpoints given to the state transfer must carry this digest, as it is the one
+/// replicas vote on and the one the states received from other replicas are checked against.
+pub fn digest_state<S>(state: &S) -> Result<Digest>
+where
+    S: MonolithicState,
+{
+    let mut serialized = Vec::new();
+
+    S::serialize_state(&mut serialized, state)?;
+
+    let mut ctx = Context::new();
+
+    ctx.update(&serialized);
+
+    Ok(ctx.finish())
+}
+
+impl Debug for CheckpointCertificate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CheckpointCertificate {{ seq: {:?}, digest: {:?}, signers: {:?} }}",
+            self.seq,
+            self.digest,
+            self.signatures.keys()
+        )
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointCertificateError {
+    #[error("The certificate has {0} signers but a quorum is {1}")]
+    NotEnoughSigners(usize, usize),
+    #[error("The signer {0:?} is not a member of the view")]
+    NotAMember(NodeId),
+    #[error("Unknown signer {0:?}")]
+    UnknownSigner(NodeId),
+    #[error("Invalid checkpoint signature from {0:?}")]
+    InvalidSignature(NodeId),
+    #[error("The stored certificate has {0} signers")]
+    TooManySigners(usize),
+    #[error("The stored signature of {0:?} is {1} bytes long")]
+    InvalidSignatureLength(NodeId, usize),
+}
+
+#[cfg(test)]
+pub(crate) mod checkpoint_votes_tests {
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::crypto::signature::{KeyPair, PublicKey};
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::{Orderable, SeqNo};
+    use atlas_core::ordering_protocol::networking::serialize::NetworkView;
+    #[cfg(feature = "serialize_serde")]
+    use serde::{Deserialize, Serialize};
+
+    use crate::message::CheckpointVote;
+
+    use super::{sign_checkpoint, CheckpointCertificateError, CheckpointVotes};
+
+    /// A view of n = 3f + 1 replicas, with ids 0 to n - 1
+    #[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+    #[derive(Clone, Debug)]
+    pub(crate) struct TestView {
+        members: Vec<NodeId>,
+        f: usize,
+    }
+
+    impl TestView {
+        pub(crate) fn new(f: usize) -> Self {
+            Self {
+                members: (0..3 * f as u32 + 1).map(NodeId::from).collect(),
+                f,
+            }
+        }
+    }
+
+    impl Orderable for TestView {
+        fn sequence_number(&self) -> SeqNo {
+            SeqNo::ZERO
+        }
+    }
+
+    impl NetworkView for TestView {
+        fn primary(&self) -> NodeId {
+            self.members[0]
+        }
+
+        fn quorum(&self) -> usize {
+            2 * self.f + 1
+        }
+
+        fn quorum_members(&self) -> &Vec<NodeId> {
+            &self.members
+        }
+
+        fn f(&self) -> usize {
+            self.f
+        }
+
+        fn n(&self) -> usize {
+            self.members.len()
+        }
+    }
+
+    fn digest(byte: u8) -> Digest {
+        Digest::from_bytes(&[byte; Digest::LENGTH]).unwrap()
+    }
+
+    fn key_pair(node: u32) -> KeyPair {
+        KeyPair::from_bytes(&[node as u8 + 1; 32][..]).unwrap()
+    }
+
+    fn public_key_of(node: NodeId) -> Option<PublicKey> {
+        Some(PublicKey::from(key_pair(u32::from(node)).public_key()))
+    }
+
+    fn vote(node: u32, seq: u32, value: u8) -> CheckpointVote {
+        let (seq, digest) = (SeqNo::from(seq), digest(value));
+
+        let signature = sign_checkpoint(&key_pair(node), seq, &digest).unwrap();
+
+        CheckpointVote::new(seq, digest, signature)
+    }
+
+    fn votes_of(votes: &[(u32, u32, u8)]) -> CheckpointVotes {
+        let mut checkpoint_votes = CheckpointVotes::new();
+
+        for (node, seq, value) in votes {
+            assert!(checkpoint_votes.insert(NodeId::from(*node), vote(*node, *seq, *value)));
+        }
+
+        checkpoint_votes
+    }
+
+    #[test]
+    fn test_certificate_needs_a_quorum_of_matching_votes() {
+        let view = TestView::new(1);
+        let seq = SeqNo::from(10);
+
+        let votes = votes_of(&[(0, 10, 1), (1, 10, 1), (2, 10, 2), (3, 20, 1)]);
+
+        assert!(votes.certificate(seq, &digest(1), view.quorum()).is_none());
+
+        let votes = votes_of(&[(0, 10, 1), (1, 10, 1), (2, 10, 2), (3, 10, 1)]);
+
+        let certificate = votes.certificate(seq, &digest(1), view.quorum()).unwrap();
+
+        assert!(certificate.is_for(seq, &digest(1)));
+        assert_eq!(
+            certificate.signers().collect::<Vec<_>>(),
+            vec![NodeId::from(0u32), NodeId::from(1u32), NodeId::from(3u32)]
+        );
+        assert!(certificate.verify(&view, public_key_of).is_ok());
+    }
+
+    #[test]
+    fn test_only_the_latest_vote_of_each_replica_counts() {
+        let view = TestView::new(1);
+
+        let mut votes = votes_of(&[(0, 10, 1), (1, 10, 1), (2, 10, 1)]);
+
+        assert!(votes
+            .certificate(SeqNo::from(10), &digest(1), view.quorum())
+            .is_some());
+
+        assert!(votes.insert(NodeId::from(2u32), vote(2, 20, 2)));
+        assert!(!votes.insert(NodeId::from(2u32), vote(2, 10, 1)));
+
+        assert!(votes
+            .certificate(SeqNo::from(10), &digest(1), view.quorum())
+            .is_none());
+    }
+
+    #[test]
+    fn test_conflicting_quorums_are_detected() {
+        let view = TestView::new(1);
+        let seq = SeqNo::from(10);
+
+        let votes = votes_of(&[(0, 10, 2), (1, 10, 2), (2, 10, 2), (3, 10, 1)]);
+
+        assert_eq!(
+            votes.conflicting(seq, &digest(1), view.quorum()),
+            Some(digest(2))
+        );
+        assert_eq!(votes.conflicting(seq, &digest(2), view.quorum()), None);
+    }
+
+    #[test]
+    fn test_votes_of_former_members_are_forgotten() {
+        let view = TestView::new(1);
+        let seq = SeqNo::from(10);
+
+        let mut votes = votes_of(&[(0, 10, 1), (1, 10, 1), (4, 10, 1)]);
+
+        let certificate = votes.certificate(seq, &digest(1), view.quorum()).unwrap();
+
+        let err = certificate
+            .verify(&view, public_key_of)
+            .unwrap_err()
+            .downcast::<CheckpointCertificateError>()
+            .unwrap();
+
+        assert!(
+            matches!(err, CheckpointCertificateError::NotAMember(node) if node == NodeId::from(4u32))
+        );
+
+        votes.retain_members(view.quorum_members());
+
+        assert!(votes.certificate(seq, &digest(1), view.quorum()).is_none());
+    }
+}
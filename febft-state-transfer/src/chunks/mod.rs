@@ -0,0 +1,1026 @@
+//! Transferring checkpoints in chunks.
+//!
+//! The serving replica serializes the checkpointed state once, to a spool file rather
+//! than to memory so it does not keep a second copy of the state around, and describes
+//! it in a [StateManifest], which carries the digest of every chunk. The recovering
+//! replica verifies each chunk against the manifest as it arrives, and keeps the
+//! ones it already has when the transfer is interrupted. The assembled state is then
+//! checked against the digest of the whole checkpoint, which is what the replicas agreed on.
+//!
+//! The digests of the chunks are not covered by the agreed digest, so a manifest is
+//! only trusted once f+1 replicas handed out the very same one. Until then, chunks are
+//! only fetched from the replica which sent the manifest, as a chunk which does not
+//! match it may just as well mean the manifest is wrong. Once confirmed, chunks are
+//! fetched from every replica which has the state at once.
+//!
+//! A replica which fell behind for a little while likely has most of the state
+//! already, in its own last checkpoint. Any chunk whose digest matches one of the
+//! chunks of that checkpoint is taken from it instead of being fetched, so only the
+//! difference between the two checkpoints goes over the network.
+//!
+//! For that to work when bytes are inserted into or removed from the serialized state,
+//! the state is cut into chunks by its content rather than at fixed offsets: a chunk
+//! ends wherever a rolling hash of the last few bytes hits a given pattern, so an edit
+//! only changes the chunks around it and the boundaries resynchronize right after.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use thiserror::Error;
+use tracing::warn;
+
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::Err;
+use atlas_smr_application::state::monolithic_state::MonolithicState;
+use atlas_smr_core::state_transfer::Checkpoint;
+
+use crate::certificate::CheckpointCertificate;
+use crate::message::{StateChunk, StateManifest};
+
+/// The most chunks a replica will serve in reply to a single request
+pub const MAX_CHUNKS_PER_REQUEST: usize = 64;
+
+/// The most chunks a manifest describes. Larger states are split into larger chunks
+pub const MAX_MANIFEST_CHUNKS: usize = 1 << 16;
+
+/// How much smaller and larger than the average chunk size a chunk may be
+const CHUNK_SIZE_SPREAD: usize = 4;
+
+/// The random values mixed into the rolling hash, one for each byte value
+const GEAR: [u64; 256] = gear_table();
+
+/// Tells apart the spool files of the checkpoints served by this process
+static SPOOL_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// One of our checkpoints, serialized so it can be served in chunks.
+/// Only the serialized state is kept, in a spool file, not the checkpoint itself
+pub struct ServedState<S> {
+    seq: SeqNo,
+    digest: Digest,
+    certificate: Option<CheckpointCertificate>,
+    /// The serialized state, which is removed once we no longer serve it
+    spool: File,
+    spool_path: PathBuf,
+    size: u64,
+    /// The ways the state was split so far, by average chunk size
+    chunkings: BTreeMap<usize, Chunking>,
+    _state: PhantomData<fn() -> S>,
+}
+
+/// The state split into chunks of a given average size
+struct Chunking {
+    chunks: Vec<Digest>,
+    lengths: Vec<u64>,
+    /// The offset at which each chunk ends
+    ends: Vec<u64>,
+}
+
+impl<S> ServedState<S>
+where
+    S: MonolithicState,
+{
+    /// Serialize the checkpoint to a spool file in the given directory
+    pub fn new(
+        checkpoint: &Checkpoint<S>,
+        certificate: Option<CheckpointCertificate>,
+        spool_dir: &Path,
+    ) -> Result<Self> {
+        let spool_path = spool_dir.join(format!(
+            "checkpoint-{}-{}-{}.state",
+            std::process::id(),
+            u32::from(checkpoint.sequence_number()),
+            SPOOL_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let spool = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&spool_path)?;
+
+        // The file is removed when dropped, so it is not left behind if the serialization fails
+        let mut served = Self {
+            seq: checkpoint.sequence_number(),
+            digest: *checkpoint.digest(),
+            certificate: certificate.filter(|certificate| certificate.certifies(checkpoint)),
+            spool,
+            spool_path,
+            size: 0,
+            chunkings: BTreeMap::new(),
+            _state: PhantomData,
+        };
+
+        let mut writer = BufWriter::new(&served.spool);
+
+        S::serialize_state(&mut writer, checkpoint.state())?;
+
+        writer.flush()?;
+
+        drop(writer);
+
+        served.size = served.spool.metadata()?.len();
+
+        Ok(served)
+    }
+}
+
+impl<S> ServedState<S> {
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    pub fn certificate(&self) -> Option<&CheckpointCertificate> {
+        self.certificate.as_ref()
+    }
+
+    /// Keep the certificate of the served checkpoint, once it became stable,
+    /// so it is handed out along with the manifests from now on
+    pub fn certify(&mut self, certificate: &CheckpointCertificate) {
+        if certificate.sequence_number() == self.seq && *certificate.digest() == self.digest {
+            self.certificate = Some(certificate.clone());
+        }
+    }
+
+    /// The size of the serialized state, in bytes
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    /// Whether the state fits in a single chunk of the given size, so it can just be sent whole
+    pub fn fits_in_chunk(&self, chunk_size: usize) -> bool {
+        self.size <= chunk_size as u64
+    }
+
+    /// The manifest of the state when split into chunks of (about) the given size
+    pub fn manifest(&mut self, chunk_size: usize) -> Result<StateManifest> {
+        // Every chunk is at least a fraction of the average size, which bounds how many there are
+        let average = chunk_size
+            .max(CHUNK_SIZE_SPREAD * self.size().div_ceil(MAX_MANIFEST_CHUNKS))
+            .max(CHUNK_SIZE_SPREAD)
+            .next_power_of_two();
+
+        let chunking = self.chunking(average)?;
+
+        Ok(StateManifest::new(
+            self.seq,
+            self.digest,
+            self.size,
+            average as u64,
+            chunking.chunks.clone(),
+            chunking.lengths.clone(),
+            self.certificate.clone(),
+        ))
+    }
+
+    /// The chunk with the given index, when split into chunks of the given average size.
+    /// The requester may follow the manifest of a replica configured with another chunk size
+    pub fn chunk(&mut self, index: u32, chunk_size: u64) -> Result<Option<StateChunk>> {
+        // Manifests only use powers of two, which also bounds how many ways a requester can make us split the state
+        let Some(average) = usize::try_from(chunk_size)
+            .ok()
+            .filter(|size| *size >= CHUNK_SIZE_SPREAD && size.is_power_of_two())
+        else {
+            return Ok(None);
+        };
+
+        let chunking = self.chunking(average)?;
+
+        let Some(end) = chunking.ends.get(index as usize).copied() else {
+            return Ok(None);
+        };
+
+        let start = (index as usize)
+            .checked_sub(1)
+            .map_or(0, |prev| chunking.ends[prev]);
+
+        let data = self.read(start, end)?;
+
+        Ok(Some(StateChunk::new(self.seq, index, data)))
+    }
+
+    /// Read the given range of the serialized state back from the spool file
+    fn read(&self, start: u64, end: u64) -> Result<Vec<u8>> {
+        let mut spool = &self.spool;
+
+        spool.seek(SeekFrom::Start(start))?;
+
+        let mut data = vec![0; (end - start) as usize];
+
+        spool.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// The state split into chunks of the given average size, which must be a power of two.
+    /// The first time, this reads the whole serialized state back to find the cut points
+    fn chunking(&mut self, average: usize) -> Result<&Chunking> {
+        if !self.chunkings.contains_key(&average) {
+            let mut spool = &self.spool;
+
+            spool.seek(SeekFrom::Start(0))?;
+
+            let mut reader = BufReader::new(spool);
+
+            let mut chunker = Chunker::new(average);
+
+            let mut buf = vec![0; 64 * 1024];
+
+            loop {
+                let read = reader.read(&mut buf)?;
+
+                if read == 0 {
+                    break;
+                }
+
+                chunker.update(&buf[..read]);
+            }
+
+            let (ends, chunks, lengths) = chunker.finish();
+
+            self.chunkings.insert(
+                average,
+                Chunking {
+                    chunks,
+                    lengths,
+                    ends,
+                },
+            );
+        }
+
+        Ok(&self.chunkings[&average])
+    }
+}
+
+impl<S> Orderable for ServedState<S> {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl<S> Drop for ServedState<S> {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.spool_path) {
+            warn!(
+                "Failed to remove the spool file {:?} of checkpoint {:?}: {:?}",
+                self.spool_path, self.seq, err
+            );
+        }
+    }
+}
+
+/// A checkpoint we are receiving in chunks
+pub(crate) struct ChunkedTransfer {
+    manifest: StateManifest,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    /// The chunks we requested and have not received yet, along with who we requested them from
+    in_flight: BTreeMap<u32, NodeId>,
+    /// The replicas which delivered a chunk since the last timeout
+    delivered: BTreeSet<NodeId>,
+    /// The replica whose manifest we follow, and the only one we fetch from until it is confirmed
+    provider: NodeId,
+    /// The replicas which handed out this same manifest
+    vouchers: BTreeSet<NodeId>,
+    /// How many replicas must hand out the same manifest for it to be trusted
+    required_vouchers: usize,
+}
+
+impl ChunkedTransfer {
+    /// Start receiving the state described by the manifest the given replica sent.
+    /// The manifest is confirmed once `required_vouchers` replicas handed out the same one
+    pub(crate) fn new(
+        manifest: StateManifest,
+        provider: NodeId,
+        required_vouchers: usize,
+    ) -> Result<Self> {
+        let average = manifest.chunk_size();
+
+        if average < CHUNK_SIZE_SPREAD as u64 || !average.is_power_of_two() {
+            return Err!(ChunkError::InvalidChunkSize(average));
+        }
+
+        if manifest.chunks().len() != manifest.lengths().len()
+            || manifest.chunks().len() > MAX_MANIFEST_CHUNKS
+        {
+            return Err!(ChunkError::ChunkCountMismatch(
+                manifest.chunks().len(),
+                manifest.lengths().len()
+            ));
+        }
+
+        let max_length = average * CHUNK_SIZE_SPREAD as u64;
+
+        if manifest
+            .lengths()
+            .iter()
+            .any(|length| *length == 0 || *length > max_length)
+            || manifest.lengths().iter().sum::<u64>() != manifest.size()
+        {
+            return Err!(ChunkError::InvalidChunkLengths(manifest.sequence_number()));
+        }
+
+        Ok(Self {
+            chunks: vec![None; manifest.chunks().len()],
+            manifest,
+            received: 0,
+            in_flight: BTreeMap::new(),
+            delivered: BTreeSet::new(),
+            provider,
+            vouchers: BTreeSet::from([provider]),
+            required_vouchers,
+        })
+    }
+
+    pub(crate) fn manifest(&self) -> &StateManifest {
+        &self.manifest
+    }
+
+    /// Whether this transfer can be resumed with the given manifest
+    pub(crate) fn resumable_with(&self, manifest: &StateManifest) -> bool {
+        self.manifest.sequence_number() == manifest.sequence_number()
+            && self.manifest.digest() == manifest.digest()
+            && self.manifest.chunk_size() == manifest.chunk_size()
+            && self.manifest.chunks() == manifest.chunks()
+            && self.manifest.lengths() == manifest.lengths()
+    }
+
+    /// Record that the given replica handed out a manifest, returning whether it matches ours
+    pub(crate) fn vouch(&mut self, peer: NodeId, manifest: &StateManifest) -> bool {
+        if !self.resumable_with(manifest) {
+            return false;
+        }
+
+        self.vouchers.insert(peer);
+
+        true
+    }
+
+    /// Resume the transfer with the same manifest, now sent by the given replica
+    pub(crate) fn resume_from(&mut self, peer: NodeId) {
+        self.vouchers.insert(peer);
+        self.provider = peer;
+
+        self.release_all();
+    }
+
+    /// The replica whose manifest we follow
+    pub(crate) fn provider(&self) -> NodeId {
+        self.provider
+    }
+
+    /// Whether enough replicas handed out the same manifest for at least one correct one to vouch for it
+    pub(crate) fn confirmed(&self) -> bool {
+        self.vouchers.len() >= self.required_vouchers
+    }
+
+    /// Whether we may fetch chunks from the given replica
+    pub(crate) fn fetches_from(&self, peer: NodeId) -> bool {
+        peer == self.provider || self.confirmed()
+    }
+
+    pub(crate) fn received(&self) -> usize {
+        self.received
+    }
+
+    pub(crate) fn total(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.received == self.chunks.len()
+    }
+
+    /// Take every chunk we are missing which the given checkpoint of ours also has,
+    /// returning how many were taken
+    pub(crate) fn reuse<S>(&mut self, base: &mut ServedState<S>) -> Result<usize> {
+        let chunking = base.chunking(self.manifest.chunk_size() as usize)?;
+
+        let mut start = 0;
+
+        let local: BTreeMap<Digest, (u64, u64)> = chunking
+            .chunks
+            .iter()
+            .zip(&chunking.ends)
+            .map(|(digest, end)| {
+                let range = (start, *end);
+
+                start = *end;
+
+                (*digest, range)
+            })
+            .collect();
+
+        let mut reused = 0;
+
+        for (slot, digest) in self.chunks.iter_mut().zip(self.manifest.chunks()) {
+            if slot.is_some() {
+                continue;
+            }
+
+            if let Some((start, end)) = local.get(digest) {
+                *slot = Some(base.read(*start, *end)?);
+                reused += 1;
+            }
+        }
+
+        self.received += reused;
+
+        Ok(reused)
+    }
+
+    /// Store a chunk we requested from the given replica, after verifying it
+    pub(crate) fn receive(
+        &mut self,
+        from: NodeId,
+        chunk: StateChunk,
+    ) -> std::result::Result<(), ChunkError> {
+        let index = chunk.index();
+
+        if chunk.sequence_number() != self.manifest.sequence_number()
+            || self.in_flight.get(&index) != Some(&from)
+        {
+            return Err(ChunkError::NotRequested(index, from));
+        }
+
+        let expected = self
+            .manifest
+            .chunks()
+            .get(index as usize)
+            .ok_or(ChunkError::NotRequested(index, from))?;
+
+        if digest_chunk(chunk.data()) != *expected {
+            return Err(ChunkError::InvalidChunk(index, from));
+        }
+
+        self.in_flight.remove(&index);
+        self.delivered.insert(from);
+
+        let slot = &mut self.chunks[index as usize];
+
+        if slot.is_none() {
+            *slot = Some(chunk.into_data());
+            self.received += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Pick the next missing chunks to request from the given replica, so it has
+    /// at most `in_flight` chunks requested at a time, and mark them as requested.
+    /// Nothing is assigned to replicas we may not fetch from yet
+    pub(crate) fn assign(&mut self, peer: NodeId, in_flight: usize) -> Vec<u32> {
+        if !self.fetches_from(peer) {
+            return Vec::new();
+        }
+
+        let already_assigned = self.in_flight.values().filter(|node| **node == peer).count();
+
+        let available = in_flight
+            .min(MAX_CHUNKS_PER_REQUEST)
+            .saturating_sub(already_assigned);
+
+        let assigned: Vec<u32> = (0..self.chunks.len() as u32)
+            .filter(|index| {
+                self.chunks[*index as usize].is_none() && !self.in_flight.contains_key(index)
+            })
+            .take(available)
+            .collect();
+
+        for index in &assigned {
+            self.in_flight.insert(*index, peer);
+        }
+
+        assigned
+    }
+
+    /// Forget the chunks requested from the given replica, so they can be requested from others
+    pub(crate) fn release(&mut self, peer: NodeId) {
+        self.in_flight.retain(|_, node| *node != peer);
+    }
+
+    /// Forget every requested chunk, as we are starting over with new requests
+    pub(crate) fn release_all(&mut self) {
+        self.in_flight.clear();
+        self.delivered.clear();
+    }
+
+    /// The replicas which have chunks requested but delivered none since the last timeout.
+    /// This also starts a new period for the next timeout
+    pub(crate) fn take_stalled(&mut self) -> BTreeSet<NodeId> {
+        let stalled = self
+            .in_flight
+            .values()
+            .filter(|node| !self.delivered.contains(node))
+            .copied()
+            .collect();
+
+        self.delivered.clear();
+
+        stalled
+    }
+
+    /// Put the chunks back together, checking them against the digest of the whole checkpoint
+    pub(crate) fn assemble(self) -> Result<Vec<u8>> {
+        let size = self.chunks.iter().flatten().map(Vec::len).sum();
+
+        let mut serialized = Vec::with_capacity(size);
+
+        for chunk in self.chunks {
+            let chunk = chunk.ok_or(ChunkError::Incomplete)?;
+
+            serialized.extend_from_slice(&chunk);
+        }
+
+        if digest_chunk(&serialized) != *self.manifest.digest() {
+            return Err!(ChunkError::InvalidState(self.manifest.sequence_number()));
+        }
+
+        Ok(serialized)
+    }
+}
+
+/// Cuts a serialized state into chunks of about the given average size, which must be a
+/// power of two, as it is read. A chunk ends after a byte at which the rolling hash has
+/// its top bits clear, so the boundaries only depend on the bytes right before them
+struct Chunker {
+    min_length: usize,
+    max_length: usize,
+    mask: u64,
+    hash: u64,
+    /// The length of the chunk being cut so far
+    length: usize,
+    ctx: Context,
+    offset: u64,
+    ends: Vec<u64>,
+    digests: Vec<Digest>,
+    lengths: Vec<u64>,
+}
+
+impl Chunker {
+    fn new(average: usize) -> Self {
+        let bits = average.trailing_zeros();
+
+        Self {
+            min_length: average / CHUNK_SIZE_SPREAD,
+            max_length: average * CHUNK_SIZE_SPREAD,
+            mask: u64::MAX.checked_shl(64 - bits).unwrap_or(0),
+            hash: 0,
+            length: 0,
+            ctx: Context::new(),
+            offset: 0,
+            ends: Vec::new(),
+            digests: Vec::new(),
+            lengths: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        // The start of the bytes of the current chunk which were not yet digested
+        let mut pending = 0;
+
+        for (at, byte) in data.iter().enumerate() {
+            let position = self.length;
+
+            self.length += 1;
+
+            let cut = if position >= self.min_length {
+                self.hash = (self.hash << 1).wrapping_add(GEAR[*byte as usize]);
+
+                self.hash & self.mask == 0
+            } else {
+                false
+            };
+
+            if cut || self.length == self.max_length {
+                self.ctx.update(&data[pending..=at]);
+
+                pending = at + 1;
+
+                self.cut();
+            }
+        }
+
+        self.ctx.update(&data[pending..]);
+    }
+
+    fn cut(&mut self) {
+        let ctx = std::mem::replace(&mut self.ctx, Context::new());
+
+        self.offset += self.length as u64;
+
+        self.ends.push(self.offset);
+        self.digests.push(ctx.finish());
+        self.lengths.push(self.length as u64);
+
+        self.hash = 0;
+        self.length = 0;
+    }
+
+    /// The offset at which each chunk ends, along with their digests and lengths
+    fn finish(mut self) -> (Vec<u64>, Vec<Digest>, Vec<u64>) {
+        if self.length > 0 {
+            self.cut();
+        }
+
+        (self.ends, self.digests, self.lengths)
+    }
+}
+
+/// Pseudo random values for the rolling hash, which must be the same on every replica
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0; 256];
+
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    let mut i = 0;
+
+    while i < table.len() {
+        // splitmix64
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        table[i] = z ^ (z >> 31);
+
+        i += 1;
+    }
+
+    table
+}
+
+fn digest_chunk(data: &[u8]) -> Digest {
+    let mut ctx = Context::new();
+
+    ctx.update(data);
+
+    ctx.finish()
+}
+
+#[derive(Error, Debug)]
+pub enum ChunkError {
+    #[error("The manifest has an average chunk size of {0}, which is not a power of two of at least 4")]
+    InvalidChunkSize(u64),
+    #[error("The manifest has {0} chunk digests but {1} chunk lengths")]
+    ChunkCountMismatch(usize, usize),
+    #[error("The chunk lengths of the manifest of checkpoint {0:?} do not add up to its size")]
+    InvalidChunkLengths(SeqNo),
+    #[error("Chunk {0} was not requested from {1:?}")]
+    NotRequested(u32, NodeId),
+    #[error("Chunk {0} from {1:?} does not match its digest")]
+    InvalidChunk(u32, NodeId),
+    #[error("Not every chunk has been received")]
+    Incomplete,
+    #[error("The assembled state does not match the digest of checkpoint {0:?}")]
+    InvalidState(SeqNo),
+}
+
+#[cfg(test)]
+mod chunks_tests {
+    use std::collections::BTreeSet;
+
+    use atlas_common::crypto::hash::Digest;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::message::{StateChunk, StateManifest};
+
+    use super::{digest_chunk, ChunkError, ChunkedTransfer, Chunker};
+
+    const AVERAGE: usize = 64;
+
+    /// Pseudo random bytes, so the content defined boundaries fall at varied offsets
+    fn data(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed | 1;
+
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+
+                state as u8
+            })
+            .collect()
+    }
+
+    fn node(id: u32) -> NodeId {
+        NodeId::from(id)
+    }
+
+    fn chunking(data: &[u8], average: usize) -> (Vec<u64>, Vec<Digest>, Vec<u64>) {
+        let mut chunker = Chunker::new(average);
+
+        chunker.update(data);
+
+        chunker.finish()
+    }
+
+    fn manifest(data: &[u8]) -> StateManifest {
+        let (_, chunks, lengths) = chunking(data, AVERAGE);
+
+        StateManifest::new(
+            SeqNo::ONE,
+            digest_chunk(data),
+            data.len() as u64,
+            AVERAGE as u64,
+            chunks,
+            lengths,
+            None,
+        )
+    }
+
+    fn other_manifest() -> StateManifest {
+        manifest(&data(4096, 3))
+    }
+
+    fn chunk(data: &[u8], manifest: &StateManifest, index: u32) -> StateChunk {
+        let start = manifest.lengths()[..index as usize].iter().sum::<u64>() as usize;
+        let end = start + manifest.lengths()[index as usize] as usize;
+
+        StateChunk::new(SeqNo::ONE, index, data[start..end].to_vec())
+    }
+
+    /// Fetch every missing chunk from the given replica
+    fn receive_all(
+        transfer: &mut ChunkedTransfer,
+        data: &[u8],
+        manifest: &StateManifest,
+        peer: NodeId,
+    ) {
+        while !transfer.is_complete() {
+            for index in transfer.assign(peer, 16) {
+                transfer
+                    .receive(peer, chunk(data, manifest, index))
+                    .unwrap();
+            }
+        }
+    }
+
+    fn invalid_manifest(manifest: StateManifest) -> ChunkError {
+        ChunkedTransfer::new(manifest, node(0), 2)
+            .err()
+            .unwrap()
+            .downcast::<ChunkError>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_manifests_which_do_not_describe_the_state_are_rejected() {
+        let data = data(4096, 1);
+        let valid = manifest(&data);
+
+        let with = |chunk_size: u64, chunks: Vec<Digest>, lengths: Vec<u64>| {
+            StateManifest::new(
+                SeqNo::ONE,
+                *valid.digest(),
+                valid.size(),
+                chunk_size,
+                chunks,
+                lengths,
+                None,
+            )
+        };
+
+        let err = invalid_manifest(with(48, valid.chunks().to_vec(), valid.lengths().to_vec()));
+        assert!(matches!(err, ChunkError::InvalidChunkSize(48)));
+
+        let err = invalid_manifest(with(
+            AVERAGE as u64,
+            valid.chunks()[1..].to_vec(),
+            valid.lengths().to_vec(),
+        ));
+        assert!(matches!(err, ChunkError::ChunkCountMismatch(_, _)));
+
+        // The lengths no longer add up to the size
+        let mut lengths = valid.lengths().to_vec();
+        lengths[0] -= 1;
+
+        let err = invalid_manifest(with(AVERAGE as u64, valid.chunks().to_vec(), lengths));
+        assert!(matches!(err, ChunkError::InvalidChunkLengths(_)));
+
+        // A single chunk larger than any the chunker cuts
+        let err = invalid_manifest(with(
+            AVERAGE as u64,
+            vec![digest_chunk(&data)],
+            vec![data.len() as u64],
+        ));
+        assert!(matches!(err, ChunkError::InvalidChunkLengths(_)));
+
+        assert!(ChunkedTransfer::new(valid, node(0), 2).is_ok());
+    }
+
+    #[test]
+    fn test_chunks_are_only_fetched_from_the_provider_until_f_plus_one_vouch() {
+        let data = data(4096, 2);
+        let manifest = manifest(&data);
+
+        let mut transfer = ChunkedTransfer::new(manifest.clone(), node(0), 2).unwrap();
+
+        assert!(!transfer.confirmed());
+        assert!(transfer.assign(node(1), 4).is_empty());
+        assert_eq!(transfer.assign(node(0), 4), vec![0, 1, 2, 3]);
+
+        // A replica handing out another manifest does not vouch for ours
+        assert!(!transfer.vouch(node(1), &other_manifest()));
+        assert!(!transfer.confirmed());
+        assert!(transfer.assign(node(1), 4).is_empty());
+
+        assert!(transfer.vouch(node(2), &manifest));
+        assert!(transfer.confirmed());
+        assert_eq!(transfer.assign(node(1), 4), vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_only_requested_chunks_matching_the_manifest_are_received() {
+        let data = data(4096, 4);
+        let manifest = manifest(&data);
+
+        let mut transfer = ChunkedTransfer::new(manifest.clone(), node(0), 1).unwrap();
+
+        let assigned = transfer.assign(node(0), 2);
+
+        assert!(matches!(
+            transfer.receive(node(1), chunk(&data, &manifest, assigned[0])),
+            Err(ChunkError::NotRequested(_, _))
+        ));
+
+        let mut tampered = chunk(&data, &manifest, assigned[0]).into_data();
+        tampered[0] ^= 1;
+
+        assert!(matches!(
+            transfer.receive(node(0), StateChunk::new(SeqNo::ONE, assigned[0], tampered)),
+            Err(ChunkError::InvalidChunk(_, _))
+        ));
+
+        for index in assigned {
+            transfer
+                .receive(node(0), chunk(&data, &manifest, index))
+                .unwrap();
+        }
+
+        assert_eq!(transfer.received(), 2);
+
+        receive_all(&mut transfer, &data, &manifest, node(0));
+
+        assert_eq!(transfer.assemble().unwrap(), data);
+    }
+
+    #[test]
+    fn test_interrupted_transfers_resume_with_the_chunks_received_so_far() {
+        let data = data(4096, 5);
+        let manifest = manifest(&data);
+
+        let mut transfer = ChunkedTransfer::new(manifest.clone(), node(0), 2).unwrap();
+
+        let assigned = transfer.assign(node(0), 4);
+
+        for index in &assigned[..2] {
+            transfer
+                .receive(node(0), chunk(&data, &manifest, *index))
+                .unwrap();
+        }
+
+        assert_eq!(transfer.take_stalled().len(), 0);
+        assert_eq!(transfer.take_stalled(), BTreeSet::from([node(0)]));
+
+        // The provider went silent, so another replica with the same manifest takes over
+        assert!(transfer.resumable_with(&manifest));
+        assert!(!transfer.resumable_with(&other_manifest()));
+
+        transfer.resume_from(node(1));
+
+        assert_eq!(transfer.provider(), node(1));
+        assert!(transfer.confirmed());
+        assert_eq!(transfer.received(), 2);
+
+        // The chunks in flight with the former provider are requested again
+        assert_eq!(transfer.assign(node(1), 2), assigned[2..].to_vec());
+
+        // Chunks of the former provider are not accepted once they were reassigned
+        assert!(transfer
+            .receive(node(0), chunk(&data, &manifest, assigned[2]))
+            .is_err());
+
+        receive_all(&mut transfer, &data, &manifest, node(1));
+
+        assert_eq!(transfer.assemble().unwrap(), data);
+    }
+
+    #[test]
+    fn test_assembled_state_must_match_the_checkpoint_digest() {
+        let data = data(4096, 6);
+        let valid = manifest(&data);
+
+        // Every chunk matches the manifest, but the manifest does not match the checkpoint
+        let manifest = StateManifest::new(
+            SeqNo::ONE,
+            digest_chunk(b"another state"),
+            valid.size(),
+            valid.chunk_size(),
+            valid.chunks().to_vec(),
+            valid.lengths().to_vec(),
+            None,
+        );
+
+        let mut transfer = ChunkedTransfer::new(manifest.clone(), node(0), 1).unwrap();
+
+        receive_all(&mut transfer, &data, &manifest, node(0));
+
+        let err = transfer
+            .assemble()
+            .unwrap_err()
+            .downcast::<ChunkError>()
+            .unwrap();
+
+        assert!(matches!(err, ChunkError::InvalidState(_)));
+    }
+
+    /// The indices of the chunks of `edited` which are not chunks of `original`
+    fn changed_chunks(original: &[u8], edited: &[u8]) -> Vec<usize> {
+        let (_, original, _) = chunking(original, AVERAGE);
+        let (_, edited, _) = chunking(edited, AVERAGE);
+
+        let original: BTreeSet<Digest> = original.into_iter().collect();
+
+        (0..edited.len())
+            .filter(|index| !original.contains(&edited[*index]))
+            .collect()
+    }
+
+    #[test]
+    fn test_chunks_are_cut_by_content_within_the_length_bounds() {
+        let data = data(64 * 1024, 7);
+
+        let (ends, digests, lengths) = chunking(&data, AVERAGE);
+
+        // The boundaries do not depend on how the state is read
+        let mut chunker = Chunker::new(AVERAGE);
+
+        data.chunks(1000).for_each(|piece| chunker.update(piece));
+
+        assert_eq!(chunker.finish(), (ends.clone(), digests, lengths.clone()));
+
+        assert_eq!(ends.last().copied(), Some(data.len() as u64));
+        assert_eq!(lengths.iter().sum::<u64>(), data.len() as u64);
+
+        let (last, lengths) = lengths.split_last().unwrap();
+
+        assert!(*last <= (AVERAGE * 4) as u64);
+        assert!(lengths
+            .iter()
+            .all(|length| (AVERAGE as u64 / 4..=AVERAGE as u64 * 4).contains(length)));
+
+        // Most chunks end where the content says so, not at the maximum length
+        let forced = lengths
+            .iter()
+            .filter(|length| **length == AVERAGE as u64 * 4)
+            .count();
+
+        assert!(forced < lengths.len() / 10);
+    }
+
+    #[test]
+    fn test_boundaries_resynchronize_after_inserted_bytes() {
+        let original = data(64 * 1024, 8);
+
+        // Only the chunks around the insertion change, out of hundreds
+        let edited = [&original[..32 * 1024], &data(10, 9), &original[32 * 1024..]].concat();
+
+        let changed = changed_chunks(&original, &edited);
+
+        assert!(!changed.is_empty() && changed.len() <= 8);
+
+        // Inserting bytes at the very start shifts the whole state, but only the first chunks change
+        let shifted = [data(3, 10), original.clone()].concat();
+
+        let changed = changed_chunks(&original, &shifted);
+
+        assert!(!changed.is_empty() && changed.iter().all(|index| *index < 8));
+    }
+
+    #[test]
+    fn test_boundaries_resynchronize_after_removed_bytes() {
+        let original = data(64 * 1024, 11);
+
+        let edited = [&original[..16 * 1024], &original[16 * 1024 + 100..]].concat();
+
+        assert!(changed_chunks(&original, &edited).len() <= 8);
+
+        // Chunks at fixed offsets would all differ after the removed bytes
+        let fixed =
+            |data: &[u8]| -> BTreeSet<Digest> { data.chunks(AVERAGE).map(digest_chunk).collect() };
+
+        let unchanged = fixed(&edited).intersection(&fixed(&original)).count();
+
+        assert_eq!(unchanged, 16 * 1024 / AVERAGE);
+    }
+}
@@ -1,5 +1,138 @@
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
-pub struct StateTransferConfig {
+use atlas_common::ordering::SeqNo;
+
+use crate::history::CheckpointArchive;
+
+/// The average size of the chunks checkpoints are split into by default
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// The average size of the chunks checkpoints are split into by default, when
+/// the requester only needs the difference against a checkpoint of its own
+pub const DEFAULT_DELTA_CHUNK_SIZE: usize = 64 * 1024;
+
+/// How many checkpoints are retained to serve other replicas by default, including the latest one
+pub const DEFAULT_RETAINED_CHECKPOINTS: usize = 1;
+
+/// How many chunks are requested from each replica at a time by default
+pub const DEFAULT_CHUNKS_IN_FLIGHT: usize = 4;
+
+/// Told about every checkpoint which becomes stable, e.g. so the ordering
+/// protocol can truncate the decisions the checkpoint covers
+pub type StableCheckpointListener = Arc<dyn Fn(SeqNo) + Send + Sync>;
+
+pub struct StateTransferConfig<S> {
     pub timeout_duration: Duration,
+    /// The average size of the chunks we split our checkpoints into when serving them,
+    /// rounded up to a power of two.
+    /// States which fit in a single chunk are sent whole
+    pub chunk_size: usize,
+    /// The average size of the chunks we split our checkpoints into when the requester
+    /// has a checkpoint of its own. Smaller chunks mean more of them can be reused
+    pub delta_chunk_size: usize,
+    /// How many chunks we request from each replica at a time when receiving a state
+    pub chunks_in_flight: usize,
+    /// How many bytes of state we send per second when serving other replicas,
+    /// so serving a large checkpoint does not starve the ordering protocol.
+    /// Unlimited when not set
+    pub serve_bytes_per_sec: Option<u64>,
+    /// The share of a core we spend preparing state for other replicas, serializing
+    /// our checkpoints and reading their chunks back, e.g. 0.25 for a quarter of a core.
+    /// Unlimited when not set
+    pub serve_cpu_share: Option<f64>,
+    /// Where the checkpoints we serve are serialized to, so we don't keep
+    /// a serialized copy of the state in memory next to the state itself
+    pub spool_dir: PathBuf,
+    /// How many checkpoints we retain to serve other replicas, including the latest one.
+    /// Only the latest is kept in memory, the others are read from the checkpoint archive
+    pub retained_checkpoints: usize,
+    /// Where the checkpoints we retain are stored, e.g. a
+    /// [DirCheckpointArchive](crate::history::DirCheckpointArchive).
+    /// Only the latest checkpoint is retained when not set
+    pub checkpoint_archive: Option<Arc<dyn CheckpointArchive<S>>>,
+    /// How many sequence numbers f+1 replicas may be ahead of our latest checkpoint before
+    /// we start the state transfer on our own. We only start it when the ordering protocol
+    /// asks us to when not set
+    pub lag_threshold: Option<u32>,
+    /// Told about every checkpoint which becomes stable
+    pub stable_checkpoint_listener: Option<StableCheckpointListener>,
+}
+
+impl<S> StateTransferConfig<S> {
+    pub fn new(timeout_duration: Duration) -> Self {
+        Self {
+            timeout_duration,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            delta_chunk_size: DEFAULT_DELTA_CHUNK_SIZE,
+            chunks_in_flight: DEFAULT_CHUNKS_IN_FLIGHT,
+            serve_bytes_per_sec: None,
+            serve_cpu_share: None,
+            spool_dir: std::env::temp_dir(),
+            retained_checkpoints: DEFAULT_RETAINED_CHECKPOINTS,
+            checkpoint_archive: None,
+            lag_threshold: None,
+            stable_checkpoint_listener: None,
+        }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+
+        self
+    }
+
+    pub fn with_delta_chunk_size(mut self, delta_chunk_size: usize) -> Self {
+        self.delta_chunk_size = delta_chunk_size;
+
+        self
+    }
+
+    pub fn with_chunks_in_flight(mut self, chunks_in_flight: usize) -> Self {
+        self.chunks_in_flight = chunks_in_flight;
+
+        self
+    }
+
+    pub fn with_serve_bytes_per_sec(mut self, serve_bytes_per_sec: u64) -> Self {
+        self.serve_bytes_per_sec = Some(serve_bytes_per_sec);
+
+        self
+    }
+
+    pub fn with_serve_cpu_share(mut self, serve_cpu_share: f64) -> Self {
+        self.serve_cpu_share = Some(serve_cpu_share);
+
+        self
+    }
+
+    pub fn with_spool_dir(mut self, spool_dir: impl Into<PathBuf>) -> Self {
+        self.spool_dir = spool_dir.into();
+
+        self
+    }
+
+    pub fn with_checkpoint_history(
+        mut self,
+        retained_checkpoints: usize,
+        checkpoint_archive: Arc<dyn CheckpointArchive<S>>,
+    ) -> Self {
+        self.retained_checkpoints = retained_checkpoints;
+        self.checkpoint_archive = Some(checkpoint_archive);
+
+        self
+    }
+
+    pub fn with_lag_threshold(mut self, lag_threshold: u32) -> Self {
+        self.lag_threshold = Some(lag_threshold);
+
+        self
+    }
+
+    pub fn with_stable_checkpoint_listener(mut self, listener: StableCheckpointListener) -> Self {
+        self.stable_checkpoint_listener = Some(listener);
+
+        self
+    }
 }
@@ -0,0 +1,537 @@
+//! The checkpoints we retain to serve to other replicas.
+//!
+//! Only the latest checkpoint is kept in memory. The older checkpoints we retain are
+//! stored by a [CheckpointArchive] and indexed here by their sequence number, digest and
+//! certificate, so retaining a longer history only takes more storage, not more memory.
+//! They are only read back from the archive when a reply which needs them gets its turn
+//! to be served. The index is rebuilt from the archive when the replica restarts.
+//!
+//! Without an archive, only the latest checkpoint is retained. [DirCheckpointArchive]
+//! keeps the checkpoints in a directory, e.g. next to the persistent log.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use thiserror::Error;
+use tracing::warn;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
+use atlas_common::globals::ReadOnly;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::Err;
+use atlas_smr_application::state::monolithic_state::MonolithicState;
+use atlas_smr_core::state_transfer::Checkpoint;
+
+use crate::certificate::CheckpointCertificate;
+
+/// The extension of the files [DirCheckpointArchive] keeps the checkpoints in
+const CHECKPOINT_EXTENSION: &str = "checkpoint";
+
+/// The extension of the files [DirCheckpointArchive] keeps the certificates in
+const CERTIFICATE_EXTENSION: &str = "certificate";
+
+/// The extension of the files [DirCheckpointArchive] is still writing
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Where the checkpoints we retain are stored, next to the persistent log.
+///
+/// Every checkpoint we retain is stored here, including the latest one,
+/// so the history survives a restart
+pub trait CheckpointArchive<S>: Send + Sync {
+    /// Store the given checkpoint, until it is discarded
+    fn store(&self, checkpoint: Arc<ReadOnly<Checkpoint<S>>>) -> Result<()>;
+
+    /// Store the certificate of a stored checkpoint which became stable
+    fn store_certificate(&self, certificate: &CheckpointCertificate) -> Result<()>;
+
+    /// Read the stored checkpoint with the given sequence number, if it is still stored
+    fn read(&self, seq: SeqNo) -> Result<Option<Arc<ReadOnly<Checkpoint<S>>>>>;
+
+    /// Discard the stored checkpoint with the given sequence number, as it is no longer retained
+    fn discard(&self, seq: SeqNo) -> Result<()>;
+
+    /// Every checkpoint still stored, without reading their states back
+    fn stored(&self) -> Result<Vec<ArchivedCheckpoint>>;
+}
+
+/// A checkpoint stored in the archive, as listed when rebuilding the index
+#[derive(Clone, Debug)]
+pub struct ArchivedCheckpoint {
+    pub seq: SeqNo,
+    pub digest: Digest,
+    pub certificate: Option<CheckpointCertificate>,
+}
+
+struct RetainedCheckpoint {
+    digest: Digest,
+    certificate: Option<CheckpointCertificate>,
+}
+
+/// The index of the checkpoints we retain, by sequence number
+pub(crate) struct CheckpointHistory<S> {
+    archive: Option<Arc<dyn CheckpointArchive<S>>>,
+    retained: BTreeMap<SeqNo, RetainedCheckpoint>,
+    /// How many checkpoints we retain, including the latest one
+    retention: usize,
+}
+
+impl<S> CheckpointHistory<S> {
+    /// Index the checkpoints left in the archive by a previous run
+    pub(crate) fn new(retention: usize, archive: Option<Arc<dyn CheckpointArchive<S>>>) -> Self {
+        if archive.is_none() && retention > 1 {
+            warn!(
+                "Retaining {} checkpoints needs a checkpoint archive, only the latest one is retained",
+                retention
+            );
+        }
+
+        let mut history = Self {
+            archive,
+            retained: BTreeMap::new(),
+            retention: retention.max(1),
+        };
+
+        let stored = match &history.archive {
+            Some(archive) => archive.stored(),
+            None => return history,
+        };
+
+        match stored {
+            Ok(stored) => {
+                for archived in stored {
+                    history.retained.insert(
+                        archived.seq,
+                        RetainedCheckpoint {
+                            digest: archived.digest,
+                            certificate: archived
+                                .certificate
+                                .filter(|certificate| certificate.digest() == &archived.digest),
+                        },
+                    );
+                }
+
+                history.trim();
+            }
+            Err(err) => {
+                warn!("Failed to list the archived checkpoints: {:?}", err);
+            }
+        }
+
+        history
+    }
+
+    /// Store a checkpoint which was written to the persistent log,
+    /// discarding the ones which are no longer retained
+    pub(crate) fn record(&mut self, checkpoint: &Arc<ReadOnly<Checkpoint<S>>>) -> Result<()> {
+        let Some(archive) = &self.archive else {
+            return Ok(());
+        };
+
+        archive.store(checkpoint.clone())?;
+
+        self.retained.insert(
+            checkpoint.sequence_number(),
+            RetainedCheckpoint {
+                digest: *checkpoint.digest(),
+                certificate: None,
+            },
+        );
+
+        self.trim();
+
+        Ok(())
+    }
+
+    fn trim(&mut self) {
+        let Some(archive) = &self.archive else {
+            return;
+        };
+
+        while self.retained.len() > self.retention {
+            let Some((seq, _)) = self.retained.pop_first() else {
+                break;
+            };
+
+            if let Err(err) = archive.discard(seq) {
+                warn!(
+                    "Failed to discard the archived checkpoint {:?}: {:?}",
+                    seq, err
+                );
+            }
+        }
+    }
+
+    /// Whether we still retain the checkpoint with the given sequence number in the archive
+    pub(crate) fn retains(&self, seq: SeqNo) -> bool {
+        self.retained.contains_key(&seq)
+    }
+
+    /// Keep the certificate of one of our retained checkpoints, so it can be served along with it
+    pub(crate) fn certify(&mut self, certificate: &CheckpointCertificate) {
+        let Some(retained) = self.retained.get_mut(&certificate.sequence_number()) else {
+            return;
+        };
+
+        if retained.digest != *certificate.digest() {
+            return;
+        }
+
+        retained.certificate = Some(certificate.clone());
+
+        if let Some(archive) = &self.archive {
+            if let Err(err) = archive.store_certificate(certificate) {
+                warn!(
+                    "Failed to archive the certificate of checkpoint {:?}: {:?}",
+                    certificate.sequence_number(),
+                    err
+                );
+            }
+        }
+    }
+
+    /// The certificate of the given retained checkpoint, if it became stable
+    pub(crate) fn certificate(&self, checkpoint: &Checkpoint<S>) -> Option<&CheckpointCertificate> {
+        self.retained
+            .get(&checkpoint.sequence_number())
+            .and_then(|retained| retained.certificate.as_ref())
+            .filter(|certificate| certificate.certifies(checkpoint))
+    }
+
+    /// Read the given retained checkpoint back from the archive
+    pub(crate) fn load(&self, seq: SeqNo) -> Result<Option<Arc<ReadOnly<Checkpoint<S>>>>> {
+        let (Some(archive), Some(retained)) = (&self.archive, self.retained.get(&seq)) else {
+            return Ok(None);
+        };
+
+        let Some(checkpoint) = archive.read(seq)? else {
+            return Ok(None);
+        };
+
+        if checkpoint.sequence_number() != seq || *checkpoint.digest() != retained.digest {
+            return Err!(CheckpointHistoryError::MismatchedCheckpoint(seq));
+        }
+
+        Ok(Some(checkpoint))
+    }
+}
+
+/// A checkpoint archive which keeps every checkpoint in a file of its own in the given
+/// directory, with the state serialized the way the application serializes it.
+///
+/// The certificate of a checkpoint is kept in a separate file, written once it became stable
+pub struct DirCheckpointArchive<S> {
+    dir: PathBuf,
+    _state: PhantomData<fn() -> S>,
+}
+
+impl<S> DirCheckpointArchive<S> {
+    /// Keep the checkpoints in the given directory, creating it if need be.
+    /// The checkpoints stored by a previous run are picked up from it
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            _state: PhantomData,
+        })
+    }
+
+    fn checkpoint_path(&self, seq: SeqNo) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", u32::from(seq), CHECKPOINT_EXTENSION))
+    }
+
+    fn certificate_path(&self, seq: SeqNo) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", u32::from(seq), CERTIFICATE_EXTENSION))
+    }
+
+    /// Write a file through a temporary one, so a crash never leaves it half written
+    fn write_file<F>(&self, path: &Path, write: F) -> Result<()>
+    where
+        F: FnOnce(&mut BufWriter<File>) -> Result<()>,
+    {
+        let mut temp_path = path.as_os_str().to_owned();
+
+        temp_path.push(format!(".{}", TEMP_EXTENSION));
+
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+        write(&mut writer)?;
+
+        writer.into_inner()?.sync_all()?;
+
+        fs::rename(&temp_path, path)?;
+
+        Ok(())
+    }
+
+    /// Read the digest a stored checkpoint starts with, leaving the reader at its state
+    fn read_digest(reader: &mut impl Read) -> Result<Digest> {
+        let mut digest = [0; Digest::LENGTH];
+
+        reader.read_exact(&mut digest)?;
+
+        Digest::from_bytes(&digest[..])
+    }
+
+    fn read_certificate(&self, seq: SeqNo) -> Result<Option<CheckpointCertificate>> {
+        match File::open(self.certificate_path(seq)) {
+            Ok(file) => CheckpointCertificate::read_from(BufReader::new(file)).map(Some),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl<S> CheckpointArchive<S> for DirCheckpointArchive<S>
+where
+    S: MonolithicState,
+{
+    fn store(&self, checkpoint: Arc<ReadOnly<Checkpoint<S>>>) -> Result<()> {
+        let seq = checkpoint.sequence_number();
+
+        // A certificate left behind by an earlier checkpoint with this sequence number is of no use
+        remove_if_present(&self.certificate_path(seq))?;
+
+        self.write_file(&self.checkpoint_path(seq), |writer| {
+            writer.write_all(checkpoint.digest().as_ref())?;
+
+            S::serialize_state(writer, checkpoint.state())
+        })
+    }
+
+    fn store_certificate(&self, certificate: &CheckpointCertificate) -> Result<()> {
+        self.write_file(
+            &self.certificate_path(certificate.sequence_number()),
+            |writer| certificate.write_to(writer),
+        )
+    }
+
+    fn read(&self, seq: SeqNo) -> Result<Option<Arc<ReadOnly<Checkpoint<S>>>>> {
+        let file = match File::open(self.checkpoint_path(seq)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut reader = BufReader::new(file);
+
+        let digest = Self::read_digest(&mut reader)?;
+
+        let state = S::deserialize_state(reader)?;
+
+        Ok(Some(Checkpoint::new(seq, state, digest)))
+    }
+
+    fn discard(&self, seq: SeqNo) -> Result<()> {
+        remove_if_present(&self.checkpoint_path(seq))?;
+        remove_if_present(&self.certificate_path(seq))
+    }
+
+    fn stored(&self) -> Result<Vec<ArchivedCheckpoint>> {
+        let mut stored = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            if path.extension().and_then(OsStr::to_str) != Some(CHECKPOINT_EXTENSION) {
+                continue;
+            }
+
+            let Some(seq) = path
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .and_then(|stem| stem.parse::<u32>().ok())
+                .map(SeqNo::from)
+            else {
+                continue;
+            };
+
+            let digest = Self::read_digest(&mut File::open(&path)?)?;
+
+            let certificate = match self.read_certificate(seq) {
+                Ok(certificate) => certificate,
+                Err(err) => {
+                    warn!(
+                        "Failed to read the archived certificate of checkpoint {:?}: {:?}",
+                        seq, err
+                    );
+
+                    None
+                }
+            };
+
+            stored.push(ArchivedCheckpoint {
+                seq,
+                digest,
+                certificate,
+            });
+        }
+
+        Ok(stored)
+    }
+}
+
+fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CheckpointHistoryError {
+    #[error("The archived checkpoint {0:?} does not match the one we retained")]
+    MismatchedCheckpoint(SeqNo),
+}
+
+#[cfg(test)]
+mod history_tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use atlas_common::crypto::signature::KeyPair;
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::{Orderable, SeqNo};
+    use atlas_smr_core::state_transfer::Checkpoint;
+
+    use crate::certificate::{sign_checkpoint, CheckpointCertificate, CheckpointVotes};
+    use crate::message::CheckpointVote;
+    use crate::serving::serving_tests::{checkpoint, test_dir, TestState};
+
+    use super::{
+        CheckpointArchive, CheckpointHistory, CheckpointHistoryError, DirCheckpointArchive,
+    };
+
+    fn archive(dir: &Path) -> Arc<dyn CheckpointArchive<TestState>> {
+        Arc::new(DirCheckpointArchive::new(dir).unwrap())
+    }
+
+    /// A certificate for the given checkpoint, signed by 3 of 4 replicas
+    fn certificate(checkpoint: &Checkpoint<TestState>) -> CheckpointCertificate {
+        let (seq, digest) = (checkpoint.sequence_number(), *checkpoint.digest());
+
+        let mut votes = CheckpointVotes::new();
+
+        for node in 0..3u32 {
+            let key_pair = KeyPair::from_bytes(&[node as u8 + 1; 32][..]).unwrap();
+
+            let signature = sign_checkpoint(&key_pair, seq, &digest).unwrap();
+
+            votes.insert(
+                NodeId::from(node),
+                CheckpointVote::new(seq, digest, signature),
+            );
+        }
+
+        votes.certificate(seq, &digest, 3).unwrap()
+    }
+
+    fn stored_seqs(archive: &Arc<dyn CheckpointArchive<TestState>>) -> Vec<u32> {
+        let mut stored: Vec<u32> = archive
+            .stored()
+            .unwrap()
+            .into_iter()
+            .map(|archived| u32::from(archived.seq))
+            .collect();
+
+        stored.sort();
+
+        stored
+    }
+
+    #[test]
+    fn test_only_the_latest_checkpoints_are_retained() {
+        let archive = archive(&test_dir("history-trim"));
+
+        let mut history = CheckpointHistory::new(2, Some(archive.clone()));
+
+        let checkpoints: Vec<_> = (1..=3)
+            .map(|seq| checkpoint(seq, seq as u8, 1024))
+            .collect();
+
+        for checkpoint in &checkpoints {
+            history.record(checkpoint).unwrap();
+        }
+
+        assert!(!history.retains(SeqNo::from(1)));
+        assert!(history.retains(SeqNo::from(2)) && history.retains(SeqNo::from(3)));
+        assert_eq!(stored_seqs(&archive), vec![2, 3]);
+
+        assert!(history.load(SeqNo::from(1)).unwrap().is_none());
+
+        let loaded = history.load(SeqNo::from(3)).unwrap().unwrap();
+
+        assert_eq!(loaded.digest(), checkpoints[2].digest());
+        assert_eq!(loaded.state(), checkpoints[2].state());
+    }
+
+    #[test]
+    fn test_history_is_rebuilt_from_the_archive_after_a_restart() {
+        let dir = test_dir("history-restart");
+
+        let checkpoints: Vec<_> = (1..=3)
+            .map(|seq| checkpoint(seq, seq as u8, 1024))
+            .collect();
+
+        {
+            let mut history = CheckpointHistory::new(3, Some(archive(&dir)));
+
+            for checkpoint in &checkpoints {
+                history.record(checkpoint).unwrap();
+            }
+
+            history.certify(&certificate(&checkpoints[1]));
+        }
+
+        // Restarting with a shorter retention discards the oldest checkpoints
+        let archive = archive(&dir);
+
+        let history = CheckpointHistory::new(2, Some(archive.clone()));
+
+        assert!(!history.retains(SeqNo::from(1)));
+        assert_eq!(stored_seqs(&archive), vec![2, 3]);
+
+        let certificate = history.certificate(&checkpoints[1]).unwrap();
+
+        assert!(certificate.certifies(&checkpoints[1]));
+        assert_eq!(certificate.signers().count(), 3);
+        assert!(history.certificate(&checkpoints[2]).is_none());
+
+        let loaded = history.load(SeqNo::from(2)).unwrap().unwrap();
+
+        assert_eq!(loaded.state(), checkpoints[1].state());
+    }
+
+    #[test]
+    fn test_archived_checkpoints_must_match_the_retained_ones() {
+        let archive = archive(&test_dir("history-mismatch"));
+
+        let mut history = CheckpointHistory::new(2, Some(archive.clone()));
+
+        history.record(&checkpoint(1, 1, 1024)).unwrap();
+
+        // The archive was tampered with behind our back
+        archive.store(checkpoint(1, 2, 1024)).unwrap();
+
+        let err = history
+            .load(SeqNo::from(1))
+            .unwrap_err()
+            .downcast::<CheckpointHistoryError>()
+            .unwrap();
+
+        assert!(matches!(
+            err,
+            CheckpointHistoryError::MismatchedCheckpoint(_)
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+//! Noticing that we fell behind the other replicas, without waiting for the ordering protocol.
+//!
+//! Every replica signs a vote for each checkpoint it reaches and sends it to the others,
+//! so we keep the latest checkpoint each replica voted for. The replies to our state cid
+//! requests which arrive after we have gathered a quorum of them are kept the same way.
+//! The highest checkpoint reported by at least f+1 replicas was reached by at least one
+//! correct replica, so a faulty replica can't make us start a state transfer for nothing.
+
+use std::collections::BTreeMap;
+
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::SeqNo;
+use atlas_core::ordering_protocol::networking::serialize::NetworkView;
+
+/// The latest checkpoint reported by each of the other replicas
+pub(crate) struct PeerCheckpoints {
+    reported: BTreeMap<NodeId, SeqNo>,
+}
+
+impl PeerCheckpoints {
+    pub(crate) fn new() -> Self {
+        Self {
+            reported: BTreeMap::new(),
+        }
+    }
+
+    /// Record the checkpoint reported by the given replica, unless it already reported a later one
+    pub(crate) fn report(&mut self, from: NodeId, seq: SeqNo) {
+        let reported = self.reported.entry(from).or_insert(seq);
+
+        if *reported < seq {
+            *reported = seq;
+        }
+    }
+
+    /// The highest checkpoint which at least f+1 members of the given view reported
+    pub(crate) fn agreed<V>(&self, view: &V) -> Option<SeqNo>
+    where
+        V: NetworkView,
+    {
+        let mut reported: Vec<SeqNo> = self
+            .reported
+            .iter()
+            .filter(|(node, _)| view.quorum_members().contains(node))
+            .map(|(_, seq)| *seq)
+            .collect();
+
+        reported.sort_unstable_by(|a, b| b.cmp(a));
+
+        reported.get(view.f()).copied()
+    }
+
+    /// The highest checkpoint which at least f+1 members of the given view reported,
+    /// if it is more than `threshold` sequence numbers ahead of ours
+    pub(crate) fn lagging<V>(&self, view: &V, ours: SeqNo, threshold: u32) -> Option<SeqNo>
+    where
+        V: NetworkView,
+    {
+        self.agreed(view)
+            .filter(|agreed| u32::from(*agreed).saturating_sub(u32::from(ours)) > threshold)
+    }
+
+    /// Forget the reports, as we are going to fetch the state anyway
+    pub(crate) fn clear(&mut self) {
+        self.reported.clear();
+    }
+}
+
+#[cfg(test)]
+mod lag_tests {
+    use atlas_common::node_id::NodeId;
+    use atlas_common::ordering::SeqNo;
+
+    use crate::certificate::checkpoint_votes_tests::TestView;
+
+    use super::PeerCheckpoints;
+
+    fn report(peers: &mut PeerCheckpoints, from: u32, seq: u32) {
+        peers.report(NodeId::from(from), SeqNo::from(seq));
+    }
+
+    #[test]
+    fn test_f_plus_one_replicas_must_be_ahead() {
+        let view = TestView::new(1);
+
+        let mut peers = PeerCheckpoints::new();
+
+        // A single replica, which may be faulty, claims to be far ahead
+        report(&mut peers, 1, 1000);
+
+        assert_eq!(peers.agreed(&view), None);
+        assert_eq!(peers.lagging(&view, SeqNo::ZERO, 100), None);
+
+        report(&mut peers, 2, 300);
+
+        // At least one correct replica reached the lower of the two checkpoints
+        assert_eq!(peers.agreed(&view), Some(SeqNo::from(300)));
+        assert_eq!(
+            peers.lagging(&view, SeqNo::ZERO, 100),
+            Some(SeqNo::from(300))
+        );
+    }
+
+    #[test]
+    fn test_lag_within_the_threshold_is_tolerated() {
+        let view = TestView::new(1);
+
+        let mut peers = PeerCheckpoints::new();
+
+        report(&mut peers, 1, 250);
+        report(&mut peers, 2, 250);
+
+        assert_eq!(peers.lagging(&view, SeqNo::from(150), 100), None);
+        assert_eq!(peers.lagging(&view, SeqNo::from(300), 100), None);
+        assert_eq!(
+            peers.lagging(&view, SeqNo::from(149), 100),
+            Some(SeqNo::from(250))
+        );
+    }
+
+    #[test]
+    fn test_only_the_latest_reports_of_members_count() {
+        let view = TestView::new(1);
+
+        let mut peers = PeerCheckpoints::new();
+
+        report(&mut peers, 1, 500);
+        report(&mut peers, 1, 200);
+
+        // Replicas outside of the view can't make us fall behind
+        report(&mut peers, 7, 500);
+
+        assert_eq!(peers.agreed(&view), None);
+
+        report(&mut peers, 2, 400);
+
+        assert_eq!(peers.agreed(&view), Some(SeqNo::from(400)));
+
+        peers.clear();
+
+        assert_eq!(peers.agreed(&view), None);
+    }
+}
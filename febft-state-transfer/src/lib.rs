@@ -1,4 +1,3 @@
-use std::cmp::Ordering;
 use std::fmt::{Debug, Formatter};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -19,12 +18,13 @@ use atlas_common::node_id::NodeId;
 use atlas_common::ordering::{Orderable, SeqNo};
 use atlas_common::{collections, Err};
 use atlas_communication::message::{Header, StoredMessage};
+use atlas_communication::reconfiguration::NetworkInformationProvider;
 use atlas_core::ordering_protocol::networking::serialize::NetworkView;
 use atlas_core::ordering_protocol::{ExecutionResult, OrderingProtocol};
 use atlas_core::persistent_log::{OperationMode, PersistableStateTransferProtocol};
 use atlas_core::timeouts::timeout::{ModTimeout, TimeoutModHandle, TimeoutableMod};
 use atlas_core::timeouts::{TimeoutID, TimeoutsHandle};
-use atlas_metrics::metrics::metric_duration;
+use atlas_metrics::metrics::{metric_duration, metric_increment, metric_store_count};
 use atlas_smr_application::state::monolithic_state::{InstallStateMessage, MonolithicState};
 use atlas_smr_core::persistent_log::MonolithicStateLog;
 use atlas_smr_core::state_transfer::monolithic_state::{
@@ -35,14 +35,31 @@ use atlas_smr_core::state_transfer::{
     Checkpoint, CstM, STPollResult, STResult, STTimeoutResult, StateTransferProtocol,
 };
 
-use crate::config::StateTransferConfig;
+use crate::certificate::{
+    digest_state, sign_checkpoint, verify_vote, CheckpointCertificate, CheckpointVotes,
+};
+use crate::chunks::{ChunkError, ChunkedTransfer};
+use crate::config::{StableCheckpointListener, StateTransferConfig};
+use crate::history::CheckpointHistory;
+use crate::lag::PeerCheckpoints;
 use crate::message::serialize::CSTMsg;
-use crate::message::{CstMessage, CstMessageKind};
-use crate::metrics::STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID;
+use crate::message::{
+    CheckpointVote, CstMessage, CstMessageKind, StateChunkRequest, StateManifest, StateRequest,
+};
+use crate::metrics::{
+    STATE_TRANSFER_REUSED_CHUNKS_ID, STATE_TRANSFER_SERVE_BACKLOG_ID,
+    STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID,
+};
+use crate::serving::StateServer;
 
+pub mod certificate;
+pub mod chunks;
 pub mod config;
+pub mod history;
+pub mod lag;
 pub mod message;
 pub mod metrics;
+pub mod serving;
 
 lazy_static!(
     static ref MOD_NAME: Arc<str> = Arc::from("ST_TRANSFER");
@@ -72,7 +89,10 @@ enum ProtoPhase<S> {
     Init,
     WaitingCheckpoint(Vec<StoredMessage<CstMessage<S>>>),
     ReceivingCid(usize),
-    ReceivingState(usize),
+    /// Receiving the state from the given replica
+    ReceivingState(NodeId),
+    /// Receiving the chunks of the state from the replicas which have it
+    ReceivingChunks,
 }
 
 impl<S> Debug for ProtoPhase<S> {
@@ -87,8 +107,11 @@ impl<S> Debug for ProtoPhase<S> {
             ProtoPhase::ReceivingCid(size) => {
                 write!(f, "Receiving CID phase {} responses", size)
             }
-            ProtoPhase::ReceivingState(size) => {
-                write!(f, "Receiving state phase {} responses", size)
+            ProtoPhase::ReceivingState(peer) => {
+                write!(f, "Receiving state phase from {:?}", peer)
+            }
+            ProtoPhase::ReceivingChunks => {
+                write!(f, "Receiving state chunks phase")
             }
         }
     }
@@ -103,30 +126,57 @@ impl<S> Debug for ProtoPhase<S> {
 #[derive(Clone, Debug)]
 pub struct RecoveryState<S> {
     pub checkpoint: Arc<ReadOnly<Checkpoint<S>>>,
+    /// Proves a quorum agreed on the checkpoint, when it is already stable
+    pub certificate: Option<CheckpointCertificate>,
 }
 
 impl<S> RecoveryState<S> {
     /// Creates a new `RecoveryState`.
     pub fn new(checkpoint: Arc<ReadOnly<Checkpoint<S>>>) -> Self {
-        Self { checkpoint }
+        Self {
+            checkpoint,
+            certificate: None,
+        }
+    }
+
+    /// Creates a new `RecoveryState` for a stable checkpoint.
+    pub fn with_certificate(
+        checkpoint: Arc<ReadOnly<Checkpoint<S>>>,
+        certificate: CheckpointCertificate,
+    ) -> Self {
+        Self {
+            checkpoint,
+            certificate: Some(certificate),
+        }
     }
 
     /// Returns the local checkpoint of this recovery state.
     pub fn checkpoint(&self) -> &Arc<ReadOnly<Checkpoint<S>>> {
         &self.checkpoint
     }
+
+    /// Returns the stable checkpoint certificate of this recovery state, if any.
+    pub fn certificate(&self) -> Option<&CheckpointCertificate> {
+        self.certificate.as_ref()
+    }
 }
 
+/// The state f+1 replicas agreed on, which we fetch from one of them at a time
 #[derive(Debug)]
-struct ReceivedState<S> {
-    count: usize,
-    state: RecoveryState<S>,
+struct StateSource {
+    seq: SeqNo,
+    digest: Digest,
+    /// The replicas which have the state
+    peers: Vec<NodeId>,
+    /// The replica we are currently fetching the state from
+    current: usize,
 }
 
-#[derive(Debug)]
-struct ReceivedStateCid {
-    cid: SeqNo,
-    count: usize,
+/// The last view we were handed. Checkpoints are finalized outside of the calls
+/// which receive the view, so we need it to know who to send our votes to.
+struct KnownView {
+    members: Vec<NodeId>,
+    quorum: usize,
 }
 
 // NOTE: in this module, we may use cid interchangeably with
@@ -143,15 +193,49 @@ where
     current_checkpoint_state: CheckpointState<S>,
     base_timeout: Duration,
     curr_timeout: Duration,
+    /// The size of the chunks we split our checkpoints into
+    chunk_size: usize,
+    /// The size of the chunks we split our checkpoints into for requesters with a checkpoint of their own
+    delta_chunk_size: usize,
+    /// How many chunks we request from each replica at a time
+    chunks_in_flight: usize,
     timeouts: TimeoutModHandle,
     // NOTE: remembers whose replies we have
     // received already, to avoid replays
     //voted: HashSet<NodeId>,
     node: Arc<NT>,
-    received_states: HashMap<Digest, ReceivedState<S>>,
-    received_state_ids: HashMap<Digest, ReceivedStateCid>,
+    state_source: Option<StateSource>,
+    /// The state we are receiving in chunks, kept across timeouts so it can be resumed
+    chunked_transfer: Option<ChunkedTransfer>,
+    /// The older checkpoints we retain in the checkpoint archive
+    history: CheckpointHistory<S>,
+    /// The replies to state requests waiting for our serving budget
+    state_server: StateServer<S>,
+    /// The state requests we received while recovering without a checkpoint of our own,
+    /// answered as soon as we have one
+    deferred_requests: Vec<StoredMessage<CstMessage<S>>>,
+    /// The replicas which replied to our cid request, by the checkpoint they reported
+    received_state_ids: HashMap<Option<(SeqNo, Digest)>, Vec<NodeId>>,
     phase: ProtoPhase<S>,
 
+    known_view: Option<KnownView>,
+    /// The checkpoint votes of the other replicas
+    checkpoint_votes: CheckpointVotes,
+    /// Our vote for the latest checkpoint, while we don't know the view to send it to
+    unsent_vote: Option<CheckpointVote>,
+    /// The certificate of the latest stable checkpoint
+    stable_certificate: Option<CheckpointCertificate>,
+    /// Told about every checkpoint which becomes stable
+    stable_checkpoint_listener: Option<StableCheckpointListener>,
+
+    /// The latest checkpoints the other replicas told us about while we were not recovering
+    peer_checkpoints: PeerCheckpoints,
+    /// How far behind the other replicas we may fall before recovering on our own
+    lag_threshold: Option<u32>,
+    /// The checkpoint the other replicas are at, when we noticed we fell behind
+    /// outside of the state transfer. Reported to the replica on the next poll
+    detected_lag: Option<SeqNo>,
+
     install_channel: ChannelSyncTx<InstallStateMessage<S>>,
 
     /// Persistent logging for the state transfer protocol.
@@ -173,9 +257,14 @@ pub enum CstStatus<S> {
     SeqNo(SeqNo),
     /// We should request the latest state from the view.
     RequestState,
+    /// We should request more chunks of the state we are receiving.
+    RequestStateChunks,
     /// We have received and validated the state from
     /// a group of replicas.
     State(RecoveryState<S>),
+    /// At least f+1 replicas are at the given checkpoint, which is further
+    /// ahead of ours than the configured threshold, so we should recover.
+    Lagging(SeqNo),
 }
 
 impl<S> Debug for CstStatus<S> {
@@ -193,12 +282,18 @@ impl<S> Debug for CstStatus<S> {
             CstStatus::RequestState => {
                 write!(f, "Request latest state")
             }
+            CstStatus::RequestStateChunks => {
+                write!(f, "Request state chunks")
+            }
             CstStatus::SeqNo(seq) => {
                 write!(f, "Received seq no {:?}", seq)
             }
             CstStatus::State(_) => {
                 write!(f, "Received state")
             }
+            CstStatus::Lagging(seq) => {
+                write!(f, "Lagging behind seq no {:?}", seq)
+            }
         }
     }
 }
@@ -274,6 +369,20 @@ where
     }
 
     fn poll(&mut self) -> Result<STPollResult<CstM<Self::Serialization>>> {
+        self.serve_pending();
+
+        if let Some(seq) = self.detected_lag.take() {
+            if matches!(self.phase, ProtoPhase::Init) {
+                info!(
+                    "{:?} // The other replicas are at checkpoint {:?}, running the state transfer",
+                    self.node.id(),
+                    seq
+                );
+
+                return Ok(STPollResult::STResult(STResult::RunStateTransfer));
+            }
+        }
+
         Ok(STPollResult::ReceiveMsg)
     }
 
@@ -287,6 +396,8 @@ where
     {
         let (header, message) = message.into_inner();
 
+        self.note_view(&view);
+
         debug!(
             "{:?} // Off context Message {:?} from {:?} with seq {:?}",
             self.node.id(),
@@ -301,11 +412,26 @@ where
 
                 return Ok(());
             }
-            CstMessageKind::RequestState => {
+            CstMessageKind::RequestState(_) => {
                 self.process_request_state(header, message);
 
                 return Ok(());
             }
+            CstMessageKind::RequestStateChunks(_) => {
+                self.process_request_chunks(header, message);
+
+                return Ok(());
+            }
+            CstMessageKind::CheckpointVote(_) => {
+                if let CstStatus::Lagging(seq) =
+                    self.process_checkpoint_vote(&view, header, message)
+                {
+                    // The replica is not running the state transfer, so we let it know when it polls
+                    self.detected_lag = Some(seq);
+                }
+
+                return Ok(());
+            }
             _ => {}
         }
 
@@ -313,6 +439,10 @@ where
 
         match status {
             CstStatus::Nil => (),
+            CstStatus::Lagging(seq) => {
+                // The replica is not running the state transfer, so we let it know when it polls
+                self.detected_lag = Some(seq);
+            }
             // should not happen...
             _ => {
                 return Err(anyhow!(format!(
@@ -335,6 +465,8 @@ where
     {
         let (header, message) = message.into_inner();
 
+        self.note_view(&view);
+
         debug!(
             "{:?} // Message {:?} from {:?} while in phase {:?}",
             self.node.id(),
@@ -349,11 +481,25 @@ where
 
                 return Ok(STResult::StateTransferRunning);
             }
-            CstMessageKind::RequestState => {
+            CstMessageKind::RequestState(_) => {
                 self.process_request_state(header, message);
 
                 return Ok(STResult::StateTransferRunning);
             }
+            CstMessageKind::RequestStateChunks(_) => {
+                self.process_request_chunks(header, message);
+
+                return Ok(STResult::StateTransferRunning);
+            }
+            CstMessageKind::CheckpointVote(_) => {
+                if let CstStatus::Lagging(seq) =
+                    self.process_checkpoint_vote(&view, header, message)
+                {
+                    self.detected_lag = Some(seq);
+                }
+
+                return Ok(STResult::StateTransferRunning);
+            }
             _ => {}
         }
 
@@ -368,6 +514,8 @@ where
         match status {
             CstStatus::Running => (),
             CstStatus::State(state) => {
+                self.adopt_received_state(&view, &state);
+
                 let start = Instant::now();
 
                 self.install_channel
@@ -393,6 +541,9 @@ where
                         seq
                     );
 
+                    self.state_source = None;
+                    self.chunked_transfer = None;
+
                     return Ok(STResult::StateTransferNotNeeded(seq));
                 }
             }
@@ -402,6 +553,12 @@ where
             CstStatus::RequestState => {
                 self.request_latest_state(view);
             }
+            CstStatus::RequestStateChunks => {
+                self.request_state_chunks();
+            }
+            CstStatus::Lagging(_) => {
+                self.request_latest_consensus_seq_no(view);
+            }
             CstStatus::Nil => {
                 // No actions are required for the CST
                 // This can happen for example when we already received the a quorum of sequence number replies
@@ -443,7 +600,7 @@ where
     PL: MonolithicStateLog<S> + 'static,
     NT: StateTransferSendNode<CSTMsg<S>> + 'static,
 {
-    type Config = StateTransferConfig;
+    type Config = StateTransferConfig<S>;
 
     fn handle_state_received_from_app(
         &mut self,
@@ -477,11 +634,9 @@ where
         NT: StateTransferSendNode<Self::Serialization>,
         PL: MonolithicStateLog<S>,
     {
-        let StateTransferConfig { timeout_duration } = config;
-
         Ok(Self::new(
             node,
-            timeout_duration,
+            config,
             timeouts,
             log,
             executor_handle,
@@ -501,20 +656,49 @@ where
     /// Create a new instance of `CollabStateTransfer`.
     pub fn new(
         node: Arc<NT>,
-        base_timeout: Duration,
+        config: StateTransferConfig<S>,
         timeouts: TimeoutModHandle,
         persistent_log: PL,
         install_channel: ChannelSyncTx<InstallStateMessage<S>>,
     ) -> Self {
+        let StateTransferConfig {
+            timeout_duration: base_timeout,
+            chunk_size,
+            delta_chunk_size,
+            chunks_in_flight,
+            serve_bytes_per_sec,
+            serve_cpu_share,
+            spool_dir,
+            retained_checkpoints,
+            checkpoint_archive,
+            lag_threshold,
+            stable_checkpoint_listener,
+        } = config;
+
         Self {
             current_checkpoint_state: CheckpointState::None,
             base_timeout,
             curr_timeout: base_timeout,
+            chunk_size,
+            delta_chunk_size,
+            chunks_in_flight: chunks_in_flight.max(1),
             timeouts,
             node,
-            received_states: collections::hash_map(),
+            state_source: None,
+            chunked_transfer: None,
+            history: CheckpointHistory::new(retained_checkpoints, checkpoint_archive),
+            state_server: StateServer::new(serve_bytes_per_sec, serve_cpu_share, spool_dir),
+            deferred_requests: Vec::new(),
             received_state_ids: collections::hash_map(),
             phase: ProtoPhase::Init,
+            known_view: None,
+            checkpoint_votes: CheckpointVotes::new(),
+            unsent_vote: None,
+            stable_certificate: None,
+            stable_checkpoint_listener,
+            peer_checkpoints: PeerCheckpoints::new(),
+            lag_threshold,
+            detected_lag: None,
             curr_seq: SeqNo::ZERO,
             persistent_log,
             install_channel,
@@ -588,40 +772,232 @@ where
     }
 
     fn process_request_state(&mut self, header: Header, message: CstMessage<S>) {
-        match &mut self.phase {
-            ProtoPhase::Init => {}
-            ProtoPhase::WaitingCheckpoint(waiting) => {
-                waiting.push(StoredMessage::new(header, message));
+        if let ProtoPhase::WaitingCheckpoint(waiting) = &mut self.phase {
+            waiting.push(StoredMessage::new(header, message));
+
+            return;
+        }
+
+        // While recovering ourselves, we still answer with our last complete checkpoint,
+        // so replicas which are recovering at the same time don't starve each other
+        let state = match self.latest_checkpoint() {
+            Some(checkpoint) => checkpoint,
+            None if matches!(self.phase, ProtoPhase::Init) => {
+                self.phase =
+                    ProtoPhase::WaitingCheckpoint(vec![StoredMessage::new(header, message)]);
 
                 return;
             }
-            _ => {
-                // We can't reply to state requests when requesting state ourselves
+            None => {
+                self.defer_state_request(StoredMessage::new(header, message));
+
                 return;
             }
+        };
+
+        // Serve the checkpoint the requester asked for, as long as we still retain it
+        let seq = match message.kind() {
+            CstMessageKind::RequestState(request) => request
+                .checkpoint()
+                .filter(|seq| self.retains(*seq))
+                .unwrap_or(state.sequence_number()),
+            _ => state.sequence_number(),
+        };
+
+        // A requester with a checkpoint of its own only needs the chunks which differ,
+        // so we use smaller chunks to make more of them match
+        let chunk_size = match message.kind() {
+            CstMessageKind::RequestState(request) if request.base().is_some() => {
+                self.delta_chunk_size
+            }
+            _ => self.chunk_size,
+        };
+
+        // The checkpoint is only loaded and serialized when the reply gets its turn
+        self.state_server
+            .queue_state(header.from(), message.sequence_number(), seq, chunk_size);
+
+        self.serve_pending();
+    }
+
+    /// Hold on to a state request until we have a checkpoint to answer it with,
+    /// replacing any earlier request from the same replica
+    fn defer_state_request(&mut self, request: StoredMessage<CstMessage<S>>) {
+        let from = request.header().from();
+
+        debug!(
+            "{:?} // Deferring the state request of {:?} until we have a checkpoint",
+            self.node.id(),
+            from
+        );
+
+        self.deferred_requests.retain(|deferred| deferred.header().from() != from);
+
+        self.deferred_requests.push(request);
+    }
+
+    /// Answer the state requests we deferred, now that we have a checkpoint
+    fn process_deferred_requests(&mut self) {
+        if self.latest_checkpoint().is_none() {
+            return;
         }
 
-        let state = match &self.current_checkpoint_state {
-            CheckpointState::PartialWithEarlier { earlier, seq: _ } => earlier.clone(),
-            CheckpointState::Complete(checkpoint) => checkpoint.clone(),
-            _ => {
-                if let ProtoPhase::WaitingCheckpoint(waiting) = &mut self.phase {
-                    waiting.push(StoredMessage::new(header, message));
-                } else {
-                    self.phase =
-                        ProtoPhase::WaitingCheckpoint(vec![StoredMessage::new(header, message)]);
+        for request in std::mem::take(&mut self.deferred_requests) {
+            let (header, message) = request.into_inner();
+
+            self.process_request_state(header, message);
+        }
+    }
+
+    /// Keep the state we received as our latest checkpoint, so we can serve it
+    /// to the replicas which are recovering along with us
+    fn adopt_received_state<V>(&mut self, view: &V, state: &RecoveryState<S>)
+    where
+        V: NetworkView,
+    {
+        let checkpoint = state.checkpoint().clone();
+
+        let newer = self.latest_checkpoint().map_or(true, |latest| {
+            latest.sequence_number() < checkpoint.sequence_number()
+        });
+
+        if !newer {
+            return;
+        }
+
+        let current = std::mem::replace(&mut self.current_checkpoint_state, CheckpointState::None);
+
+        self.current_checkpoint_state = match current {
+            CheckpointState::None | CheckpointState::Complete(_) => {
+                CheckpointState::Complete(checkpoint)
+            }
+            CheckpointState::Partial { seq } | CheckpointState::PartialWithEarlier { seq, .. } => {
+                CheckpointState::PartialWithEarlier {
+                    seq,
+                    earlier: checkpoint,
                 }
+            }
+        };
 
-                return;
+        if let Some(certificate) = state.certificate() {
+            if certificate.certifies(state.checkpoint())
+                && self.certificate_valid(view, certificate)
+            {
+                self.set_stable_certificate(certificate.clone());
             }
+        }
+
+        self.process_deferred_requests();
+    }
+
+    /// Send the queued replies to state requests, as far as our serving budget allows
+    fn serve_pending(&mut self) {
+        let node_id = self.node.id();
+        let latest = self.latest_checkpoint();
+        let history = &self.history;
+        let stable_certificate = self.stable_certificate.as_ref();
+
+        // Older checkpoints are only read back from the archive when a reply which needs them gets its turn
+        self.state_server.serve(&*self.node, |seq| {
+            let checkpoint = match &latest {
+                Some(latest) if latest.sequence_number() == seq => latest.clone(),
+                _ => match history.load(seq) {
+                    Ok(checkpoint) => checkpoint?,
+                    Err(err) => {
+                        error!(
+                            "{:?} // Failed to read checkpoint {:?} from the archive: {:?}",
+                            node_id, seq, err
+                        );
+
+                        return None;
+                    }
+                },
+            };
+
+            let certificate = certificate_of(stable_certificate, history, &checkpoint);
+
+            Some((checkpoint, certificate))
+        });
+
+        metric_store_count(STATE_TRANSFER_SERVE_BACKLOG_ID, self.state_server.backlog());
+    }
+
+    /// Record the checkpoint reported by another replica while we are not recovering,
+    /// checking whether enough replicas are far enough ahead of us that we should
+    fn note_peer_checkpoint<V>(&mut self, view: &V, from: NodeId, seq: SeqNo) -> CstStatus<S>
+    where
+        V: NetworkView,
+    {
+        let Some(threshold) = self.lag_threshold else {
+            return CstStatus::Nil;
         };
 
-        let reply = CstMessage::new(
-            message.sequence_number(),
-            CstMessageKind::ReplyState(RecoveryState { checkpoint: state }),
+        if from == self.node.id() {
+            return CstStatus::Nil;
+        }
+
+        self.peer_checkpoints.report(from, seq);
+
+        let ours = self.current_checkpoint_state.sequence_number();
+
+        let Some(agreed) = self.peer_checkpoints.lagging(view, ours, threshold) else {
+            return CstStatus::Nil;
+        };
+
+        warn!(
+            "{:?} // f+1 replicas are at checkpoint {:?}, while we are at {:?}",
+            self.node.id(),
+            agreed,
+            ours
         );
 
-        self.node.send_signed(reply, header.from(), true).unwrap();
+        self.peer_checkpoints.clear();
+
+        CstStatus::Lagging(agreed)
+    }
+
+    /// Our latest complete checkpoint
+    fn latest_checkpoint(&self) -> Option<Arc<ReadOnly<Checkpoint<S>>>> {
+        match &self.current_checkpoint_state {
+            CheckpointState::PartialWithEarlier { earlier, .. } => Some(earlier.clone()),
+            CheckpointState::Complete(checkpoint) => Some(checkpoint.clone()),
+            _ => None,
+        }
+    }
+
+    /// The stable checkpoint certificate of the given checkpoint, if it has one
+    fn certificate_for(&self, checkpoint: &Checkpoint<S>) -> Option<CheckpointCertificate> {
+        certificate_of(self.stable_certificate.as_ref(), &self.history, checkpoint)
+    }
+
+    /// Whether we still retain the checkpoint with the given sequence number
+    fn retains(&self, seq: SeqNo) -> bool {
+        self.latest_checkpoint()
+            .is_some_and(|latest| latest.sequence_number() == seq)
+            || self.history.retains(seq)
+    }
+
+    fn process_request_chunks(&mut self, header: Header, message: CstMessage<S>) {
+        let CstMessageKind::RequestStateChunks(request) = message.kind() else {
+            return;
+        };
+
+        // We can only serve chunks of the checkpoints we retain
+        if !self.retains(request.sequence_number()) {
+            debug!(
+                "{:?} // Ignoring request from {:?} for chunks of checkpoint {:?}, as we no longer retain it",
+                self.node.id(),
+                header.from(),
+                request.sequence_number()
+            );
+
+            return;
+        }
+
+        // The checkpoint is only loaded, and the chunks read back from it, when their turn to be sent comes
+        self.state_server.queue_chunks(header.from(), message.sequence_number(), request);
+
+        self.serve_pending();
     }
 
     /// Advances the state of the CST state machine.
@@ -642,14 +1018,14 @@ where
                     CstMessageKind::RequestStateCid => {
                         self.process_request_seq(header, message);
                     }
-                    CstMessageKind::RequestState => {
+                    CstMessageKind::RequestState(_) => {
                         self.process_request_state(header, message);
                     }
-                    // we are not running cst, so drop any reply msgs
-                    //
-                    // TODO: maybe inspect cid msgs, and passively start
-                    // the state transfer protocol, by returning
-                    // CstStatus::RequestState
+                    // Late replies to our cid requests tell us how far ahead the others are
+                    CstMessageKind::ReplyStateCid(Some((seq, _))) => {
+                        return self.note_peer_checkpoint(&view, header.from(), *seq);
+                    }
+                    // we are not running cst, so drop any other reply msgs
                     _ => (),
                 }
 
@@ -681,42 +1057,29 @@ where
 
                 match message.kind() {
                     CstMessageKind::ReplyStateCid(state_cid) => {
+                        let replied = self
+                            .received_state_ids
+                            .values()
+                            .any(|nodes| nodes.contains(&header.from()));
+
+                        if replied {
+                            debug!(
+                                "{:?} // Dropping repeated state cid reply from {:?}",
+                                self.node.id(),
+                                header.from()
+                            );
+
+                            return CstStatus::Running;
+                        }
+
                         if let Some((cid, digest)) = state_cid {
-                            debug!("{:?} // Received state cid {:?} with digest {:?} from {:?} with seq {:?}",
-                            self.node.id(), state_cid, digest, header.from(), cid);
-
-                            let currently_active_states = self
-                                .received_state_ids
-                                .entry(*digest)
-                                .or_insert_with(|| ReceivedStateCid {
-                                    cid: *cid,
-                                    count: 0,
-                                });
-
-                            match (*cid).cmp(&currently_active_states.cid) {
-                                Ordering::Greater => {
-                                    info!("{:?} // Received newer state for old cid {:?} vs new cid {:?} with digest {:?}.",
-                                    self.node.id(), currently_active_states.cid, *cid, digest);
-
-                                    currently_active_states.cid = *cid;
-                                    currently_active_states.count = 1;
-                                }
-                                Ordering::Equal => {
-                                    info!("{:?} // Received matching state for cid {:?} with digest {:?}. Count {}",
-                                self.node.id(), currently_active_states.cid, digest, currently_active_states.count + 1);
-
-                                    currently_active_states.count += 1;
-                                }
-                                _ => {
-                                    debug!(
-                                        "{:?} // Received older state cid {:?} vs new cid {:?} with digest {:?}.",
-                                        self.node.id(),
-                                        currently_active_states.cid,
-                                        *cid,
-                                        digest
-                                    );
-                                }
-                            }
+                            debug!(
+                                "{:?} // Received state cid {:?} with digest {:?} from {:?}",
+                                self.node.id(),
+                                cid,
+                                digest,
+                                header.from()
+                            );
                         } else {
                             debug!(
                                 "{:?} // Received blank state cid from node {:?}",
@@ -724,13 +1087,20 @@ where
                                 header.from()
                             );
                         }
+
+                        // Replicas at different checkpoints may still report the same digest,
+                        // e.g. when the state did not change, so only matching pairs are counted
+                        self.received_state_ids
+                            .entry(*state_cid)
+                            .or_default()
+                            .push(header.from());
                     }
                     CstMessageKind::RequestStateCid => {
                         self.process_request_seq(header, message);
 
                         return CstStatus::Running;
                     }
-                    CstMessageKind::RequestState => {
+                    CstMessageKind::RequestState(_) => {
                         self.process_request_state(header, message);
 
                         return CstStatus::Running;
@@ -739,10 +1109,6 @@ where
                     _ => return CstStatus::Running,
                 }
 
-                // check if we have gathered enough cid
-                // replies from peer nodes
-                //
-                // TODO: check for more than one reply from the same node
                 let i = i + 1;
 
                 debug!(
@@ -754,167 +1120,853 @@ where
                     self.received_state_ids
                 );
 
-                if i >= view.quorum() {
+                // f+1 matching replies guarantee at least one correct replica has
+                // the state, so it is enough to then fetch it from any one of them
+                let agreed = self
+                    .received_state_ids
+                    .iter()
+                    .filter_map(|(state_cid, nodes)| state_cid.map(|cid| (cid, nodes)))
+                    .filter(|(_, nodes)| nodes.len() > view.f())
+                    .max_by_key(|((cid, _), _)| *cid);
+
+                if let Some(((seq, digest), nodes)) = agreed {
+                    info!("{:?} // Received f+1 matching states for CST Seq {:?} with digest {:?} and seq {:?} from {:?}",
+                        self.node.id(), self.curr_seq, digest, seq, nodes);
+
+                    self.state_source = Some(StateSource {
+                        seq,
+                        digest,
+                        peers: nodes.clone(),
+                        current: 0,
+                    });
+
                     self.phase = ProtoPhase::Init;
 
                     // reset timeout, since req was successful
                     self.curr_timeout = self.base_timeout;
 
-                    let mut received_state_ids: Vec<_> = self
-                        .received_state_ids
-                        .iter()
-                        .map(|(digest, cid)| (digest, cid.cid, cid.count))
-                        .collect();
-
-                    received_state_ids
-                        .sort_by(|(_, _, count), (_, _, count2)| count.cmp(count2).reverse());
-
-                    if let Some((digest, seq, count)) = received_state_ids.first() {
-                        if *count >= view.quorum() {
-                            info!("{:?} // Received quorum of states for CST Seq {:?} with digest {:?} and seq {:?}",
-                                self.node.id(), self.curr_seq, digest, seq);
+                    return CstStatus::SeqNo(seq);
+                }
 
-                            return CstStatus::SeqNo(*seq);
-                        } else {
-                            warn!("Received quorum state messages but we still don't have a quorum of states? Faulty replica? {:?}", self.received_state_ids)
-                        }
-                    } else {
-                        // If we are completely blank, then no replicas have state, so we can initialize
+                if i >= view.quorum() && self.received_state_ids.keys().all(Option::is_none) {
+                    // If we are completely blank, then no replicas have state, so we can initialize
+                    warn!("We have received a quorum of blank messages, which means we are probably at the start");
 
-                        warn!("We have received a quorum of blank messages, which means we are probably at the start");
-                        return CstStatus::SeqNo(SeqNo::ZERO);
-                    }
+                    self.phase = ProtoPhase::Init;
+                    self.curr_timeout = self.base_timeout;
 
-                    // we don't need the latest cid to be available in at least
-                    // f+1 replicas since the replica has the proof that the system
-                    // has decided
+                    return CstStatus::SeqNo(SeqNo::ZERO);
                 }
 
                 self.phase = ProtoPhase::ReceivingCid(i);
 
                 CstStatus::Running
             }
-            ProtoPhase::ReceivingState(i) => {
-                let (_header, mut message) = getmessage!(progress, CstStatus::RequestState);
+            ProtoPhase::ReceivingState(peer) => {
+                let (header, message) = getmessage!(progress, CstStatus::RequestState);
 
                 if message.sequence_number() != self.curr_seq {
                     // NOTE: check comment above, on ProtoPhase::ReceivingCid
                     return CstStatus::Running;
                 }
 
-                let state = match message.take_state() {
-                    Some(state) => state,
+                if header.from() != peer {
+                    debug!(
+                        "{:?} // Dropping state from {:?}, as we requested it from {:?}",
+                        self.node.id(),
+                        header.from(),
+                        peer
+                    );
+
+                    return CstStatus::Running;
+                }
+
+                let state = match message.into_kind() {
+                    CstMessageKind::ReplyState(state) => state,
+                    CstMessageKind::ReplyStateManifest(manifest) => {
+                        return self.receive_manifest(&view, peer, manifest);
+                    }
                     // drop invalid message kinds
-                    None => return CstStatus::Running,
+                    _ => return CstStatus::Running,
                 };
 
-                let state_digest = *state.checkpoint.digest();
+                // A stable checkpoint does not need to be confirmed by other replicas,
+                // as long as it is not older than the state they agreed on
+                let valid = self.state_expected(&state)
+                    || (self.state_certified(&view, &state)
+                        && self.state_source.as_ref().is_some_and(|source| {
+                            state.checkpoint().sequence_number() >= source.seq
+                        }));
+
+                if !valid {
+                    warn!(
+                        "{:?} // The state received from {:?} does not match the agreed upon state {:?}, trying the next replica",
+                        self.node.id(),
+                        peer,
+                        self.state_source.as_ref().map(|source| (source.seq, source.digest))
+                    );
 
-                debug!(
-                    "{:?} // Received state with digest {:?}, is contained in map? {}",
-                    self.node.id(),
-                    state_digest,
-                    self.received_states.contains_key(&state_digest)
-                );
+                    return self.rotate_state_source();
+                }
 
-                if let std::collections::hash_map::Entry::Vacant(e) =
-                    self.received_states.entry(state_digest)
-                {
-                    e.insert(ReceivedState { count: 1, state });
-                } else {
-                    let current_state = self.received_states.get_mut(&state_digest).unwrap();
+                self.phase = ProtoPhase::Init;
+                self.state_source = None;
+                self.chunked_transfer = None;
 
-                    let current_state_seq: SeqNo =
-                        current_state.state.checkpoint().sequence_number();
-                    let recv_state_seq: SeqNo = state.checkpoint().sequence_number();
+                // reset timeout, since req was successful
+                self.curr_timeout = self.base_timeout;
 
-                    match recv_state_seq.cmp(&current_state_seq) {
-                        Ordering::Less | Ordering::Equal => {
-                            // we have just verified that the state is the same, but the decision log is
-                            // smaller than the one we have already received
-                            current_state.count += 1;
-                        }
-                        Ordering::Greater => {
-                            current_state.state = state;
-                            // We have also verified that the state is the same but the decision log is
-                            // Larger, so we want to store the newest one. However we still want to increment the count
-                            // We can do this since to be in the decision log, a replica must have all of the messages
-                            // From at least 2f+1 replicas, so we know that the log is valid
-                            current_state.count += 1;
-                        }
-                    }
-                }
+                info!("{:?} // Received state for CST Seq {:?} with seq {:?} from {:?}, returning the state to the replica",
+                    self.node.id(), self.curr_seq, state.checkpoint().sequence_number(), peer);
 
-                // check if we have gathered enough state
-                // replies from peer nodes
-                //
-                // TODO: check for more than one reply from the same node
-                let i = i + 1;
+                CstStatus::State(state)
+            }
+            ProtoPhase::ReceivingChunks => {
+                let (header, message) = getmessage!(progress, CstStatus::RequestStateChunks);
 
-                if i <= view.f() {
-                    self.phase = ProtoPhase::ReceivingState(i);
-                    return CstStatus::Running;
-                }
+                let from = header.from();
 
-                // NOTE: clear saved states when we return;
-                // this is important, because each state
-                // may be several GBs in size
-
-                // check if we have at least f+1 matching states
-                let digest = {
-                    let received_state = self.received_states.iter().max_by_key(|(_, st)| st.count);
-
-                    match received_state {
-                        Some((digest, _)) => *digest,
-                        None => {
-                            return if i >= view.quorum() {
-                                self.received_states.clear();
-
-                                debug!(
-                                    "{:?} // No matching states found, clearing",
-                                    self.node.id()
-                                );
-                                CstStatus::RequestState
-                            } else {
-                                CstStatus::Running
-                            };
-                        }
+                // Chunks are verified against the manifest, so unlike the other
+                // replies they are accepted no matter which request they answer
+                let chunk = match message.into_kind() {
+                    CstMessageKind::ReplyStateChunk(chunk) => chunk,
+                    CstMessageKind::ReplyStateManifest(manifest) => {
+                        return self.receive_manifest_confirmation(from, manifest);
                     }
+                    // drop invalid message kinds
+                    _ => return CstStatus::Running,
                 };
 
-                let received_state = {
-                    let received_state = self.received_states.remove(&digest);
-                    self.received_states.clear();
-                    received_state
+                let Some(transfer) = &mut self.chunked_transfer else {
+                    return CstStatus::Running;
                 };
 
-                // reset timeout, since req was successful
-                self.curr_timeout = self.base_timeout;
+                match transfer.receive(from, chunk) {
+                    Ok(()) => {}
+                    Err(ChunkError::InvalidChunk(index, _)) if !transfer.confirmed() => {
+                        // We only fetch from the provider of an unconfirmed manifest, and we can't
+                        // tell whether it was the chunk or the manifest which was wrong
+                        warn!(
+                            "{:?} // Received chunk {} from {:?} which does not match its own manifest, trying the next replica",
+                            self.node.id(),
+                            index,
+                            from
+                        );
+
+                        self.chunked_transfer = None;
 
-                // return the state
-                let f = view.f();
+                        return self.rotate_state_source();
+                    }
+                    Err(ChunkError::InvalidChunk(index, _)) => {
+                        warn!(
+                            "{:?} // Received invalid chunk {} from {:?}, no longer fetching chunks from it",
+                            self.node.id(),
+                            index,
+                            from
+                        );
 
-                match received_state {
-                    Some(ReceivedState { count, state }) if count > f => {
-                        self.phase = ProtoPhase::Init;
+                        transfer.release(from);
 
-                        info!("{:?} // Received quorum of states for CST Seq {:?} with digest {:?}, returning the state to the replica",
-                            self.node.id(), self.curr_seq, digest);
+                        if let Some(source) = &mut self.state_source {
+                            source.peers.retain(|peer| *peer != from);
+                        }
 
-                        CstStatus::State(state)
+                        return CstStatus::RequestStateChunks;
                     }
-                    _ => {
+                    Err(err) => {
                         debug!(
-                            "{:?} // No states with more than f {} count",
+                            "{:?} // Dropping chunk from {:?}: {:?}",
                             self.node.id(),
-                            f
+                            from,
+                            err
                         );
 
-                        CstStatus::RequestState
+                        return CstStatus::Running;
                     }
                 }
+
+                debug!(
+                    "{:?} // Received chunk from {:?}, {}/{} chunks received",
+                    self.node.id(),
+                    from,
+                    transfer.received(),
+                    transfer.total()
+                );
+
+                if transfer.is_complete() {
+                    return self.install_chunked_state();
+                }
+
+                CstStatus::RequestStateChunks
+            }
+        }
+    }
+
+    /// Start (or resume) receiving the state described by the given manifest in chunks
+    fn receive_manifest<V>(&mut self, view: &V, peer: NodeId, manifest: StateManifest) -> CstStatus<S>
+    where
+        V: NetworkView,
+    {
+        let seq = manifest.sequence_number();
+
+        let expected = self
+            .state_source
+            .as_ref()
+            .is_some_and(|source| seq == source.seq && *manifest.digest() == source.digest);
+
+        // As with whole states, a certified checkpoint may be newer than the one agreed on
+        let certified = !expected
+            && self.state_source.as_ref().is_some_and(|source| seq >= source.seq)
+            && manifest.certificate().is_some_and(|certificate| {
+                certificate.is_for(seq, manifest.digest())
+                    && self.certificate_valid(view, certificate)
+            });
+
+        if !expected && !certified {
+            warn!(
+                "{:?} // The manifest received from {:?} does not match the agreed upon state {:?}, trying the next replica",
+                self.node.id(),
+                peer,
+                self.state_source.as_ref().map(|source| (source.seq, source.digest))
+            );
+
+            return self.rotate_state_source();
+        }
+
+        if certified {
+            // Only the signers of the certificate are known to have this checkpoint
+            let peers: Vec<NodeId> = manifest
+                .certificate()
+                .into_iter()
+                .flat_map(|certificate| certificate.signers())
+                .filter(|signer| *signer != self.node.id())
+                .collect();
+
+            self.state_source = Some(StateSource {
+                seq,
+                digest: *manifest.digest(),
+                peers,
+                current: 0,
+            });
+        }
+
+        let resumable = self
+            .chunked_transfer
+            .as_ref()
+            .is_some_and(|transfer| transfer.resumable_with(&manifest));
+
+        if resumable {
+            if let Some(transfer) = &mut self.chunked_transfer {
+                info!(
+                    "{:?} // Resuming the transfer of checkpoint {:?} with {}/{} chunks already received",
+                    self.node.id(),
+                    seq,
+                    transfer.received(),
+                    transfer.total()
+                );
+
+                transfer.resume_from(peer);
+            }
+        } else {
+            match ChunkedTransfer::new(manifest, peer, view.f() + 1) {
+                Ok(mut transfer) => {
+                    let reused = self.reuse_local_chunks(&mut transfer);
+
+                    info!(
+                        "{:?} // Receiving checkpoint {:?} in {} chunks, {} of which we already have",
+                        self.node.id(),
+                        seq,
+                        transfer.total(),
+                        reused
+                    );
+
+                    self.chunked_transfer = Some(transfer);
+                }
+                Err(err) => {
+                    warn!(
+                        "{:?} // Received an invalid manifest from {:?}: {:?}",
+                        self.node.id(),
+                        peer,
+                        err
+                    );
+
+                    return self.rotate_state_source();
+                }
             }
         }
+
+        if self
+            .chunked_transfer
+            .as_ref()
+            .is_some_and(|transfer| transfer.is_complete())
+        {
+            return self.install_chunked_state();
+        }
+
+        self.phase = ProtoPhase::ReceivingChunks;
+
+        self.next_seq();
+        self.request_manifest_confirmations();
+        self.request_chunks_timeout();
+
+        CstStatus::RequestStateChunks
+    }
+
+    /// Ask the other replicas which have the state for their manifest of it,
+    /// so we can confirm the one we are following and fetch from all of them
+    fn request_manifest_confirmations(&mut self) {
+        let (Some(transfer), Some(source)) = (&self.chunked_transfer, &self.state_source) else {
+            return;
+        };
+
+        if transfer.confirmed() {
+            return;
+        }
+
+        let base = self
+            .latest_checkpoint()
+            .map(|checkpoint| (checkpoint.sequence_number(), *checkpoint.digest()));
+
+        let message = CstMessage::new(
+            self.curr_seq,
+            CstMessageKind::RequestState(StateRequest::new(
+                Some(transfer.manifest().sequence_number()),
+                base,
+            )),
+        );
+
+        let targets = source
+            .peers
+            .iter()
+            .copied()
+            .filter(|peer| *peer != transfer.provider() && *peer != self.node.id());
+
+        let _ = self.node.broadcast_signed(message, targets);
+    }
+
+    /// Another replica which has the state handed out its manifest of it,
+    /// which confirms ours if they match
+    fn receive_manifest_confirmation(
+        &mut self,
+        from: NodeId,
+        manifest: StateManifest,
+    ) -> CstStatus<S> {
+        let (Some(transfer), Some(source)) = (&mut self.chunked_transfer, &self.state_source)
+        else {
+            return CstStatus::Running;
+        };
+
+        if transfer.confirmed() || !source.peers.contains(&from) {
+            return CstStatus::Running;
+        }
+
+        if !transfer.vouch(from, &manifest) {
+            warn!(
+                "{:?} // The manifest of checkpoint {:?} sent by {:?} does not match the one sent by {:?}",
+                self.node.id(),
+                manifest.sequence_number(),
+                from,
+                transfer.provider()
+            );
+
+            return CstStatus::Running;
+        }
+
+        if !transfer.confirmed() {
+            return CstStatus::Running;
+        }
+
+        info!(
+            "{:?} // The manifest of checkpoint {:?} is confirmed, fetching chunks from every replica",
+            self.node.id(),
+            manifest.sequence_number()
+        );
+
+        CstStatus::RequestStateChunks
+    }
+
+    /// Take the chunks of the state we are receiving which our own latest checkpoint
+    /// also has, so we only fetch the difference between the two
+    fn reuse_local_chunks(&mut self, transfer: &mut ChunkedTransfer) -> usize {
+        let Some(base) = self.latest_checkpoint() else {
+            return 0;
+        };
+
+        let certificate = self.certificate_for(&base);
+
+        let reused = match self
+            .state_server
+            .served_state(&base, certificate)
+            .and_then(|served| transfer.reuse(served))
+        {
+            Ok(reused) => reused,
+            Err(err) => {
+                warn!(
+                    "{:?} // Failed to reuse the chunks of our checkpoint {:?}: {:?}",
+                    self.node.id(),
+                    base.sequence_number(),
+                    err
+                );
+
+                return 0;
+            }
+        };
+
+        metric_increment(STATE_TRANSFER_REUSED_CHUNKS_ID, Some(reused as u64));
+
+        reused
+    }
+
+    /// Request the missing chunks from the replicas which have the state,
+    /// keeping each of them with at most the configured amount of chunks in flight
+    fn request_state_chunks(&mut self) {
+        let (Some(transfer), Some(source)) = (&mut self.chunked_transfer, &self.state_source)
+        else {
+            return;
+        };
+
+        let seq = transfer.manifest().sequence_number();
+        let chunk_size = transfer.manifest().chunk_size();
+
+        for peer in &source.peers {
+            let chunks = transfer.assign(*peer, self.chunks_in_flight);
+
+            if chunks.is_empty() {
+                continue;
+            }
+
+            debug!(
+                "{:?} // Requesting chunks {:?} of checkpoint {:?} from {:?}",
+                self.node.id(),
+                chunks,
+                seq,
+                peer
+            );
+
+            let message = CstMessage::new(
+                self.curr_seq,
+                CstMessageKind::RequestStateChunks(StateChunkRequest::new(seq, chunk_size, chunks)),
+            );
+
+            let _ = self.node.send_signed(message, *peer, true);
+        }
+    }
+
+    fn request_chunks_timeout(&mut self) {
+        let missing = self
+            .chunked_transfer
+            .as_ref()
+            .map_or(1, |transfer| transfer.total() - transfer.received());
+
+        let _ = self.timeouts.request_timeout(
+            TimeoutID::SeqNoBased(self.curr_seq),
+            None,
+            self.curr_timeout,
+            missing.max(1),
+            false,
+        );
+    }
+
+    /// Put the received chunks back together and deserialize the state
+    fn install_chunked_state(&mut self) -> CstStatus<S> {
+        let Some(transfer) = self.chunked_transfer.take() else {
+            return CstStatus::Running;
+        };
+
+        let seq = transfer.manifest().sequence_number();
+        let digest = *transfer.manifest().digest();
+
+        let state = transfer
+            .assemble()
+            .and_then(|serialized| S::deserialize_state(&serialized[..]));
+
+        match state {
+            Ok(state) => {
+                self.phase = ProtoPhase::Init;
+                self.state_source = None;
+
+                // reset timeout, since req was successful
+                self.curr_timeout = self.base_timeout;
+
+                info!("{:?} // Received every chunk of checkpoint {:?} for CST Seq {:?}, returning the state to the replica",
+                    self.node.id(), seq, self.curr_seq);
+
+                CstStatus::State(RecoveryState::new(Checkpoint::new(seq, state, digest)))
+            }
+            Err(err) => {
+                // The chunks matched the manifest, so it was the manifest which was wrong
+                warn!(
+                    "{:?} // Failed to assemble checkpoint {:?}: {:?}, trying the next replica",
+                    self.node.id(),
+                    seq,
+                    err
+                );
+
+                self.rotate_state_source()
+            }
+        }
+    }
+
+    /// The replicas we requested chunks from have not delivered any of them in time,
+    /// so we give up on them. The chunks we already have are kept, and reused if we
+    /// end up receiving the same checkpoint
+    fn chunks_timed_out(&mut self) -> CstStatus<S> {
+        let (Some(transfer), Some(source)) = (&mut self.chunked_transfer, &mut self.state_source)
+        else {
+            return CstStatus::RequestStateCid;
+        };
+
+        let stalled = transfer.take_stalled();
+
+        for peer in &stalled {
+            transfer.release(*peer);
+        }
+
+        source.peers.retain(|peer| !stalled.contains(peer));
+
+        warn!(
+            "{:?} // Timed out receiving chunks from {:?}, {}/{} chunks received",
+            self.node.id(),
+            stalled,
+            transfer.received(),
+            transfer.total()
+        );
+
+        if source.peers.is_empty() {
+            self.state_source = None;
+            self.curr_timeout *= 2;
+
+            return CstStatus::RequestStateCid;
+        }
+
+        // No one else may be fetched from until the manifest is confirmed, so we ask
+        // the next replica for its manifest, which resumes the transfer if it matches
+        if !transfer.confirmed() && stalled.contains(&transfer.provider()) {
+            return self.rotate_state_source();
+        }
+
+        self.request_chunks_timeout();
+
+        CstStatus::RequestStateChunks
+    }
+
+    /// Sign the given checkpoint and send the vote to the other replicas,
+    /// so the checkpoint can become stable
+    fn vote_checkpoint(&mut self, checkpoint: &Checkpoint<S>) {
+        let key_pair = self.node.network_info_provider().get_key_pair().clone();
+
+        let seq = checkpoint.sequence_number();
+
+        let signature = match sign_checkpoint(&key_pair, seq, checkpoint.digest()) {
+            Ok(signature) => signature,
+            Err(err) => {
+                error!(
+                    "{:?} // Failed to sign the checkpoint {:?}: {:?}",
+                    self.node.id(),
+                    seq,
+                    err
+                );
+
+                return;
+            }
+        };
+
+        let vote = CheckpointVote::new(seq, *checkpoint.digest(), signature);
+
+        self.checkpoint_votes.insert(self.node.id(), vote.clone());
+
+        if self.known_view.is_some() {
+            self.send_vote(vote);
+        } else {
+            self.unsent_vote = Some(vote);
+        }
+
+        self.try_stabilize_checkpoint();
+    }
+
+    fn send_vote(&self, vote: CheckpointVote) {
+        let Some(known_view) = &self.known_view else {
+            return;
+        };
+
+        debug!(
+            "{:?} // Sending checkpoint vote for {:?}",
+            self.node.id(),
+            vote.sequence_number()
+        );
+
+        let message = CstMessage::new(vote.sequence_number(), CstMessageKind::CheckpointVote(vote));
+
+        let targets = known_view
+            .members
+            .clone()
+            .into_iter()
+            .filter(|id| *id != self.node.id());
+
+        let _ = self.node.broadcast_signed(message, targets);
+    }
+
+    /// Keep track of the latest view we were handed, sending our
+    /// checkpoint vote if it was waiting for it
+    fn note_view<V>(&mut self, view: &V)
+    where
+        V: NetworkView,
+    {
+        self.known_view = Some(KnownView {
+            members: view.quorum_members().clone(),
+            quorum: view.quorum(),
+        });
+
+        // Votes we received before knowing the view, or from replicas which left it
+        self.checkpoint_votes.retain_members(view.quorum_members());
+
+        if let Some(vote) = self.unsent_vote.take() {
+            self.send_vote(vote);
+
+            self.try_stabilize_checkpoint();
+        }
+    }
+
+    /// Keep a checkpoint vote from another replica, which also tells us how far ahead it is
+    fn process_checkpoint_vote<V>(
+        &mut self,
+        view: &V,
+        header: Header,
+        message: CstMessage<S>,
+    ) -> CstStatus<S>
+    where
+        V: NetworkView,
+    {
+        let CstMessageKind::CheckpointVote(vote) = message.kind() else {
+            return CstStatus::Nil;
+        };
+
+        let from = header.from();
+
+        // Until we know the view, the votes are kept and then filtered once we learn it
+        if self
+            .known_view
+            .as_ref()
+            .is_some_and(|known_view| !known_view.members.contains(&from))
+        {
+            debug!(
+                "{:?} // Dropping checkpoint vote from {:?}, which is not a member of the view",
+                self.node.id(),
+                from
+            );
+
+            return CstStatus::Nil;
+        }
+
+        if vote.sequence_number() != message.sequence_number() {
+            debug!(
+                "{:?} // Dropping checkpoint vote from {:?} with mismatched seq {:?} vs {:?}",
+                self.node.id(),
+                from,
+                message.sequence_number(),
+                vote.sequence_number()
+            );
+
+            return CstStatus::Nil;
+        }
+
+        let vote_valid = self
+            .node
+            .network_info_provider()
+            .get_node_info(&from)
+            .is_some_and(|info| verify_vote(info.public_key(), vote).is_ok());
+
+        if !vote_valid {
+            warn!(
+                "{:?} // Dropping checkpoint vote from {:?} with an invalid signature",
+                self.node.id(),
+                from
+            );
+
+            return CstStatus::Nil;
+        }
+
+        if self.checkpoint_votes.insert(from, vote.clone()) {
+            self.try_stabilize_checkpoint();
+        }
+
+        // Every replica votes for each checkpoint it reaches, so we hear how far ahead
+        // the others are even when we never ask them for their state cid
+        if matches!(self.phase, ProtoPhase::Init) {
+            return self.note_peer_checkpoint(view, from, vote.sequence_number());
+        }
+
+        CstStatus::Nil
+    }
+
+    /// Check whether a quorum voted for our latest checkpoint, making it stable
+    fn try_stabilize_checkpoint(&mut self) {
+        let checkpoint = match &self.current_checkpoint_state {
+            CheckpointState::PartialWithEarlier { earlier, .. } => earlier,
+            CheckpointState::Complete(checkpoint) => checkpoint,
+            _ => return,
+        };
+
+        let Some(known_view) = &self.known_view else {
+            return;
+        };
+
+        if self
+            .stable_certificate
+            .as_ref()
+            .is_some_and(|certificate| certificate.certifies(checkpoint))
+        {
+            return;
+        }
+
+        let seq = checkpoint.sequence_number();
+
+        if let Some(certificate) =
+            self.checkpoint_votes
+                .certificate(seq, checkpoint.digest(), known_view.quorum)
+        {
+            info!(
+                "{:?} // Checkpoint {:?} is now stable with certificate {:?}",
+                self.node.id(),
+                seq,
+                certificate
+            );
+
+            self.history.certify(&certificate);
+
+            self.set_stable_certificate(certificate);
+        } else if let Some(digest) =
+            self.checkpoint_votes
+                .conflicting(seq, checkpoint.digest(), known_view.quorum)
+        {
+            error!(
+                "{:?} // A quorum agreed on digest {:?} for checkpoint {:?}, but ours is {:?}",
+                self.node.id(),
+                digest,
+                seq,
+                checkpoint.digest()
+            );
+        }
+    }
+
+    /// Keep the certificate of the latest stable checkpoint, and tell the listener about it
+    fn set_stable_certificate(&mut self, certificate: CheckpointCertificate) {
+        let seq = certificate.sequence_number();
+
+        self.state_server.certify(&certificate);
+
+        self.stable_certificate = Some(certificate);
+
+        if let Some(listener) = &self.stable_checkpoint_listener {
+            listener(seq);
+        }
+    }
+
+    /// Whether the given state carries a valid certificate of a stable checkpoint,
+    /// which matches the state itself
+    fn state_certified<V>(&self, view: &V, state: &RecoveryState<S>) -> bool
+    where
+        V: NetworkView,
+    {
+        let Some(certificate) = state.certificate() else {
+            return false;
+        };
+
+        if !certificate.certifies(state.checkpoint()) || !self.certificate_valid(view, certificate) {
+            return false;
+        }
+
+        match digest_state(state.checkpoint().state()) {
+            Ok(digest) if digest == *state.checkpoint().digest() => true,
+            Ok(digest) => {
+                warn!(
+                    "{:?} // Received certified state whose digest {:?} does not match the checkpoint's {:?}",
+                    self.node.id(),
+                    digest,
+                    state.checkpoint().digest()
+                );
+
+                false
+            }
+            Err(err) => {
+                warn!(
+                    "{:?} // Failed to digest the received state: {:?}",
+                    self.node.id(),
+                    err
+                );
+
+                false
+            }
+        }
+    }
+
+    fn certificate_valid<V>(&self, view: &V, certificate: &CheckpointCertificate) -> bool
+    where
+        V: NetworkView,
+    {
+        let network_info = self.node.network_info_provider();
+
+        let verified = certificate.verify(view, |signer| {
+            network_info
+                .get_node_info(&signer)
+                .map(|info| info.public_key().clone())
+        });
+
+        if let Err(err) = &verified {
+            warn!(
+                "{:?} // Received an invalid checkpoint certificate: {:?}",
+                self.node.id(),
+                err
+            );
+        }
+
+        verified.is_ok()
+    }
+
+    /// Whether the given state is the one f+1 replicas agreed on
+    fn state_expected(&self, state: &RecoveryState<S>) -> bool {
+        let Some(source) = &self.state_source else {
+            return false;
+        };
+
+        let checkpoint = state.checkpoint();
+
+        if checkpoint.sequence_number() != source.seq || *checkpoint.digest() != source.digest {
+            return false;
+        }
+
+        // The replica which sent the state may have lied about its contents
+        match digest_state(checkpoint.state()) {
+            Ok(digest) => digest == source.digest,
+            Err(err) => {
+                warn!(
+                    "{:?} // Failed to digest the received state: {:?}",
+                    self.node.id(),
+                    err
+                );
+
+                false
+            }
+        }
+    }
+
+    /// Move on to the next replica which has the agreed upon state.
+    /// Once we have tried all of them, we have to find out the latest state again
+    fn rotate_state_source(&mut self) -> CstStatus<S> {
+        let Some(source) = &mut self.state_source else {
+            return CstStatus::RequestStateCid;
+        };
+
+        source.current += 1;
+
+        if source.current < source.peers.len() {
+            CstStatus::RequestState
+        } else {
+            self.state_source = None;
+            self.curr_timeout *= 2;
+
+            CstStatus::RequestStateCid
+        }
     }
 
     /// End the state of an on-going checkpoint.
@@ -922,6 +1974,7 @@ where
     /// This method should only be called when `finalize_request()` reports
     /// `Info::BeginCheckpoint`, and the requested application state is received
     /// on the core server task's master channel.
+    /// The digest of the checkpoint must be the one given by [digest_state].
     pub fn finalize_checkpoint(&mut self, checkpoint: Arc<ReadOnly<Checkpoint<S>>>) -> Result<()>
     where
         PL: MonolithicStateLog<S>,
@@ -933,14 +1986,61 @@ where
             CheckpointState::Complete(_) => {
                 Err!(StateTransferError::CheckpointAlreadyFinalized)
             }
+            CheckpointState::PartialWithEarlier { earlier, .. }
+                if earlier.sequence_number() > checkpoint.sequence_number() =>
+            {
+                // We adopted a newer state from the others while the application was
+                // checkpointing, so this checkpoint is already outdated
+                warn!(
+                    "{:?} // Discarding checkpoint {:?}, as we already have the newer checkpoint {:?}",
+                    self.node.id(),
+                    checkpoint.sequence_number(),
+                    earlier.sequence_number()
+                );
+
+                self.current_checkpoint_state = CheckpointState::Complete(earlier.clone());
+
+                Ok(())
+            }
             CheckpointState::Partial { seq: _ }
             | CheckpointState::PartialWithEarlier { seq: _, .. } => {
+                // The others check the states we send them against this digest
+                let digest = digest_state(checkpoint.state())?;
+
+                if digest != *checkpoint.digest() {
+                    return Err!(StateTransferError::MismatchedCheckpointDigest(
+                        checkpoint.sequence_number(),
+                        *checkpoint.digest(),
+                        digest
+                    ));
+                }
+
                 let checkpoint_state = CheckpointState::Complete(checkpoint.clone());
 
                 self.current_checkpoint_state = checkpoint_state;
 
                 self.persistent_log
-                    .write_checkpoint(OperationMode::NonBlockingSync(None), checkpoint)?;
+                    .write_checkpoint(OperationMode::NonBlockingSync(None), checkpoint.clone())?;
+
+                if let Err(err) = self.history.record(&checkpoint) {
+                    warn!(
+                        "{:?} // Failed to archive checkpoint {:?}: {:?}",
+                        self.node.id(),
+                        checkpoint.sequence_number(),
+                        err
+                    );
+                }
+
+                // The serialized copies of the checkpoints we no longer retain are of no more use
+                let latest = checkpoint.sequence_number();
+                let history = &self.history;
+
+                self.state_server
+                    .retain_served(|seq| seq == latest || history.retains(seq));
+
+                self.vote_checkpoint(&checkpoint);
+
+                self.process_deferred_requests();
 
                 Ok(())
             }
@@ -977,6 +2077,11 @@ where
 
                 true
             }
+            CstStatus::RequestStateChunks => {
+                self.request_state_chunks();
+
+                true
+            }
             // nothing to do
             _ => false,
         }
@@ -1002,10 +2107,9 @@ where
                 self.curr_timeout *= 2;
                 CstStatus::RequestStateCid
             }
-            ProtoPhase::ReceivingState(_) => {
-                self.curr_timeout *= 2;
-                CstStatus::RequestState
-            }
+            // the replica we asked did not deliver, so try the next one
+            ProtoPhase::ReceivingState(_) => self.rotate_state_source(),
+            ProtoPhase::ReceivingChunks => self.chunks_timed_out(),
             // ignore timeouts if not receiving any kind
             // of state from peer nodes
             _ => CstStatus::Nil,
@@ -1018,8 +2122,15 @@ where
     where
         V: NetworkView,
     {
+        self.note_view(&view);
+
         // Reset the map of received state ids
         self.received_state_ids.clear();
+        self.state_source = None;
+
+        // We are recovering already
+        self.peer_checkpoints.clear();
+        self.detected_lag = None;
 
         self.next_seq();
 
@@ -1039,7 +2150,14 @@ where
             false,
         );
 
-        self.phase = ProtoPhase::ReceivingCid(0);
+        // The requests waiting for our checkpoint must still be answered while we recover
+        let previous = std::mem::replace(&mut self.phase, ProtoPhase::ReceivingCid(0));
+
+        if let ProtoPhase::WaitingCheckpoint(waiting) = previous {
+            waiting
+                .into_iter()
+                .for_each(|request| self.defer_state_request(request));
+        }
 
         let message = CstMessage::new(cst_seq, CstMessageKind::RequestStateCid);
 
@@ -1053,20 +2171,34 @@ where
     }
 
     /// Used by a recovering node to retrieve the latest state.
+    ///
+    /// The state is fetched from a single one of the replicas which agreed on it,
+    /// so we must first learn which state to fetch with [Self::request_latest_consensus_seq_no].
     pub fn request_latest_state<V>(&mut self, view: V)
     where
         V: NetworkView,
     {
-        // reset hashmap of received states
-        self.received_states.clear();
+        let Some((peer, target)) = self
+            .state_source
+            .as_ref()
+            .and_then(|source| Some((*source.peers.get(source.current)?, source.seq)))
+        else {
+            // We don't know which state to fetch (or who has it) yet
+            self.request_latest_consensus_seq_no(view);
+
+            return;
+        };
+
+        self.note_view(&view);
 
         self.next_seq();
 
         let cst_seq = self.curr_seq();
 
         info!(
-            "{:?} // Requesting latest state with cst msg seq {:?}",
+            "{:?} // Requesting latest state from {:?} with cst msg seq {:?}",
             self.node.id(),
+            peer,
             cst_seq
         );
 
@@ -1074,22 +2206,26 @@ where
             TimeoutID::SeqNoBased(cst_seq),
             None,
             self.curr_timeout,
-            view.quorum(),
+            1,
             false,
         );
 
-        self.phase = ProtoPhase::ReceivingState(0);
+        self.phase = ProtoPhase::ReceivingState(peer);
 
         //TODO: Maybe attempt to use followers to rebuild state and avoid
         // Overloading the replicas
-        let message = CstMessage::new(cst_seq, CstMessageKind::RequestState);
-        let targets = view
-            .quorum_members()
-            .clone()
-            .into_iter()
-            .filter(|id| *id != self.node.id());
+        // Ask for the checkpoint the replicas agreed on, and let the replica know
+        // which checkpoint we have, so we only fetch what changed since
+        let base = self
+            .latest_checkpoint()
+            .map(|checkpoint| (checkpoint.sequence_number(), *checkpoint.digest()));
+
+        let message = CstMessage::new(
+            cst_seq,
+            CstMessageKind::RequestState(StateRequest::new(Some(target), base)),
+        );
 
-        let _ = self.node.broadcast_signed(message, targets);
+        let _ = self.node.send_signed(message, peer, true);
     }
 }
 
@@ -1109,10 +2245,25 @@ impl<S> Orderable for CheckpointState<S> {
     }
 }
 
+/// The stable checkpoint certificate of the given checkpoint, either the latest
+/// stable one or the one we retained along with an older checkpoint
+fn certificate_of<S>(
+    stable_certificate: Option<&CheckpointCertificate>,
+    history: &CheckpointHistory<S>,
+    checkpoint: &Checkpoint<S>,
+) -> Option<CheckpointCertificate> {
+    stable_certificate
+        .filter(|certificate| certificate.certifies(checkpoint))
+        .or_else(|| history.certificate(checkpoint))
+        .cloned()
+}
+
 #[derive(Error, Debug)]
 pub enum StateTransferError {
     #[error("The checkpoint has already been finalized")]
     CheckpointAlreadyFinalized,
     #[error("No checkpoint has been initiated yet")]
     CheckpointNotInitiated,
+    #[error("Checkpoint {0:?} has the digest {1:?} but its state digests to {2:?}")]
+    MismatchedCheckpointDigest(SeqNo, Digest, Digest),
 }
@@ -1,11 +1,13 @@
 use std::fmt::{Debug, Formatter};
 
 use atlas_common::crypto::hash::Digest;
+use atlas_common::crypto::signature::Signature;
 #[cfg(feature = "serialize_serde")]
 use serde::{Deserialize, Serialize};
 
 use atlas_common::ordering::{Orderable, SeqNo};
 
+use crate::certificate::CheckpointCertificate;
 use crate::RecoveryState;
 
 pub mod serialize;
@@ -22,8 +24,8 @@ pub struct CstMessage<S> {
 impl<S> Debug for CstMessage<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match &self.kind {
-            CstMessageKind::RequestState => {
-                write!(f, "Request state message")
+            CstMessageKind::RequestState(request) => {
+                write!(f, "Request state message with base {:?}", request.base)
             }
             CstMessageKind::ReplyState(_) => {
                 write!(f, "Reply with state message")
@@ -38,6 +40,39 @@ impl<S> Debug for CstMessage<S> {
                     write!(f, "Reply with state cid message None")
                 }
             }
+            CstMessageKind::ReplyStateManifest(manifest) => {
+                write!(
+                    f,
+                    "Reply with state manifest message {:?} {:?} ({} chunks)",
+                    manifest.seq,
+                    manifest.digest,
+                    manifest.chunks.len()
+                )
+            }
+            CstMessageKind::RequestStateChunks(request) => {
+                write!(
+                    f,
+                    "Request state chunks message {:?} {:?}",
+                    request.seq,
+                    request.chunks
+                )
+            }
+            CstMessageKind::ReplyStateChunk(chunk) => {
+                write!(
+                    f,
+                    "Reply with state chunk message {:?} {}",
+                    chunk.seq,
+                    chunk.index
+                )
+            }
+            CstMessageKind::CheckpointVote(vote) => {
+                write!(
+                    f,
+                    "Checkpoint vote message {:?} {:?}",
+                    vote.seq,
+                    vote.digest
+                )
+            }
         }
     }
 }
@@ -47,8 +82,73 @@ impl<S> Debug for CstMessage<S> {
 pub enum CstMessageKind<S> {
     RequestStateCid,
     ReplyStateCid(Option<(SeqNo, Digest)>),
-    RequestState,
+    RequestState(StateRequest),
     ReplyState(RecoveryState<S>),
+    /// Sent instead of the state when it does not fit in a single chunk
+    ReplyStateManifest(StateManifest),
+    RequestStateChunks(StateChunkRequest),
+    ReplyStateChunk(StateChunk),
+    CheckpointVote(CheckpointVote),
+}
+
+/// Asks for the latest state, or for a specific checkpoint
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct StateRequest {
+    /// The sequence number of the checkpoint the requester wants, if it wants a specific one.
+    /// The latest state is served when the replica no longer retains that checkpoint
+    checkpoint: Option<SeqNo>,
+    /// The last checkpoint of the requester. When present, the requester only
+    /// needs the chunks which differ from it, so the state is described in finer chunks
+    base: Option<(SeqNo, Digest)>,
+}
+
+/// Describes a checkpoint which was split into chunks, so the chunks
+/// can be verified individually as they are received
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct StateManifest {
+    seq: SeqNo,
+    /// The digest of the whole checkpoint
+    digest: Digest,
+    /// The size of the serialized state
+    size: u64,
+    /// The average size of the chunks, which are cut by their content
+    chunk_size: u64,
+    /// The digest of each of the chunks, in order
+    chunks: Vec<Digest>,
+    /// The length of each of the chunks, in order
+    lengths: Vec<u64>,
+    certificate: Option<CheckpointCertificate>,
+}
+
+/// Asks for the given chunks of the checkpoint with the given sequence number
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone, Debug)]
+pub struct StateChunkRequest {
+    seq: SeqNo,
+    /// The average chunk size of the manifest the requester is following
+    chunk_size: u64,
+    chunks: Vec<u32>,
+}
+
+/// A chunk of a serialized checkpoint
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct StateChunk {
+    seq: SeqNo,
+    index: u32,
+    data: Vec<u8>,
+}
+
+/// A replica's signed statement that it has finalized the checkpoint
+/// with the given sequence number and state digest
+#[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
+pub struct CheckpointVote {
+    seq: SeqNo,
+    digest: Digest,
+    signature: Signature,
 }
 
 impl<S> Orderable for CstMessage<S> {
@@ -58,6 +158,157 @@ impl<S> Orderable for CstMessage<S> {
     }
 }
 
+impl StateRequest {
+    pub fn new(checkpoint: Option<SeqNo>, base: Option<(SeqNo, Digest)>) -> Self {
+        Self { checkpoint, base }
+    }
+
+    pub fn checkpoint(&self) -> Option<SeqNo> {
+        self.checkpoint
+    }
+
+    pub fn base(&self) -> Option<&(SeqNo, Digest)> {
+        self.base.as_ref()
+    }
+}
+
+impl StateManifest {
+    pub fn new(
+        seq: SeqNo,
+        digest: Digest,
+        size: u64,
+        chunk_size: u64,
+        chunks: Vec<Digest>,
+        lengths: Vec<u64>,
+        certificate: Option<CheckpointCertificate>,
+    ) -> Self {
+        Self {
+            seq,
+            digest,
+            size,
+            chunk_size,
+            chunks,
+            lengths,
+            certificate,
+        }
+    }
+
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    pub fn chunks(&self) -> &[Digest] {
+        &self.chunks
+    }
+
+    pub fn lengths(&self) -> &[u64] {
+        &self.lengths
+    }
+
+    pub fn certificate(&self) -> Option<&CheckpointCertificate> {
+        self.certificate.as_ref()
+    }
+}
+
+impl Orderable for StateManifest {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl StateChunkRequest {
+    pub fn new(seq: SeqNo, chunk_size: u64, chunks: Vec<u32>) -> Self {
+        Self {
+            seq,
+            chunk_size,
+            chunks,
+        }
+    }
+
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    pub fn chunks(&self) -> &[u32] {
+        &self.chunks
+    }
+}
+
+impl Orderable for StateChunkRequest {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl StateChunk {
+    pub fn new(seq: SeqNo, index: u32, data: Vec<u8>) -> Self {
+        Self { seq, index, data }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl Orderable for StateChunk {
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl Debug for StateChunk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "StateChunk {{ seq: {:?}, index: {}, len: {} }}",
+            self.seq,
+            self.index,
+            self.data.len()
+        )
+    }
+}
+
+impl Orderable for CheckpointVote {
+    /// Returns the sequence number of the checkpoint this vote is for.
+    fn sequence_number(&self) -> SeqNo {
+        self.seq
+    }
+}
+
+impl CheckpointVote {
+    pub fn new(seq: SeqNo, digest: Digest, signature: Signature) -> Self {
+        Self {
+            seq,
+            digest,
+            signature,
+        }
+    }
+
+    pub fn digest(&self) -> &Digest {
+        &self.digest
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+}
+
 impl<S> CstMessage<S> {
     /// Creates a new `CstMessage` with sequence number `seq`,
     /// and of the kind `kind`.
@@ -70,9 +321,14 @@ impl<S> CstMessage<S> {
         &self.kind
     }
 
+    /// Consumes the message, returning its kind.
+    pub fn into_kind(self) -> CstMessageKind<S> {
+        self.kind
+    }
+
     /// Takes the recovery state embedded in this cst message, if it is available.
     pub fn take_state(&mut self) -> Option<RecoveryState<S>> {
-        let kind = std::mem::replace(&mut self.kind, CstMessageKind::RequestState);
+        let kind = std::mem::replace(&mut self.kind, CstMessageKind::RequestStateCid);
         match kind {
             CstMessageKind::ReplyState(state) => Some(state),
             _ => {
@@ -6,12 +6,44 @@ use atlas_metrics::{MetricLevel, MetricRegistry};
 pub const STATE_TRANSFER_STATE_INSTALL_CLONE_TIME: &str = "LT_STATE_CLONE_TIME";
 pub const STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID: usize = 600;
 
+pub const STATE_TRANSFER_REUSED_CHUNKS: &str = "LT_REUSED_CHUNKS";
+pub const STATE_TRANSFER_REUSED_CHUNKS_ID: usize = 601;
+
+pub const STATE_TRANSFER_SERVE_BACKLOG: &str = "LT_SERVE_BACKLOG";
+pub const STATE_TRANSFER_SERVE_BACKLOG_ID: usize = 602;
+
+pub const STATE_TRANSFER_SERVE_CPU_TIME: &str = "LT_SERVE_CPU_TIME";
+pub const STATE_TRANSFER_SERVE_CPU_TIME_ID: usize = 603;
+
 pub fn metrics() -> Vec<MetricRegistry> {
-    vec![(
-        STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID,
-        STATE_TRANSFER_STATE_INSTALL_CLONE_TIME.to_string(),
-        MetricKind::Duration,
-        MetricLevel::Info,
-    )
-        .into()]
+    vec![
+        (
+            STATE_TRANSFER_STATE_INSTALL_CLONE_TIME_ID,
+            STATE_TRANSFER_STATE_INSTALL_CLONE_TIME.to_string(),
+            MetricKind::Duration,
+            MetricLevel::Info,
+        )
+            .into(),
+        (
+            STATE_TRANSFER_REUSED_CHUNKS_ID,
+            STATE_TRANSFER_REUSED_CHUNKS.to_string(),
+            MetricKind::Counter,
+            MetricLevel::Info,
+        )
+            .into(),
+        (
+            STATE_TRANSFER_SERVE_BACKLOG_ID,
+            STATE_TRANSFER_SERVE_BACKLOG.to_string(),
+            MetricKind::Count,
+            MetricLevel::Info,
+        )
+            .into(),
+        (
+            STATE_TRANSFER_SERVE_CPU_TIME_ID,
+            STATE_TRANSFER_SERVE_CPU_TIME.to_string(),
+            MetricKind::Duration,
+            MetricLevel::Info,
+        )
+            .into(),
+    ]
 }
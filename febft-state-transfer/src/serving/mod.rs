@@ -0,0 +1,782 @@
+//! Serving our checkpoints to recovering replicas without starving the ordering protocol.
+//!
+//! Replies to state requests are queued instead of being sent right away, and are
+//! sent from [StateTransferProtocol::poll] as the configured budgets allow. Both the
+//! bytes we send and the time we spend preparing them, serializing the checkpoint and
+//! reading its chunks back, are budgeted, and the checkpoint is only serialized once
+//! the first reply which needs it gets its turn. Older checkpoints are only read back
+//! from the archive at that point too, so a request costs nothing until then.
+//! A few checkpoints are kept serialized at once, so replicas fetching different ones
+//! don't make us serialize them over and over, and a checkpoint is only serialized once
+//! no matter how many replicas request it. A newer request from a replica replaces the
+//! older one still in the queue.
+//!
+//! [StateTransferProtocol::poll]: atlas_smr_core::state_transfer::StateTransferProtocol::poll
+
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tracing::{debug, error, warn};
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
+use atlas_common::globals::ReadOnly;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_metrics::metrics::metric_duration;
+use atlas_smr_application::state::monolithic_state::MonolithicState;
+use atlas_smr_core::state_transfer::networking::StateTransferSendNode;
+use atlas_smr_core::state_transfer::Checkpoint;
+
+use crate::certificate::CheckpointCertificate;
+use crate::chunks::{ServedState, MAX_CHUNKS_PER_REQUEST};
+use crate::message::serialize::CSTMsg;
+use crate::message::{CstMessage, CstMessageKind, StateChunkRequest};
+use crate::metrics::STATE_TRANSFER_SERVE_CPU_TIME_ID;
+use crate::RecoveryState;
+
+/// The most chunks we keep queued for a single replica
+const MAX_QUEUED_CHUNKS: usize = 4 * MAX_CHUNKS_PER_REQUEST;
+
+/// The most checkpoints we keep serialized at once. The least recently used one is
+/// dropped to make room for another, and serialized again if it is requested again
+const MAX_SERVED_CHECKPOINTS: usize = 4;
+
+/// A checkpoint we retain, read back to be served, along with its certificate if it has one
+pub(crate) type LoadedCheckpoint<S> = (Arc<ReadOnly<Checkpoint<S>>>, Option<CheckpointCertificate>);
+
+/// A checkpoint ready to be served, along with the checkpoint itself if it was just loaded
+type PreparedState<'a, S> = (&'a mut ServedState<S>, Option<LoadedCheckpoint<S>>);
+
+/// A reply waiting for its turn to be sent
+enum PendingReply {
+    /// A reply to a state request, with either the state itself or its manifest,
+    /// depending on its size once serialized
+    State {
+        to: NodeId,
+        /// The sequence number of the request being answered
+        cst_seq: SeqNo,
+        seq: SeqNo,
+        chunk_size: usize,
+    },
+    /// Chunks of a checkpoint, which are only read back from it when they are sent
+    Chunks {
+        to: NodeId,
+        /// The sequence number of the request being answered
+        cst_seq: SeqNo,
+        seq: SeqNo,
+        chunk_size: u64,
+        chunks: VecDeque<u32>,
+    },
+}
+
+impl PendingReply {
+    fn to(&self) -> NodeId {
+        match self {
+            PendingReply::State { to, .. } | PendingReply::Chunks { to, .. } => *to,
+        }
+    }
+}
+
+/// A token bucket limiting how much of a resource we spend per second
+struct ServeBudget {
+    per_sec: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl ServeBudget {
+    fn new(per_sec: f64) -> Self {
+        Self {
+            per_sec,
+            tokens: per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Whether we may spend right now. Spending may overdraw the budget,
+    /// so a reply costing more than a second's worth of budget is still sent eventually
+    fn available(&mut self) -> bool {
+        let now = Instant::now();
+
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+
+        // We can save up at most a second's worth of budget
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.per_sec);
+        self.last_refill = now;
+
+        self.tokens > 0.0
+    }
+
+    fn spend(&mut self, amount: f64) {
+        self.tokens -= amount;
+    }
+}
+
+/// The queue of replies to the state requests of other replicas
+pub(crate) struct StateServer<S> {
+    queue: VecDeque<PendingReply>,
+    /// The bytes we send, unlimited when not present
+    bandwidth: Option<ServeBudget>,
+    /// The seconds of CPU time we spend preparing replies, unlimited when not present
+    cpu: Option<ServeBudget>,
+    /// The checkpoints we are serving, serialized for the replicas which requested them
+    served: BTreeMap<SeqNo, Served<S>>,
+    /// Counts the uses of the served checkpoints, to tell which was used least recently
+    uses: u64,
+    /// Where the served checkpoints are serialized to
+    spool_dir: PathBuf,
+}
+
+struct Served<S> {
+    state: ServedState<S>,
+    last_used: u64,
+}
+
+impl<S> StateServer<S>
+where
+    S: MonolithicState + 'static,
+{
+    pub(crate) fn new(
+        serve_bytes_per_sec: Option<u64>,
+        serve_cpu_share: Option<f64>,
+        spool_dir: PathBuf,
+    ) -> Self {
+        Self {
+            queue: VecDeque::new(),
+            bandwidth: serve_bytes_per_sec.map(|bytes| ServeBudget::new(bytes as f64)),
+            cpu: serve_cpu_share.map(ServeBudget::new),
+            served: BTreeMap::new(),
+            uses: 0,
+            spool_dir,
+        }
+    }
+
+    /// The amount of bytes waiting to be served, as far as we know.
+    /// State replies whose checkpoint is yet to be serialized are not counted
+    pub(crate) fn backlog(&self) -> usize {
+        self.queue
+            .iter()
+            .map(|pending| match pending {
+                PendingReply::State { seq, .. } => {
+                    self.served.get(seq).map_or(0, |served| served.state.size())
+                }
+                PendingReply::Chunks {
+                    chunk_size, chunks, ..
+                } => *chunk_size as usize * chunks.len(),
+            })
+            .sum()
+    }
+
+    /// The given checkpoint serialized, serializing it if we have not done so yet.
+    /// The time spent doing so is taken from the CPU budget
+    pub(crate) fn served_state(
+        &mut self,
+        checkpoint: &Arc<ReadOnly<Checkpoint<S>>>,
+        certificate: Option<CheckpointCertificate>,
+    ) -> Result<&mut ServedState<S>> {
+        let seq = checkpoint.sequence_number();
+
+        let served = match self
+            .served
+            .remove(&seq)
+            .filter(|served| served.state.digest() == checkpoint.digest())
+        {
+            Some(mut served) => {
+                if let Some(certificate) = &certificate {
+                    served.state.certify(certificate);
+                }
+
+                served.state
+            }
+            None => self.spool(checkpoint, certificate, Instant::now())?,
+        };
+
+        Ok(self.insert(served))
+    }
+
+    /// Keep the certificate of a checkpoint we serve, once it became stable
+    pub(crate) fn certify(&mut self, certificate: &CheckpointCertificate) {
+        if let Some(served) = self.served.get_mut(&certificate.sequence_number()) {
+            served.state.certify(certificate);
+        }
+    }
+
+    /// Stop serving the serialized checkpoints which are of no more use
+    pub(crate) fn retain_served<F>(&mut self, mut retained: F)
+    where
+        F: FnMut(SeqNo) -> bool,
+    {
+        self.served.retain(|seq, _| retained(*seq));
+    }
+
+    /// Queue a reply to a state request, replacing any earlier one to the same replica.
+    /// The checkpoint is only loaded and serialized when the reply gets its turn
+    pub(crate) fn queue_state(
+        &mut self,
+        to: NodeId,
+        cst_seq: SeqNo,
+        seq: SeqNo,
+        chunk_size: usize,
+    ) {
+        self.queue.retain(|pending| {
+            !(matches!(pending, PendingReply::State { .. }) && pending.to() == to)
+        });
+
+        self.queue.push_back(PendingReply::State {
+            to,
+            cst_seq,
+            seq,
+            chunk_size,
+        });
+    }
+
+    /// Queue the chunks requested by the given replica. Requests for chunks of the same
+    /// checkpoint are merged, while those for chunks of other checkpoints are dropped
+    pub(crate) fn queue_chunks(&mut self, to: NodeId, cst_seq: SeqNo, request: &StateChunkRequest) {
+        self.queue.retain(|pending| match pending {
+            PendingReply::Chunks {
+                to: pending_to,
+                seq,
+                chunk_size,
+                ..
+            } => {
+                *pending_to != to
+                    || (*seq == request.sequence_number() && *chunk_size == request.chunk_size())
+            }
+            _ => true,
+        });
+
+        let existing = self.queue.iter_mut().find_map(|pending| match pending {
+            PendingReply::Chunks {
+                to: pending_to,
+                cst_seq: pending_cst_seq,
+                chunks,
+                ..
+            } if *pending_to == to => Some((pending_cst_seq, chunks)),
+            _ => None,
+        });
+
+        let requested = request.chunks().iter().take(MAX_CHUNKS_PER_REQUEST);
+
+        match existing {
+            Some((pending_cst_seq, chunks)) => {
+                *pending_cst_seq = cst_seq;
+
+                for index in requested {
+                    if chunks.len() >= MAX_QUEUED_CHUNKS {
+                        warn!(
+                            "Too many chunks queued for {:?}, dropping the request for chunk {}",
+                            to, index
+                        );
+
+                        break;
+                    }
+
+                    if !chunks.contains(index) {
+                        chunks.push_back(*index);
+                    }
+                }
+            }
+            None => self.queue.push_back(PendingReply::Chunks {
+                to,
+                cst_seq,
+                seq: request.sequence_number(),
+                chunk_size: request.chunk_size(),
+                chunks: requested.copied().collect(),
+            }),
+        }
+    }
+
+    /// Send as many of the queued replies as the budgets allow, taking turns between the replicas.
+    /// The checkpoints which are not serialized yet are loaded with the given function
+    pub(crate) fn serve<NT, F>(&mut self, node: &NT, mut load: F)
+    where
+        NT: StateTransferSendNode<CSTMsg<S>>,
+        F: FnMut(SeqNo) -> Option<LoadedCheckpoint<S>>,
+    {
+        loop {
+            let bandwidth_available = self.bandwidth.as_mut().map_or(true, ServeBudget::available);
+            let cpu_available = self.cpu.as_mut().map_or(true, ServeBudget::available);
+
+            if !bandwidth_available || !cpu_available {
+                break;
+            }
+
+            let Some(pending) = self.queue.pop_front() else {
+                break;
+            };
+
+            let sent = match pending {
+                PendingReply::State {
+                    to,
+                    cst_seq,
+                    seq,
+                    chunk_size,
+                } => self.send_state(node, to, cst_seq, seq, chunk_size, &mut load),
+                PendingReply::Chunks {
+                    to,
+                    cst_seq,
+                    seq,
+                    chunk_size,
+                    mut chunks,
+                } => {
+                    let served = match self.prepare(seq, &mut load) {
+                        Ok(Some((served, _))) => served,
+                        Ok(None) => {
+                            debug!(
+                                "{:?} // Dropping the chunks of checkpoint {:?} queued for {:?}, as we no longer retain it",
+                                node.id(),
+                                seq,
+                                to
+                            );
+
+                            continue;
+                        }
+                        Err(err) => {
+                            error!(
+                                "{:?} // Failed to serialize checkpoint {:?} to serve it: {:?}",
+                                node.id(),
+                                seq,
+                                err
+                            );
+
+                            continue;
+                        }
+                    };
+
+                    let Some(index) = chunks.pop_front() else {
+                        continue;
+                    };
+
+                    let started = Instant::now();
+
+                    let chunk = served.chunk(index, chunk_size);
+
+                    self.spend_cpu(started);
+
+                    let sent = match chunk {
+                        Ok(Some(chunk)) => {
+                            let len = chunk.data().len();
+
+                            let reply =
+                                CstMessage::new(cst_seq, CstMessageKind::ReplyStateChunk(chunk));
+
+                            if let Err(err) = node.send_signed(reply, to, true) {
+                                warn!(
+                                    "{:?} // Failed to send chunk {} to {:?}: {:?}",
+                                    node.id(),
+                                    index,
+                                    to,
+                                    err
+                                );
+                            }
+
+                            len
+                        }
+                        Ok(None) => 0,
+                        Err(err) => {
+                            error!(
+                                "{:?} // Failed to read chunk {} of checkpoint {:?}: {:?}",
+                                node.id(),
+                                index,
+                                seq,
+                                err
+                            );
+
+                            0
+                        }
+                    };
+
+                    // Let the other replicas have their turn before sending the next chunk
+                    if !chunks.is_empty() {
+                        self.queue.push_back(PendingReply::Chunks {
+                            to,
+                            cst_seq,
+                            seq,
+                            chunk_size,
+                            chunks,
+                        });
+                    }
+
+                    sent
+                }
+            };
+
+            if let Some(bandwidth) = &mut self.bandwidth {
+                bandwidth.spend(sent as f64);
+            }
+        }
+    }
+
+    /// Reply to a state request with the state itself, or its manifest if it does not
+    /// fit in a single chunk, returning how many bytes that cost
+    fn send_state<NT, F>(
+        &mut self,
+        node: &NT,
+        to: NodeId,
+        cst_seq: SeqNo,
+        seq: SeqNo,
+        chunk_size: usize,
+        load: &mut F,
+    ) -> usize
+    where
+        NT: StateTransferSendNode<CSTMsg<S>>,
+        F: FnMut(SeqNo) -> Option<LoadedCheckpoint<S>>,
+    {
+        let (served, loaded) = match self.prepare(seq, load) {
+            Ok(Some(prepared)) => prepared,
+            Ok(None) => {
+                debug!(
+                    "{:?} // Dropping the state reply to {:?}, as we no longer retain checkpoint {:?}",
+                    node.id(),
+                    to,
+                    seq
+                );
+
+                return 0;
+            }
+            Err(err) => {
+                error!(
+                    "{:?} // Failed to serialize checkpoint {:?} to serve it: {:?}",
+                    node.id(),
+                    seq,
+                    err
+                );
+
+                return 0;
+            }
+        };
+
+        // Large states are described by their manifest, and then fetched in chunks
+        let (kind, cost) = if served.fits_in_chunk(chunk_size) {
+            let cost = served.size();
+
+            // Only the serialized state is kept, but this one is small enough to just load again
+            // when it was serialized for an earlier reply
+            let loaded = loaded.or_else(|| {
+                let started = Instant::now();
+
+                let loaded = load(seq);
+
+                self.spend_cpu(started);
+
+                loaded
+            });
+
+            let Some((checkpoint, certificate)) = loaded else {
+                return 0;
+            };
+
+            let state = match certificate {
+                Some(certificate) => RecoveryState::with_certificate(checkpoint, certificate),
+                None => RecoveryState::new(checkpoint),
+            };
+
+            (CstMessageKind::ReplyState(state), cost)
+        } else {
+            let started = Instant::now();
+
+            let manifest = served.manifest(chunk_size);
+
+            self.spend_cpu(started);
+
+            match manifest {
+                Ok(manifest) => {
+                    let cost = manifest.chunks().len() * std::mem::size_of::<Digest>();
+
+                    (CstMessageKind::ReplyStateManifest(manifest), cost)
+                }
+                Err(err) => {
+                    error!(
+                        "{:?} // Failed to split checkpoint {:?} into chunks: {:?}",
+                        node.id(),
+                        seq,
+                        err
+                    );
+
+                    return 0;
+                }
+            }
+        };
+
+        if let Err(err) = node.send_signed(CstMessage::new(cst_seq, kind), to, true) {
+            warn!(
+                "{:?} // Failed to reply to the state request of {:?}: {:?}",
+                node.id(),
+                to,
+                err
+            );
+        }
+
+        cost
+    }
+
+    /// The serialized checkpoint with the given sequence number, loading and serializing
+    /// it if it is not serialized already, along with the checkpoint if it had to be loaded.
+    /// None when we no longer retain it
+    fn prepare<F>(&mut self, seq: SeqNo, load: &mut F) -> Result<Option<PreparedState<'_, S>>>
+    where
+        F: FnMut(SeqNo) -> Option<LoadedCheckpoint<S>>,
+    {
+        let (served, loaded) = match self.served.remove(&seq) {
+            Some(served) => (served.state, None),
+            None => {
+                let started = Instant::now();
+
+                let Some((checkpoint, certificate)) = load(seq) else {
+                    return Ok(None);
+                };
+
+                let served = self.spool(&checkpoint, certificate.clone(), started)?;
+
+                (served, Some((checkpoint, certificate)))
+            }
+        };
+
+        Ok(Some((self.insert(served), loaded)))
+    }
+
+    /// Serialize the given checkpoint, taking the time spent since `started` from the
+    /// CPU budget. The least recently used checkpoints are dropped first to make room
+    fn spool(
+        &mut self,
+        checkpoint: &Checkpoint<S>,
+        certificate: Option<CheckpointCertificate>,
+        started: Instant,
+    ) -> Result<ServedState<S>> {
+        while self.served.len() >= MAX_SERVED_CHECKPOINTS {
+            let Some(seq) = self
+                .served
+                .iter()
+                .min_by_key(|(_, served)| served.last_used)
+                .map(|(seq, _)| *seq)
+            else {
+                break;
+            };
+
+            self.served.remove(&seq);
+        }
+
+        let served = ServedState::new(checkpoint, certificate, &self.spool_dir);
+
+        self.spend_cpu(started);
+
+        served
+    }
+
+    /// Keep the given checkpoint serialized, as the most recently used one
+    fn insert(&mut self, state: ServedState<S>) -> &mut ServedState<S> {
+        self.uses += 1;
+
+        let served = Served {
+            state,
+            last_used: self.uses,
+        };
+
+        &mut self
+            .served
+            .entry(served.state.sequence_number())
+            .or_insert(served)
+            .state
+    }
+
+    /// Take the time spent since `started` from the CPU budget
+    fn spend_cpu(&mut self, started: Instant) {
+        let elapsed = started.elapsed();
+
+        metric_duration(STATE_TRANSFER_SERVE_CPU_TIME_ID, elapsed);
+
+        if let Some(cpu) = &mut self.cpu {
+            cpu.spend(elapsed.as_secs_f64());
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod serving_tests {
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use atlas_common::error::*;
+    use atlas_common::globals::ReadOnly;
+    use atlas_common::ordering::SeqNo;
+    use atlas_smr_application::state::monolithic_state::MonolithicState;
+    use atlas_smr_core::state_transfer::Checkpoint;
+    #[cfg(feature = "serialize_serde")]
+    use serde::{Deserialize, Serialize};
+
+    use crate::certificate::digest_state;
+
+    use super::{LoadedCheckpoint, ServeBudget, StateServer, MAX_SERVED_CHECKPOINTS};
+
+    /// An application state made up of opaque bytes
+    #[cfg_attr(feature = "serialize_serde", derive(Serialize, Deserialize))]
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub(crate) struct TestState {
+        pub(crate) data: Vec<u8>,
+    }
+
+    impl MonolithicState for TestState {
+        fn serialize_state<W>(mut w: W, state: &Self) -> Result<()>
+        where
+            W: Write,
+        {
+            w.write_all(&state.data)?;
+
+            Ok(())
+        }
+
+        fn deserialize_state<R>(mut r: R) -> Result<Self>
+        where
+            R: Read,
+        {
+            let mut data = Vec::new();
+
+            r.read_to_end(&mut data)?;
+
+            Ok(Self { data })
+        }
+    }
+
+    pub(crate) fn checkpoint(
+        seq: u32,
+        byte: u8,
+        len: usize,
+    ) -> Arc<ReadOnly<Checkpoint<TestState>>> {
+        let state = TestState {
+            data: vec![byte; len],
+        };
+
+        let digest = digest_state(&state).unwrap();
+
+        Checkpoint::new(SeqNo::from(seq), state, digest)
+    }
+
+    /// An empty directory of its own for the given test
+    pub(crate) fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("febft-st-{}-{}", std::process::id(), name));
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    fn spooled_files(dir: &Path) -> usize {
+        std::fs::read_dir(dir).unwrap().count()
+    }
+
+    #[test]
+    fn test_budgets_hold_back_replies_until_refilled() {
+        let mut budget = ServeBudget::new(100.0);
+
+        assert!(budget.available());
+
+        // A reply larger than the budget is still sent, but the next ones wait
+        budget.spend(250.0);
+
+        assert!(!budget.available());
+
+        budget.last_refill -= Duration::from_secs(1);
+
+        assert!(!budget.available());
+
+        budget.last_refill -= Duration::from_secs(2);
+
+        assert!(budget.available());
+
+        // No more than a second's worth of budget is saved up while idle
+        budget.last_refill -= Duration::from_secs(10);
+
+        assert!(budget.available());
+        assert!(budget.tokens <= 100.0);
+    }
+
+    #[test]
+    fn test_serializing_checkpoints_is_charged_to_the_cpu_budget() {
+        let dir = test_dir("cpu-budget");
+
+        let mut server = StateServer::new(None, Some(0.25), dir.clone());
+
+        server
+            .served_state(&checkpoint(1, 1, 1024 * 1024), None)
+            .unwrap();
+
+        let cpu = server.cpu.as_ref().unwrap();
+
+        assert!(cpu.tokens < 0.25);
+        assert!(server.bandwidth.is_none());
+
+        drop(server);
+
+        assert_eq!(spooled_files(&dir), 0);
+    }
+
+    #[test]
+    fn test_loaded_checkpoints_are_handed_out_once_serialized() {
+        let dir = test_dir("prepare");
+
+        let mut server = StateServer::new(None, None, dir);
+
+        let checkpoint = checkpoint(1, 1, 16);
+
+        let mut loads = 0;
+
+        let mut load = |seq: SeqNo| -> Option<LoadedCheckpoint<TestState>> {
+            loads += 1;
+
+            (seq == SeqNo::from(1)).then(|| (checkpoint.clone(), None))
+        };
+
+        let (served, loaded) = server.prepare(SeqNo::from(1), &mut load).unwrap().unwrap();
+
+        assert_eq!(served.size(), 16);
+        assert!(loaded.is_some_and(|(loaded, _)| Arc::ptr_eq(&loaded, &checkpoint)));
+
+        // The serialized checkpoint is reused without loading it again
+        let (_, loaded) = server.prepare(SeqNo::from(1), &mut load).unwrap().unwrap();
+
+        assert!(loaded.is_none());
+
+        assert!(server.prepare(SeqNo::from(2), &mut load).unwrap().is_none());
+
+        assert_eq!(loads, 2);
+    }
+
+    #[test]
+    fn test_least_recently_used_checkpoints_are_evicted() {
+        let dir = test_dir("eviction");
+
+        let mut server = StateServer::new(None, None, dir.clone());
+
+        let checkpoints: Vec<_> = (1..=MAX_SERVED_CHECKPOINTS as u32 + 1)
+            .map(|seq| checkpoint(seq, seq as u8, 64))
+            .collect();
+
+        for checkpoint in &checkpoints[..MAX_SERVED_CHECKPOINTS] {
+            server.served_state(checkpoint, None).unwrap();
+        }
+
+        // The first checkpoint is used again, so the second is now the least recently used
+        server.served_state(&checkpoints[0], None).unwrap();
+
+        server
+            .served_state(&checkpoints[MAX_SERVED_CHECKPOINTS], None)
+            .unwrap();
+
+        let served: Vec<u32> = server.served.keys().map(|seq| u32::from(*seq)).collect();
+
+        assert_eq!(served, vec![1, 3, 4, 5]);
+        assert_eq!(spooled_files(&dir), MAX_SERVED_CHECKPOINTS);
+
+        server.retain_served(|seq| seq == SeqNo::from(5));
+
+        assert_eq!(spooled_files(&dir), 1);
+    }
+}
@@ -0,0 +1,30 @@
+//! Run the ordering protocol with a byzantine replica and an adversarial network,
+//! checking that the correct replicas never decide different batches.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use febft_fuzz::alloc::{MemoryWatch, TrackingAllocator};
+use febft_fuzz::cluster::Cluster;
+use febft_fuzz::input::Action;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+/// The memory each step can take up, as the messages of every undecided instance are kept
+const BYTES_PER_STEP: usize = 64 * 1024;
+
+fuzz_target!(|actions: Vec<Action>| {
+    let watch = MemoryWatch::start();
+
+    let mut cluster = Cluster::new().expect("Failed to set up the cluster");
+
+    for action in actions.iter().cloned() {
+        cluster.step(action).expect("Failed to run the step");
+    }
+
+    drop(cluster);
+
+    watch.assert_bounded(actions.len(), BYTES_PER_STEP);
+});
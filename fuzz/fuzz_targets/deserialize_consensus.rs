@@ -0,0 +1,32 @@
+//! Decode arbitrary bytes as protocol messages.
+//!
+//! `deserialize_consensus` goes through whichever backend the crate is built
+//! with, so run this target with `--features serialize_capnp` to cover capnp.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use febft_fuzz::alloc::{MemoryWatch, TrackingAllocator};
+use febft_fuzz::request::FuzzRequest;
+use febft_pbft_consensus::bft::message::serialize::deserialize_consensus;
+use febft_pbft_consensus::bft::message::PBFTMessage;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+/// The memory a decoded message can take up for each byte of its encoding
+const BYTES_PER_INPUT_BYTE: usize = 64;
+
+fuzz_target!(|data: &[u8]| {
+    let watch = MemoryWatch::start();
+
+    let _ = deserialize_consensus::<&[u8], FuzzRequest>(data);
+
+    let _ = bincode::serde::decode_from_slice::<PBFTMessage<FuzzRequest>, _>(
+        data,
+        bincode::config::standard(),
+    );
+
+    watch.assert_bounded(data.len(), BYTES_PER_INPUT_BYTE);
+});
@@ -0,0 +1,174 @@
+//! Feed a replica running the collaborative state transfer protocol with
+//! arbitrary replies and requests from the other replicas.
+
+#![no_main]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use libfuzzer_sys::fuzz_target;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::SeqNo;
+use atlas_smr_core::state_transfer::monolithic_state::MonolithicStateTransfer;
+use atlas_smr_core::state_transfer::StateTransferProtocol;
+
+use febft_fuzz::alloc::{MemoryWatch, TrackingAllocator};
+use febft_fuzz::cluster::N;
+use febft_fuzz::input::{replica, CstAction, FuzzCstMessage};
+use febft_fuzz::mock::{self, MockCheckpointArchive, MockNode, MockStateLog};
+use febft_fuzz::request::FuzzState;
+use febft_pbft_consensus::bft::sync::view::ViewInfo;
+use febft_state_transfer::certificate::sign_checkpoint;
+use febft_state_transfer::chunks::ServedState;
+use febft_state_transfer::config::StateTransferConfig;
+use febft_state_transfer::message::{
+    CheckpointVote, CstMessage, CstMessageKind, StateChunkRequest, StateRequest,
+};
+use febft_state_transfer::{CollabStateTransfer, RecoveryState};
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+/// The memory each step can take up, as a state can be kept for every replica
+const BYTES_PER_STEP: usize = 64 * 1024;
+
+/// Small enough that most of the generated states are transferred in chunks
+const CHUNK_SIZE: usize = 16;
+
+/// Small enough that the generated sequence numbers can run ahead of ours by more
+const LAG_THRESHOLD: u32 = 8;
+
+/// Enough that requests for older checkpoints are served from the archive
+const RETAINED_CHECKPOINTS: usize = 3;
+
+/// Split a checkpoint of the given state into chunks, as a replica serving it would
+fn served_state(seq: SeqNo, state: FuzzState, digests: &mut Vec<Digest>) -> ServedState<FuzzState> {
+    let checkpoint = mock::checkpoint(seq, state).expect("Failed to create the checkpoint");
+
+    digests.push(*checkpoint.digest());
+
+    ServedState::new(&checkpoint, None, &std::env::temp_dir())
+        .expect("Failed to serialize the checkpoint")
+}
+
+fuzz_target!(|actions: Vec<CstAction>| {
+    mock::init();
+
+    let watch = MemoryWatch::start();
+
+    let id = NodeId::from(0u32);
+    let node = MockNode::<CstMessage<FuzzState>>::new(id);
+    let (install_tx, install_rx) = mock::install_channel();
+
+    let config = StateTransferConfig::new(Duration::from_secs(1))
+        .with_chunk_size(CHUNK_SIZE)
+        .with_lag_threshold(LAG_THRESHOLD)
+        .with_checkpoint_history(
+            RETAINED_CHECKPOINTS,
+            Arc::new(MockCheckpointArchive::default()),
+        );
+
+    let mut cst = CollabStateTransfer::new(
+        node.clone(),
+        config,
+        mock::timeouts(id),
+        MockStateLog,
+        install_tx,
+    );
+
+    let view = ViewInfo::new(SeqNo::ZERO, N, 1).expect("Failed to create the view");
+
+    // The digests of the states seen so far, so replies can agree on them
+    let mut digests: Vec<Digest> = Vec::new();
+
+    for action in actions.iter().cloned() {
+        match action {
+            CstAction::Receive { from, seq, message } => {
+                let kind = match message {
+                    FuzzCstMessage::RequestStateCid => CstMessageKind::RequestStateCid,
+                    FuzzCstMessage::ReplyStateCid(reply) => CstMessageKind::ReplyStateCid(
+                        reply.map(|(seq, digest)| (seq.into(), digest.resolve(&digests))),
+                    ),
+                    FuzzCstMessage::RequestState { checkpoint, base } => {
+                        CstMessageKind::RequestState(StateRequest::new(
+                            checkpoint.map(Into::into),
+                            base.map(|(seq, digest)| (seq.into(), digest.resolve(&digests))),
+                        ))
+                    }
+                    FuzzCstMessage::ReplyState { seq, state } => {
+                        let checkpoint = mock::checkpoint(seq.into(), state)
+                            .expect("Failed to create the checkpoint");
+
+                        digests.push(*checkpoint.digest());
+
+                        CstMessageKind::ReplyState(RecoveryState::new(checkpoint))
+                    }
+                    FuzzCstMessage::ReplyStateManifest { seq, state } => {
+                        let mut served = served_state(seq.into(), state, &mut digests);
+
+                        match served.manifest(CHUNK_SIZE) {
+                            Ok(manifest) => CstMessageKind::ReplyStateManifest(manifest),
+                            Err(_) => continue,
+                        }
+                    }
+                    FuzzCstMessage::RequestStateChunks { seq, chunks } => {
+                        CstMessageKind::RequestStateChunks(StateChunkRequest::new(
+                            seq.into(),
+                            CHUNK_SIZE as u64,
+                            chunks.into_iter().map(u32::from).collect(),
+                        ))
+                    }
+                    FuzzCstMessage::ReplyStateChunk { seq, state, index } => {
+                        let mut served = served_state(seq.into(), state, &mut digests);
+
+                        match served.chunk(u32::from(index), CHUNK_SIZE as u64) {
+                            Ok(Some(chunk)) => CstMessageKind::ReplyStateChunk(chunk),
+                            _ => continue,
+                        }
+                    }
+                    FuzzCstMessage::CheckpointVote(digest) => {
+                        let digest = digest.resolve(&digests);
+                        let key_pair = mock::key_pair(replica(from, N));
+
+                        let signature = sign_checkpoint(&key_pair, seq.into(), &digest)
+                            .expect("Failed to sign the checkpoint");
+
+                        CstMessageKind::CheckpointVote(CheckpointVote::new(
+                            seq.into(),
+                            digest,
+                            signature,
+                        ))
+                    }
+                };
+
+                let message = mock::stored(replica(from, N), id, CstMessage::new(seq.into(), kind))
+                    .expect("Failed to store the message");
+
+                let _ = StateTransferProtocol::process_message(&mut cst, view.clone(), message);
+            }
+            CstAction::Checkpoint { seq, state } => {
+                let checkpoint =
+                    mock::checkpoint(seq.into(), state).expect("Failed to create the checkpoint");
+
+                digests.push(*checkpoint.digest());
+
+                let _ = cst.handle_state_received_from_app(checkpoint);
+            }
+            CstAction::RequestLatestState => {
+                let _ = cst.request_latest_state(view.clone());
+            }
+        }
+
+        let _ = StateTransferProtocol::poll(&mut cst);
+
+        node.take_outbox();
+
+        while install_rx.try_recv().is_ok() {}
+    }
+
+    drop(cst);
+
+    watch.assert_bounded(actions.len(), BYTES_PER_STEP);
+});
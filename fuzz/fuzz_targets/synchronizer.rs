@@ -0,0 +1,52 @@
+//! Run the view change protocol from a cluster which has already decided some
+//! instances, with a byzantine replica and an adversarial network.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use febft_fuzz::alloc::{MemoryWatch, TrackingAllocator};
+use febft_fuzz::cluster::Cluster;
+use febft_fuzz::input::Action;
+use febft_fuzz::request::FuzzRequest;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+/// The memory each step can take up, as the messages of every undecided instance are kept
+const BYTES_PER_STEP: usize = 64 * 1024;
+
+/// How many batches we decide before the view change, at most
+const MAX_DECIDED: usize = 4;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    /// The batches decided before the view change
+    decided: Vec<Vec<FuzzRequest>>,
+    actions: Vec<Action>,
+}
+
+fuzz_target!(|input: Input| {
+    let watch = MemoryWatch::start();
+
+    let mut cluster = Cluster::new().expect("Failed to set up the cluster");
+
+    for requests in input.decided.into_iter().take(MAX_DECIDED) {
+        cluster
+            .step(Action::Propose { requests })
+            .expect("Failed to propose");
+
+        cluster.settle().expect("Failed to decide");
+    }
+
+    cluster.time_out_all().expect("Failed to start the view change");
+
+    for action in input.actions.iter().cloned() {
+        cluster.step(action).expect("Failed to run the step");
+    }
+
+    drop(cluster);
+
+    watch.assert_bounded(MAX_DECIDED + input.actions.len(), BYTES_PER_STEP);
+});
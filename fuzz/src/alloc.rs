@@ -0,0 +1,90 @@
+//! An allocator that keeps track of the memory in use, so the targets can check
+//! that the state machines do not grow without bounds on malicious input.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct TrackingAllocator;
+
+/// Memory allowed on top of what the input accounts for, to cover lazily initialized
+/// globals and the preallocation done by the decoders on length prefixes
+const BASE_ALLOWANCE: usize = 4 * 1024 * 1024;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+
+        if !new_ptr.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+
+            allocated(new_size);
+        }
+
+        new_ptr
+    }
+}
+
+fn allocated(size: usize) {
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+/// Tracks the memory allocated while running a single input
+pub struct MemoryWatch {
+    baseline: usize,
+}
+
+impl MemoryWatch {
+    /// Start watching from the memory currently in use
+    pub fn start() -> Self {
+        let baseline = LIVE_BYTES.load(Ordering::Relaxed);
+
+        PEAK_BYTES.store(baseline, Ordering::Relaxed);
+
+        Self { baseline }
+    }
+
+    /// The most memory in use at any point since we started watching, above the baseline
+    pub fn peak_growth(&self) -> usize {
+        PEAK_BYTES
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.baseline)
+    }
+
+    /// Check that the memory used while running the input is proportional to the input.
+    ///
+    /// Every message can legitimately be kept around until its instance is decided,
+    /// so we allow a fixed amount of memory per step of the input, but nothing more.
+    pub fn assert_bounded(&self, steps: usize, bytes_per_step: usize) {
+        let allowed = BASE_ALLOWANCE + steps * bytes_per_step;
+        let growth = self.peak_growth();
+
+        assert!(
+            growth <= allowed,
+            "Memory grew by {} bytes over {} steps, more than the allowed {} bytes",
+            growth,
+            steps,
+            allowed
+        );
+    }
+}
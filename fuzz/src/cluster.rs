@@ -0,0 +1,608 @@
+//! A cluster of replicas running the ordering protocol, with the network under the
+//! control of the fuzzer.
+//!
+//! The cluster has n = 4 replicas and tolerates f = 1 fault. Replicas 0 to 2 run the real
+//! consensus and view change state machines, while replica 3 is byzantine: it runs nothing
+//! and instead sends whatever the fuzzer makes up. Every message sent between correct
+//! replicas stays in flight until the fuzzer decides to deliver, duplicate or drop it.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::error::*;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_communication::message::StoredMessage;
+use atlas_core::messages::SessionBased;
+use atlas_core::ordering_protocol::ShareableMessage;
+use atlas_core::request_pre_processing::RequestPreProcessor;
+use atlas_core::timeouts::timeout::TimeoutModHandle;
+
+use febft_pbft_consensus::bft::config::{DecisionLogConfig, FaultModel};
+use febft_pbft_consensus::bft::consensus::decision::DecisionOptions;
+use febft_pbft_consensus::bft::consensus::{
+    Consensus, ConsensusPollStatus, ProposerConsensusGuard,
+};
+use febft_pbft_consensus::bft::log::decisions::{
+    CollectData, IncompleteProof, PrepareSet, Proof, ViewDecisionPair,
+};
+use febft_pbft_consensus::bft::log::{initialize_decided_log, Log};
+use febft_pbft_consensus::bft::log_transfer::{LogTransfer, LogTransferStatus};
+use febft_pbft_consensus::bft::message::{
+    ConsensusMessage, ConsensusMessageKind, FwdConsensusMessage, PBFTMessage, ViewChangeMessage,
+    ViewChangeMessageKind,
+};
+use febft_pbft_consensus::bft::sync::{
+    AbstractSynchronizer, LeaderCollects, Synchronizer, SynchronizerPollStatus,
+    SynchronizerStatus,
+};
+
+use crate::input::{replica, Action, FuzzMessage};
+use crate::mock::{self, MockNode, FIRST_CLIENT_ID};
+use crate::request::FuzzRequest;
+
+pub const N: usize = 4;
+pub const F: usize = 1;
+
+/// The replica whose messages are made up by the fuzzer
+pub const BYZANTINE_ID: u32 = 3;
+
+/// How many instances can be running at the same time
+const WATERMARK: u32 = 8;
+
+/// How many queued messages we process after each step, before handing control back
+const POLL_LIMIT: usize = 64;
+
+/// How many messages we deliver when letting the cluster run on its own
+const SETTLE_LIMIT: usize = 1024;
+
+type Message = ShareableMessage<PBFTMessage<FuzzRequest>>;
+
+/// A correct replica, along with everything it needs to run the protocol
+pub struct Replica {
+    id: NodeId,
+    node: Arc<MockNode<PBFTMessage<FuzzRequest>>>,
+    timeouts: TimeoutModHandle,
+    pre_processor: RequestPreProcessor<FuzzRequest>,
+    consensus: Consensus<FuzzRequest>,
+    synchronizer: Arc<Synchronizer<FuzzRequest>>,
+    log: Log<FuzzRequest>,
+    log_transfer: LogTransfer<FuzzRequest>,
+    /// Are we running the view change protocol
+    syncing: bool,
+}
+
+impl Replica {
+    fn new(id: NodeId) -> Result<Self> {
+        let quorum = NodeId::targets_u32(0..N as u32).collect();
+
+        let synchronizer = Synchronizer::initialize_with_quorum(
+            id,
+            SeqNo::ZERO,
+            quorum,
+            Duration::from_secs(1),
+            FaultModel::Byzantine,
+        )?;
+
+        let view = synchronizer.view();
+        let timeouts = mock::timeouts(id);
+
+        let consensus = Consensus::new_replica(
+            id,
+            &view,
+            SeqNo::ZERO,
+            WATERMARK,
+            ProposerConsensusGuard::new(view.clone(), WATERMARK),
+            timeouts.clone(),
+            DecisionOptions {
+                usig: None,
+                fast_path_window: None,
+                sign_votes: false,
+                mac_keys: None,
+                pre_prepare_slot_timeout: None,
+            },
+        );
+
+        Ok(Self {
+            id,
+            node: MockNode::new(id),
+            timeouts,
+            pre_processor: mock::pre_processor(),
+            consensus,
+            synchronizer,
+            log: initialize_decided_log(id, &DecisionLogConfig::default())?,
+            log_transfer: LogTransfer::new(id, Duration::from_secs(1)),
+            syncing: false,
+        })
+    }
+
+    /// Process a message the way the replica's main loop would, returning
+    /// the instances decided as a consequence
+    fn handle(&mut self, message: Message) -> Result<Vec<Proof<FuzzRequest>>> {
+        self.dispatch(message)?;
+
+        // Process whatever the message unblocked
+        for _ in 0..POLL_LIMIT {
+            let next = if self.syncing {
+                match self.synchronizer.poll() {
+                    SynchronizerPollStatus::NextMessage(message) => message,
+                    _ => break,
+                }
+            } else {
+                match self.consensus.poll() {
+                    ConsensusPollStatus::NextMessage(message) => message,
+                    ConsensusPollStatus::Decided(_) | ConsensusPollStatus::Recv => break,
+                }
+            };
+
+            self.dispatch(next)?;
+        }
+
+        self.finalize()
+    }
+
+    fn dispatch(&mut self, message: Message) -> Result<()> {
+        match message.message() {
+            PBFTMessage::Consensus(_) if self.syncing => self.consensus.queue(message),
+            PBFTMessage::Consensus(_) => {
+                self.consensus.process_message(
+                    message,
+                    &self.synchronizer,
+                    &self.timeouts,
+                    &self.node,
+                )?;
+            }
+            PBFTMessage::ViewChange(_) => {
+                let status = self.synchronizer.process_message(
+                    message,
+                    &self.timeouts,
+                    &mut self.log,
+                    &self.pre_processor,
+                    &mut self.consensus,
+                    &self.node,
+                );
+
+                self.synchronizer.signal();
+
+                match status {
+                    SynchronizerStatus::Running => self.syncing = true,
+                    SynchronizerStatus::NewView(_, _)
+                    | SynchronizerStatus::NewViewJoinedQuorum(_, _, _) => self.syncing = false,
+                    SynchronizerStatus::RunLogTransfer { first, last } => {
+                        let view = self.synchronizer.view();
+
+                        self.log_transfer
+                            .start(first, last, &view, &*self.node, &self.timeouts);
+                    }
+                    _ => {}
+                }
+            }
+            PBFTMessage::LogTransfer(_) => {
+                let status = self.log_transfer.process_message(
+                    message,
+                    &self.synchronizer,
+                    &mut self.consensus,
+                    &mut self.log,
+                    &*self.node,
+                    &self.timeouts,
+                );
+
+                if let LogTransferStatus::Finished(_) = status {
+                    // Resume the view change, as polling the protocol's sync phase would
+                    let resumed = self.synchronizer.resume_view_change(
+                        &mut self.log,
+                        &self.timeouts,
+                        &mut self.consensus,
+                        &self.node,
+                    );
+
+                    if let Some(
+                        SynchronizerStatus::NewView(_, _)
+                        | SynchronizerStatus::NewViewJoinedQuorum(_, _, _),
+                    ) = resumed
+                    {
+                        self.syncing = false;
+                    }
+                }
+            }
+            PBFTMessage::ObserverMessage(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Finalize every decided instance, as `finalize_all_possible` does
+    fn finalize(&mut self) -> Result<Vec<Proof<FuzzRequest>>> {
+        let mut view = self.synchronizer.view();
+        let mut proofs = Vec::new();
+
+        while self.consensus.can_finalize() {
+            let Some(completed_batch) = self.consensus.finalize(&view)? else {
+                break;
+            };
+
+            if !completed_batch.skipped_leaders().is_empty() {
+                if let Some(reduced) = self
+                    .synchronizer
+                    .remove_leaders(completed_batch.skipped_leaders())
+                {
+                    self.consensus.remove_leaders(&reduced);
+
+                    view = reduced;
+                }
+            }
+
+            self.log.finalize_batch(completed_batch)?;
+
+            proofs.extend(self.log.last_proof());
+        }
+
+        Ok(proofs)
+    }
+}
+
+/// A message sent by a replica, which has not been delivered yet
+struct InFlight {
+    to: NodeId,
+    message: Message,
+}
+
+pub struct Cluster {
+    replicas: Vec<Replica>,
+    in_flight: Vec<InFlight>,
+    /// The digest each instance was decided with, by the first replica to decide it
+    decided: BTreeMap<SeqNo, Digest>,
+    /// Every digest seen so far, for the byzantine replica to reuse
+    digests: Vec<Digest>,
+    /// The view change messages sent to the byzantine replica, for it to relay
+    received_collects: Vec<StoredMessage<PBFTMessage<FuzzRequest>>>,
+    /// A proof decided by the correct replicas, for the byzantine replica to (mis)use
+    last_proof: Option<Proof<FuzzRequest>>,
+}
+
+impl Cluster {
+    pub fn new() -> Result<Self> {
+        mock::init();
+
+        let replicas = NodeId::targets_u32(0..N as u32)
+            .filter(|id| *id != byzantine())
+            .map(Replica::new)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            replicas,
+            in_flight: Vec::new(),
+            decided: BTreeMap::new(),
+            digests: Vec::new(),
+            received_collects: Vec::new(),
+            last_proof: None,
+        })
+    }
+
+    /// The instances decided so far, with the digest of their batch
+    pub fn decided(&self) -> &BTreeMap<SeqNo, Digest> {
+        &self.decided
+    }
+
+    /// Run a step of the execution, checking that no two correct replicas
+    /// decided differently
+    pub fn step(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Propose { requests } => self.propose(requests)?,
+            Action::Deliver { index } => {
+                if let Some(in_flight) = self.take(index) {
+                    self.deliver(in_flight.to, in_flight.message)?;
+                }
+            }
+            Action::Drop { index } => {
+                self.take(index);
+            }
+            Action::Duplicate { index } => {
+                if let Some(in_flight) = self.peek(index) {
+                    self.deliver(in_flight.to, in_flight.message)?;
+                }
+            }
+            Action::Inject { to, message } => {
+                let to = replica(to, N);
+
+                if to != byzantine() {
+                    let message = self.forge(to, message)?;
+
+                    self.deliver(to, message)?;
+                }
+            }
+            Action::TimeOut { replica: index } => {
+                let replica = &self.replicas[index as usize % self.replicas.len()];
+
+                replica.synchronizer.begin_view_change(
+                    None,
+                    &*replica.node,
+                    &replica.timeouts,
+                    &replica.log,
+                );
+            }
+        }
+
+        self.collect_sent();
+
+        Ok(())
+    }
+
+    /// Deliver every message in flight, in the order it was sent, until the cluster goes quiet
+    pub fn settle(&mut self) -> Result<()> {
+        for _ in 0..SETTLE_LIMIT {
+            if self.in_flight.is_empty() {
+                break;
+            }
+
+            self.step(Action::Deliver { index: 0 })?;
+        }
+
+        Ok(())
+    }
+
+    /// Have the client requests of every correct replica time out
+    pub fn time_out_all(&mut self) -> Result<()> {
+        for replica in 0..self.replicas.len() {
+            self.step(Action::TimeOut {
+                replica: replica as u8,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn take(&mut self, index: u16) -> Option<InFlight> {
+        if self.in_flight.is_empty() {
+            return None;
+        }
+
+        let index = index as usize % self.in_flight.len();
+
+        Some(self.in_flight.remove(index))
+    }
+
+    fn peek(&self, index: u16) -> Option<InFlight> {
+        if self.in_flight.is_empty() {
+            return None;
+        }
+
+        let in_flight = &self.in_flight[index as usize % self.in_flight.len()];
+
+        Some(InFlight {
+            to: in_flight.to,
+            message: in_flight.message.clone(),
+        })
+    }
+
+    /// The current leader, if correct, proposes the requests for its next instance
+    fn propose(&mut self, requests: Vec<FuzzRequest>) -> Result<()> {
+        let Some(leader) = self
+            .replicas
+            .iter()
+            .find(|replica| !replica.syncing && replica.synchronizer.view().leader() == replica.id)
+        else {
+            return Ok(());
+        };
+
+        let requests = client_requests(leader.id, requests)?;
+
+        let view = leader.synchronizer.view();
+        let propose = leader.consensus.forge_propose(requests, &view);
+
+        let _ = leader
+            .node
+            .broadcast_signed(propose, view.quorum_members().clone().into_iter());
+
+        Ok(())
+    }
+
+    fn deliver(&mut self, to: NodeId, message: Message) -> Result<()> {
+        let Some(replica) = self.replicas.iter_mut().find(|replica| replica.id == to) else {
+            return Ok(());
+        };
+
+        // Malicious messages may legitimately be rejected with an error
+        let Ok(proofs) = replica.handle(message) else {
+            return Ok(());
+        };
+
+        for proof in proofs {
+            self.record(proof);
+        }
+
+        Ok(())
+    }
+
+    /// Check that the decision agrees with every other decision of the same instance
+    fn record(&mut self, proof: Proof<FuzzRequest>) {
+        let digest = proof.batch_digest();
+        let seq = proof.sequence_number();
+
+        let decided = *self.decided.entry(seq).or_insert(digest);
+
+        assert_eq!(
+            decided, digest,
+            "Correct replicas decided different batches for instance {:?}",
+            seq
+        );
+
+        self.last_proof = Some(proof);
+    }
+
+    /// Move everything the replicas sent into flight
+    fn collect_sent(&mut self) {
+        for replica in &self.replicas {
+            for (to, message) in replica.node.take_outbox() {
+                if let PBFTMessage::Consensus(consensus) = message.message() {
+                    match consensus.kind() {
+                        ConsensusMessageKind::Prepare(digest)
+                        | ConsensusMessageKind::Commit(digest) => self.digests.push(*digest),
+                        ConsensusMessageKind::PrePrepare(_) => {
+                            self.digests.push(*message.header().digest())
+                        }
+                        ConsensusMessageKind::SkipSlot(_) => {}
+                    }
+                }
+
+                if to == byzantine() {
+                    if let PBFTMessage::ViewChange(view_change) = message.message() {
+                        if let ViewChangeMessageKind::StopData(_) = view_change.kind() {
+                            self.received_collects.push((**message).clone());
+                        }
+                    }
+
+                    continue;
+                }
+
+                self.in_flight.push(InFlight { to, message });
+            }
+        }
+    }
+
+    /// Build the message the byzantine replica sends to `to`
+    fn forge(&self, to: NodeId, message: FuzzMessage) -> Result<Message> {
+        let byzantine = byzantine();
+
+        let message = match message {
+            FuzzMessage::PrePrepare {
+                seq,
+                view,
+                requests,
+            } => consensus(
+                seq.into(),
+                view.into(),
+                ConsensusMessageKind::PrePrepare(client_requests(byzantine, requests)?),
+            ),
+            FuzzMessage::Prepare { seq, view, digest } => consensus(
+                seq.into(),
+                view.into(),
+                ConsensusMessageKind::Prepare(digest.resolve(&self.digests)),
+            ),
+            FuzzMessage::Commit { seq, view, digest } => consensus(
+                seq.into(),
+                view.into(),
+                ConsensusMessageKind::Commit(digest.resolve(&self.digests)),
+            ),
+            FuzzMessage::SkipSlot { seq, view, leader } => consensus(
+                seq.into(),
+                view.into(),
+                ConsensusMessageKind::SkipSlot(replica(leader, N)),
+            ),
+            FuzzMessage::Stop { view, requests } => view_change(
+                view.into(),
+                ViewChangeMessageKind::Stop(client_requests(byzantine, requests)?),
+            ),
+            FuzzMessage::StopQuorumJoin { view, node } => view_change(
+                view.into(),
+                ViewChangeMessageKind::StopQuorumJoin(replica(node, N)),
+            ),
+            FuzzMessage::StopData {
+                view,
+                executing,
+                prepared,
+                quorum_prepared,
+                with_last_proof,
+            } => {
+                let prepared = prepared
+                    .into_iter()
+                    .map(|(view, digest)| {
+                        ViewDecisionPair(view.into(), digest.resolve(&self.digests))
+                    })
+                    .collect();
+
+                let quorum_prepared = quorum_prepared.map(|(view, digest)| {
+                    ViewDecisionPair(view.into(), digest.resolve(&self.digests))
+                });
+
+                let incomplete =
+                    IncompleteProof::new(executing.into(), PrepareSet(prepared), quorum_prepared);
+
+                let last_proof = self.last_proof.clone().filter(|_| with_last_proof);
+
+                view_change(
+                    view.into(),
+                    ViewChangeMessageKind::StopData(CollectData::new(incomplete, last_proof)),
+                )
+            }
+            FuzzMessage::Sync {
+                view,
+                seq,
+                requests,
+                collects,
+            } => {
+                let view = SeqNo::from(view);
+
+                let proposed = ConsensusMessage::new(
+                    seq.into(),
+                    view,
+                    ConsensusMessageKind::PrePrepare(client_requests(byzantine, requests)?),
+                );
+
+                let (header, _) =
+                    mock::stored(byzantine, to, PBFTMessage::Consensus(proposed.clone()))?
+                        .into_inner();
+
+                let collects = if self.received_collects.is_empty() {
+                    Vec::new()
+                } else {
+                    collects
+                        .into_iter()
+                        .map(|index| {
+                            self.received_collects[index as usize % self.received_collects.len()]
+                                .clone()
+                        })
+                        .collect()
+                };
+
+                view_change(
+                    view,
+                    ViewChangeMessageKind::Sync(LeaderCollects::new(
+                        FwdConsensusMessage::new(header, proposed),
+                        collects,
+                        None,
+                    )),
+                )
+            }
+        };
+
+        mock::shareable(byzantine, to, message)
+    }
+}
+
+/// The replica whose messages are made up by the fuzzer
+pub fn byzantine() -> NodeId {
+    NodeId::from(BYZANTINE_ID)
+}
+
+fn consensus(
+    seq: SeqNo,
+    view: SeqNo,
+    kind: ConsensusMessageKind<FuzzRequest>,
+) -> PBFTMessage<FuzzRequest> {
+    PBFTMessage::Consensus(ConsensusMessage::new(seq, view, kind))
+}
+
+fn view_change(
+    view: SeqNo,
+    kind: ViewChangeMessageKind<FuzzRequest>,
+) -> PBFTMessage<FuzzRequest> {
+    PBFTMessage::ViewChange(ViewChangeMessage::new(view, kind))
+}
+
+/// Sign the requests as if each came from the client of its session
+fn client_requests(
+    to: NodeId,
+    requests: Vec<FuzzRequest>,
+) -> Result<Vec<StoredMessage<FuzzRequest>>> {
+    requests
+        .into_iter()
+        .map(|request| {
+            let client = NodeId::from(FIRST_CLIENT_ID + u32::from(request.session_number()) % 16);
+
+            mock::stored(client, to, request)
+        })
+        .collect()
+}
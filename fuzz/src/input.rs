@@ -0,0 +1,164 @@
+//! The inputs of the state machine targets, generated from the fuzzer's bytes.
+//!
+//! Rather than raw bytes, the protocols are fed well typed messages, so the fuzzer spends
+//! its time exploring the protocol logic instead of the decoders (which have their own target).
+//! Sequence numbers and views are kept small, so messages land close to the current instance.
+
+use arbitrary::Arbitrary;
+
+use atlas_common::crypto::hash::Digest;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::SeqNo;
+
+use crate::mock;
+use crate::request::{FuzzRequest, FuzzState};
+
+/// How far ahead of the first instance the generated messages can reach
+const SEQ_RANGE: u32 = 8;
+
+/// How far ahead of the first view the generated messages can reach
+const VIEW_RANGE: u32 = 4;
+
+/// A sequence number close to the start of the execution
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub struct FuzzSeq(u8);
+
+impl From<FuzzSeq> for SeqNo {
+    fn from(seq: FuzzSeq) -> Self {
+        SeqNo::from(u32::from(seq.0) % SEQ_RANGE)
+    }
+}
+
+/// A view close to the first one
+#[derive(Arbitrary, Debug, Clone, Copy)]
+pub struct FuzzView(u8);
+
+impl From<FuzzView> for SeqNo {
+    fn from(view: FuzzView) -> Self {
+        SeqNo::from(u32::from(view.0) % VIEW_RANGE)
+    }
+}
+
+/// A digest, either one already seen in the execution or a fresh one
+#[derive(Arbitrary, Debug, Clone)]
+pub enum FuzzDigest {
+    Known(u16),
+    Fresh(Vec<u8>),
+}
+
+impl FuzzDigest {
+    /// Resolve the digest against the ones seen so far
+    pub fn resolve(&self, known: &[Digest]) -> Digest {
+        match self {
+            FuzzDigest::Known(index) if !known.is_empty() => {
+                known[*index as usize % known.len()]
+            }
+            FuzzDigest::Known(index) => mock::digest_of(&index.to_le_bytes()),
+            FuzzDigest::Fresh(bytes) => mock::digest_of(bytes),
+        }
+    }
+}
+
+/// A message crafted by the byzantine replica
+#[derive(Arbitrary, Debug, Clone)]
+pub enum FuzzMessage {
+    PrePrepare {
+        seq: FuzzSeq,
+        view: FuzzView,
+        requests: Vec<FuzzRequest>,
+    },
+    Prepare {
+        seq: FuzzSeq,
+        view: FuzzView,
+        digest: FuzzDigest,
+    },
+    Commit {
+        seq: FuzzSeq,
+        view: FuzzView,
+        digest: FuzzDigest,
+    },
+    SkipSlot {
+        seq: FuzzSeq,
+        view: FuzzView,
+        leader: u8,
+    },
+    Stop {
+        view: FuzzView,
+        requests: Vec<FuzzRequest>,
+    },
+    StopQuorumJoin {
+        view: FuzzView,
+        node: u8,
+    },
+    StopData {
+        view: FuzzView,
+        executing: FuzzSeq,
+        prepared: Vec<(FuzzView, FuzzDigest)>,
+        quorum_prepared: Option<(FuzzView, FuzzDigest)>,
+        with_last_proof: bool,
+    },
+    Sync {
+        view: FuzzView,
+        seq: FuzzSeq,
+        requests: Vec<FuzzRequest>,
+        /// Indices of the STOP-DATA messages the byzantine replica received, to relay
+        collects: Vec<u16>,
+    },
+}
+
+/// A step of an execution of the cluster
+#[derive(Arbitrary, Debug, Clone)]
+pub enum Action {
+    /// The current leader proposes a batch of requests
+    Propose { requests: Vec<FuzzRequest> },
+    /// Deliver a message in flight
+    Deliver { index: u16 },
+    /// Lose a message in flight
+    Drop { index: u16 },
+    /// Deliver a message in flight, but keep it around to be delivered again
+    Duplicate { index: u16 },
+    /// The byzantine replica sends a message of its own making
+    Inject { to: u8, message: FuzzMessage },
+    /// A replica's client requests time out, so it calls for a view change
+    TimeOut { replica: u8 },
+}
+
+/// A message received by the replica under test of the state transfer target
+#[derive(Arbitrary, Debug, Clone)]
+pub enum FuzzCstMessage {
+    RequestStateCid,
+    ReplyStateCid(Option<(FuzzSeq, FuzzDigest)>),
+    /// A request for the given checkpoint, from a replica with the given checkpoint
+    RequestState {
+        checkpoint: Option<FuzzSeq>,
+        base: Option<(FuzzSeq, FuzzDigest)>,
+    },
+    ReplyState { seq: FuzzSeq, state: FuzzState },
+    /// The manifest of the given state, split into chunks
+    ReplyStateManifest { seq: FuzzSeq, state: FuzzState },
+    RequestStateChunks { seq: FuzzSeq, chunks: Vec<u8> },
+    /// A chunk of the given state
+    ReplyStateChunk { seq: FuzzSeq, state: FuzzState, index: u8 },
+    /// A checkpoint vote, signed by the sender
+    CheckpointVote(FuzzDigest),
+}
+
+/// A step of an execution of the state transfer target
+#[derive(Arbitrary, Debug, Clone)]
+pub enum CstAction {
+    /// Receive a message from one of the other replicas
+    Receive {
+        from: u8,
+        seq: FuzzSeq,
+        message: FuzzCstMessage,
+    },
+    /// The application hands us a new checkpoint
+    Checkpoint { seq: FuzzSeq, state: FuzzState },
+    /// We fall behind and ask the others for the latest state
+    RequestLatestState,
+}
+
+/// Pick one of the `n` replicas
+pub fn replica(index: u8, n: usize) -> NodeId {
+    NodeId::from(index as u32 % n as u32)
+}
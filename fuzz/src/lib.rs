@@ -0,0 +1,16 @@
+//! Fuzzing harnesses for the febft protocols.
+//!
+//! The targets in `fuzz_targets` drive the message decoders, the consensus and view change
+//! state machines and the collaborative state transfer with inputs generated by `cargo fuzz`.
+//! Replicas are wired together through [mock::MockNode], which captures what they send so
+//! the harness can decide what gets delivered, dropped, duplicated or reordered.
+//!
+//! Besides not panicking, the targets check that memory use stays proportional to the
+//! input ([alloc::MemoryWatch]) and that correct replicas never decide different batches
+//! for the same sequence number ([cluster::Cluster]).
+
+pub mod alloc;
+pub mod cluster;
+pub mod input;
+pub mod mock;
+pub mod request;
@@ -0,0 +1,498 @@
+//! Stand-ins for the networking, timeout and request pre processing layers, so the
+//! protocols can be run in a single thread with the harness in control of delivery.
+
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+
+use serde::Serialize;
+
+use atlas_common::channel;
+use atlas_common::channel::{ChannelSyncRx, ChannelSyncTx};
+use atlas_common::crypto::hash::{Context, Digest};
+use atlas_common::crypto::signature::{KeyPair, PublicKey};
+use atlas_common::error::*;
+use atlas_common::globals::ReadOnly;
+use atlas_common::node_id::NodeId;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_common::peer_addr::PeerAddr;
+use atlas_communication::lookup_table::MessageModule;
+use atlas_communication::message::{
+    Header, SerializedMessage, StoredMessage, StoredSerializedMessage, WireMessage,
+};
+use atlas_communication::reconfiguration::{NetworkInformationProvider, NodeInfo, NodeType};
+use atlas_core::messages::ForwardedRequestsMessage;
+use atlas_core::ordering_protocol::networking::OrderProtocolSendNode;
+use atlas_core::ordering_protocol::ShareableMessage;
+use atlas_core::persistent_log::OperationMode;
+use atlas_core::request_pre_processing::{PreProcessorMessage, RequestPreProcessor};
+use atlas_core::timeouts::timeout::TimeoutModHandle;
+use atlas_core::timeouts::TimeoutsHandle;
+use atlas_smr_application::state::monolithic_state::InstallStateMessage;
+use atlas_smr_core::persistent_log::MonolithicStateLog;
+use atlas_smr_core::state_transfer::networking::StateTransferSendNode;
+use atlas_smr_core::state_transfer::Checkpoint;
+
+use febft_pbft_consensus::bft::message::PBFTMessage;
+use febft_pbft_consensus::bft::PBFT;
+use febft_state_transfer::certificate::digest_state;
+use febft_state_transfer::certificate::CheckpointCertificate;
+use febft_state_transfer::history::{ArchivedCheckpoint, CheckpointArchive};
+use febft_state_transfer::message::serialize::CSTMsg;
+use febft_state_transfer::message::CstMessage;
+
+use crate::request::{FuzzRequest, FuzzState};
+
+/// The clients issuing the fuzzed requests are numbered from here on
+pub const FIRST_CLIENT_ID: u32 = 1000;
+
+const CHANNEL_SIZE: usize = 1024;
+
+static INIT: Once = Once::new();
+
+/// Initialize the thread pool used by the protocols to sign and serialize messages
+pub fn init() {
+    INIT.call_once(|| {
+        let conf = atlas_common::InitConfig {
+            threadpool_threads: 2,
+            async_threads: 1,
+            id: None,
+        };
+
+        // The guard must live for the whole process, as every input reuses the pool
+        std::mem::forget(unsafe { atlas_common::init(conf) }.expect("Failed to initialize"));
+    });
+}
+
+/// The key pair of a node, derived from its id so every run is reproducible
+pub fn key_pair(node: NodeId) -> KeyPair {
+    let seed = [u32::from(node) as u8 + 1; 32];
+
+    KeyPair::from_bytes(&seed[..]).expect("Failed to derive the key pair")
+}
+
+/// The digest of a serialized payload
+pub fn digest_of(payload: &[u8]) -> Digest {
+    let mut ctx = Context::new();
+
+    ctx.update(payload);
+    ctx.finish()
+}
+
+/// Serialize a message the way it would go over the wire
+pub fn encode<M: Serialize>(message: &M) -> Result<Vec<u8>> {
+    Ok(bincode::serde::encode_to_vec(
+        message,
+        bincode::config::standard(),
+    )?)
+}
+
+/// Build the header a node would attach to a message, optionally signing it
+pub fn header(
+    from: NodeId,
+    to: NodeId,
+    payload: Vec<u8>,
+    digest: Digest,
+    key_pair: Option<&KeyPair>,
+) -> Header {
+    let (header, _, _) = WireMessage::new(
+        from,
+        to,
+        MessageModule::Protocol,
+        payload.into(),
+        0,
+        Some(digest),
+        key_pair,
+    )
+    .into_inner();
+
+    header
+}
+
+/// Store a message as if `from` had sent it to `to`, signed with its key
+pub fn stored<M: Serialize>(from: NodeId, to: NodeId, message: M) -> Result<StoredMessage<M>> {
+    let payload = encode(&message)?;
+    let digest = digest_of(&payload);
+    let key_pair = key_pair(from);
+
+    let header = header(from, to, payload, digest, Some(&key_pair));
+
+    Ok(StoredMessage::new(header, message))
+}
+
+/// Store a message as if `from` had sent it to `to`, ready to be handed to a protocol
+pub fn shareable<M: Serialize>(
+    from: NodeId,
+    to: NodeId,
+    message: M,
+) -> Result<ShareableMessage<M>> {
+    Ok(Arc::new(ReadOnly::new(stored(from, to, message)?)))
+}
+
+/// The identities of every node in the system, replicas and clients alike
+pub struct MockNetworkInfo {
+    id: NodeId,
+    key_pair: Arc<KeyPair>,
+}
+
+impl MockNetworkInfo {
+    pub fn new(id: NodeId) -> Self {
+        Self {
+            id,
+            key_pair: Arc::new(key_pair(id)),
+        }
+    }
+}
+
+impl NetworkInformationProvider for MockNetworkInfo {
+    fn get_own_id(&self) -> NodeId {
+        self.id
+    }
+
+    fn get_key_pair(&self) -> &Arc<KeyPair> {
+        &self.key_pair
+    }
+
+    fn get_node_info(&self, node: &NodeId) -> Option<NodeInfo> {
+        let node_type = if u32::from(*node) >= FIRST_CLIENT_ID {
+            NodeType::Client
+        } else {
+            NodeType::Replica
+        };
+
+        let public_key = PublicKey::from(key_pair(*node).public_key());
+        let address = PeerAddr::new(([127, 0, 0, 1], 10000).into(), format!("node-{:?}", node));
+
+        Some(NodeInfo::new(*node, node_type, public_key, address))
+    }
+}
+
+/// A node which, instead of sending messages, keeps them in an outbox for the harness
+/// to deliver. Messages are signed and given a header just like the real network would.
+pub struct MockNode<M> {
+    network_info: Arc<MockNetworkInfo>,
+    outbox: Mutex<Vec<(NodeId, ShareableMessage<M>)>>,
+    _phantom: PhantomData<fn() -> M>,
+}
+
+impl<M> MockNode<M>
+where
+    M: Serialize + Clone,
+{
+    pub fn new(id: NodeId) -> Arc<Self> {
+        Arc::new(Self {
+            network_info: Arc::new(MockNetworkInfo::new(id)),
+            outbox: Mutex::new(Vec::new()),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Take every message sent since the last call, along with its destination
+    pub fn take_outbox(&self) -> Vec<(NodeId, ShareableMessage<M>)> {
+        std::mem::take(&mut *self.outbox.lock().unwrap())
+    }
+
+    fn push<I>(&self, message: M, targets: I, signed: bool) -> Result<()>
+    where
+        I: Iterator<Item = NodeId>,
+    {
+        let payload = encode(&message)?;
+        let digest = digest_of(&payload);
+        let key_pair = signed.then(|| &**self.network_info.get_key_pair());
+
+        let mut outbox = self.outbox.lock().unwrap();
+
+        for target in targets {
+            let header = header(self.id(), target, payload.clone(), digest, key_pair);
+
+            outbox.push((
+                target,
+                Arc::new(ReadOnly::new(StoredMessage::new(header, message.clone()))),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn id(&self) -> NodeId {
+        self.network_info.get_own_id()
+    }
+
+    fn serialize_digest(&self, message: M) -> Result<(SerializedMessage<M>, Digest)> {
+        let payload = encode(&message)?;
+        let digest = digest_of(&payload);
+
+        Ok((SerializedMessage::new(message, payload.into()), digest))
+    }
+}
+
+impl OrderProtocolSendNode<FuzzRequest, PBFT<FuzzRequest>> for MockNode<PBFTMessage<FuzzRequest>> {
+    type NetworkInfoProvider = MockNetworkInfo;
+
+    fn id(&self) -> NodeId {
+        MockNode::id(self)
+    }
+
+    fn network_info_provider(&self) -> &Arc<Self::NetworkInfoProvider> {
+        &self.network_info
+    }
+
+    fn forward_requests<I>(
+        &self,
+        _fwd_requests: ForwardedRequestsMessage<FuzzRequest>,
+        _targets: I,
+    ) -> std::result::Result<(), Vec<NodeId>>
+    where
+        I: Iterator<Item = NodeId>,
+    {
+        // Forwarded requests go to the proposer, which is not part of the harness
+        Ok(())
+    }
+
+    fn send(
+        &self,
+        message: PBFTMessage<FuzzRequest>,
+        target: NodeId,
+        _flush: bool,
+    ) -> Result<()> {
+        self.push(message, std::iter::once(target), false)
+    }
+
+    fn send_signed(
+        &self,
+        message: PBFTMessage<FuzzRequest>,
+        target: NodeId,
+        _flush: bool,
+    ) -> Result<()> {
+        self.push(message, std::iter::once(target), true)
+    }
+
+    fn broadcast<I>(
+        &self,
+        message: PBFTMessage<FuzzRequest>,
+        targets: I,
+    ) -> std::result::Result<(), Vec<NodeId>>
+    where
+        I: Iterator<Item = NodeId>,
+    {
+        self.push(message, targets, false).map_err(|_| Vec::new())
+    }
+
+    fn broadcast_signed<I>(
+        &self,
+        message: PBFTMessage<FuzzRequest>,
+        targets: I,
+    ) -> std::result::Result<(), Vec<NodeId>>
+    where
+        I: Iterator<Item = NodeId>,
+    {
+        self.push(message, targets, true).map_err(|_| Vec::new())
+    }
+
+    fn serialize_digest_message(
+        &self,
+        message: PBFTMessage<FuzzRequest>,
+    ) -> Result<(SerializedMessage<PBFTMessage<FuzzRequest>>, Digest)> {
+        self.serialize_digest(message)
+    }
+
+    fn broadcast_serialized(
+        &self,
+        messages: BTreeMap<NodeId, StoredSerializedMessage<PBFTMessage<FuzzRequest>>>,
+    ) -> std::result::Result<(), Vec<NodeId>> {
+        let mut outbox = self.outbox.lock().unwrap();
+
+        for (target, stored) in messages {
+            let (header, serialized) = stored.into_inner();
+            let (message, _buf) = serialized.into_inner();
+
+            outbox.push((
+                target,
+                Arc::new(ReadOnly::new(StoredMessage::new(header, message))),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl StateTransferSendNode<CSTMsg<FuzzState>> for MockNode<CstMessage<FuzzState>> {
+    type NetworkInfoProvider = MockNetworkInfo;
+
+    fn id(&self) -> NodeId {
+        MockNode::id(self)
+    }
+
+    fn network_info_provider(&self) -> &Arc<Self::NetworkInfoProvider> {
+        &self.network_info
+    }
+
+    fn send(&self, message: CstMessage<FuzzState>, target: NodeId, _flush: bool) -> Result<()> {
+        self.push(message, std::iter::once(target), false)
+    }
+
+    fn send_signed(
+        &self,
+        message: CstMessage<FuzzState>,
+        target: NodeId,
+        _flush: bool,
+    ) -> Result<()> {
+        self.push(message, std::iter::once(target), true)
+    }
+
+    fn broadcast<I>(
+        &self,
+        message: CstMessage<FuzzState>,
+        targets: I,
+    ) -> std::result::Result<(), Vec<NodeId>>
+    where
+        I: Iterator<Item = NodeId>,
+    {
+        self.push(message, targets, false).map_err(|_| Vec::new())
+    }
+
+    fn broadcast_signed<I>(
+        &self,
+        message: CstMessage<FuzzState>,
+        targets: I,
+    ) -> std::result::Result<(), Vec<NodeId>>
+    where
+        I: Iterator<Item = NodeId>,
+    {
+        self.push(message, targets, true).map_err(|_| Vec::new())
+    }
+
+    fn serialize_digest_message(
+        &self,
+        message: CstMessage<FuzzState>,
+    ) -> Result<(SerializedMessage<CstMessage<FuzzState>>, Digest)> {
+        self.serialize_digest(message)
+    }
+}
+
+/// A timeout handle whose timeouts never fire. The harness delivers the
+/// consequences of timeouts (e.g. STOP messages) explicitly instead.
+pub fn timeouts(id: NodeId) -> TimeoutModHandle {
+    let (handle, rx) = TimeoutsHandle::new(id, CHANNEL_SIZE);
+
+    // Nobody ever processes the requests, so keep the receiving end around
+    std::mem::forget(rx);
+
+    handle.for_mod("febft-fuzz")
+}
+
+/// A request pre processor with no pending requests, which answers
+/// the protocol's queries from a background thread
+pub fn pre_processor() -> RequestPreProcessor<FuzzRequest> {
+    let (tx, rx) = channel::new_bounded_sync(CHANNEL_SIZE);
+
+    thread::spawn(move || serve_pre_processor(rx));
+
+    RequestPreProcessor::new(tx)
+}
+
+fn serve_pre_processor(rx: ChannelSyncRx<PreProcessorMessage<FuzzRequest>>) {
+    while let Ok(message) = rx.recv() {
+        if let PreProcessorMessage::CollectAllPendingMessages(reply) = message {
+            let _ = reply.send_return(Vec::new());
+        }
+    }
+}
+
+/// A channel to install states on, which keeps the states around for the harness to inspect
+pub fn install_channel() -> (
+    ChannelSyncTx<InstallStateMessage<FuzzState>>,
+    ChannelSyncRx<InstallStateMessage<FuzzState>>,
+) {
+    channel::new_bounded_sync(CHANNEL_SIZE)
+}
+
+/// A local checkpoint of the given state, digested the way a recovering replica verifies it
+pub fn checkpoint(seq: SeqNo, state: FuzzState) -> Result<Arc<ReadOnly<Checkpoint<FuzzState>>>> {
+    let digest = digest_state(&state)?;
+
+    Ok(Checkpoint::new(seq, state, digest))
+}
+
+/// A state transfer log which does not persist anything
+#[derive(Clone, Default)]
+pub struct MockStateLog;
+
+impl MonolithicStateLog<FuzzState> for MockStateLog {
+    fn read_checkpoint(&self) -> Result<Option<FuzzState>> {
+        Ok(None)
+    }
+
+    fn write_checkpoint(
+        &self,
+        _write_mode: OperationMode,
+        _checkpoint: Arc<ReadOnly<Checkpoint<FuzzState>>>,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+type ArchivedState = (
+    Arc<ReadOnly<Checkpoint<FuzzState>>>,
+    Option<CheckpointCertificate>,
+);
+
+/// A checkpoint archive which keeps the checkpoints in memory
+#[derive(Default)]
+pub struct MockCheckpointArchive {
+    stored: Mutex<BTreeMap<SeqNo, ArchivedState>>,
+}
+
+impl CheckpointArchive<FuzzState> for MockCheckpointArchive {
+    fn store(&self, checkpoint: Arc<ReadOnly<Checkpoint<FuzzState>>>) -> Result<()> {
+        self.stored
+            .lock()
+            .unwrap()
+            .insert(checkpoint.sequence_number(), (checkpoint, None));
+
+        Ok(())
+    }
+
+    fn store_certificate(&self, certificate: &CheckpointCertificate) -> Result<()> {
+        if let Some((_, stored)) = self
+            .stored
+            .lock()
+            .unwrap()
+            .get_mut(&certificate.sequence_number())
+        {
+            *stored = Some(certificate.clone());
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, seq: SeqNo) -> Result<Option<Arc<ReadOnly<Checkpoint<FuzzState>>>>> {
+        Ok(self
+            .stored
+            .lock()
+            .unwrap()
+            .get(&seq)
+            .map(|(checkpoint, _)| checkpoint.clone()))
+    }
+
+    fn discard(&self, seq: SeqNo) -> Result<()> {
+        self.stored.lock().unwrap().remove(&seq);
+
+        Ok(())
+    }
+
+    fn stored(&self) -> Result<Vec<ArchivedCheckpoint>> {
+        Ok(self
+            .stored
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(seq, (checkpoint, certificate))| ArchivedCheckpoint {
+                seq: *seq,
+                digest: *checkpoint.digest(),
+                certificate: certificate.clone(),
+            })
+            .collect())
+    }
+}
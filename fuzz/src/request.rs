@@ -0,0 +1,59 @@
+//! The client requests and application state used by the harnesses.
+
+use std::io::{Read, Write};
+
+use arbitrary::Arbitrary;
+use serde::{Deserialize, Serialize};
+
+use atlas_common::error::*;
+use atlas_common::ordering::{Orderable, SeqNo};
+use atlas_core::messages::SessionBased;
+use atlas_smr_application::state::monolithic_state::MonolithicState;
+
+/// A client request with an opaque payload
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Arbitrary)]
+pub struct FuzzRequest {
+    session: u32,
+    seq: u32,
+    payload: Vec<u8>,
+}
+
+impl Orderable for FuzzRequest {
+    fn sequence_number(&self) -> SeqNo {
+        SeqNo::from(self.seq)
+    }
+}
+
+impl SessionBased for FuzzRequest {
+    fn session_number(&self) -> SeqNo {
+        SeqNo::from(self.session)
+    }
+}
+
+/// An application state made up of opaque bytes
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Arbitrary)]
+pub struct FuzzState {
+    data: Vec<u8>,
+}
+
+impl MonolithicState for FuzzState {
+    fn serialize_state<W>(mut w: W, state: &Self) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_all(&state.data)?;
+
+        Ok(())
+    }
+
+    fn deserialize_state<R>(mut r: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut data = Vec::new();
+
+        r.read_to_end(&mut data)?;
+
+        Ok(Self { data })
+    }
+}